@@ -0,0 +1,55 @@
+//! Compares the flat and combining-tree barriers.
+//!
+//! Run with `cargo bench --bench barrier`.
+
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{SenseBarrier, TreeBarrier};
+use std::sync::Barrier;
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 2000;
+const THREADS: [usize; 4] = [8, 32, 64, 128];
+const FAN_IN: usize = 4;
+
+/// Runs `ROUNDS` rounds of `wait` on `threads` threads and returns the average time per round.
+fn run<W: Fn(usize) + Sync>(threads: usize, wait: W) -> Duration {
+    let start = Instant::now();
+    scope(|s| {
+        for id in 0..threads {
+            let wait = &wait;
+            s.spawn(move |_| {
+                for _ in 0..ROUNDS {
+                    wait(id);
+                }
+            });
+        }
+    })
+    .unwrap();
+    start.elapsed() / ROUNDS as u32
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>16} {:>16} {:>16}",
+        "threads", "std::Barrier", "SenseBarrier", "TreeBarrier"
+    );
+    for &threads in THREADS.iter() {
+        let std_barrier = Barrier::new(threads);
+        let sense_barrier = SenseBarrier::new(threads);
+        let tree_barrier = TreeBarrier::new(threads, FAN_IN);
+
+        let std_time = run(threads, |_| {
+            let _ = std_barrier.wait();
+        });
+        let sense_time = run(threads, |_| {
+            let _ = sense_barrier.wait();
+        });
+        let tree_time = run(threads, |id| {
+            let _ = tree_barrier.wait(id);
+        });
+        println!(
+            "{:>8} {:>16?} {:>16?} {:>16?}",
+            threads, std_time, sense_time, tree_time
+        );
+    }
+}
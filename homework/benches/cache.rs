@@ -0,0 +1,51 @@
+//! Compares the throughput of the insertions into a `Cache` with a single shard and with the
+//! default shards.
+//!
+//! Run with `cargo bench --bench cache`. Prints the throughput in Mops/s. Each thread inserts its own new keys, and looks up each of
+//! them once more, so that the threads contend only for the locks of the shards.
+
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::hello_server::Cache;
+use std::time::{Duration, Instant};
+
+/// The number of keys inserted by each thread.
+const KEYS: usize = 20_000;
+const THREADS: [usize; 5] = [1, 4, 16, 32, 64];
+
+/// Runs the insertions and the lookups on `threads` threads and returns the elapsed time.
+fn run(cache: &Cache<usize, usize>, threads: usize) -> Duration {
+    let start = Instant::now();
+    scope(|s| {
+        for id in 0..threads {
+            s.spawn(move |_| {
+                for key in id * KEYS..(id + 1) * KEYS {
+                    let _ = cache.get_or_insert_with(key, |k| k + 1);
+                    let _ = cache.get_or_insert_with(key, |_| unreachable!());
+                }
+            });
+        }
+    })
+    .unwrap();
+    start.elapsed()
+}
+
+fn main() {
+    let shards = Cache::<usize, usize>::default().shards();
+    println!(
+        "{:>8} {:>16} {:>16}",
+        "threads",
+        "1 shard",
+        format!("{} shards", shards)
+    );
+    for &threads in THREADS.iter() {
+        let ops = (2 * threads * KEYS) as f64;
+        let single = run(&Cache::with_shards(1), threads);
+        let sharded = run(&Cache::with_shards(shards), threads);
+        println!(
+            "{:>8} {:>16.3} {:>16.3}",
+            threads,
+            ops / single.as_secs_f64() / 1e6,
+            ops / sharded.as_secs_f64() / 1e6
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Compares the concurrent sets under the standard workloads.
+//!
+//! Run with `cargo bench --bench workloads`. Criterion reports the time of each batch of operations
+//! and the throughput, and a table of the throughput and the mean latency per operation of every
+//! run is printed at the end.
+
+use criterion::{criterion_group, BenchmarkId, Criterion, Throughput};
+use cs492_concur_homework::testing::workload::{Target, Workload};
+use cs492_concur_homework::{AtomicHamt, OrderedListSet, RadixTree, SplitOrderedList};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const WORKLOADS: [Workload; 3] = [
+    Workload::READ_HEAVY,
+    Workload::WRITE_HEAVY,
+    Workload::ZIPFIAN,
+];
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+/// The number of operations of each thread in a batch.
+const OPS: usize = 1000;
+
+/// A row of the final table.
+struct Row {
+    workload: &'static str,
+    target: &'static str,
+    threads: usize,
+    ops: usize,
+    elapsed: Duration,
+}
+
+lazy_static::lazy_static! {
+    static ref ROWS: Mutex<Vec<Row>> = Mutex::new(Vec::new());
+}
+
+fn bench_target<T: Target, F: Fn() -> T>(c: &mut Criterion, name: &'static str, new: F) {
+    for workload in WORKLOADS.iter() {
+        let mut group = c.benchmark_group(workload.name);
+        for &threads in THREADS.iter() {
+            let target = new();
+            workload.prefill(&target);
+            let _ = group
+                .throughput(Throughput::Elements((threads * OPS) as u64))
+                .bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                    b.iter_custom(|iters| {
+                        let elapsed = (0..iters)
+                            .map(|_| workload.run(&target, threads, OPS))
+                            .sum::<Duration>();
+                        ROWS.lock().unwrap().push(Row {
+                            workload: workload.name,
+                            target: name,
+                            threads,
+                            ops: threads * OPS * iters as usize,
+                            elapsed,
+                        });
+                        elapsed
+                    })
+                });
+        }
+        group.finish();
+    }
+}
+
+fn workloads(c: &mut Criterion) {
+    bench_target(c, "Mutex<BTreeSet>", || Mutex::new(BTreeSet::new()));
+    bench_target(c, "OrderedListSet", OrderedListSet::new);
+    bench_target(c, "SplitOrderedList", SplitOrderedList::<()>::new);
+    bench_target(c, "RadixTree", RadixTree::<()>::new);
+    bench_target(c, "AtomicHamt", AtomicHamt::<usize, ()>::new);
+}
+
+/// Prints the throughput and the mean latency of every workload, target, and thread count.
+fn print_table() {
+    let mut rows = ROWS.lock().unwrap();
+    rows.sort_by_key(|r| (r.workload, r.target, r.threads));
+    println!(
+        "{:>12} {:>18} {:>8} {:>14} {:>14}",
+        "workload", "target", "threads", "Mops/s", "latency (ns)"
+    );
+    let mut i = 0;
+    while i < rows.len() {
+        // Sums up the warm-up and the measurement.
+        let (workload, target, threads) = (rows[i].workload, rows[i].target, rows[i].threads);
+        let (mut ops, mut elapsed) = (0, Duration::default());
+        while i < rows.len()
+            && (rows[i].workload, rows[i].target, rows[i].threads) == (workload, target, threads)
+        {
+            ops += rows[i].ops;
+            elapsed += rows[i].elapsed;
+            i += 1;
+        }
+        let secs = elapsed.as_secs_f64();
+        println!(
+            "{:>12} {:>18} {:>8} {:>14.3} {:>14.1}",
+            workload,
+            target,
+            threads,
+            ops as f64 / secs / 1e6,
+            secs * 1e9 * threads as f64 / ops as f64
+        );
+    }
+}
+
+criterion_group!(benches, workloads);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    print_table();
+}
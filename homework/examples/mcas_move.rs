@@ -0,0 +1,88 @@
+//! Moving items between the slots of a `GrowableArray` with MCAS.
+//!
+//! A move clears the source slot and fills the destination slot. With two single-word CASes, an
+//! item is either missing or in both slots in between, so a concurrent reader or mover may lose or
+//! duplicate it. With MCAS, both slots are changed at once.
+
+use crossbeam_epoch::{self as epoch, Owned, Shared};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::mcas::{self, Mcas};
+use cs492_concur_homework::GrowableArray;
+use rand::{thread_rng, Rng};
+use std::sync::atomic::Ordering;
+
+const NUM_SLOTS: usize = 64;
+const NUM_ITEMS: usize = 16;
+const NUM_THREADS: usize = 4;
+const NUM_MOVES: usize = 10_000;
+
+#[derive(Debug)]
+struct Item {
+    id: usize,
+}
+
+/// Moves the item in `from` to `to` if `from` is occupied and `to` is empty. Returns whether it
+/// moved.
+fn move_item(slots: &GrowableArray<Item>, from: usize, to: usize) -> bool {
+    let guard = epoch::pin();
+    let (from, to) = (slots.get(from, &guard), slots.get(to, &guard));
+    let item = mcas::load(from, &guard);
+    if item.is_null() || !mcas::load(to, &guard).is_null() {
+        return false;
+    }
+
+    let mut op = Mcas::new();
+    let _ = op
+        .add(from, item, Shared::null())
+        .add(to, Shared::null(), item);
+    op.execute(&guard)
+}
+
+fn main() {
+    let slots = GrowableArray::new();
+    {
+        let guard = epoch::pin();
+        for id in 0..NUM_ITEMS {
+            slots
+                .get(id, &guard)
+                .store(Owned::new(Item { id }), Ordering::Relaxed);
+        }
+    }
+
+    let moved = scope(|s| {
+        let handles = (0..NUM_THREADS)
+            .map(|_| {
+                s.spawn(|_| {
+                    let mut rng = thread_rng();
+                    (0..NUM_MOVES)
+                        .filter(|_| {
+                            let from = rng.gen_range(0, NUM_SLOTS);
+                            let to = rng.gen_range(0, NUM_SLOTS);
+                            from != to && move_item(&slots, from, to)
+                        })
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum::<usize>()
+    })
+    .unwrap();
+
+    // Every item is in exactly one slot.
+    let guard = epoch::pin();
+    let mut ids = Vec::new();
+    for index in 0..NUM_SLOTS {
+        let item = slots
+            .get(index, &guard)
+            .swap(Shared::null(), Ordering::Relaxed, &guard);
+        if !item.is_null() {
+            ids.push(unsafe { item.into_owned() }.id);
+        }
+    }
+    ids.sort_unstable();
+    assert_eq!(ids, (0..NUM_ITEMS).collect::<Vec<_>>());
+    println!("{} moves, {} items in place", moved, ids.len());
+}
@@ -0,0 +1,72 @@
+//! A bounded cache whose map and eviction queue are updated in one transaction.
+//!
+//! With locks, the map and the queue need either one big lock or a careful lock order. With
+//! `TVar`s, each update is written as if it were sequential, and `atomically` makes it atomic.
+
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{atomically, TVar};
+use std::collections::{HashMap, VecDeque};
+
+const CAPACITY: usize = 16;
+const NUM_THREADS: usize = 4;
+const NUM_KEYS: usize = 64;
+
+#[derive(Debug)]
+struct StmCache {
+    entries: TVar<HashMap<usize, String>>,
+    /// Keys in the insertion order. The oldest one is evicted first.
+    order: TVar<VecDeque<usize>>,
+}
+
+impl StmCache {
+    fn new() -> Self {
+        Self {
+            entries: TVar::new(HashMap::new()),
+            order: TVar::new(VecDeque::new()),
+        }
+    }
+
+    fn get_or_insert_with<F: Fn(usize) -> String>(&self, key: usize, f: F) -> String {
+        atomically(|tx| {
+            let mut entries = tx.read(&self.entries)?;
+            if let Some(value) = entries.get(&key) {
+                return Ok(value.clone());
+            }
+
+            let mut order = tx.read(&self.order)?;
+            let value = f(key);
+            let _ = entries.insert(key, value.clone());
+            order.push_back(key);
+            if order.len() > CAPACITY {
+                let oldest = order.pop_front().unwrap();
+                let _ = entries.remove(&oldest);
+            }
+
+            tx.write(&self.entries, entries)?;
+            tx.write(&self.order, order)?;
+            Ok(value)
+        })
+    }
+}
+
+fn main() {
+    let cache = StmCache::new();
+    scope(|s| {
+        for t in 0..NUM_THREADS {
+            let cache = &cache;
+            s.spawn(move |_| {
+                for i in 0..NUM_KEYS {
+                    let key = (i * (t + 1)) % NUM_KEYS;
+                    let value = cache.get_or_insert_with(key, |k| format!("value {}", k));
+                    assert_eq!(value, format!("value {}", key));
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let (entries, order) = atomically(|tx| Ok((tx.read(&cache.entries)?, tx.read(&cache.order)?)));
+    assert_eq!(entries.len(), order.len());
+    assert!(order.iter().all(|k| entries.contains_key(k)));
+    println!("{} entries: {:?}", entries.len(), order);
+}
@@ -0,0 +1,88 @@
+//! Runs the lookups of a few threads on `Cache` in an order chosen by `DeterministicPool`, and
+//! checks that each key is computed only once and keeps its value.
+//!
+//! ```text
+//! cargo +nightly fuzz run cache
+//! ```
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use cs492_concur_homework::hello_server::Cache;
+use cs492_concur_homework::testing::DeterministicPool;
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const MAX_THREADS: usize = 4;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Get(u8),
+    /// Looks up `outer`, whose computation looks up `inner`.
+    GetNested { outer: u8, inner: u8 },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    seed: u64,
+    threads: Vec<Vec<Op>>,
+}
+
+#[derive(Default)]
+struct State {
+    cache: Cache<u8, u64>,
+    /// The value computed for each key, which is the number of the computations before it.
+    model: Mutex<HashMap<u8, u64>>,
+}
+
+impl State {
+    /// Looks up `key`, and checks that it is computed only on the first lookup.
+    fn get(&self, key: u8, inner: Option<u8>) -> u64 {
+        let expected = self.model.lock().unwrap().get(&key).copied();
+        let value = self.cache.get_or_insert_with(key, |key| {
+            assert_eq!(expected, None, "{} is computed twice", key);
+            if let Some(inner) = inner {
+                let _ = self.get(inner, None);
+            }
+            let mut model = self.model.lock().unwrap();
+            let value = model.len() as u64;
+            let _ = model.insert(key, value);
+            value
+        });
+        assert_eq!(Some(value), self.model.lock().unwrap().get(&key).copied());
+        value
+    }
+
+    fn apply(&self, op: &Op) {
+        match *op {
+            Op::Get(key) => {
+                let _ = self.get(key, None);
+            }
+            // A computation that looks up its own key would deadlock.
+            Op::GetNested { outer, inner } if outer != inner => {
+                let _ = self.get(outer, Some(inner));
+            }
+            Op::GetNested { .. } => {}
+        }
+    }
+}
+
+/// Runs `ops[i]`, and then queues `ops[i + 1]`, so that the operations of a thread are in order.
+fn run(pool: DeterministicPool, state: Arc<State>, ops: Arc<Vec<Op>>, i: usize) {
+    if let Some(op) = ops.get(i) {
+        state.apply(op);
+        let next = pool.clone();
+        pool.execute(move || run(next, state, ops, i + 1));
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let pool = DeterministicPool::new(input.seed);
+    let state = Arc::new(State::default());
+    for ops in input.threads.into_iter().take(MAX_THREADS) {
+        let (next, state, ops) = (pool.clone(), state.clone(), Arc::new(ops));
+        pool.execute(move || run(next, state, ops, 0));
+    }
+    pool.join();
+});
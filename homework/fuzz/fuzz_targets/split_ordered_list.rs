@@ -0,0 +1,138 @@
+//! Runs the operations of a few threads on `SplitOrderedList` in an order chosen by
+//! `DeterministicPool`, and checks each result against `BTreeMap`.
+//!
+//! ```text
+//! cargo +nightly fuzz run split_ordered_list
+//! ```
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use crossbeam_epoch as epoch;
+use cs492_concur_homework::debug_dump::{self, Format};
+use cs492_concur_homework::testing::DeterministicPool;
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+use libfuzzer_sys::fuzz_target;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+const MAX_THREADS: usize = 4;
+/// The keys of `Op::Fill` start from here, above the keys of the other operations.
+const FILL_BASE: usize = 1 << 8;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert(u8, u32),
+    Delete(u8),
+    Lookup(u8),
+    /// Inserts `len` keys from `FILL_BASE + 256 * start`, which makes the list resize.
+    Fill { start: u8, len: u8 },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    seed: u64,
+    threads: Vec<Vec<Op>>,
+}
+
+#[derive(Default)]
+struct State {
+    list: SplitOrderedList<u32>,
+    /// The reference model, and all the keys used so far.
+    model: Mutex<(BTreeMap<usize, u32>, BTreeSet<usize>)>,
+}
+
+impl State {
+    fn insert(&self, model: &mut BTreeMap<usize, u32>, key: usize, value: u32) {
+        let guard = epoch::pin();
+        let expected = if model.contains_key(&key) {
+            Err(value)
+        } else {
+            let _ = model.insert(key, value);
+            Ok(())
+        };
+        assert_eq!(
+            self.list.insert(&key, value, &guard),
+            expected,
+            "insert({})\n{}",
+            key,
+            debug_dump::split_ordered_list(&self.list, Format::Text, &guard)
+        );
+    }
+
+    fn apply(&self, op: &Op) {
+        let guard = epoch::pin();
+        let mut model = self.model.lock().unwrap();
+        let (model, keys) = &mut *model;
+        match *op {
+            Op::Insert(key, value) => {
+                let _ = keys.insert(key as usize);
+                self.insert(model, key as usize, value);
+            }
+            Op::Delete(key) => {
+                let key = key as usize;
+                let _ = keys.insert(key);
+                assert_eq!(
+                    self.list.delete(&key, &guard).map(|v| *v),
+                    model.remove(&key).ok_or(()),
+                    "delete({})\n{}",
+                    key,
+                    debug_dump::split_ordered_list(&self.list, Format::Text, &guard)
+                );
+            }
+            Op::Lookup(key) => {
+                let key = key as usize;
+                let _ = keys.insert(key);
+                assert_eq!(
+                    self.list.lookup(&key, &guard),
+                    model.get(&key),
+                    "lookup({})\n{}",
+                    key,
+                    debug_dump::split_ordered_list(&self.list, Format::Text, &guard)
+                );
+            }
+            Op::Fill { start, len } => {
+                for i in 0..len as usize {
+                    let key = FILL_BASE + 256 * start as usize + i;
+                    let _ = keys.insert(key);
+                    self.insert(model, key, key as u32);
+                }
+            }
+        }
+    }
+
+    /// Checks every key used so far.
+    fn check(&self) {
+        let guard = epoch::pin();
+        let (model, keys) = &*self.model.lock().unwrap();
+        for key in keys {
+            assert_eq!(
+                self.list.lookup(key, &guard),
+                model.get(key),
+                "final lookup({})\n{}",
+                key,
+                debug_dump::split_ordered_list(&self.list, Format::Text, &guard)
+            );
+        }
+    }
+}
+
+/// Runs `ops[i]`, and then queues `ops[i + 1]`, so that the operations of a thread are in order.
+fn run(pool: DeterministicPool, state: Arc<State>, ops: Arc<Vec<Op>>, i: usize) {
+    if let Some(op) = ops.get(i) {
+        state.apply(op);
+        let next = pool.clone();
+        pool.execute(move || run(next, state, ops, i + 1));
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let pool = DeterministicPool::new(input.seed);
+    let state = Arc::new(State::default());
+    for ops in input.threads.into_iter().take(MAX_THREADS) {
+        let (next, state, ops) = (pool.clone(), state.clone(), Arc::new(ops));
+        pool.execute(move || run(next, state, ops, 0));
+    }
+    pool.join();
+    state.check();
+});
@@ -0,0 +1,122 @@
+//! Append-only concurrent vector.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{unprotected, Guard, Owned};
+
+use crate::hash_table::GrowableArray;
+
+/// Vector to which many threads push values concurrently.
+///
+/// `push` reserves the next index with a single `fetch_add` and then stores the value in the
+/// `GrowableArray` at that index, so pushes never retry and never move the values pushed before.
+/// Values are never removed until the vector is dropped, so a reference to a value is valid as long
+/// as the vector is.
+///
+/// An index is reserved before its value is stored, so a reader may see an index below `len()`
+/// whose value is not there yet. `get` returns `None` for such an index.
+#[derive(Debug)]
+pub struct AppendVec<T> {
+    values: GrowableArray<T>,
+    /// The number of reserved indices.
+    len: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for AppendVec<T> {}
+// The values pushed by a thread may be dropped by another thread.
+unsafe impl<T: Send + Sync> Sync for AppendVec<T> {}
+
+/// Iterator over the values of an `AppendVec`.
+#[derive(Debug)]
+pub struct Iter<'g, T> {
+    vec: &'g AppendVec<T>,
+    index: usize,
+    len: usize,
+    guard: &'g Guard,
+}
+
+impl<T> Default for AppendVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> AppendVec<T> {
+    /// Creates a new empty vector.
+    pub fn new() -> Self {
+        Self {
+            values: GrowableArray::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends `value`, and returns its index.
+    pub fn push(&self, value: T, guard: &Guard) -> usize {
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        self.values
+            .get(index, guard)
+            .store(Owned::new(value), Ordering::Release);
+        index
+    }
+
+    /// Returns the value at `index`, or `None` if it is not pushed yet.
+    pub fn get<'g>(&'g self, index: usize, guard: &'g Guard) -> Option<&'g T> {
+        // Avoids allocating the segments for the indices that are not reserved.
+        if index >= self.len.load(Ordering::Relaxed) {
+            return None;
+        }
+        let value = self.values.get(index, guard).load(Ordering::Acquire, guard);
+        unsafe { value.as_ref() }
+    }
+
+    /// Returns the number of reserved indices, including those whose values are not stored yet.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if no index is reserved.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the iterator over the values pushed so far, in the order of the indices.
+    ///
+    /// The indices below `len()` at the time of the call are visited, and those whose values are
+    /// not stored yet when visited are skipped.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T> {
+        Iter {
+            vec: self,
+            index: 0,
+            len: self.len(),
+            guard,
+        }
+    }
+}
+
+impl<'g, T> Iterator for Iter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.len {
+            let index = self.index;
+            self.index += 1;
+            if let Some(value) = self.vec.get(index, self.guard) {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<T> Drop for AppendVec<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = unprotected();
+            for index in 0..*self.len.get_mut() {
+                let value = self.values.get(index, guard).load(Ordering::Relaxed, guard);
+                if !value.is_null() {
+                    drop(value.into_owned());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Arena allocator for the nodes of concurrent data structures.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::mem::{self, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{unprotected, Atomic, Guard, Owned};
+
+/// The number of slots in a chunk.
+const CHUNK_LEN: usize = 64;
+
+/// A slot holding a value. The pointer to the value is the pointer to the slot.
+#[repr(C)]
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    chunk: *const Chunk<T>,
+}
+
+struct Chunk<T> {
+    slots: Box<[Slot<T>]>,
+    /// The next slot to allocate.
+    next: AtomicUsize,
+    /// The number of slots not freed yet, plus one while the chunk is the arena's current chunk.
+    live: AtomicUsize,
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    chunks_allocated: AtomicUsize,
+    chunks_freed: AtomicUsize,
+    nodes_allocated: AtomicUsize,
+    nodes_freed: AtomicUsize,
+}
+
+/// Allocation statistics of an `Arena`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// The number of chunks allocated.
+    pub chunks_allocated: usize,
+    /// The number of chunks freed.
+    pub chunks_freed: usize,
+    /// The number of nodes allocated.
+    pub nodes_allocated: usize,
+    /// The number of nodes freed.
+    pub nodes_freed: usize,
+}
+
+/// Arena that allocates nodes in chunks.
+///
+/// A node is allocated by bumping the index of the current chunk, and a new chunk is allocated
+/// when it is full. Retiring a node with `retire` drops it after the epoch advances past the
+/// current readers, like `Guard::defer_destroy`, and a chunk is freed as a whole once all its
+/// nodes are dropped. So there is a `malloc`/`free` pair per chunk rather than per node.
+///
+/// Chunks may outlive the arena, until all their nodes are freed.
+#[derive(Debug)]
+pub struct Arena<T> {
+    current: Atomic<Chunk<T>>,
+    counters: Arc<Counters>,
+}
+
+unsafe impl<T: Send> Send for Arena<T> {}
+unsafe impl<T: Send> Sync for Arena<T> {}
+
+/// A node allocated by an `Arena` that is not shared yet. Like `Owned`, the node is freed when the
+/// box is dropped, but through the arena.
+#[derive(Debug)]
+pub struct ArenaBox<T> {
+    node: *mut T,
+}
+
+unsafe impl<T: Send> Send for ArenaBox<T> {}
+unsafe impl<T: Sync> Sync for ArenaBox<T> {}
+
+impl<T> Chunk<T> {
+    fn new(counters: Arc<Counters>) -> Owned<Self> {
+        let mut chunk = Owned::new(Self {
+            slots: Box::new([]),
+            next: AtomicUsize::new(0),
+            live: AtomicUsize::new(CHUNK_LEN + 1),
+            counters,
+        });
+        let chunk_ptr = &*chunk as *const Self;
+        chunk.slots = (0..CHUNK_LEN)
+            .map(|_| Slot {
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+                chunk: chunk_ptr,
+            })
+            .collect();
+        chunk
+    }
+
+    /// Releases `n` slots of the chunk, and frees it if nothing is left.
+    unsafe fn release(chunk: *const Self, n: usize) {
+        if (*chunk).live.fetch_sub(n, Ordering::AcqRel) == n {
+            let chunk = Box::from_raw(chunk as *mut Self);
+            let _ = chunk.counters.chunks_freed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Arena<T> {
+    /// Creates a new arena.
+    pub fn new() -> Self {
+        Self {
+            current: Atomic::null(),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Allocates a node with `value`.
+    pub fn alloc(&self, value: T, guard: &Guard) -> *mut T {
+        loop {
+            let current = self.current.load(Ordering::Acquire, guard);
+            if let Some(chunk) = unsafe { current.as_ref() } {
+                let index = chunk.next.fetch_add(1, Ordering::Relaxed);
+                if index < CHUNK_LEN {
+                    let node = chunk.slots[index].value.get() as *mut T;
+                    unsafe { ptr::write(node, value) };
+                    let _ = self
+                        .counters
+                        .nodes_allocated
+                        .fetch_add(1, Ordering::Relaxed);
+                    return node;
+                }
+            }
+
+            // The chunk is full. Replace it with a new one.
+            match self.current.compare_and_set(
+                current,
+                Chunk::new(self.counters.clone()),
+                Ordering::AcqRel,
+                guard,
+            ) {
+                Ok(_) => {
+                    let _ = self
+                        .counters
+                        .chunks_allocated
+                        .fetch_add(1, Ordering::Relaxed);
+                    if !current.is_null() {
+                        // The other threads may still be bumping the old chunk's index.
+                        let current = current.as_raw();
+                        unsafe { guard.defer_unchecked(move || Chunk::release(current, 1)) };
+                    }
+                }
+                Err(e) => drop(e.new),
+            }
+        }
+    }
+
+    /// Same as `alloc`, but returns the node in a box that frees it when dropped.
+    pub fn alloc_box(&self, value: T, guard: &Guard) -> ArenaBox<T> {
+        ArenaBox {
+            node: self.alloc(value, guard),
+        }
+    }
+
+    /// Drops the node and frees its slot.
+    ///
+    /// # Safety
+    ///
+    /// `node` should have been allocated by an arena and not freed yet, and nobody else should be
+    /// accessing it.
+    pub unsafe fn free(node: *mut T) {
+        ptr::drop_in_place(node);
+        Self::release(node);
+    }
+
+    /// Frees the slot of the node without dropping it.
+    unsafe fn release(node: *mut T) {
+        let chunk = (*(node as *const Slot<T>)).chunk;
+        let _ = (*chunk)
+            .counters
+            .nodes_freed
+            .fetch_add(1, Ordering::Relaxed);
+        Chunk::release(chunk, 1);
+    }
+
+    /// Frees the node after all the threads currently pinned are unpinned.
+    ///
+    /// # Safety
+    ///
+    /// `node` should have been allocated by an arena and unlinked from the data structure, and it
+    /// should not be retired or freed twice.
+    pub unsafe fn retire(node: *mut T, guard: &Guard) {
+        guard.defer_unchecked(move || Self::free(node));
+    }
+
+    /// Returns the allocation statistics.
+    pub fn stats(&self) -> ArenaStats {
+        ArenaStats {
+            chunks_allocated: self.counters.chunks_allocated.load(Ordering::Relaxed),
+            chunks_freed: self.counters.chunks_freed.load(Ordering::Relaxed),
+            nodes_allocated: self.counters.nodes_allocated.load(Ordering::Relaxed),
+            nodes_freed: self.counters.nodes_freed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T> Drop for Arena<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let current = self.current.load(Ordering::Relaxed, unprotected());
+            if let Some(chunk) = current.as_ref() {
+                // Releases the unused slots, too.
+                let used = chunk.next.load(Ordering::Relaxed).min(CHUNK_LEN);
+                Chunk::release(current.as_raw(), CHUNK_LEN - used + 1);
+            }
+        }
+    }
+}
+
+impl<T> ArenaBox<T> {
+    /// Creates a box from a node allocated by an arena.
+    ///
+    /// # Safety
+    ///
+    /// `node` should have been allocated by an arena and not freed yet, and nobody else should be
+    /// accessing it.
+    pub unsafe fn from_raw(node: *mut T) -> Self {
+        Self { node }
+    }
+
+    /// Consumes the box, and returns the node. The node should be freed by `Arena::free` or
+    /// `Arena::retire`.
+    pub fn into_raw(self) -> *mut T {
+        let node = self.node;
+        mem::forget(self);
+        node
+    }
+
+    /// Consumes the box, and returns the value of the node after freeing it.
+    pub fn into_inner(self) -> T {
+        unsafe {
+            let node = self.into_raw();
+            let value = ptr::read(node);
+            Arena::release(node);
+            value
+        }
+    }
+}
+
+impl<T> Deref for ArenaBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.node }
+    }
+}
+
+impl<T> DerefMut for ArenaBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.node }
+    }
+}
+
+impl<T> Drop for ArenaBox<T> {
+    fn drop(&mut self) {
+        unsafe { Arena::free(self.node) };
+    }
+}
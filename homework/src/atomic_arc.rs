@@ -0,0 +1,153 @@
+//! Atomically swappable `Arc`.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use crossbeam_epoch::{pin, Guard};
+use std::sync::Arc;
+
+/// `Arc` that can be loaded and replaced atomically.
+///
+/// The cell owns one strong count of the `Arc` it currently holds. When the `Arc` is replaced, the
+/// count is not decremented right away, because a concurrent `load` may have read the pointer but
+/// not yet incremented the count. Instead the decrement is deferred through the epoch GC, which
+/// runs it after all the threads that were pinned at the time of the replacement are unpinned.
+///
+/// This gives read-mostly shared state (e.g. a router) lock-free reads and atomic replacement
+/// without `RwLock`.
+pub struct AtomicArc<T> {
+    ptr: AtomicPtr<T>,
+    _marker: PhantomData<Arc<T>>,
+}
+
+/// The error returned on failed `compare_exchange`.
+#[derive(Debug)]
+pub struct CompareExchangeError<T> {
+    /// The value in the cell at the time of the failed `compare_exchange`.
+    pub current: Arc<T>,
+    /// The new value that was not installed.
+    pub new: Arc<T>,
+}
+
+impl<T: Default + Send + Sync> Default for AtomicArc<T> {
+    fn default() -> Self {
+        Self::new(Arc::new(T::default()))
+    }
+}
+
+impl<T: fmt::Debug + Send + Sync> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicArc").field(&self.load()).finish()
+    }
+}
+
+impl<T> AtomicArc<T> {
+    /// Creates a new cell holding `value`.
+    pub fn new(value: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Arc::into_raw(value) as *mut T),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consumes the cell and returns the `Arc` it holds.
+    pub fn into_inner(self) -> Arc<T> {
+        let this = ManuallyDrop::new(self);
+        unsafe { Arc::from_raw(this.ptr.load(Ordering::Relaxed)) }
+    }
+}
+
+impl<T: Send + Sync> AtomicArc<T> {
+    /// Returns the reference to the current value. The reference is valid while `guard` is alive.
+    ///
+    /// Unlike `load`, this doesn't touch the reference count.
+    pub fn load_with<'g>(&'g self, _guard: &'g Guard) -> &'g T {
+        // The count owned by the cell is decremented only after `guard` is unpinned.
+        unsafe { &*self.ptr.load(Ordering::Acquire) }
+    }
+
+    /// Returns the current value.
+    pub fn load(&self) -> Arc<T> {
+        let guard = pin();
+        let ptr = self.ptr.load(Ordering::Acquire);
+        unsafe { Self::clone_raw(ptr, &guard) }
+    }
+
+    /// Replaces the current value with `new`.
+    pub fn store(&self, new: Arc<T>) {
+        let guard = pin();
+        let old = self
+            .ptr
+            .swap(Arc::into_raw(new) as *mut T, Ordering::AcqRel);
+        unsafe { Self::defer_release(old, &guard) };
+    }
+
+    /// Replaces the current value with `new`, and returns the old value.
+    pub fn swap(&self, new: Arc<T>) -> Arc<T> {
+        let guard = pin();
+        let old = self
+            .ptr
+            .swap(Arc::into_raw(new) as *mut T, Ordering::AcqRel);
+        unsafe {
+            let result = Self::clone_raw(old, &guard);
+            Self::defer_release(old, &guard);
+            result
+        }
+    }
+
+    /// Replaces the current value with `new` if the current value is pointer-equal to `current`.
+    ///
+    /// Returns the old value on success, and the current value together with `new` on failure.
+    pub fn compare_exchange(
+        &self,
+        current: &Arc<T>,
+        new: Arc<T>,
+    ) -> Result<Arc<T>, CompareExchangeError<T>> {
+        let guard = pin();
+        let new_ptr = Arc::into_raw(new) as *mut T;
+        match self.ptr.compare_exchange(
+            Arc::as_ptr(current) as *mut T,
+            new_ptr,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(old) => unsafe {
+                let result = Self::clone_raw(old, &guard);
+                Self::defer_release(old, &guard);
+                Ok(result)
+            },
+            Err(actual) => unsafe {
+                Err(CompareExchangeError {
+                    current: Self::clone_raw(actual, &guard),
+                    new: Arc::from_raw(new_ptr),
+                })
+            },
+        }
+    }
+
+    /// Increments the count of the `Arc` at `ptr` and returns it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` should have been read from the cell while `guard` is pinned.
+    unsafe fn clone_raw(ptr: *const T, _guard: &Guard) -> Arc<T> {
+        let arc = ManuallyDrop::new(Arc::from_raw(ptr));
+        Arc::clone(&arc)
+    }
+
+    /// Decrements the count of the `Arc` at `ptr` after all the current readers are unpinned.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` should be the count previously owned by the cell, and should be already unlinked.
+    unsafe fn defer_release(ptr: *const T, guard: &Guard) {
+        guard.defer_unchecked(move || drop(Arc::from_raw(ptr)));
+    }
+}
+
+impl<T> Drop for AtomicArc<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Arc::from_raw(self.ptr.load(Ordering::Relaxed)) });
+    }
+}
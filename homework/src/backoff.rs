@@ -0,0 +1,139 @@
+//! Exponential backoff.
+
+use core::cell::Cell;
+use core::sync::atomic;
+
+/// Spins for the first steps.
+const SPIN_LIMIT: u32 = 6;
+/// Yields for the next steps.
+const YIELD_LIMIT: u32 = 10;
+/// Then parks for up to `2^PARK_LIMIT` microseconds.
+const PARK_LIMIT: u32 = 10;
+
+/// The number of times an `ExponentialBackoff` has spun, yielded, and parked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackoffStats {
+    /// The number of spins.
+    pub spins: usize,
+    /// The number of yields.
+    pub yields: usize,
+    /// The number of parks.
+    pub parks: usize,
+}
+
+impl BackoffStats {
+    /// Returns the total number of backoffs.
+    pub fn total(&self) -> usize {
+        self.spins + self.yields + self.parks
+    }
+}
+
+/// Backoff for the retry loops of failed CASes.
+///
+/// Each `backoff` waits about twice as long as the previous one: it first spins, then yields the
+/// CPU, and finally parks the thread for a while. The waiting time is randomized between the half
+/// and the full of it, so that the threads that failed together do not retry together again.
+///
+/// Unlike `crossbeam_utils::Backoff`, it never stops growing until parking for about a
+/// millisecond, and it counts its backoffs. With the `metrics` feature, the backoffs of all the
+/// instances are also reported as the `backoff.spin`, `backoff.yield` and `backoff.park` counters,
+/// which grow quickly while a data structure is contended.
+#[derive(Debug, Default)]
+pub struct ExponentialBackoff {
+    step: Cell<u32>,
+    stats: Cell<BackoffStats>,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        use rand::{thread_rng, Rng};
+        use std::thread;
+        use std::time::Duration;
+
+        /// Returns a random number between `n / 2` and `n`.
+        fn jitter(n: u64) -> u64 {
+            thread_rng().gen_range(n / 2, n + 1)
+        }
+
+        fn yield_now() {
+            thread::yield_now();
+        }
+
+        /// Parks the thread for about `micros` microseconds.
+        fn park(micros: u64) {
+            thread::park_timeout(Duration::from_micros(jitter(micros)));
+        }
+    } else {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static JITTER: AtomicUsize = AtomicUsize::new(0);
+
+        /// Returns a random number between `n / 2` and `n`.
+        fn jitter(n: u64) -> u64 {
+            // A Weyl sequence mixed by the finalizer of MurmurHash3, which needs neither an OS nor
+            // 64-bit atomics.
+            let mut z = JITTER.fetch_add(0x9e37_79b9, Ordering::Relaxed) as u32;
+            z = (z ^ (z >> 16)).wrapping_mul(0x85eb_ca6b);
+            z = (z ^ (z >> 13)).wrapping_mul(0xc2b2_ae35);
+            z ^= z >> 16;
+            n / 2 + u64::from(z) % (n - n / 2 + 1)
+        }
+
+        /// Without an OS to yield to, spins as long as the longest spin.
+        fn yield_now() {
+            for _ in 0..1 << SPIN_LIMIT {
+                atomic::spin_loop_hint();
+            }
+        }
+
+        /// Without an OS to park in, spins 64 times per microsecond.
+        fn park(micros: u64) {
+            for _ in 0..jitter(micros) << 6 {
+                atomic::spin_loop_hint();
+            }
+        }
+    }
+}
+
+impl ExponentialBackoff {
+    /// Creates a new backoff.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resets the waiting time to the shortest, e.g. after a successful attempt.
+    pub fn reset(&self) {
+        self.step.set(0);
+    }
+
+    /// Waits after a failed attempt.
+    pub fn backoff(&self) {
+        let step = self.step.get();
+        let mut stats = self.stats.get();
+        if step < SPIN_LIMIT {
+            for _ in 0..jitter(1 << step) {
+                atomic::spin_loop_hint();
+            }
+            stats.spins += 1;
+            counter!("backoff.spin");
+        } else if step < YIELD_LIMIT {
+            yield_now();
+            stats.yields += 1;
+            counter!("backoff.yield");
+        } else {
+            let micros = 1 << (step - YIELD_LIMIT).min(PARK_LIMIT);
+            park(micros);
+            stats.parks += 1;
+            counter!("backoff.park");
+        }
+        self.stats.set(stats);
+        if step < YIELD_LIMIT + PARK_LIMIT {
+            self.step.set(step + 1);
+        }
+    }
+
+    /// Returns the backoffs of this instance.
+    pub fn stats(&self) -> BackoffStats {
+        self.stats.get()
+    }
+}
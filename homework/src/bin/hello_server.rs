@@ -1,12 +1,16 @@
-use crossbeam_channel::{bounded, unbounded};
+use cs492_concur_homework::channel::{mpsc, oneshot};
 use cs492_concur_homework::hello_server::{
     CancellableTcpListener, Handler, Statistics, ThreadPool,
 };
+use cs492_concur_homework::Semaphore;
 use std::io;
 use std::sync::Arc;
 
 const ADDR: &str = "localhost:7878";
 
+/// The maximum number of connections that are being handled or waiting to be handled.
+const MAX_CONNECTIONS: usize = 64;
+
 fn main() -> io::Result<()> {
     // Use a browser that doesn't cache too eagerly so that request is always sent. For example,
     // Firefox works well.  If you want to test using command line only, use curl. If you want to
@@ -27,10 +31,10 @@ fn main() -> io::Result<()> {
     let pool = Arc::new(ThreadPool::new(7));
 
     // The (MPSC) channel of reports between workers and the reporter.
-    let (report_sender, report_receiver) = unbounded();
+    let (report_sender, report_receiver) = mpsc::channel(MAX_CONNECTIONS);
 
     // The (SPSC one-shot) channel of stats between the reporter and the main thread.
-    let (stat_sender, stat_receiver) = bounded(0);
+    let (stat_sender, stat_receiver) = oneshot::channel();
 
     // Listens to the address.
     let listener = Arc::new(CancellableTcpListener::bind(ADDR)?);
@@ -42,6 +46,10 @@ fn main() -> io::Result<()> {
     })
     .expect("Error setting Ctrl-C handler");
 
+    // Limits the number of connections. The listener stops accepting new connections while there
+    // are too many.
+    let connection_limiter = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
     // Executes the listener.
     let listener_pool = pool.clone();
     pool.execute(move || {
@@ -49,15 +57,26 @@ fn main() -> io::Result<()> {
         let handler = Handler::default();
 
         // For each incoming connection...
-        for (id, stream) in listener.incoming().enumerate() {
+        let mut incoming = listener.incoming().enumerate();
+        loop {
+            // The permit is acquired before accepting, so that the connections beyond the limit
+            // wait in the backlog of the OS instead of in the pool.
+            let permit = connection_limiter.clone().acquire_owned();
+            let (id, stream) = match incoming.next() {
+                Some(connection) => connection,
+                None => break,
+            };
+
             // send a job to the thread pool.
             let report_sender = report_sender.clone();
             let handler = handler.clone();
             listener_pool.execute(move || {
                 let report = handler.handle_conn(id, stream.unwrap());
                 report_sender.send(report).unwrap();
+                drop(permit);
             });
         }
+        println!("[cache] {:?}", handler.cache_stats());
     });
 
     // Executes the reporter.
@@ -0,0 +1,525 @@
+//! Hammers the concurrent structures with randomized operations, e.g. under the sanitizers.
+//!
+//! ```text
+//! cargo run --release --bin stress -- [OPTIONS]
+//!
+//! --structure NAME     one of the structures below, or all (default)
+//! --threads N          the number of threads (default: the number of CPUs)
+//! --duration SECS      how long to run each structure (default: 10)
+//! --key-range N        the keys are in 0..N, which must be a power of two (default: 1024)
+//! --read-percent P     the percentage of `contains` (default: 50)
+//! --zipf THETA         draws the keys from a Zipfian distribution instead of uniformly
+//! ```
+//!
+//! Under ThreadSanitizer, with `cargo_tsan` of `scripts/grade-utils.sh`:
+//!
+//! ```text
+//! cargo_tsan run --release --bin stress -- --duration 60
+//! ```
+//!
+//! The structures are checked after the threads finish:
+//!
+//! - The sets, `list-set`, `split-ordered-list`, `radix-tree`, `hamt`, `nm-tree`,
+//!   `lock-coupling-bst`, `growable-array`, and `btree`: the size is the number of successful
+//!   insertions minus removals.
+//! - `cache`: every lookup returns the value of its key, the lookups that compute or wait for the
+//!   value are counted in the statistics, and the cache holds at most half of the key range. The
+//!   reads are `get`, and the rest is split between `get_or_insert_with` and `invalidate`.
+//! - The stacks and the queue, `elim-stack`, `bounded-stack`, and `queue`: every value that was
+//!   pushed is popped exactly once, including those left at the end. The threads push and pop
+//!   evenly, and the read percentage and the keys are not used.
+//! - `mpsc`: one thread receives every message that the others sent, exactly once.
+//! - `broadcast`: one thread sends, and each of the others receives the messages in order, except
+//!   those it missed because it lagged behind.
+//!
+//! The channels and `bounded-stack` hold at most the key range of values, and the channels run on
+//! at least two threads.
+
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::channel::{broadcast, mpsc};
+use cs492_concur_homework::hello_server::Cache;
+use cs492_concur_homework::testing::workload::{KeyDistribution, KeyGenerator, Target, Workload};
+use cs492_concur_homework::{
+    AtomicHamt, BoundedStack, ElimStack, GrowableArray, LockCouplingBst, NmTree, OrderedListSet,
+    RadixTree, SplitOrderedList, Stack,
+};
+use lockfree::Queue;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
+use std::env;
+use std::process;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const STRUCTURES: [&str; 14] = [
+    "list-set",
+    "split-ordered-list",
+    "radix-tree",
+    "hamt",
+    "nm-tree",
+    "lock-coupling-bst",
+    "growable-array",
+    "btree",
+    "cache",
+    "elim-stack",
+    "bounded-stack",
+    "queue",
+    "mpsc",
+    "broadcast",
+];
+
+/// The number of operations between the checks of the deadline.
+const BATCH: usize = 256;
+
+struct Config {
+    structure: String,
+    threads: usize,
+    duration: Duration,
+    workload: Workload,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: stress [--structure {}|all] [--threads N] [--duration SECS] [--key-range N] \
+         [--read-percent P] [--zipf THETA]",
+        STRUCTURES.join("|")
+    );
+    process::exit(2)
+}
+
+fn parse<T: FromStr>(flag: &str, value: Option<String>) -> T {
+    value.and_then(|v| v.parse().ok()).unwrap_or_else(|| {
+        eprintln!("invalid value for {}", flag);
+        usage()
+    })
+}
+
+fn parse_args() -> Config {
+    let mut config = Config {
+        structure: "all".to_string(),
+        threads: num_cpus::get(),
+        duration: Duration::from_secs(10),
+        workload: Workload {
+            name: "stress",
+            read_percent: 50,
+            key_range: 1 << 10,
+            distribution: KeyDistribution::Uniform,
+        },
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--structure" => config.structure = parse(&flag, args.next()),
+            "--threads" => config.threads = parse(&flag, args.next()),
+            "--duration" => config.duration = Duration::from_secs(parse(&flag, args.next())),
+            "--key-range" => config.workload.key_range = parse(&flag, args.next()),
+            "--read-percent" => config.workload.read_percent = parse(&flag, args.next()),
+            "--zipf" => {
+                config.workload.distribution = KeyDistribution::Zipfian(parse(&flag, args.next()))
+            }
+            "-h" | "--help" => usage(),
+            _ => {
+                eprintln!("unknown option {}", flag);
+                usage()
+            }
+        }
+    }
+
+    if config.structure != "all" && !STRUCTURES.contains(&config.structure.as_str()) {
+        eprintln!("unknown structure {}", config.structure);
+        usage()
+    }
+    if config.threads == 0
+        || !config.workload.key_range.is_power_of_two()
+        || config.workload.read_percent > 100
+    {
+        usage()
+    }
+    if let KeyDistribution::Zipfian(theta) = config.workload.distribution {
+        if !(theta > 0.0 && theta < 1.0) {
+            eprintln!("THETA should be in (0, 1)");
+            usage()
+        }
+    }
+    config
+}
+
+/// Concurrent collection of values that are pushed and popped, e.g. a stack or a queue.
+trait Collection: Sync {
+    /// Pushes `value`. Returns `false` if the collection is full.
+    fn push(&self, value: usize) -> bool;
+
+    /// Pops a value if there is one.
+    fn pop(&self) -> Option<usize>;
+}
+
+impl Collection for ElimStack<usize> {
+    fn push(&self, value: usize) -> bool {
+        Stack::push(self, value);
+        true
+    }
+
+    fn pop(&self) -> Option<usize> {
+        Stack::pop(self)
+    }
+}
+
+impl Collection for BoundedStack<usize> {
+    fn push(&self, value: usize) -> bool {
+        self.push(value).is_ok()
+    }
+
+    fn pop(&self) -> Option<usize> {
+        self.pop()
+    }
+}
+
+impl Collection for Queue<usize> {
+    fn push(&self, value: usize) -> bool {
+        self.push(value, &pin());
+        true
+    }
+
+    fn pop(&self) -> Option<usize> {
+        self.try_pop(&pin())
+    }
+}
+
+/// The number of values and their wrapping sum, which tells the lost or duplicated values apart
+/// from the delivered ones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    count: usize,
+    sum: usize,
+}
+
+impl Tally {
+    fn add(&mut self, value: usize) {
+        self.count += 1;
+        self.sum = self.sum.wrapping_add(value);
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            count: self.count + other.count,
+            sum: self.sum.wrapping_add(other.sum),
+        }
+    }
+}
+
+/// Runs `f` on each of `threads` threads until the deadline, where `f(t, deadline)` returns the
+/// number of operations and whatever the thread computed, and returns the total number of
+/// operations with the results.
+fn run<R, F>(threads: usize, config: &Config, f: F) -> (usize, Vec<R>)
+where
+    R: Send,
+    F: Fn(usize, Instant) -> (usize, R) + Sync,
+{
+    let deadline = Instant::now() + config.duration;
+    let results = scope(|s| {
+        let handles = (0..threads)
+            .map(|t| {
+                let f = &f;
+                s.spawn(move |_| f(t, deadline))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+    let ops = results.iter().map(|(ops, _)| ops).sum();
+    (ops, results.into_iter().map(|(_, r)| r).collect())
+}
+
+fn report(name: &str, ops: usize, config: &Config, size: usize, unit: &str) {
+    println!(
+        "{:>18}: {} ops, {:.3} Mops/s, {} {}",
+        name,
+        ops,
+        ops as f64 / config.duration.as_secs_f64() / 1e6,
+        size,
+        unit
+    );
+}
+
+/// Runs the randomized operations on `target` until the deadline, and checks the size of it.
+fn stress<T: Target>(name: &str, target: T, config: &Config) {
+    let workload = &config.workload;
+    workload.prefill(&target);
+    let initial = (workload.key_range + 1) / 2;
+
+    // The net number of successful insertions of each thread.
+    let (ops, deltas) = run(config.threads, config, |t, deadline| {
+        let mut handle = target.handle();
+        let mut keys = KeyGenerator::new(workload, t as u64);
+        let mut rng = StdRng::seed_from_u64(!(t as u64));
+        let (mut ops, mut delta) = (0usize, 0isize);
+        while Instant::now() < deadline {
+            for _ in 0..BATCH {
+                let key = keys.next_key();
+                let dice = rng.gen_range(0, 200);
+                if dice < workload.read_percent * 2 {
+                    let _ = target.contains(&mut handle, key);
+                } else if dice % 2 == 0 {
+                    delta += target.insert(&mut handle, key) as isize;
+                } else {
+                    delta -= target.remove(&mut handle, key) as isize;
+                }
+            }
+            ops += BATCH;
+        }
+        (ops, delta)
+    });
+
+    let mut handle = target.handle();
+    let size = (0..workload.key_range)
+        .filter(|&key| target.contains(&mut handle, key))
+        .count();
+    report(name, ops, config, size, "keys");
+    assert_eq!(
+        size as isize,
+        initial as isize + deltas.into_iter().sum::<isize>(),
+        "{}: the size doesn't match the successful operations",
+        name
+    );
+
+    // Also frees the elements of `GrowableArray`, which doesn't own them.
+    let removed = (0..workload.key_range)
+        .filter(|&key| target.remove(&mut handle, key))
+        .count();
+    assert_eq!(removed, size, "{}: the keys can't be removed", name);
+}
+
+/// Runs the lookups and invalidations on a cache that holds half of the key range, and checks the
+/// values and the statistics.
+fn stress_cache(name: &str, config: &Config) {
+    let workload = &config.workload;
+    let capacity = (workload.key_range / 2).max(1);
+    let cache = Cache::with_capacity(capacity);
+
+    // The number of `get_or_insert_with` of each thread.
+    let (ops, lookups) = run(config.threads, config, |t, deadline| {
+        let mut keys = KeyGenerator::new(workload, t as u64);
+        let mut rng = StdRng::seed_from_u64(!(t as u64));
+        let (mut ops, mut lookups) = (0usize, 0u64);
+        while Instant::now() < deadline {
+            for _ in 0..BATCH {
+                let key = keys.next_key();
+                let dice = rng.gen_range(0, 200);
+                let value = if dice < workload.read_percent * 2 {
+                    cache.get(&key)
+                } else if dice % 2 == 0 {
+                    lookups += 1;
+                    Some(cache.get_or_insert_with(key, |key| key))
+                } else {
+                    cache.invalidate(&key)
+                };
+                assert!(
+                    value.is_none() || value == Some(key),
+                    "{}: wrong value",
+                    name
+                );
+            }
+            ops += BATCH;
+        }
+        (ops, lookups)
+    });
+
+    let stats = cache.stats();
+    report(name, ops, config, cache.len(), "keys");
+    assert_eq!(
+        stats.hits + stats.misses,
+        lookups.into_iter().sum::<u64>(),
+        "{}: the statistics don't match the lookups",
+        name
+    );
+    assert!(cache.len() <= capacity, "{}: over capacity", name);
+}
+
+/// Pushes and pops the values evenly, and checks that every pushed value is popped exactly once.
+fn stress_collection<C: Collection>(name: &str, collection: C, config: &Config) {
+    let threads = config.threads;
+    let (ops, tallies) = run(threads, config, |t, deadline| {
+        let mut rng = StdRng::seed_from_u64(!(t as u64));
+        let (mut ops, mut pushed, mut popped) = (0, Tally::default(), Tally::default());
+        // The values of the thread are `t`, `t + threads`, and so on, so that they are distinct.
+        let mut next = t;
+        while Instant::now() < deadline {
+            for _ in 0..BATCH {
+                if rng.gen() {
+                    if collection.push(next) {
+                        pushed.add(next);
+                        next += threads;
+                    }
+                } else if let Some(value) = collection.pop() {
+                    popped.add(value);
+                }
+            }
+            ops += BATCH;
+        }
+        (ops, (pushed, popped))
+    });
+
+    let (pushed, mut popped) = tallies
+        .into_iter()
+        .fold(Default::default(), |(a, b): (Tally, Tally), (c, d)| {
+            (a.merge(c), b.merge(d))
+        });
+    let mut left = 0;
+    while let Some(value) = collection.pop() {
+        popped.add(value);
+        left += 1;
+    }
+    report(name, ops, config, left, "values");
+    assert_eq!(
+        pushed, popped,
+        "{}: the popped values don't match the pushed ones",
+        name
+    );
+}
+
+/// Sends messages from all the threads but one, which receives them, and checks that every
+/// message is received exactly once.
+fn stress_mpsc(name: &str, config: &Config) {
+    let (sender, receiver) = mpsc::channel(config.workload.key_range);
+    let senders = config.threads.max(2) - 1;
+    let senders = Mutex::new((0..senders).map(|_| sender.clone()).collect::<Vec<_>>());
+    drop(sender);
+    let receiver = Mutex::new(Some(receiver));
+
+    let (ops, tallies) = run(config.threads.max(2), config, |t, deadline| {
+        let mut tally = Tally::default();
+        if t == 0 {
+            // Receives until all the senders are dropped.
+            let receiver = receiver.lock().unwrap().take().unwrap();
+            for value in receiver {
+                tally.add(value);
+            }
+            return (0, (false, tally));
+        }
+        let sender = senders.lock().unwrap().pop().unwrap();
+        let mut ops = 0;
+        // The values of the thread are `t`, `t + threads`, and so on, so that they are distinct.
+        let mut next = t;
+        while Instant::now() < deadline {
+            for _ in 0..BATCH {
+                sender.send(next).unwrap();
+                tally.add(next);
+                next += config.threads.max(2);
+            }
+            ops += BATCH;
+        }
+        (ops, (true, tally))
+    });
+
+    let mut sent = Tally::default();
+    let mut received = Tally::default();
+    for (is_sender, tally) in tallies {
+        if is_sender {
+            sent = sent.merge(tally);
+        } else {
+            received = tally;
+        }
+    }
+    report(name, ops, config, received.count, "messages");
+    assert_eq!(
+        sent, received,
+        "{}: the received messages don't match the sent ones",
+        name
+    );
+}
+
+/// Sends the numbers in order from one thread to all the others, and checks that each receiver
+/// gets them in order, skipping only those it missed.
+fn stress_broadcast(name: &str, config: &Config) {
+    let (sender, receiver) = broadcast::channel(config.workload.key_range);
+    let receivers = config.threads.max(2) - 1;
+    let receivers = Mutex::new(
+        (1..receivers)
+            .map(|_| sender.subscribe())
+            .chain(Some(receiver))
+            .collect::<Vec<_>>(),
+    );
+    let sender = Mutex::new(Some(sender));
+
+    // The number of messages sent by the sender, and the next one expected by each receiver.
+    let (ops, counts) = run(config.threads.max(2), config, |t, deadline| {
+        if t == 0 {
+            let sender = sender.lock().unwrap().take().unwrap();
+            let mut next = 0u64;
+            while Instant::now() < deadline {
+                for _ in 0..BATCH {
+                    let _ = sender.send(next).unwrap();
+                    next += 1;
+                }
+            }
+            return (next as usize, next);
+        }
+        let mut receiver = receivers.lock().unwrap().pop().unwrap();
+        let mut expected = 0;
+        loop {
+            match receiver.recv() {
+                Ok(value) => {
+                    assert_eq!(value, expected, "{}: out of order", name);
+                    expected += 1;
+                }
+                Err(broadcast::RecvError::Lagged(missed)) => expected += missed,
+                Err(broadcast::RecvError::Closed) => break,
+            }
+        }
+        (0, expected)
+    });
+
+    report(name, ops, config, counts.len() - 1, "receivers");
+    for &count in &counts[1..] {
+        assert_eq!(
+            count, counts[0],
+            "{}: the received messages don't match the sent ones",
+            name
+        );
+    }
+}
+
+fn main() {
+    let config = parse_args();
+    println!(
+        "{} threads, {:?} each, keys in 0..{}, {}% reads, {:?}",
+        config.threads,
+        config.duration,
+        config.workload.key_range,
+        config.workload.read_percent,
+        config.workload.distribution
+    );
+
+    for &structure in STRUCTURES.iter() {
+        if config.structure != "all" && config.structure != structure {
+            continue;
+        }
+        match structure {
+            "list-set" => stress(structure, OrderedListSet::<usize>::new(), &config),
+            "split-ordered-list" => stress(structure, SplitOrderedList::<()>::new(), &config),
+            "radix-tree" => stress(structure, RadixTree::<()>::new(), &config),
+            "hamt" => stress(structure, AtomicHamt::<usize, ()>::new(), &config),
+            "nm-tree" => stress(structure, NmTree::<usize, ()>::new(), &config),
+            "lock-coupling-bst" => stress(structure, LockCouplingBst::<usize, ()>::new(), &config),
+            "growable-array" => stress(structure, GrowableArray::<usize>::new(), &config),
+            "btree" => stress(structure, Mutex::new(BTreeSet::<usize>::new()), &config),
+            "cache" => stress_cache(structure, &config),
+            "elim-stack" => stress_collection(structure, ElimStack::<usize>::default(), &config),
+            "bounded-stack" => {
+                let stack = BoundedStack::<usize>::new(config.workload.key_range);
+                stress_collection(structure, stack, &config)
+            }
+            "queue" => stress_collection(structure, Queue::<usize>::new(), &config),
+            "mpsc" => stress_mpsc(structure, &config),
+            "broadcast" => stress_broadcast(structure, &config),
+            _ => unreachable!(),
+        }
+    }
+}
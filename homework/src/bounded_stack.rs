@@ -0,0 +1,173 @@
+//! Bounded array-based stack.
+
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use crossbeam_utils::CachePadded;
+
+use crate::backoff::ExponentialBackoff;
+
+/// The slot is empty.
+const FREE: u8 = 0;
+/// A push is writing the value.
+const WRITING: u8 = 1;
+/// The slot holds a value.
+const FULL: u8 = 2;
+/// A pop is reading the value.
+const READING: u8 = 3;
+
+struct Slot<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// `top` in the lower half, and `version` in the upper half.
+fn pack(top: u32, version: u32) -> u64 {
+    u64::from(top) | (u64::from(version) << 32)
+}
+
+fn unpack(data: u64) -> (u32, u32) {
+    (data as u32, (data >> 32) as u32)
+}
+
+/// Fixed-capacity concurrent stack on an array.
+///
+/// The top index is packed with a version in a single word, which is incremented by every push
+/// and pop so that a CAS with a stale top fails even if the index is the same. A push (pop) first
+/// claims the slot above (at) the top by changing its state, and then moves the top with a CAS.
+/// If either fails because of contention, it backs off exponentially and retries.
+///
+/// Unlike `ElimStack`, nothing is allocated after the stack is created. But it is not lock-free:
+/// a thread preempted between the two steps delays the others that need the same slot.
+pub struct BoundedStack<T> {
+    top: CachePadded<AtomicU64>,
+    slots: Box<[Slot<T>]>,
+}
+
+unsafe impl<T: Send> Send for BoundedStack<T> {}
+unsafe impl<T: Send> Sync for BoundedStack<T> {}
+
+impl<T> fmt::Debug for BoundedStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedStack")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+impl<T> BoundedStack<T> {
+    /// Creates a new stack that holds at most `capacity` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` does not fit in `u32`.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity <= u32::MAX as usize, "capacity should fit in u32");
+        Self {
+            top: CachePadded::new(AtomicU64::new(pack(0, 0))),
+            slots: (0..capacity)
+                .map(|_| Slot {
+                    state: AtomicU8::new(FREE),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the capacity.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of values in the stack.
+    pub fn len(&self) -> usize {
+        unpack(self.top.load(Ordering::Acquire)).0 as usize
+    }
+
+    /// Returns `true` if the stack is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pushes a value. Returns it back if the stack is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let current = self.top.load(Ordering::Acquire);
+            let (top, version) = unpack(current);
+            let slot = some_or!(self.slots.get(top as usize), return Err(value));
+
+            if slot
+                .state
+                .compare_exchange(FREE, WRITING, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                if self
+                    .top
+                    .compare_exchange(
+                        current,
+                        pack(top + 1, version.wrapping_add(1)),
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+                {
+                    unsafe { (*slot.value.get()).as_mut_ptr().write(value) };
+                    slot.state.store(FULL, Ordering::Release);
+                    return Ok(());
+                }
+                slot.state.store(FREE, Ordering::Release);
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Pops a value. Returns `None` if the stack is empty.
+    pub fn pop(&self) -> Option<T> {
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let current = self.top.load(Ordering::Acquire);
+            let (top, version) = unpack(current);
+            if top == 0 {
+                return None;
+            }
+            let slot = &self.slots[top as usize - 1];
+
+            if slot
+                .state
+                .compare_exchange(FULL, READING, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                if self
+                    .top
+                    .compare_exchange(
+                        current,
+                        pack(top - 1, version.wrapping_add(1)),
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+                {
+                    let value = unsafe { ptr::read((*slot.value.get()).as_ptr()) };
+                    slot.state.store(FREE, Ordering::Release);
+                    return Some(value);
+                }
+                slot.state.store(FULL, Ordering::Release);
+            }
+            backoff.backoff();
+        }
+    }
+}
+
+impl<T> Drop for BoundedStack<T> {
+    fn drop(&mut self) {
+        let (top, _) = unpack(*self.top.get_mut());
+        for slot in self.slots[..top as usize].iter_mut() {
+            unsafe { ptr::drop_in_place((*slot.value.get()).as_mut_ptr()) };
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Bounded broadcast channel where every receiver sees every message.
+//!
+//! Messages are stored in a ring buffer. Each receiver has its own cursor into the buffer, and each
+//! slot counts the receivers that have not read it yet so that the message is dropped as soon as
+//! the last receiver reads it.
+//!
+//! When the buffer is full because some receiver is slow, the channel follows its `LagPolicy`:
+//! either the oldest message is overwritten and the slow receiver is notified of how many messages
+//! it missed, or the sender blocks until the slow receiver catches up.
+
+use core::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+
+use super::select::{Sealed, Waiters};
+
+/// What to do when a message is sent while the buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Overwrite the oldest message. The receivers that haven't read it get `Lagged`.
+    Overwrite,
+    /// Block the sender until all the receivers read the oldest message.
+    Block,
+}
+
+/// Error returned by `Sender::send` when there are no receivers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Error returned by `Receiver::recv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The receiver missed the given number of messages, which were overwritten. The next `recv`
+    /// returns the oldest message in the buffer.
+    Lagged(u64),
+    /// All the senders are dropped and there are no more messages.
+    Closed,
+}
+
+/// Error returned by `Receiver::try_recv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// There are no messages yet.
+    Empty,
+    /// The receiver missed the given number of messages, which were overwritten.
+    Lagged(u64),
+    /// All the senders are dropped and there are no more messages.
+    Closed,
+}
+
+struct Slot<T> {
+    /// The position of the message. The message at position `pos` is stored at `pos % capacity`.
+    pos: u64,
+    /// The number of receivers that have not read the message.
+    remaining: usize,
+    /// `None` if the slot is empty or all the receivers read the message.
+    value: Option<T>,
+}
+
+struct Inner<T> {
+    buffer: Box<[Slot<T>]>,
+    /// The position of the next message.
+    head: u64,
+    num_senders: usize,
+    num_receivers: usize,
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    policy: LagPolicy,
+    /// Notified when a message is sent or the senders are dropped.
+    recv_cvar: Condvar,
+    /// Notified when a slot is released by the receivers.
+    send_cvar: Condvar,
+    /// The `Select`s waiting on the receivers.
+    selectors: Waiters,
+}
+
+/// The sending half of a broadcast channel.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// The receiving half of a broadcast channel.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    /// The position of the next message to receive.
+    cursor: u64,
+}
+
+/// Creates a broadcast channel with the given capacity that overwrites the oldest message when
+/// full. Panics if the capacity is 0.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with_policy(capacity, LagPolicy::Overwrite)
+}
+
+/// Creates a broadcast channel with the given capacity and lag policy. Panics if the capacity is
+/// 0.
+pub fn channel_with_policy<T: Clone>(
+    capacity: usize,
+    policy: LagPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0);
+    let buffer = (0..capacity)
+        .map(|_| Slot {
+            pos: 0,
+            remaining: 0,
+            value: None,
+        })
+        .collect();
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            buffer,
+            head: 0,
+            num_senders: 1,
+            num_receivers: 1,
+        }),
+        policy,
+        recv_cvar: Condvar::new(),
+        send_cvar: Condvar::new(),
+        selectors: Waiters::default(),
+    });
+    let receiver = Receiver {
+        shared: shared.clone(),
+        cursor: 0,
+    };
+    (Sender { shared }, receiver)
+}
+
+impl<T> Inner<T> {
+    fn capacity(&self) -> u64 {
+        self.buffer.len() as u64
+    }
+
+    fn slot(&mut self, pos: u64) -> &mut Slot<T> {
+        let index = (pos % self.capacity()) as usize;
+        &mut self.buffer[index]
+    }
+
+    /// Marks the messages in `from..head` as read by a receiver.
+    fn release(&mut self, from: u64) {
+        let oldest = self.head.saturating_sub(self.capacity());
+        for pos in from.max(oldest)..self.head {
+            let slot = self.slot(pos);
+            slot.remaining -= 1;
+            if slot.remaining == 0 {
+                slot.value = None;
+            }
+        }
+    }
+}
+
+impl<T: Clone> Shared<T> {
+    /// Receives the message at `cursor` and advances it.
+    fn try_recv(&self, inner: &mut Inner<T>, cursor: &mut u64) -> Result<T, TryRecvError> {
+        if *cursor == inner.head {
+            return Err(if inner.num_senders == 0 {
+                TryRecvError::Closed
+            } else {
+                TryRecvError::Empty
+            });
+        }
+
+        let oldest = inner.head.saturating_sub(inner.capacity());
+        if *cursor < oldest {
+            let missed = oldest - *cursor;
+            *cursor = oldest;
+            return Err(TryRecvError::Lagged(missed));
+        }
+
+        let slot = inner.slot(*cursor);
+        debug_assert_eq!(slot.pos, *cursor);
+        slot.remaining -= 1;
+        let value = if slot.remaining == 0 {
+            let value = slot.value.take().unwrap();
+            self.send_cvar.notify_all();
+            value
+        } else {
+            slot.value.clone().unwrap()
+        };
+        *cursor += 1;
+        Ok(value)
+    }
+}
+
+impl<T: Clone> Sender<T> {
+    /// Sends a message to all the current receivers, and returns the number of them.
+    ///
+    /// Returns `Err` if there are no receivers. If the buffer is full, overwrites the oldest
+    /// message or blocks, depending on the lag policy.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        if self.shared.policy == LagPolicy::Block {
+            loop {
+                let head = inner.head;
+                if inner.num_receivers == 0 || inner.slot(head).remaining == 0 {
+                    break;
+                }
+                inner = self.shared.send_cvar.wait(inner).unwrap();
+            }
+        }
+
+        let num_receivers = inner.num_receivers;
+        if num_receivers == 0 {
+            return Err(SendError(value));
+        }
+
+        let head = inner.head;
+        *inner.slot(head) = Slot {
+            pos: head,
+            remaining: num_receivers,
+            value: Some(value),
+        };
+        inner.head += 1;
+        drop(inner);
+
+        self.shared.recv_cvar.notify_all();
+        self.shared.selectors.notify();
+        Ok(num_receivers)
+    }
+
+    /// Creates a new receiver that receives the messages sent after this call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        inner.num_receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            cursor: inner.head,
+        }
+    }
+
+    /// Returns the number of receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.inner.lock().unwrap().num_receivers
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Receives the next message, blocking until one is sent.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        loop {
+            match self.shared.try_recv(&mut inner, &mut self.cursor) {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Lagged(n)) => return Err(RecvError::Lagged(n)),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+            }
+            inner = self.shared.recv_cvar.wait(inner).unwrap();
+        }
+    }
+
+    /// Receives the next message if there is one.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        self.shared.try_recv(&mut inner, &mut self.cursor)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.inner.lock().unwrap().num_senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.inner.lock().unwrap();
+        inner.num_senders -= 1;
+        if inner.num_senders == 0 {
+            self.shared.recv_cvar.notify_all();
+            drop(inner);
+            self.shared.selectors.notify();
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    /// Creates a new receiver at the same position as this one.
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.inner.lock().unwrap();
+        inner.num_receivers += 1;
+        let oldest = inner.head.saturating_sub(inner.capacity());
+        for pos in self.cursor.max(oldest)..inner.head {
+            inner.slot(pos).remaining += 1;
+        }
+        Self {
+            shared: self.shared.clone(),
+            cursor: self.cursor,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.inner.lock().unwrap();
+        inner.num_receivers -= 1;
+        inner.release(self.cursor);
+        self.shared.send_cvar.notify_all();
+    }
+}
+
+impl<T> Sealed for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        let inner = self.shared.inner.lock().unwrap();
+        self.cursor != inner.head || inner.num_senders == 0
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.shared.selectors
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sender { .. }")
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("cursor", &self.cursor)
+            .finish()
+    }
+}
@@ -0,0 +1,10 @@
+//! Channels.
+
+pub mod broadcast;
+pub mod mpsc;
+pub mod oneshot;
+mod select;
+mod synchronous_queue;
+
+pub use select::{Select, Selectable};
+pub use synchronous_queue::SynchronousQueue;
@@ -0,0 +1,574 @@
+//! Bounded multi-producer single-consumer channel.
+//!
+//! Messages are stored in a linked list of blocks, each of which is a small ring of slots. A sender
+//! claims a slot by incrementing the tail index, writes the message and marks the slot as ready.
+//! The sender that claims the last slot of a block allocates the next block and links it. The
+//! receiver reads the slots in order, and frees a block as soon as it has read all the slots in it.
+//!
+//! The capacity is enforced by a separate counter of the messages in the channel. Blocking senders
+//! and the blocking receiver wait on condition variables, which are notified only when somebody is
+//! waiting.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use crossbeam_utils::CachePadded;
+use std::time::{Duration, Instant};
+
+use super::select::{Sealed, Waiters};
+use crate::mock::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use crate::mock::sync::{Arc, Condvar, Mutex};
+use crate::mock::{self, fence, Backoff};
+
+/// The number of indices per block. The last index of each block doesn't correspond to a slot, and
+/// the tail index stays there while the next block is being installed.
+const LAP: usize = 32;
+/// The number of slots in a block.
+const BLOCK_CAP: usize = LAP - 1;
+
+/// Error returned by `Sender::send` when the receiver is dropped.
+#[derive(PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Error returned by `Sender::try_send`.
+#[derive(PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is full.
+    Full(T),
+    /// The receiver is dropped.
+    Disconnected(T),
+}
+
+/// Error returned by `Receiver::recv` when all the senders are dropped and the channel is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+/// Error returned by `Receiver::try_recv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The channel is empty.
+    Empty,
+    /// All the senders are dropped and the channel is empty.
+    Disconnected,
+}
+
+/// Error returned by `Receiver::recv_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    /// No message is sent before the timeout.
+    Timeout,
+    /// All the senders are dropped and the channel is empty.
+    Disconnected,
+}
+
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    /// Set by the sender after writing the value.
+    ready: AtomicBool,
+}
+
+struct Block<T> {
+    slots: [Slot<T>; BLOCK_CAP],
+    next: AtomicPtr<Block<T>>,
+}
+
+impl<T> Block<T> {
+    fn new() -> Box<Self> {
+        // Loom's atomics are not valid when zeroed, so the slots are initialized one by one.
+        let mut slots: [MaybeUninit<Slot<T>>; BLOCK_CAP] =
+            unsafe { MaybeUninit::uninit().assume_init() };
+        for slot in slots.iter_mut() {
+            *slot = MaybeUninit::new(Slot {
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+                ready: AtomicBool::new(false),
+            });
+        }
+        Box::new(Self {
+            slots: unsafe { ptr::read(slots.as_ptr() as *const [Slot<T>; BLOCK_CAP]) },
+            next: AtomicPtr::new(ptr::null_mut()),
+        })
+    }
+
+    /// Waits until the next block is linked, and returns it.
+    fn wait_next(&self) -> *mut Self {
+        let backoff = Backoff::new();
+        loop {
+            let next = self.next.load(Ordering::Acquire);
+            if !next.is_null() {
+                return next;
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+/// The position of the receiver. Only accessed by the receiver, and by `drop`.
+struct Head<T> {
+    index: usize,
+    block: *mut Block<T>,
+}
+
+struct Channel<T> {
+    /// The index of the next slot to be claimed by a sender.
+    tail: CachePadded<AtomicUsize>,
+    /// The block containing the tail index.
+    tail_block: AtomicPtr<Block<T>>,
+    head: CachePadded<UnsafeCell<Head<T>>>,
+
+    cap: usize,
+    /// The number of messages that are claimed by senders and not yet received.
+    len: AtomicUsize,
+
+    num_senders: AtomicUsize,
+    receiver_dropped: AtomicBool,
+
+    lock: Mutex<()>,
+    /// The number of senders waiting on `not_full`.
+    waiting_senders: AtomicUsize,
+    not_full: Condvar,
+    /// Whether the receiver is waiting on `not_empty`.
+    receiver_waiting: AtomicBool,
+    not_empty: Condvar,
+    /// The `Select`s waiting on the receiver.
+    selectors: Waiters,
+}
+
+unsafe impl<T: Send> Send for Channel<T> {}
+unsafe impl<T: Send> Sync for Channel<T> {}
+
+/// The sending half of a channel.
+pub struct Sender<T> {
+    chan: Arc<Channel<T>>,
+}
+
+/// The receiving half of a channel.
+pub struct Receiver<T> {
+    chan: Arc<Channel<T>>,
+    /// There is only one receiver, which is not shared between threads.
+    _marker: PhantomData<Cell<()>>,
+}
+
+/// Creates a channel that holds at most `cap` messages. Panics if `cap` is 0.
+pub fn channel<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(cap > 0);
+    let block = Box::into_raw(Block::new());
+    let chan = Arc::new(Channel {
+        tail: CachePadded::new(AtomicUsize::new(0)),
+        tail_block: AtomicPtr::new(block),
+        head: CachePadded::new(UnsafeCell::new(Head { index: 0, block })),
+        cap,
+        len: AtomicUsize::new(0),
+        num_senders: AtomicUsize::new(1),
+        receiver_dropped: AtomicBool::new(false),
+        lock: Mutex::new(()),
+        waiting_senders: AtomicUsize::new(0),
+        not_full: Condvar::new(),
+        receiver_waiting: AtomicBool::new(false),
+        not_empty: Condvar::new(),
+        selectors: Waiters::default(),
+    });
+    let receiver = Receiver {
+        chan: chan.clone(),
+        _marker: PhantomData,
+    };
+    (Sender { chan }, receiver)
+}
+
+impl<T> Channel<T> {
+    /// Reserves room for a message. Returns `false` if the channel is full.
+    fn try_reserve(&self) -> bool {
+        let mut len = self.len.load(Ordering::Relaxed);
+        loop {
+            if len == self.cap {
+                return false;
+            }
+            match self
+                .len
+                .compare_exchange_weak(len, len + 1, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return true,
+                Err(l) => len = l,
+            }
+        }
+    }
+
+    /// Writes a message to the slot at the tail. The caller should have reserved room for it.
+    fn push(&self, value: T) {
+        let backoff = Backoff::new();
+        let mut next_block = None;
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            let block = self.tail_block.load(Ordering::Acquire);
+            let offset = tail % LAP;
+
+            // Another sender is installing the next block.
+            if offset == BLOCK_CAP {
+                backoff.snooze();
+                continue;
+            }
+
+            // Allocate the next block before claiming the last slot, so that the other senders
+            // wait for it only briefly.
+            if offset + 1 == BLOCK_CAP && next_block.is_none() {
+                next_block = Some(Block::new());
+            }
+
+            if self
+                .tail
+                .compare_exchange_weak(tail, tail + 1, Ordering::SeqCst, Ordering::Acquire)
+                .is_err()
+            {
+                backoff.spin();
+                continue;
+            }
+
+            unsafe {
+                if offset + 1 == BLOCK_CAP {
+                    // The block must be stored before the index so that a sender that sees the new
+                    // index also sees the new block.
+                    let next_block = Box::into_raw(next_block.unwrap());
+                    self.tail_block.store(next_block, Ordering::Release);
+                    self.tail.store(tail + 2, Ordering::Release);
+                    (*block).next.store(next_block, Ordering::Release);
+                }
+
+                let slot = (*block).slots.get_unchecked(offset);
+                slot.value.get().write(MaybeUninit::new(value));
+                slot.ready.store(true, Ordering::Release);
+            }
+            return;
+        }
+    }
+
+    /// Reads the message at the head if it is ready. Also returns whether a sender may be waiting
+    /// for room, which the caller should wake up with `notify_sender`.
+    ///
+    /// # Safety
+    ///
+    /// Only the receiver may call this function.
+    unsafe fn pop(&self) -> Option<(T, bool)> {
+        let head = &mut *self.head.get();
+        let offset = head.index % LAP;
+        let slot = (*head.block).slots.get_unchecked(offset);
+        if !slot.ready.load(Ordering::Acquire) {
+            return None;
+        }
+        let value = slot.value.get().read().assume_init();
+
+        if offset + 1 == BLOCK_CAP {
+            // All the slots of the block are read, and the senders don't touch the block anymore.
+            let next = (*head.block).wait_next();
+            drop(Box::from_raw(head.block));
+            head.block = next;
+            head.index += 2;
+        } else {
+            head.index += 1;
+        }
+
+        let _ = self.len.fetch_sub(1, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        Some((value, self.waiting_senders.load(Ordering::Relaxed) > 0))
+    }
+
+    /// Wakes up a sender waiting for room. The caller must not hold `lock`.
+    fn notify_sender(&self) {
+        drop(self.lock.lock().unwrap());
+        self.not_full.notify_one();
+    }
+
+    /// Returns `true` if the message at the head is ready.
+    ///
+    /// # Safety
+    ///
+    /// Only the receiver may call this function.
+    unsafe fn peek(&self) -> bool {
+        let head = &*self.head.get();
+        let slot = (*head.block).slots.get_unchecked(head.index % LAP);
+        slot.ready.load(Ordering::Acquire)
+    }
+
+    /// Wakes up the receiver if it is waiting.
+    fn notify_receiver(&self) {
+        fence(Ordering::SeqCst);
+        if self.receiver_waiting.load(Ordering::Relaxed) {
+            drop(self.lock.lock().unwrap());
+            self.not_empty.notify_one();
+        }
+        self.selectors.notify();
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.num_senders.load(Ordering::Acquire) == 0
+    }
+}
+
+impl<T> Drop for Channel<T> {
+    fn drop(&mut self) {
+        // All the senders are dropped, so all the claimed slots are ready.
+        //
+        // NOTE: loom's atomics have no `get_mut`, so they are read by loads, which see the last
+        // values here anyway.
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.get_mut();
+        while head.index != tail {
+            let offset = head.index % LAP;
+            unsafe {
+                if offset == BLOCK_CAP {
+                    let next = (*head.block).next.load(Ordering::Relaxed);
+                    drop(Box::from_raw(head.block));
+                    head.block = next;
+                } else {
+                    let slot = (*head.block).slots.get_unchecked(offset);
+                    drop(slot.value.get().read().assume_init());
+                }
+            }
+            head.index += 1;
+        }
+        drop(unsafe { Box::from_raw(head.block) });
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends a message, blocking while the channel is full. Returns `Err` if the receiver is
+    /// dropped.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self.try_send(value) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Disconnected(value)) => Err(SendError(value)),
+            Err(TrySendError::Full(value)) => {
+                let chan = &*self.chan;
+                let mut lock = chan.lock.lock().unwrap();
+                let _ = chan.waiting_senders.fetch_add(1, Ordering::Relaxed);
+                fence(Ordering::SeqCst);
+                let result = loop {
+                    if chan.receiver_dropped.load(Ordering::Relaxed) {
+                        break Err(SendError(value));
+                    }
+                    if chan.try_reserve() {
+                        break Ok(value);
+                    }
+                    lock = chan.not_full.wait(lock).unwrap();
+                };
+                let _ = chan.waiting_senders.fetch_sub(1, Ordering::Relaxed);
+                drop(lock);
+
+                let value = result?;
+                chan.push(value);
+                chan.notify_receiver();
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends a message if the channel is not full.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let chan = &*self.chan;
+        if chan.receiver_dropped.load(Ordering::Relaxed) {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if !chan.try_reserve() {
+            return Err(TrySendError::Full(value));
+        }
+        chan.push(value);
+        chan.notify_receiver();
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let _ = self.chan.num_senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.chan.num_senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // The receiver may be waiting for a message that will never come.
+            drop(self.chan.lock.lock().unwrap());
+            self.chan.not_empty.notify_one();
+            self.chan.selectors.notify();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives a message if there is one.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let (value, sender_waiting) = self.try_pop()?;
+        if sender_waiting {
+            self.chan.notify_sender();
+        }
+        Ok(value)
+    }
+
+    /// Like `try_recv`, but returns whether a sender may be waiting for room instead of waking it
+    /// up, so that the caller holding `lock` can wake it up after releasing it.
+    fn try_pop(&self) -> Result<(T, bool), TryRecvError> {
+        if let Some(popped) = unsafe { self.chan.pop() } {
+            return Ok(popped);
+        }
+        if !self.chan.is_disconnected() {
+            return Err(TryRecvError::Empty);
+        }
+        // A message may have been sent right before the last sender was dropped.
+        unsafe { self.chan.pop() }.ok_or(TryRecvError::Disconnected)
+    }
+
+    /// Receives a message, blocking until one is sent. Returns `Err` if all the senders are
+    /// dropped and the channel is empty.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_deadline(None).map_err(|_| RecvError)
+    }
+
+    /// Receives a message, blocking until one is sent or the timeout expires.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline(Some(Instant::now() + timeout))
+    }
+
+    /// Returns an iterator that receives messages until all the senders are dropped.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { receiver: self }
+    }
+
+    fn recv_deadline(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        // A sender may be in the middle of writing the message, so spin for a while.
+        let backoff = Backoff::new();
+        while !backoff.is_completed() {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => backoff.snooze(),
+            }
+        }
+
+        let chan = &*self.chan;
+        let mut lock = chan.lock.lock().unwrap();
+        chan.receiver_waiting.store(true, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        let result = loop {
+            match self.try_pop() {
+                Ok(popped) => break Ok(popped),
+                Err(TryRecvError::Disconnected) => break Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            match deadline {
+                None => lock = chan.not_empty.wait(lock).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break Err(RecvTimeoutError::Timeout);
+                    }
+                    lock = mock::wait_timeout(&chan.not_empty, lock, deadline - now);
+                }
+            }
+        };
+        chan.receiver_waiting.store(false, Ordering::Relaxed);
+        drop(lock);
+
+        let (value, sender_waiting) = result?;
+        if sender_waiting {
+            chan.notify_sender();
+        }
+        Ok(value)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.receiver_dropped.store(true, Ordering::Relaxed);
+        // Wake up the blocked senders. The messages are dropped with the channel.
+        drop(self.chan.lock.lock().unwrap());
+        self.chan.not_full.notify_all();
+    }
+}
+
+impl<T> Sealed for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        let ready = unsafe { self.chan.peek() };
+        ready || self.chan.is_disconnected()
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.chan.selectors
+    }
+}
+
+/// Iterator over the messages of a receiver.
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    receiver: &'a Receiver<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Owning iterator over the messages of a receiver.
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { receiver: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+// The messages need not be `Debug`, e.g. the jobs of a thread pool.
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SendError(..)")
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("Full(..)"),
+            TrySendError::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sender { .. }")
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Receiver { .. }")
+    }
+}
@@ -0,0 +1,208 @@
+//! Channel for sending a single value.
+//!
+//! The channel has no lock. The sender writes the value and then sets the `SENT` bit of the state,
+//! and the receiver reads the value only after it sees the bit. A blocked receiver sets the
+//! `WAITING` bit before waiting on the wake-up event, so that the sender knows to set it.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use std::time::{Duration, Instant};
+
+use super::select::{Sealed, Waiters};
+use crate::mock::sync::atomic::{AtomicUsize, Ordering};
+use crate::mock::sync::Arc;
+use crate::mock::Event;
+
+/// The value is sent.
+const SENT: usize = 0x1;
+/// The receiver is waiting on `wakeup`.
+const WAITING: usize = 0x2;
+/// The sender is dropped.
+const TX_CLOSED: usize = 0x4;
+/// The receiver is dropped.
+const RX_CLOSED: usize = 0x8;
+
+/// Error returned by `Receiver::recv` when the sender is dropped without sending a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+/// Error returned by `Receiver::try_recv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The value is not sent yet.
+    Empty,
+    /// The sender is dropped without sending a value, or the value is already received.
+    Closed,
+}
+
+/// Error returned by `Receiver::recv_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    /// The value is not sent before the timeout.
+    Timeout,
+    /// The sender is dropped without sending a value, or the value is already received.
+    Closed,
+}
+
+struct Inner<T> {
+    state: AtomicUsize,
+    /// Written by the sender before setting `SENT`.
+    value: UnsafeCell<Option<T>>,
+    /// Set by the sender to wake up the waiting receiver.
+    wakeup: Event,
+    /// The `Select`s waiting on the receiver.
+    selectors: Waiters,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+/// The sending half of a oneshot channel.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving half of a oneshot channel.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Creates a oneshot channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        state: AtomicUsize::new(0),
+        value: UnsafeCell::new(None),
+        wakeup: Event::new(),
+        selectors: Waiters::default(),
+    });
+    let receiver = Receiver {
+        inner: inner.clone(),
+    };
+    (Sender { inner }, receiver)
+}
+
+impl<T> Sender<T> {
+    /// Sends the value. Returns `Err(value)` if the receiver is dropped.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let inner = &*self.inner;
+        if inner.state.load(Ordering::Relaxed) & RX_CLOSED != 0 {
+            return Err(value);
+        }
+
+        unsafe { *inner.value.get() = Some(value) };
+        let state = inner.state.fetch_or(SENT, Ordering::AcqRel);
+        if state & RX_CLOSED != 0 {
+            // The receiver won't read the value, so take it back.
+            return Err(unsafe { (*inner.value.get()).take().unwrap() });
+        }
+        if state & WAITING != 0 {
+            inner.wakeup.set();
+        }
+        inner.selectors.notify();
+        Ok(())
+    }
+
+    /// Returns `true` if the receiver is dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.state.load(Ordering::Relaxed) & RX_CLOSED != 0
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let state = self.inner.state.fetch_or(TX_CLOSED, Ordering::AcqRel);
+        if state & (SENT | WAITING) == WAITING {
+            self.inner.wakeup.set();
+        }
+        if state & SENT == 0 {
+            self.inner.selectors.notify();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Blocks until the value is sent and returns it. Returns `Err` if the sender is dropped
+    /// without sending a value.
+    pub fn recv(mut self) -> Result<T, RecvError> {
+        self.recv_deadline(None).map_err(|_| RecvError)
+    }
+
+    /// Blocks until the value is sent or the timeout expires.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline(Some(Instant::now() + timeout))
+    }
+
+    /// Returns the value if it is sent.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let state = self.inner.state.load(Ordering::Acquire);
+        if state & SENT != 0 {
+            unsafe { (*self.inner.value.get()).take() }.ok_or(TryRecvError::Closed)
+        } else if state & TX_CLOSED != 0 {
+            Err(TryRecvError::Closed)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    fn recv_deadline(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Closed) => return Err(RecvTimeoutError::Closed),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let timeout = match deadline {
+                None => None,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    Some(deadline - now)
+                }
+            };
+
+            // The sender sets `SENT` or `TX_CLOSED` only once, so the event is set only once, and
+            // after that, the next `try_recv` returns without waiting again.
+            let state = self.inner.state.fetch_or(WAITING, Ordering::AcqRel);
+            if state & (SENT | TX_CLOSED) == 0 {
+                match timeout {
+                    None => self.inner.wakeup.wait(),
+                    Some(timeout) => {
+                        let _ = self.inner.wakeup.wait_timeout(timeout);
+                    }
+                }
+            }
+            let _ = self.inner.state.fetch_and(!WAITING, Ordering::AcqRel);
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let _ = self.inner.state.fetch_or(RX_CLOSED, Ordering::AcqRel);
+    }
+}
+
+impl<T> Sealed for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        self.inner.state.load(Ordering::Acquire) & (SENT | TX_CLOSED) != 0
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.inner.selectors
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sender { .. }")
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Receiver { .. }")
+    }
+}
@@ -0,0 +1,186 @@
+//! Waiting on multiple receivers at once.
+//!
+//! A blocked `Select` registers a signal with each of its receivers. A channel fires all the
+//! registered signals when a message is sent or when the senders are dropped, and the woken
+//! `Select` unregisters its signal and checks the receivers again.
+
+use core::fmt;
+use rand::{thread_rng, Rng};
+use std::time::{Duration, Instant};
+
+use crate::mock::sync::atomic::{AtomicUsize, Ordering};
+use crate::mock::sync::{Arc, Mutex};
+use crate::mock::{fence, Event};
+
+/// A thread blocked in `Select`.
+#[derive(Debug, Default)]
+pub struct Signal {
+    fired: Event,
+}
+
+impl Signal {
+    fn fire(&self) {
+        self.fired.set();
+    }
+}
+
+/// The signals registered with a channel.
+#[derive(Debug, Default)]
+pub struct Waiters {
+    signals: Mutex<Vec<Arc<Signal>>>,
+    /// The number of signals, which lets `notify` skip the lock when nobody is selecting.
+    len: AtomicUsize,
+}
+
+impl Waiters {
+    fn register(&self, signal: &Arc<Signal>) {
+        let mut signals = self.signals.lock().unwrap();
+        signals.push(signal.clone());
+        let _ = self.len.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn unregister(&self, signal: &Arc<Signal>) {
+        let mut signals = self.signals.lock().unwrap();
+        if let Some(i) = signals.iter().position(|s| Arc::ptr_eq(s, signal)) {
+            let _ = signals.swap_remove(i);
+            let _ = self.len.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Fires all the registered signals. Should be called after the state change that makes a
+    /// receiver ready is published.
+    pub fn notify(&self) {
+        fence(Ordering::SeqCst);
+        if self.len.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        for signal in self.signals.lock().unwrap().iter() {
+            signal.fire();
+        }
+    }
+}
+
+/// The methods of `Selectable`, hidden from the users of the crate.
+pub trait Sealed {
+    /// Returns `true` if receiving from the receiver doesn't block, i.e. there is a message or the
+    /// senders are dropped.
+    fn is_ready(&self) -> bool;
+
+    /// Returns the signals registered with the channel of the receiver.
+    fn waiters(&self) -> &Waiters;
+}
+
+/// Receiver that can be used with `Select`.
+pub trait Selectable: Sealed {}
+
+impl<T: Sealed + ?Sized> Selectable for T {}
+
+/// Waits until one of multiple receivers becomes ready.
+///
+/// ```
+/// use cs492_concur_homework::channel::{mpsc, oneshot, Select};
+///
+/// let (job_sender, jobs) = mpsc::channel::<i32>(16);
+/// let (shutdown_sender, shutdown) = oneshot::channel::<()>();
+/// shutdown_sender.send(()).unwrap();
+///
+/// let mut sel = Select::new();
+/// let _job = sel.recv(&jobs);
+/// let stop = sel.recv(&shutdown);
+/// assert_eq!(sel.ready(), stop);
+/// ```
+///
+/// `ready` returns the index of a ready receiver, and the caller should receive from it.
+/// When multiple receivers are ready, one of them is chosen at random so that no receiver starves.
+#[derive(Default)]
+pub struct Select<'a> {
+    receivers: Vec<&'a dyn Selectable>,
+}
+
+impl fmt::Debug for Select<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select")
+            .field("len", &self.receivers.len())
+            .finish()
+    }
+}
+
+impl<'a> Select<'a> {
+    /// Creates an empty `Select`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a receiver, and returns its index.
+    pub fn recv<R: Selectable>(&mut self, receiver: &'a R) -> usize {
+        self.receivers.push(receiver);
+        self.receivers.len() - 1
+    }
+
+    /// Returns the index of a ready receiver if there is one.
+    pub fn try_ready(&self) -> Option<usize> {
+        let len = self.receivers.len();
+        if len == 0 {
+            return None;
+        }
+        let start = thread_rng().gen_range(0, len);
+        (0..len)
+            .map(|i| (start + i) % len)
+            .find(|&i| self.receivers[i].is_ready())
+    }
+
+    /// Blocks until a receiver becomes ready, and returns its index. Panics if there are no
+    /// receivers.
+    pub fn ready(&self) -> usize {
+        assert!(!self.receivers.is_empty());
+        self.ready_deadline(None).unwrap()
+    }
+
+    /// Blocks until a receiver becomes ready or the timeout expires.
+    pub fn ready_timeout(&self, timeout: Duration) -> Option<usize> {
+        self.ready_deadline(Some(Instant::now() + timeout))
+    }
+
+    fn ready_deadline(&self, deadline: Option<Instant>) -> Option<usize> {
+        loop {
+            if let Some(index) = self.try_ready() {
+                return Some(index);
+            }
+
+            let signal = Arc::new(Signal::default());
+            for receiver in &self.receivers {
+                receiver.waiters().register(&signal);
+            }
+
+            // A message sent before the registration is seen here, and a message sent after the
+            // registration fires the signal.
+            fence(Ordering::SeqCst);
+            let mut index = self.try_ready();
+            if index.is_none() {
+                match deadline {
+                    None => signal.fired.wait(),
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if now < deadline {
+                            let _ = signal.fired.wait_timeout(deadline - now);
+                        }
+                    }
+                }
+                index = self.try_ready();
+            }
+
+            for receiver in &self.receivers {
+                receiver.waiters().unregister(&signal);
+            }
+
+            if index.is_some() {
+                return index;
+            }
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return self.try_ready();
+                }
+            }
+        }
+    }
+}
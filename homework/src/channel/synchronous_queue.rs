@@ -0,0 +1,203 @@
+//! Synchronous queue.
+//!
+//! The queue is a dual data structure: it holds either the waiting putters with their values or
+//! the waiting takers, never both. A putter that finds a waiting taker hands its value over and
+//! wakes it up, and vice versa. Otherwise, it enqueues itself and parks until a counterpart
+//! matches it or the timeout elapses.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+struct Waiter<T> {
+    thread: Thread,
+    /// The value of a putter, or the value received by a taker. Accessed by the others only with
+    /// the queue locked, before `matched` is set.
+    value: UnsafeCell<Option<T>>,
+    matched: AtomicBool,
+}
+
+struct Waiters<T> {
+    /// `true` if the waiters are putters.
+    data: bool,
+    queue: VecDeque<Arc<Waiter<T>>>,
+}
+
+/// Queue with no capacity, where each put waits for a take and vice versa.
+///
+/// In the fair mode, the waiting threads are matched in the FIFO order. In the unfair mode, they
+/// are matched in the LIFO order, which keeps the recently active threads busy and lets the others
+/// time out, e.g. the idle workers of a thread pool.
+pub struct SynchronousQueue<T> {
+    fair: bool,
+    waiters: Mutex<Waiters<T>>,
+}
+
+unsafe impl<T: Send> Send for SynchronousQueue<T> {}
+unsafe impl<T: Send> Sync for SynchronousQueue<T> {}
+
+impl<T> fmt::Debug for SynchronousQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SynchronousQueue")
+            .field("fair", &self.fair)
+            .field("waiting", &self.waiting())
+            .finish()
+    }
+}
+
+impl<T> Default for SynchronousQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SynchronousQueue<T> {
+    /// Creates a new unfair queue.
+    pub fn new() -> Self {
+        Self::with_fairness(false)
+    }
+
+    /// Creates a new fair queue.
+    pub fn fair() -> Self {
+        Self::with_fairness(true)
+    }
+
+    fn with_fairness(fair: bool) -> Self {
+        Self {
+            fair,
+            waiters: Mutex::new(Waiters {
+                data: false,
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Returns `true` if the queue is fair.
+    pub fn is_fair(&self) -> bool {
+        self.fair
+    }
+
+    /// Returns the number of threads waiting to put or take.
+    pub fn waiting(&self) -> usize {
+        self.waiters.lock().unwrap().queue.len()
+    }
+
+    /// Hands `value` over to a taker, blocking until one takes it.
+    pub fn put(&self, value: T) {
+        let result = self.transfer(Some(value), None);
+        debug_assert!(result.is_ok());
+    }
+
+    /// Blocks until a putter hands a value over, and returns it.
+    pub fn take(&self) -> T {
+        self.transfer(None, None).ok().flatten().unwrap()
+    }
+
+    /// Hands `value` over to a taker if one is waiting. Returns it back otherwise.
+    pub fn offer(&self, value: T) -> Result<(), T> {
+        self.put_deadline(value, Instant::now())
+    }
+
+    /// Takes a value if a putter is waiting.
+    pub fn poll(&self) -> Option<T> {
+        self.take_deadline(Instant::now())
+    }
+
+    /// Hands `value` over to a taker, blocking until one takes it or `timeout` elapses. Returns
+    /// it back on timeout.
+    pub fn put_timeout(&self, value: T, timeout: Duration) -> Result<(), T> {
+        self.put_deadline(value, Instant::now() + timeout)
+    }
+
+    /// Blocks until a putter hands a value over or `timeout` elapses. Returns `None` on timeout.
+    pub fn take_timeout(&self, timeout: Duration) -> Option<T> {
+        self.take_deadline(Instant::now() + timeout)
+    }
+
+    fn put_deadline(&self, value: T, deadline: Instant) -> Result<(), T> {
+        self.transfer(Some(value), Some(deadline))
+            .map(|_| ())
+            .map_err(|value| value.unwrap())
+    }
+
+    fn take_deadline(&self, deadline: Instant) -> Option<T> {
+        self.transfer(None, Some(deadline)).ok().flatten()
+    }
+
+    /// Puts `Some(value)` or takes on `None`, until `deadline` if any. Returns the value taken on
+    /// success, and `value` back on timeout.
+    fn transfer(
+        &self,
+        value: Option<T>,
+        deadline: Option<Instant>,
+    ) -> Result<Option<T>, Option<T>> {
+        let data = value.is_some();
+        let mut waiters = self.waiters.lock().unwrap();
+
+        // Matches a waiting counterpart.
+        if waiters.data != data {
+            let waiter = if self.fair {
+                waiters.queue.pop_front()
+            } else {
+                waiters.queue.pop_back()
+            };
+            if let Some(waiter) = waiter {
+                drop(waiters);
+                let received = unsafe { mem::replace(&mut *waiter.value.get(), value) };
+                waiter.matched.store(true, Ordering::Release);
+                waiter.thread.unpark();
+                return Ok(received);
+            }
+        }
+
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return Err(value);
+            }
+        }
+
+        // Waits for a counterpart.
+        let waiter = Arc::new(Waiter {
+            thread: thread::current(),
+            value: UnsafeCell::new(value),
+            matched: AtomicBool::new(false),
+        });
+        waiters.data = data;
+        waiters.queue.push_back(waiter.clone());
+        drop(waiters);
+
+        loop {
+            if waiter.matched.load(Ordering::Acquire) {
+                return Ok(unsafe { (*waiter.value.get()).take() });
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now < deadline {
+                        thread::park_timeout(deadline - now);
+                        continue;
+                    }
+
+                    let mut waiters = self.waiters.lock().unwrap();
+                    // A counterpart may have dequeued the waiter just before the lock.
+                    if let Some(index) = waiters.queue.iter().position(|w| Arc::ptr_eq(w, &waiter))
+                    {
+                        let _ = waiters.queue.remove(index);
+                        drop(waiters);
+                        return Err(unsafe { (*waiter.value.get()).take() });
+                    }
+                    drop(waiters);
+                    while !waiter.matched.load(Ordering::Acquire) {
+                        thread::yield_now();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Striped counter.
+
+use core::fmt;
+use core::sync::atomic::{AtomicIsize, Ordering};
+use crossbeam_utils::CachePadded;
+
+/// The number of stripes.
+const STRIPES: usize = 16;
+
+/// Counter whose updates are spread over the stripes of the threads, so that the threads that
+/// update it at the same time don't contend for a single cache line.
+///
+/// A thread updates only its own stripe, and `sum` adds up all the stripes. The sum is
+/// approximate while the counter is updated concurrently: it may miss the updates that happen
+/// during `sum`, and as the stripes are read one by one, it may even be negative when the
+/// decrements are counted before the increments that they follow.
+pub struct StripedCounter {
+    stripes: [CachePadded<AtomicIsize>; STRIPES],
+}
+
+impl Default for StripedCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StripedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StripedCounter")
+            .field("sum", &self.sum())
+            .finish()
+    }
+}
+
+impl StripedCounter {
+    /// Creates a new counter of 0.
+    pub fn new() -> Self {
+        Self {
+            stripes: Default::default(),
+        }
+    }
+
+    /// Adds 1, and returns the new value of the stripe of the current thread.
+    pub fn increment(&self) -> isize {
+        self.add(1)
+    }
+
+    /// Subtracts 1, and returns the new value of the stripe of the current thread.
+    pub fn decrement(&self) -> isize {
+        self.add(-1)
+    }
+
+    /// Adds `delta`, and returns the new value of the stripe of the current thread.
+    ///
+    /// The stripe changes by 1 at a time with `increment` and `decrement`, so it is a cheap way to
+    /// decide when to look at the expensive `sum`, e.g. whenever it crosses a multiple of some
+    /// interval.
+    pub fn add(&self, delta: isize) -> isize {
+        self.stripes[stripe_index() % STRIPES]
+            .fetch_add(delta, Ordering::Release)
+            .wrapping_add(delta)
+    }
+
+    /// Returns the sum of the stripes.
+    pub fn sum(&self) -> isize {
+        self.stripes.iter().fold(0, |sum, stripe| {
+            sum.wrapping_add(stripe.load(Ordering::Acquire))
+        })
+    }
+}
+
+/// Returns the index of the stripe of the current thread, before taking the remainder.
+#[cfg(feature = "std")]
+fn stripe_index() -> usize {
+    crate::current_thread_id()
+}
+
+/// Returns the index of the stripe of the current thread, before taking the remainder.
+///
+/// Without the thread IDs, the threads are told apart by the addresses of their stacks, which are
+/// usually more than 64KiB apart.
+#[cfg(not(feature = "std"))]
+fn stripe_index() -> usize {
+    let local = 0u8;
+    (&local as *const u8 as usize) >> 16
+}
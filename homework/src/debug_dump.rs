@@ -0,0 +1,220 @@
+//! Human-readable and DOT renderings of the internals of the data structures.
+//!
+//! The dumps are meant for the failure messages of tests, e.g.
+//!
+//! ```
+//! use crossbeam_epoch as epoch;
+//! use cs492_concur_homework::debug_dump::{self, Format};
+//! use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+//!
+//! let list = SplitOrderedList::new();
+//! let guard = epoch::pin();
+//! assert_eq!(list.insert(&1, 10, &guard), Ok(()));
+//! assert!(
+//!     list.lookup(&1, &guard).is_some(),
+//!     "{}",
+//!     debug_dump::split_ordered_list(&list, Format::Text, &guard)
+//! );
+//! ```
+//!
+//! `Format::Text` writes a node per line, indented by its depth. `Format::Dot` writes a Graphviz
+//! graph, which is rendered by e.g. `dot -Tsvg`. The dumps read the structures without
+//! synchronizing with the other threads beyond their guards and locks, so a dump taken while
+//! other threads modify the structure may mix the states before and after the modifications.
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+use core::hash::BuildHasher;
+use crossbeam_epoch::Guard;
+
+use crate::list::List;
+#[cfg(feature = "std")]
+use crate::OrderedListSet;
+use crate::{GrowableArray, SplitOrderedList};
+
+/// Format of a dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A node per line, indented by its depth.
+    Text,
+    /// A Graphviz graph.
+    Dot,
+}
+
+/// Kind of a node, which decides its style in DOT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// An element.
+    Element,
+    /// A logically deleted element.
+    Deleted,
+    /// A sentinel node of `SplitOrderedList`.
+    Sentinel,
+    /// A segment of `GrowableArray`, or a bucket of `SplitOrderedList`.
+    Segment,
+}
+
+/// Writes the nodes and the edges of a structure in a `Format`.
+///
+/// A node is identified by a prefix and a number, e.g. its address.
+#[derive(Debug)]
+pub(crate) struct Writer {
+    format: Format,
+    out: String,
+}
+
+impl Writer {
+    /// Starts a dump titled `title`.
+    pub(crate) fn new(format: Format, title: fmt::Arguments<'_>) -> Self {
+        let mut out = String::new();
+        match format {
+            Format::Text => writeln!(out, "{}", title).unwrap(),
+            Format::Dot => {
+                out.push_str("digraph {\n");
+                writeln!(out, "  label=\"{}\";", Escape(title)).unwrap();
+                out.push_str("  labelloc=t;\n  node [fontname=monospace];\n");
+            }
+        }
+        Self { format, out }
+    }
+
+    pub(crate) fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Writes a node. In `Format::Text`, it is a line indented by `depth`.
+    pub(crate) fn node(
+        &mut self,
+        id: (&str, usize),
+        depth: usize,
+        kind: Kind,
+        label: fmt::Arguments<'_>,
+    ) {
+        match self.format {
+            Format::Text => {
+                for _ in 0..=depth {
+                    self.out.push_str("  ");
+                }
+                write!(self.out, "{}", label).unwrap();
+                if kind == Kind::Deleted {
+                    self.out.push_str(" (deleted)");
+                }
+                self.out.push('\n');
+            }
+            Format::Dot => {
+                let style = match kind {
+                    Kind::Element => "shape=ellipse",
+                    Kind::Deleted => "shape=ellipse, style=dashed, color=gray",
+                    Kind::Sentinel => "shape=box, style=filled, fillcolor=lightgray",
+                    Kind::Segment => "shape=box",
+                };
+                writeln!(
+                    self.out,
+                    "  {}{} [label=\"{}\", {}];",
+                    id.0,
+                    id.1,
+                    Escape(label),
+                    style
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    /// Writes an edge. Only `Format::Dot` shows the edges.
+    pub(crate) fn edge(&mut self, from: (&str, usize), to: (&str, usize), label: Option<usize>) {
+        if self.format == Format::Dot {
+            write!(self.out, "  {}{} -> {}{}", from.0, from.1, to.0, to.1).unwrap();
+            if let Some(label) = label {
+                write!(self.out, " [label=\"{}\"]", label).unwrap();
+            }
+            self.out.push_str(";\n");
+        }
+    }
+
+    /// Finishes the dump.
+    pub(crate) fn finish(mut self) -> String {
+        if self.format == Format::Dot {
+            self.out.push_str("}\n");
+        }
+        self.out
+    }
+}
+
+/// Escapes the quotes, the backslashes, and the newlines in a DOT string.
+struct Escape<T>(T);
+
+impl<T: fmt::Display> fmt::Display for Escape<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Escaper<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+        impl Write for Escaper<'_, '_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                for c in s.chars() {
+                    match c {
+                        '"' => self.0.write_str("\\\"")?,
+                        '\\' => self.0.write_str("\\\\")?,
+                        '\n' => self.0.write_str("\\n")?,
+                        c => self.0.write_char(c)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        write!(Escaper(f), "{}", self.0)
+    }
+}
+
+/// Writes the sorted `indices` as ranges, e.g. `0-3, 7`.
+pub(crate) fn write_ranges<I: Iterator<Item = usize>>(out: &mut String, indices: I) {
+    let mut indices = indices.peekable();
+    let mut first = true;
+    while let Some(start) = indices.next() {
+        let mut end = start;
+        while indices.peek() == Some(&(end + 1)) {
+            end += 1;
+            let _ = indices.next();
+        }
+        if !first {
+            out.push_str(", ");
+        }
+        first = false;
+        if start == end {
+            write!(out, "{}", start).unwrap();
+        } else {
+            write!(out, "{}-{}", start, end).unwrap();
+        }
+    }
+}
+
+/// Dumps a `SplitOrderedList`: the buckets, and the sentinels and the elements in split order.
+///
+/// In `Format::Text`, each sentinel is followed by the elements of its bucket.
+pub fn split_ordered_list<V: fmt::Debug, S: BuildHasher>(
+    list: &SplitOrderedList<V, S>,
+    format: Format,
+    guard: &Guard,
+) -> String {
+    list.dump(format, guard)
+}
+
+/// Dumps the tree of the segments of a `GrowableArray`, and the indices of the non-null elements.
+pub fn growable_array<T>(array: &GrowableArray<T>, format: Format, guard: &Guard) -> String {
+    array.dump(format, guard)
+}
+
+/// Dumps a lock-free `List` in order, including the logically deleted nodes.
+pub fn list<K: fmt::Debug, V: fmt::Debug>(
+    list: &List<K, V>,
+    format: Format,
+    guard: &Guard,
+) -> String {
+    list.dump(format, guard)
+}
+
+/// Dumps an `OrderedListSet` in order. The set is locked while it is dumped.
+#[cfg(feature = "std")]
+pub fn ordered_list_set<T: fmt::Debug>(set: &OrderedListSet<T>, format: Format) -> String {
+    set.dump(format)
+}
@@ -1,10 +1,13 @@
 use core::marker::PhantomData;
 use core::mem::ManuallyDrop;
 use core::ops::Deref;
-use crossbeam_epoch::{pin, Atomic, Guard, Owned};
+use crossbeam_epoch::{pin, Guard, Owned};
 use rand::{thread_rng, Rng};
 use std::time;
 
+use crate::backoff::ExponentialBackoff;
+use crate::sync::Exchanger;
+
 pub const ELIM_SIZE: usize = 16;
 pub const ELIM_DELAY: time::Duration = time::Duration::from_millis(10);
 
@@ -40,10 +43,14 @@ pub trait Stack<T>: Default {
     fn push(&self, t: T) {
         let mut req = Owned::new(Self::PushReq::from(t));
         let guard = pin();
+        let backoff = ExponentialBackoff::new();
         loop {
             match self.try_push(req, &guard) {
                 Ok(_) => break,
-                Err(r) => req = r,
+                Err(r) => {
+                    req = r;
+                    backoff.backoff();
+                }
             }
         }
     }
@@ -53,10 +60,12 @@ pub trait Stack<T>: Default {
     /// Returns `Some(v)` if `v` is popped; `None` if the stack is empty.
     fn pop(&self) -> Option<T> {
         let guard = pin();
+        let backoff = ExponentialBackoff::new();
         loop {
             if let Ok(result) = self.try_pop(&guard) {
                 return result;
             }
+            backoff.backoff();
         }
     }
 }
@@ -64,7 +73,8 @@ pub trait Stack<T>: Default {
 #[derive(Debug)]
 pub struct ElimStack<T, S: Stack<T>> {
     pub(crate) inner: S,
-    pub(crate) slots: [Atomic<S::PushReq>; ELIM_SIZE],
+    /// A push offers `Some(req)`, and a pop offers `None`.
+    pub(crate) slots: [Exchanger<Option<Owned<S::PushReq>>>; ELIM_SIZE],
     _marker: PhantomData<T>,
 }
 
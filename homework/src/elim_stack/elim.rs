@@ -1,9 +1,6 @@
 use core::mem::ManuallyDrop;
-use core::ops::Deref;
 use core::ptr;
-use core::sync::atomic::Ordering;
-use crossbeam_epoch::{Guard, Owned, Shared};
-use std::thread;
+use crossbeam_epoch::{Guard, Owned};
 
 use super::base::{get_random_elim_index, ElimStack, Stack, ELIM_DELAY};
 
@@ -21,10 +18,14 @@ impl<T, S: Stack<T>> Stack<T> for ElimStack<T, S> {
         };
 
         let index = get_random_elim_index();
-        let slot_ref = unsafe { self.slots.get_unchecked(index) };
-        let slot = slot_ref.load(Ordering::Acquire, guard);
-
-        unimplemented!()
+        let slot = unsafe { self.slots.get_unchecked(index) };
+        match slot.exchange_timeout(Some(req), ELIM_DELAY) {
+            // Eliminated by a pop.
+            Ok(None) => Ok(()),
+            // Met another push, and took over its request. Or timed out.
+            Ok(Some(req)) | Err(Some(req)) => Err(req),
+            Err(None) => unreachable!(),
+        }
     }
 
     fn try_pop(&self, guard: &Guard) -> Result<Option<T>, ()> {
@@ -33,10 +34,13 @@ impl<T, S: Stack<T>> Stack<T> for ElimStack<T, S> {
         }
 
         let index = get_random_elim_index();
-        let slot_ref = unsafe { self.slots.get_unchecked(index) };
-        let slot = slot_ref.load(Ordering::Acquire, guard);
-
-        unimplemented!()
+        let slot = unsafe { self.slots.get_unchecked(index) };
+        match slot.exchange_timeout(None, ELIM_DELAY) {
+            // Eliminated a push.
+            Ok(Some(req)) => Ok(Some(unsafe { ManuallyDrop::into_inner(ptr::read(&**req)) })),
+            // Met another pop, or timed out.
+            _ => Err(()),
+        }
     }
 
     fn is_empty(&self, guard: &Guard) -> bool {
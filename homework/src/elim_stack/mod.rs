@@ -5,6 +5,7 @@ mod elim;
 mod treiber_stack;
 
 pub use base::Stack;
+pub(crate) use treiber_stack::TreiberStack;
 
 /// Elimination-backoff stack based on Treiber's stack.
 pub type ElimStack<T> = base::ElimStack<T, treiber_stack::TreiberStack<T>>;
@@ -12,6 +13,7 @@ pub type ElimStack<T> = base::ElimStack<T, treiber_stack::TreiberStack<T>>;
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::testing::linearizability::{self, Recorder, Specification};
     use crossbeam_utils::thread::scope;
 
     #[test]
@@ -32,4 +34,72 @@ mod test {
 
         assert!(stack.pop().is_none());
     }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+    struct StackSpec(Vec<usize>);
+
+    #[derive(Debug)]
+    enum StackOp {
+        Push(usize),
+        Pop,
+    }
+
+    impl Specification for StackSpec {
+        type Op = StackOp;
+        type Ret = Option<usize>;
+
+        fn apply(&mut self, op: &StackOp) -> Option<usize> {
+            match op {
+                StackOp::Push(value) => {
+                    self.0.push(*value);
+                    None
+                }
+                StackOp::Pop => self.0.pop(),
+            }
+        }
+    }
+
+    #[test]
+    fn linearizable() {
+        const THREADS: usize = 4;
+        const STEPS: usize = 40;
+
+        for _ in 0..16 {
+            let stack = ElimStack::default();
+            let recorder = Recorder::new();
+            let history = scope(|scope| {
+                let handles = (0..THREADS)
+                    .map(|t| {
+                        let stack = &stack;
+                        let recorder = &recorder;
+                        scope.spawn(move |_| {
+                            (0..STEPS)
+                                .map(|i| {
+                                    let op = if i % 2 == 0 {
+                                        StackOp::Push(t * STEPS + i)
+                                    } else {
+                                        StackOp::Pop
+                                    };
+                                    recorder.record(op, |op| match op {
+                                        StackOp::Push(value) => {
+                                            stack.push(*value);
+                                            None
+                                        }
+                                        StackOp::Pop => stack.pop(),
+                                    })
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+
+            assert!(linearizability::check(StackSpec::default(), &history).is_ok());
+        }
+    }
 }
@@ -3,7 +3,8 @@ use core::ops::Deref;
 use core::ptr;
 use core::sync::atomic::Ordering;
 
-use crossbeam_epoch::{unprotected, Atomic, Guard, Owned};
+use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
+use lockfree::StampedAtomic;
 
 use super::base::Stack;
 
@@ -16,9 +17,12 @@ pub struct Node<T> {
 /// Treiber's lock-free stack.
 ///
 /// Usable with any number of producers and consumers.
+///
+/// The head is stamped, so that a pop does not succeed after the head is popped and pushed back in
+/// the meantime, even if the nodes are reused without waiting for the epoch to advance.
 #[derive(Debug)]
 pub struct TreiberStack<T> {
-    head: Atomic<Node<T>>,
+    head: StampedAtomic<Node<T>>,
 }
 
 impl<T> From<T> for Node<T> {
@@ -41,7 +45,7 @@ impl<T> Deref for Node<T> {
 impl<T> Default for TreiberStack<T> {
     fn default() -> Self {
         TreiberStack {
-            head: Atomic::null(),
+            head: StampedAtomic::null(),
         }
     }
 }
@@ -54,32 +58,39 @@ impl<T> Stack<T> for TreiberStack<T> {
         req: Owned<Self::PushReq>,
         guard: &Guard,
     ) -> Result<(), Owned<Self::PushReq>> {
-        let head = self.head.load(Ordering::Relaxed, &guard);
-        req.next.store(head, Ordering::Relaxed);
+        let head = self.head.load(Ordering::Relaxed);
+        req.next
+            .store(Shared::from(head.ptr() as *const _), Ordering::Relaxed);
+        let req = req.into_shared(guard).as_raw() as *mut Node<T>;
         self.head
-            .compare_and_set(head, req, Ordering::Release, &guard)
+            .compare_exchange(head, req, Ordering::Release, Ordering::Relaxed)
             .map(|_| ())
-            .map_err(|e| e.new)
+            .map_err(|_| unsafe { Owned::from_raw(req) })
     }
 
     fn try_pop(&self, guard: &Guard) -> Result<Option<T>, ()> {
-        let head = self.head.load(Ordering::Acquire, &guard);
-        let head_ref = some_or!(unsafe { head.as_ref() }, return Ok(None));
+        let head = self.head.load(Ordering::Acquire);
+        let head_ref = some_or!(unsafe { head.ptr().as_ref() }, return Ok(None));
         let next = head_ref.next.load(Ordering::Relaxed, &guard);
 
         self.head
-            .compare_and_set(head, next, Ordering::Relaxed, &guard)
+            .compare_exchange(
+                head,
+                next.as_raw() as *mut _,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
             .map_err(|_| ())?;
 
         Ok(Some(unsafe {
             let data = ptr::read(&(*head_ref).data);
-            guard.defer_destroy(head);
+            guard.defer_destroy(Shared::from(head.ptr() as *const Node<T>));
             ManuallyDrop::into_inner(data)
         }))
     }
 
-    fn is_empty(&self, guard: &Guard) -> bool {
-        self.head.load(Ordering::Acquire, &guard).is_null()
+    fn is_empty(&self, _guard: &Guard) -> bool {
+        self.head.load(Ordering::Acquire).ptr().is_null()
     }
 }
 
@@ -0,0 +1,465 @@
+//! Persistent hash array mapped trie.
+//!
+//! - From Bagwell. Ideal Hash Trees. 2001
+//!   (https://infoscience.epfl.ch/record/64398)
+
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::slice;
+use crossbeam_epoch::Guard;
+use std::collections::hash_map::RandomState;
+use std::sync::Arc;
+
+use crate::atomic_arc::AtomicArc;
+use crate::backoff::ExponentialBackoff;
+use crate::map::ConcurrentMap;
+
+/// The number of hash bits consumed by each level.
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+#[derive(Debug)]
+enum Node<K, V> {
+    /// The children for the set bits of `bitmap`, in the order of the bits.
+    Branch {
+        bitmap: u32,
+        children: Vec<Arc<Node<K, V>>>,
+    },
+    /// The entries whose keys have the hash `hash`.
+    Leaf { hash: u64, entries: Vec<(K, V)> },
+}
+
+/// Returns the bit of `hash` at the level of `shift`.
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+/// Returns the index of the child for `bit` among the children of `bitmap`.
+fn position(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl<K: Eq, V> Node<K, V> {
+    fn get<Q>(&self, hash: u64, shift: u32, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match self {
+            Self::Branch { bitmap, children } => {
+                let bit = bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                children[position(*bitmap, bit)].get(hash, shift + BITS, key)
+            }
+            Self::Leaf { hash: h, entries } => {
+                if *h != hash {
+                    return None;
+                }
+                entries
+                    .iter()
+                    .find(|(k, _)| k.borrow() == key)
+                    .map(|(_, v)| v)
+            }
+        }
+    }
+}
+
+impl<K: Eq + Clone, V: Clone> Node<K, V> {
+    /// Returns the copy of `node` with `key` mapped to `value`.
+    fn insert(node: &Arc<Self>, hash: u64, shift: u32, key: K, value: V) -> Self {
+        match &**node {
+            Self::Branch { bitmap, children } => {
+                let bit = bit(hash, shift);
+                let pos = position(*bitmap, bit);
+                let mut children = children.clone();
+                if bitmap & bit == 0 {
+                    children.insert(
+                        pos,
+                        Arc::new(Self::Leaf {
+                            hash,
+                            entries: vec![(key, value)],
+                        }),
+                    );
+                } else {
+                    let child = Self::insert(&children[pos], hash, shift + BITS, key, value);
+                    children[pos] = Arc::new(child);
+                }
+                Self::Branch {
+                    bitmap: bitmap | bit,
+                    children,
+                }
+            }
+            Self::Leaf { hash: h, entries } if *h == hash => {
+                let mut entries = entries.clone();
+                match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some(entry) => entry.1 = value,
+                    None => entries.push((key, value)),
+                }
+                Self::Leaf { hash, entries }
+            }
+            Self::Leaf { hash: h, .. } => {
+                // Pushes down the leaf. The hashes differ, so they are eventually split.
+                let branch = Arc::new(Self::Branch {
+                    bitmap: bit(*h, shift),
+                    children: vec![node.clone()],
+                });
+                Self::insert(&branch, hash, shift, key, value)
+            }
+        }
+    }
+
+    /// Returns the copy of `node` without `key`, which is `None` if it is empty. Returns `None` if
+    /// `key` is not in `node`.
+    fn remove<Q>(node: &Arc<Self>, hash: u64, shift: u32, key: &Q) -> Option<Option<Arc<Self>>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match &**node {
+            Self::Branch { bitmap, children } => {
+                let bit = bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let pos = position(*bitmap, bit);
+                let child = Self::remove(&children[pos], hash, shift + BITS, key)?;
+                let mut children = children.clone();
+                let bitmap = match child {
+                    Some(child) => {
+                        children[pos] = child;
+                        *bitmap
+                    }
+                    None => {
+                        let _ = children.remove(pos);
+                        bitmap & !bit
+                    }
+                };
+                // Pulls up the only leaf, so that the trie doesn't stay deeper than necessary.
+                match children.as_slice() {
+                    [] => Some(None),
+                    [child] if matches!(**child, Self::Leaf { .. }) => Some(Some(child.clone())),
+                    _ => Some(Some(Arc::new(Self::Branch { bitmap, children }))),
+                }
+            }
+            Self::Leaf { hash: h, entries } => {
+                if *h != hash {
+                    return None;
+                }
+                let pos = entries.iter().position(|(k, _)| k.borrow() == key)?;
+                if entries.len() == 1 {
+                    return Some(None);
+                }
+                let mut entries = entries.clone();
+                let _ = entries.remove(pos);
+                Some(Some(Arc::new(Self::Leaf { hash, entries })))
+            }
+        }
+    }
+}
+
+/// Persistent hash map.
+///
+/// A `Hamt` is never modified. `insert` and `remove` return a new map that shares all but the path
+/// to the changed entry with the old one, so they take `O(log n)` time and memory, and cloning a
+/// map takes `O(1)`. Old versions of a map stay valid as long as they are referenced.
+///
+/// See `AtomicHamt` for the concurrent map built on it.
+pub struct Hamt<K, V, S = RandomState> {
+    root: Option<Arc<Node<K, V>>>,
+    len: usize,
+    hasher: S,
+}
+
+/// Iterator over the entries of a `Hamt`.
+#[derive(Debug)]
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+    entries: slice::Iter<'a, (K, V)>,
+}
+
+impl<K, V, S: Clone> Clone for Hamt<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K, V, S: Default> Default for Hamt<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for Hamt<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Hamt<K, V> {
+    /// Creates a new empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, S> Hamt<K, V, S> {
+    /// Creates a new empty map that hashes the keys with `hasher`.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            root: None,
+            len: 0,
+            hasher,
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the iterator over the entries, in an arbitrary order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: self.root.iter().map(|root| &**root).collect(),
+            entries: [].iter(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> Hamt<K, V, S> {
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.root.as_ref()?.get(self.hash(key), 0, key)
+    }
+
+    /// Returns `true` if the map contains `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone> Hamt<K, V, S> {
+    /// Returns the map with `key` mapped to `value`, replacing the old value if any.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let hash = self.hash(&key);
+        let len = if self.contains_key(&key) {
+            self.len
+        } else {
+            self.len + 1
+        };
+        let root = match &self.root {
+            Some(root) => Node::insert(root, hash, 0, key, value),
+            None => Node::Leaf {
+                hash,
+                entries: vec![(key, value)],
+            },
+        };
+        Self {
+            root: Some(Arc::new(root)),
+            len,
+            hasher: self.hasher.clone(),
+        }
+    }
+
+    /// Returns the map without `key`.
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let root = self
+            .root
+            .as_ref()
+            .and_then(|root| Node::remove(root, self.hash(key), 0, key));
+        match root {
+            Some(root) => Self {
+                root,
+                len: self.len - 1,
+                hasher: self.hasher.clone(),
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.entries.next() {
+                return Some((k, v));
+            }
+            match self.stack.pop()? {
+                Node::Branch { children, .. } => {
+                    self.stack.extend(children.iter().map(|child| &**child))
+                }
+                Node::Leaf { entries, .. } => self.entries = entries.iter(),
+            }
+        }
+    }
+}
+
+/// Concurrent hash map whose versions are `Hamt`s.
+///
+/// The current version is published through an `AtomicArc`. A reader takes a snapshot of the whole
+/// map without waiting for anything, and the snapshot never changes while the writers go on. A
+/// writer builds the new version from the current one and installs it with a CAS, retrying from
+/// the newer version if another writer has won. So every version is the result of applying the
+/// successful updates one by one, and a reader never sees a half-done update even when it spans
+/// many keys.
+///
+/// Compared to the mutable concurrent maps, the writers allocate `O(log n)` nodes per update and
+/// contend on a single root, so this is for read-mostly maps whose readers need consistent views,
+/// e.g. configurations and routing tables.
+pub struct AtomicHamt<K, V, S = RandomState> {
+    root: AtomicArc<Hamt<K, V, S>>,
+}
+
+impl<K, V, S> Default for AtomicHamt<K, V, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    S: Default + Send + Sync,
+{
+    fn default() -> Self {
+        Self::from(Hamt::default())
+    }
+}
+
+impl<K, V, S> From<Hamt<K, V, S>> for AtomicHamt<K, V, S> {
+    fn from(map: Hamt<K, V, S>) -> Self {
+        Self {
+            root: AtomicArc::new(Arc::new(map)),
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for AtomicHamt<K, V, S>
+where
+    K: fmt::Debug + Send + Sync,
+    V: fmt::Debug + Send + Sync,
+    S: Send + Sync,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicHamt").field(&self.snapshot()).finish()
+    }
+}
+
+impl<K, V> AtomicHamt<K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+    /// Creates a new empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, S> AtomicHamt<K, V, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    S: Send + Sync,
+{
+    /// Returns the current version.
+    pub fn snapshot(&self) -> Arc<Hamt<K, V, S>> {
+        self.root.load()
+    }
+
+    /// Returns the current version, which is valid while `guard` is alive.
+    ///
+    /// Unlike `snapshot`, this doesn't touch the reference count.
+    pub fn snapshot_with<'g>(&'g self, guard: &'g Guard) -> &'g Hamt<K, V, S> {
+        self.root.load_with(guard)
+    }
+
+    /// Replaces the current version with the one returned by `f` from it, until no other writer
+    /// interferes. Returns the replaced version, or the current one if `f` returns `None`.
+    ///
+    /// `f` may be called multiple times.
+    #[allow(clippy::type_complexity)]
+    pub fn fetch_update<F>(&self, mut f: F) -> Result<Arc<Hamt<K, V, S>>, Arc<Hamt<K, V, S>>>
+    where
+        F: FnMut(&Hamt<K, V, S>) -> Option<Hamt<K, V, S>>,
+    {
+        let backoff = ExponentialBackoff::new();
+        let mut current = self.root.load();
+        loop {
+            let new = some_or!(f(&current), return Err(current));
+            match self.root.compare_exchange(&current, Arc::new(new)) {
+                Ok(_) => return Ok(current),
+                Err(e) => {
+                    current = e.current;
+                    backoff.backoff();
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, S> ConcurrentMap<K, V> for AtomicHamt<K, V, S>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    fn lookup<'a, F, R>(&'a self, key: &'a K, guard: &'a Guard, f: F) -> R
+    where
+        F: FnOnce(Option<&V>) -> R,
+    {
+        f(self.snapshot_with(guard).get(key))
+    }
+
+    fn insert<'a>(&'a self, key: &'a K, value: V, _guard: &'a Guard) -> Result<(), V> {
+        self.fetch_update(|map| {
+            if map.contains_key(key) {
+                None
+            } else {
+                Some(map.insert(key.clone(), value.clone()))
+            }
+        })
+        .map(|_| ())
+        .map_err(|_| value)
+    }
+
+    fn delete(&self, key: &K, _guard: &Guard) -> Result<V, ()> {
+        match self.fetch_update(|map| {
+            if map.contains_key(key) {
+                Some(map.remove(key))
+            } else {
+                None
+            }
+        }) {
+            Ok(old) => Ok(old.get(key).unwrap().clone()),
+            Err(_) => Err(()),
+        }
+    }
+}
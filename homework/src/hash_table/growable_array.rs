@@ -1,13 +1,17 @@
 //! Growable array.
 
-use core::fmt::Debug;
-use core::marker::PhantomData;
+use alloc::alloc::{self as heap, Layout};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
 use core::mem;
-use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicUsize, Ordering};
-use std::ptr::null;
-use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Pointer, Shared};
-use mem::size_of;
+use crossbeam_epoch::{unprotected, Atomic, Guard, Pointer, Shared};
+
+use crate::backoff::ExponentialBackoff;
+use crate::debug_dump::{self, Format, Kind, Writer};
 
 /// Growable array of `Atomic<T>`.
 ///
@@ -133,41 +137,338 @@ use mem::size_of;
 ///
 #[derive(Debug)]
 pub struct GrowableArray<T> {
-    root: Atomic<Segment>,
-    _marker: PhantomData<T>,
+    /// Tagged with the height of the tree. The root of height 1 is a `Leaf`.
+    root: Atomic<Segment<T>>,
+    /// The number of the segments in the tree.
+    segments: AtomicUsize,
+    /// The segments to reuse before allocating new ones.
+    free: Arc<FreeList<T>>,
 }
 
 const SEGMENT_LOGSIZE: usize = 10;
+const SEGMENT_SIZE: usize = 1 << SEGMENT_LOGSIZE;
+/// Tag of the null slots of the segments that `truncate` removes, so that a concurrent `get` doesn't
+/// add a segment under them.
+const FROZEN: usize = 1;
+/// The maximum number of the free segments that an array keeps for reuse, i.e. 128KiB.
+const FREE_SEGMENTS: usize = 16;
+
+/// The number of the segments allocated so far by all the arrays.
+#[cfg(feature = "alloc-tracking")]
+static SEGMENTS_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+/// The number of the segments deallocated so far by all the arrays.
+#[cfg(feature = "alloc-tracking")]
+static SEGMENTS_DEALLOCATED: AtomicUsize = AtomicUsize::new(0);
+/// The number of the segments that lost the race to be installed so far.
+#[cfg(feature = "alloc-tracking")]
+static SEGMENTS_LOST: AtomicUsize = AtomicUsize::new(0);
+/// The number of the segments taken from the free lists so far.
+#[cfg(feature = "alloc-tracking")]
+static SEGMENTS_REUSED: AtomicUsize = AtomicUsize::new(0);
+
+/// Counters of the segments of all the `GrowableArray`s, returned by `debug_allocation_stats`.
+#[cfg(feature = "alloc-tracking")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentStats {
+    /// The number of the segments allocated so far.
+    pub allocated: usize,
+    /// The number of the segments deallocated so far, including the lost ones.
+    pub deallocated: usize,
+    /// The number of the segments that lost the race to be installed in `get`, and were returned
+    /// to the free list of the array or deallocated right away.
+    pub lost: usize,
+    /// The number of the segments that were taken from the free list of an array instead of
+    /// being allocated.
+    pub reused: usize,
+}
+
+/// Returns the counters of the segments of all the `GrowableArray`s.
+///
+/// The segments of an array, including the ones in its free list, are deallocated when it is
+/// dropped and the segments retired by `GrowableArray::compact` are collected. So once the arrays
+/// are dropped and the garbage is collected, `allocated == deallocated` unless a segment is leaked.
+#[cfg(feature = "alloc-tracking")]
+pub fn debug_allocation_stats() -> SegmentStats {
+    SegmentStats {
+        allocated: SEGMENTS_ALLOCATED.load(Ordering::Relaxed),
+        deallocated: SEGMENTS_DEALLOCATED.load(Ordering::Relaxed),
+        lost: SEGMENTS_LOST.load(Ordering::Relaxed),
+        reused: SEGMENTS_REUSED.load(Ordering::Relaxed),
+    }
+}
+
+/// Internal segment, whose slots point to the segments one level below.
+///
+/// The segments of height 2 point to `Leaf`s. The pointers are cast to the right type by the
+/// height, which keeps their provenance, unlike storing them as integers.
+struct Segment<T> {
+    children: [Atomic<Segment<T>>; SEGMENT_SIZE],
+}
+
+/// Returns the height of the tree that has room for `index`.
+fn height_for(index: usize) -> usize {
+    let mut height = 1;
+    while SEGMENT_LOGSIZE * height < mem::size_of::<usize>() * 8
+        && index >> (SEGMENT_LOGSIZE * height) != 0
+    {
+        height += 1;
+    }
+    height
+}
+
+/// Segment of height 1, whose slots point to the elements.
+struct Leaf<T> {
+    elements: [Atomic<T>; SEGMENT_SIZE],
+}
+
+/// Lock-free stack of the free segments of an array, which are linked by their first slots.
+///
+/// The segments that lose the race to be installed and the ones that `compact` removes are pushed
+/// to it, up to `FREE_SEGMENTS`, and the others are deallocated. They are pushed by the closures
+/// deferred to the collector, which share the list, so that no thread still reads a segment that
+/// is reused or deallocated. E.g. a thread that pops a segment reads its first slot, even if the
+/// segment is popped by another thread in the meantime.
+///
+/// The same deferral rules out ABA: a pop is pinned from when it reads the head until its CAS, and
+/// a segment that is popped in the meantime is pushed back only after every such pop unpins.
+///
+/// The leaves are kept as `Segment`s, since they are allocated alike.
+struct FreeList<T> {
+    head: Atomic<Segment<T>>,
+    /// The number of the segments in the list, or more while they are being pushed or popped.
+    len: AtomicUsize,
+}
+
+/// How much of the tree `GrowableArray::reserve` allocates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reserve {
+    /// The segments on the path to the index.
+    Path,
+    /// The segments of all the indices up to the index.
+    All,
+}
+
+/// Memory footprint of the tree of a `GrowableArray`, returned by `GrowableArray::memory_usage`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The number of the segments in the tree, including the leaves.
+    pub segments: usize,
+    /// The size of the segments in bytes.
+    pub bytes: usize,
+    /// The height of the tree.
+    pub height: usize,
+}
+
+/// Error returned by `GrowableArray::try_get` when a segment can't be allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
 
-struct Segment {
-    /// `AtomicUsize` here means `Atomic<T>` or `Atomic<Segment>`.
-    inner: [AtomicUsize; 1 << SEGMENT_LOGSIZE],
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to allocate a segment of the growable array")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+/// Iterator over the non-null slots of a `GrowableArray`, returned by `GrowableArray::iter`.
+#[derive(Debug)]
+pub struct Iter<'g, T> {
+    /// The path from the root to the segment being walked.
+    stack: Vec<Frame<'g, T>>,
+    guard: &'g Guard,
+}
+
+/// A segment on the path of `Iter`.
+#[derive(Debug)]
+struct Frame<'g, T> {
+    segment: &'g Segment<T>,
+    height: usize,
+    /// The index of the first slot under the segment.
+    base: usize,
+    /// The slot of the segment to visit next.
+    next: usize,
+}
+
+impl<T> Segment<T> {
+    /// The layout of the segments, which is also that of the leaves.
+    fn layout() -> Layout {
+        Layout::new::<Self>()
+    }
+
+    /// Allocates an empty segment, which is also an empty leaf. All the segments are allocated by
+    /// this function.
+    fn try_alloc() -> Result<*mut Self, AllocError> {
+        // A null `Atomic` is all zeros.
+        let segment = unsafe { heap::alloc_zeroed(Self::layout()) } as *mut Self;
+        if segment.is_null() {
+            return Err(AllocError);
+        }
+        #[cfg(feature = "alloc-tracking")]
+        let _ = SEGMENTS_ALLOCATED.fetch_add(1, Ordering::Relaxed);
+        Ok(segment)
+    }
+
+    /// Returns the smallest index under this segment of `height` whose slot is not null, or the
+    /// largest one if `last`. `base` is the index of the first slot under the segment.
+    fn occupied(&self, height: usize, base: usize, last: bool, guard: &Guard) -> Option<usize> {
+        let slots = (0..SEGMENT_SIZE).map(|i| if last { SEGMENT_SIZE - 1 - i } else { i });
+        if height == 1 {
+            let leaf = unsafe { &*(self as *const Self as *const Leaf<T>) };
+            for i in slots {
+                if !leaf.elements[i].load(Ordering::Acquire, guard).is_null() {
+                    return Some(base + i);
+                }
+            }
+            return None;
+        }
+
+        for i in slots {
+            let child = self.children[i].load(Ordering::Acquire, guard);
+            // An empty child may be left until `compact`, so the search goes on to the next one.
+            if let Some(child) = unsafe { child.as_ref() } {
+                let base = base + (i << (SEGMENT_LOGSIZE * (height - 1)));
+                if let Some(index) = child.occupied(height - 1, base, last, guard) {
+                    return Some(index);
+                }
+            }
+        }
+        None
+    }
+
+    /// Deallocates a segment or a leaf allocated by `try_alloc`, but not its children.
+    unsafe fn dealloc(segment: *mut Self) {
+        #[cfg(feature = "alloc-tracking")]
+        let _ = SEGMENTS_DEALLOCATED.fetch_add(1, Ordering::Relaxed);
+        heap::dealloc(segment as *mut u8, Self::layout());
+    }
 }
 
-impl Segment {
+impl<T> FreeList<T> {
     fn new() -> Self {
         Self {
-            inner: unsafe { mem::zeroed() },
+            head: Atomic::null(),
+            len: AtomicUsize::new(0),
         }
     }
+
+    /// Pushes a segment that no thread reads, or deallocates it if the list is full.
+    fn push(&self, segment: *mut Segment<T>) {
+        if self.len.fetch_add(1, Ordering::Relaxed) >= FREE_SEGMENTS {
+            let _ = self.len.fetch_sub(1, Ordering::Relaxed);
+            unsafe { Segment::dealloc(segment) };
+            return;
+        }
+
+        // The head is not dereferenced, so the list needs no protection.
+        let guard = unsafe { unprotected() };
+        let next = unsafe { &(*segment).children[0] };
+        let segment = Shared::from(segment as *const _);
+        let mut head = self.head.load(Ordering::Relaxed, guard);
+        loop {
+            next.store(head, Ordering::Relaxed);
+            match self
+                .head
+                .compare_and_set(head, segment, Ordering::Release, guard)
+            {
+                Ok(_) => return,
+                Err(e) => head = e.current,
+            }
+        }
+    }
+
+    /// Pops a segment, and clears its slots.
+    fn pop(&self, guard: &Guard) -> Option<*mut Segment<T>> {
+        let mut head = self.head.load(Ordering::Acquire, guard);
+        loop {
+            // The segment is not deallocated while `guard` is pinned even if it is popped by
+            // another thread in the meantime, since it is then pushed back through the collector.
+            let segment = unsafe { head.as_ref() }?;
+            let next = segment.children[0].load(Ordering::Relaxed, guard);
+            match self
+                .head
+                .compare_and_set(head, next, Ordering::Acquire, guard)
+            {
+                Ok(_) => break,
+                Err(e) => head = e.current,
+            }
+        }
+        let _ = self.len.fetch_sub(1, Ordering::Relaxed);
+        #[cfg(feature = "alloc-tracking")]
+        let _ = SEGMENTS_REUSED.fetch_add(1, Ordering::Relaxed);
+
+        let segment = unsafe { head.deref() };
+        for child in segment.children.iter() {
+            child.store(Shared::null(), Ordering::Relaxed);
+        }
+        Some(head.as_raw() as *mut _)
+    }
 }
 
-impl Deref for Segment {
-    type Target = [AtomicUsize; 1 << SEGMENT_LOGSIZE];
+impl<T> Drop for FreeList<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = unprotected();
+            let mut segment = self.head.load(Ordering::Relaxed, guard).as_raw() as *mut Segment<T>;
+            while !segment.is_null() {
+                let next = (*segment).children[0].load(Ordering::Relaxed, guard);
+                Segment::dealloc(segment);
+                segment = next.as_raw() as *mut _;
+            }
+        }
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+impl<T> fmt::Debug for FreeList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FreeList")
+            .field("len", &self.len.load(Ordering::Relaxed))
+            .finish()
     }
 }
 
-impl DerefMut for Segment {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
+impl<'g, T> Iterator for Iter<'g, T> {
+    type Item = (usize, &'g Atomic<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if frame.next == SEGMENT_SIZE {
+                let _ = self.stack.pop();
+                continue;
+            }
+            let i = frame.next;
+            frame.next += 1;
+
+            if frame.height == 1 {
+                let leaf = unsafe { &*(frame.segment as *const Segment<T> as *const Leaf<T>) };
+                let slot = &leaf.elements[i];
+                if !slot.load(Ordering::Acquire, self.guard).is_null() {
+                    return Some((frame.base + i, slot));
+                }
+                continue;
+            }
+
+            let child = frame.segment.children[i].load(Ordering::Acquire, self.guard);
+            // The segments are retired by `compact`, so they are not deallocated while `guard` is
+            // pinned.
+            if let Some(segment) = unsafe { child.as_ref() } {
+                let height = frame.height - 1;
+                let base = frame.base + (i << (SEGMENT_LOGSIZE * height));
+                self.stack.push(Frame {
+                    segment,
+                    height,
+                    base,
+                    next: 0,
+                });
+            }
+        }
     }
 }
 
-impl Debug for Segment {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+impl<T> fmt::Debug for Segment<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Segment")
     }
 }
@@ -175,34 +476,28 @@ impl Debug for Segment {
 impl<T> Drop for GrowableArray<T> {
     /// Deallocate segments, but not the individual elements.
     fn drop(&mut self) {
-        
-        unsafe{
-            let seg = self.root.load(Ordering::Acquire,unprotected());
-            if seg.is_null()==false{
-                drop_seg(seg,seg.tag());
+        unsafe {
+            let guard = unprotected();
+            let root = self.root.load(Ordering::Relaxed, guard);
+            if root.is_null() {
+                return;
             }
-        }
-        
-        fn drop_seg(seg: Shared<Segment>, height: usize){
-            let mut index = 0usize;
-            loop {
-                unsafe{ 
-                    let index_ptr = &*(seg.deref()).get_unchecked(index); 
-                    let next_seg: Shared<Segment> = Shared::from_usize(index_ptr.load(Ordering::Acquire)); 
-                    if next_seg.is_null()==false {
-                        if height>1 {
-                            drop_seg(next_seg,height-1)
-                        } 
+
+            // The segments to deallocate with their heights, walked without recursion so that a
+            // tall tree doesn't overflow the stack.
+            let mut segments = vec![(root.as_raw() as *mut Segment<T>, root.tag())];
+            while let Some((segment, height)) = segments.pop() {
+                if height > 1 {
+                    for child in (*segment).children.iter() {
+                        let child = child.load(Ordering::Relaxed, guard);
+                        if !child.is_null() {
+                            segments.push((child.as_raw() as *mut Segment<T>, height - 1));
+                        }
                     }
                 }
-                if index==1023  {break;}
-                else { index+=1; }
-            }
-            unsafe{
-                seg.into_owned();
+                Segment::dealloc(segment);
             }
         }
-
     }
 }
 
@@ -213,82 +508,599 @@ impl<T> Default for GrowableArray<T> {
 }
 
 impl<T> GrowableArray<T> {
+    /// The tag bits of the slots, i.e. the bits below the alignment of `T`.
+    ///
+    /// Only the root is tagged by the array, with the height of the tree. The array never reads or
+    /// writes the tags of the slots, so all these bits are for the users of the slots.
+    pub const TAG_MASK: usize = mem::align_of::<T>() - 1;
+
     /// Create a new growable array.
     pub fn new() -> Self {
         Self {
             root: Atomic::null(),
-            _marker: PhantomData,
+            segments: AtomicUsize::new(0),
+            free: Arc::new(FreeList::new()),
+        }
+    }
+
+    /// Returns the height of the tree, which is 0 if no segment is allocated.
+    pub fn height(&self) -> usize {
+        // Only the tag is read.
+        self.root
+            .load(Ordering::Acquire, unsafe { unprotected() })
+            .tag()
+    }
+
+    /// Returns the number of the indices that the tree has room for without growing, i.e. one
+    /// more than the largest addressable index, or 0 if no segment is allocated. Saturates at
+    /// `usize::MAX` when every index is addressable.
+    pub fn capacity(&self) -> usize {
+        let height = self.height();
+        let bits = SEGMENT_LOGSIZE * height;
+        if height == 0 {
+            0
+        } else if bits >= mem::size_of::<usize>() * 8 {
+            usize::MAX
+        } else {
+            1 << bits
+        }
+    }
+
+    /// Returns the number of the allocated segments in the tree, including the leaves.
+    pub fn allocated_segments(&self) -> usize {
+        self.segments.load(Ordering::Relaxed)
+    }
+
+    /// Takes a segment from the free list, or allocates one if it is empty.
+    fn alloc_segment(&self, guard: &Guard) -> Result<*mut Segment<T>, AllocError> {
+        match self.free.pop(guard) {
+            Some(segment) => Ok(segment),
+            None => Segment::try_alloc(),
+        }
+    }
+
+    /// Returns the child at `index` of `segment`. Allocates one if there is none. Returns
+    /// `Ok(None)` if the slot is frozen by `truncate`.
+    fn child<'g>(
+        &self,
+        segment: &Segment<T>,
+        index: usize,
+        guard: &'g Guard,
+    ) -> Result<Option<Shared<'g, Segment<T>>>, AllocError> {
+        let slot = &segment.children[index];
+        let child = slot.load(Ordering::Acquire, guard);
+        if !child.is_null() {
+            return Ok(Some(child));
+        }
+        if child.tag() == FROZEN {
+            return Ok(None);
+        }
+
+        let new = Shared::from(self.alloc_segment(guard)? as *const Segment<T>);
+        match slot.compare_and_set(Shared::null(), new, Ordering::AcqRel, guard) {
+            Ok(_) => {
+                let _ = self.segments.fetch_add(1, Ordering::Relaxed);
+                Ok(Some(new))
+            }
+            Err(e) => {
+                #[cfg(feature = "alloc-tracking")]
+                let _ = SEGMENTS_LOST.fetch_add(1, Ordering::Relaxed);
+                unsafe { self.recycle(new, guard) };
+                // Null if frozen.
+                if e.current.is_null() {
+                    Ok(None)
+                } else {
+                    Ok(Some(e.current))
+                }
+            }
+        }
+    }
+
+    /// Pushes a segment that is not in the tree to the free list, but not its children, once the
+    /// other threads can't read it.
+    unsafe fn recycle(&self, segment: Shared<'_, Segment<T>>, guard: &Guard) {
+        let segment = segment.as_raw() as *mut Segment<T>;
+        let free = self.free.clone();
+        guard.defer_unchecked(move || free.push(segment));
+    }
+
+    /// Retires a segment that is unlinked from the tree, but not its children.
+    unsafe fn retire(&self, segment: Shared<'_, Segment<T>>, guard: &Guard) {
+        let _ = self.segments.fetch_sub(1, Ordering::Relaxed);
+        self.recycle(segment, guard);
+    }
+
+    /// Retires a segment of `height` that is unlinked from the tree with its descendants. Its slots
+    /// are frozen, so that a concurrent `get` that has reached it doesn't add a segment under it.
+    unsafe fn prune(&self, segment: Shared<'_, Segment<T>>, height: usize, guard: &Guard) {
+        if height > 1 {
+            for slot in segment.deref().children.iter() {
+                let child = slot.swap(Shared::null().with_tag(FROZEN), Ordering::AcqRel, guard);
+                if !child.is_null() {
+                    self.prune(child, height - 1, guard);
+                }
+            }
+        }
+        self.retire(segment, guard);
+    }
+
+    /// Removes the descendants of `segment` of `height` whose slots are all null, and returns
+    /// whether all the slots of `segment` are null.
+    fn compact_segment(&self, segment: &Segment<T>, height: usize, guard: &Guard) -> bool {
+        if height == 1 {
+            let leaf = unsafe { &*(segment as *const Segment<T> as *const Leaf<T>) };
+            return leaf
+                .elements
+                .iter()
+                .all(|element| element.load(Ordering::Acquire, guard).is_null());
+        }
+
+        let mut empty = true;
+        for slot in segment.children.iter() {
+            let child = slot.load(Ordering::Acquire, guard);
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+            if !self.compact_segment(child_ref, height - 1, guard) {
+                empty = false;
+                continue;
+            }
+            if slot
+                .compare_and_set(child, Shared::null(), Ordering::AcqRel, guard)
+                .is_ok()
+            {
+                unsafe { self.retire(child, guard) };
+            } else {
+                empty = false;
+            }
+        }
+        empty
+    }
+
+    /// Returns the memory footprint of the tree by walking it. The elements and the free segments
+    /// kept for reuse are not counted.
+    ///
+    /// Unlike `allocated_segments`, the segments are counted one by one, so the usage may or may
+    /// not include the segments that are concurrently added or removed.
+    pub fn memory_usage(&self, guard: &Guard) -> MemoryUsage {
+        let root = self.root.load(Ordering::Acquire, guard);
+        let mut usage = MemoryUsage {
+            height: root.tag(),
+            ..MemoryUsage::default()
+        };
+        let mut stack = Vec::new();
+        if let Some(segment) = unsafe { root.with_tag(0).as_ref() } {
+            stack.push((segment, root.tag()));
+        }
+        while let Some((segment, height)) = stack.pop() {
+            usage.segments += 1;
+            if height == 1 {
+                continue;
+            }
+            for child in segment.children.iter() {
+                if let Some(child) = unsafe { child.load(Ordering::Acquire, guard).as_ref() } {
+                    stack.push((child, height - 1));
+                }
+            }
         }
+        usage.bytes = usage.segments * mem::size_of::<Segment<T>>();
+        usage
+    }
+
+    /// Returns the root, growing the tree to at least `height`.
+    fn grow<'g>(
+        &self,
+        height: usize,
+        guard: &'g Guard,
+    ) -> Result<Shared<'g, Segment<T>>, AllocError> {
+        let backoff = ExponentialBackoff::new();
+        let mut root = self.root.load(Ordering::Acquire, guard);
+        while root.tag() < height {
+            // The old root becomes the first child of the new one.
+            let new_height = root.tag() + 1;
+            let new = self.alloc_segment(guard)?;
+            if !root.is_null() {
+                unsafe { (*new).children[0].store(root.with_tag(0), Ordering::Relaxed) };
+            }
+            let new = Shared::from(new as *const Segment<T>).with_tag(new_height);
+
+            match self
+                .root
+                .compare_and_set(root, new, Ordering::AcqRel, guard)
+            {
+                Ok(_) => {
+                    let _ = self.segments.fetch_add(1, Ordering::Relaxed);
+                    counter!("growable_array.grow");
+                    gauge!("growable_array.height", new_height);
+                    root = new;
+                }
+                Err(e) => {
+                    #[cfg(feature = "alloc-tracking")]
+                    let _ = SEGMENTS_LOST.fetch_add(1, Ordering::Relaxed);
+                    unsafe { self.recycle(new.with_tag(0), guard) };
+                    root = e.current;
+                    backoff.backoff();
+                }
+            }
+        }
+        Ok(root)
     }
 
     /// Returns the reference to the `Atomic` pointer at `index`. Allocates new segments if
     /// necessary.
-    pub fn get(&self, mut index: usize, guard: &Guard) -> &Atomic<T> {
-        
-        let bit_num = 64-index.leading_zeros();
-        let new_ptr = Owned::new(Segment::new());
-        let r = self.root.compare_and_set(Shared::null(), new_ptr.with_tag(1), Ordering::AcqRel, guard);
-
-        let mut root = match r {
-            Err(e) => e.current,
-            Ok(t) => t
+    ///
+    /// The slot is valid while `guard` is pinned, since `compact` may retire its segment. Its tag
+    /// bits, `TAG_MASK`, are left to the caller.
+    ///
+    /// Calls `handle_alloc_error` if a segment can't be allocated. See `try_get`.
+    pub fn get<'g>(&'g self, index: usize, guard: &'g Guard) -> &'g Atomic<T> {
+        self.try_get(index, guard)
+            .unwrap_or_else(|_| heap::handle_alloc_error(Segment::<T>::layout()))
+    }
+
+    /// Like `get`, but returns `Err` if a segment can't be allocated. The segments allocated
+    /// before the failure are kept.
+    pub fn try_get<'g>(
+        &'g self,
+        index: usize,
+        guard: &'g Guard,
+    ) -> Result<&'g Atomic<T>, AllocError> {
+        let backoff = ExponentialBackoff::new();
+        'retry: loop {
+            let root = self.grow(height_for(index), guard)?;
+
+            let mut segment = root.with_tag(0);
+            for height in (1..root.tag()).rev() {
+                let child_index = (index >> (SEGMENT_LOGSIZE * height)) & (SEGMENT_SIZE - 1);
+                let child = self.child(unsafe { segment.deref() }, child_index, guard)?;
+                // The segment is removed by `truncate`, so the path is searched again.
+                segment = some_or!(child, {
+                    backoff.backoff();
+                    continue 'retry;
+                });
+            }
+            // The segments are retired by `compact` and `truncate`, so they are not deallocated
+            // while `guard` is pinned.
+            let leaf = unsafe { &*(segment.as_raw() as *const Leaf<T>) };
+            return Ok(&leaf.elements[index & (SEGMENT_SIZE - 1)]);
+        }
+    }
+
+    /// Returns the pointer at `index` without its tag, and the tag. Never allocates a segment, and
+    /// returns null and 0 if the segment of `index` is not allocated.
+    pub fn get_with_tag<'g>(
+        &'g self,
+        index: usize,
+        ord: Ordering,
+        guard: &'g Guard,
+    ) -> (Shared<'g, T>, usize) {
+        let pointer = match self.get_if_exists(index, guard) {
+            Some(slot) => slot.load(ord, guard),
+            None => Shared::null(),
         };
+        (pointer.with_tag(0), pointer.tag())
+    }
 
-        let mut height = root.tag();
-        let bit_height;
-        if (bit_num%10)==0 {
-            bit_height = (bit_num/10) as usize;
+    /// Sets the tag of the pointer at `index` to `tag`, keeping the pointer, and returns the
+    /// previous pointer with its tag. Allocates new segments if necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` has a bit out of `TAG_MASK`, which would be lost.
+    pub fn set_tag<'g>(
+        &'g self,
+        index: usize,
+        tag: usize,
+        ord: Ordering,
+        guard: &'g Guard,
+    ) -> Shared<'g, T> {
+        assert_eq!(
+            tag & !Self::TAG_MASK,
+            0,
+            "tag {:#x} does not fit in the tag bits {:#x}",
+            tag,
+            Self::TAG_MASK
+        );
+        let slot = self.get(index, guard);
+        let mut current = slot.load(Ordering::Relaxed, guard);
+        loop {
+            match slot.compare_and_set(current, current.with_tag(tag), ord, guard) {
+                Ok(_) => return current,
+                Err(e) => current = e.current,
+            }
         }
-        else {
-            bit_height = ((bit_num/10)+1) as usize;
+    }
+
+    /// Grows the tree for `max_index`, and allocates the segments of `reserve` ahead of time, so
+    /// that `get` of the indices up to `max_index` doesn't allocate.
+    ///
+    /// `Reserve::All` allocates a leaf per 1024 indices up to `max_index`.
+    pub fn reserve(&self, max_index: usize, reserve: Reserve, guard: &Guard) {
+        let _ = self
+            .grow(height_for(max_index), guard)
+            .unwrap_or_else(|_| heap::handle_alloc_error(Segment::<T>::layout()));
+        if reserve == Reserve::All {
+            for leaf in 0..max_index >> SEGMENT_LOGSIZE {
+                let _ = self.get(leaf << SEGMENT_LOGSIZE, guard);
+            }
         }
-        
-        loop {
-            if height<bit_height{
-                let next = Owned::new(Segment::new()); 
-                unsafe {
-                    let index_zero = &*next.get_unchecked(usize::MIN);
-                    index_zero.store(root.into_usize(), Ordering::Release);
-                    let result = self.root.compare_and_set(root, next.with_tag(height+1), Ordering::AcqRel, guard);
-                    match result {
-                        Err(e) => root = e.current,
-                        Ok(t) => root = t
-                    }
+        let _ = self.get(max_index, guard);
+    }
+
+    /// Stores `new` at `index`, and returns the previous pointer. Allocates new segments if
+    /// necessary.
+    pub fn swap<'g, P: Pointer<T>>(
+        &'g self,
+        index: usize,
+        new: P,
+        ord: Ordering,
+        guard: &'g Guard,
+    ) -> Shared<'g, T> {
+        self.get(index, guard).swap(new, ord, guard)
+    }
+
+    /// Replaces the pointer at `index` with null, and returns it. Returns null without allocating
+    /// if the segment of `index` is not allocated.
+    pub fn take<'g>(&'g self, index: usize, guard: &'g Guard) -> Shared<'g, T> {
+        match self.get_if_exists(index, guard) {
+            Some(slot) => slot.swap(Shared::null(), Ordering::AcqRel, guard),
+            None => Shared::null(),
+        }
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index` if its segment is allocated.
+    /// Unlike `get`, never allocates.
+    pub fn get_if_exists<'g>(&'g self, index: usize, guard: &'g Guard) -> Option<&'g Atomic<T>> {
+        let root = self.root.load(Ordering::Acquire, guard);
+        if root.is_null()
+            || (SEGMENT_LOGSIZE * root.tag() < mem::size_of::<usize>() * 8
+                && index >> (SEGMENT_LOGSIZE * root.tag()) != 0)
+        {
+            return None;
+        }
+
+        let mut segment = root.with_tag(0);
+        for height in (1..root.tag()).rev() {
+            let child_index = (index >> (SEGMENT_LOGSIZE * height)) & (SEGMENT_SIZE - 1);
+            segment = unsafe { segment.deref() }.children[child_index].load(Ordering::Acquire, guard);
+            if segment.is_null() {
+                return None;
+            }
+        }
+        let leaf = unsafe { &*(segment.as_raw() as *const Leaf<T>) };
+        Some(&leaf.elements[index & (SEGMENT_SIZE - 1)])
+    }
+
+    /// Returns an iterator over the indices and the slots whose pointers are not null, in the
+    /// order of the indices. Never allocates a segment.
+    ///
+    /// The iterator may or may not see the slots that are concurrently set or cleared.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T> {
+        let root = self.root.load(Ordering::Acquire, guard);
+        let mut stack = Vec::new();
+        if let Some(segment) = unsafe { root.with_tag(0).as_ref() } {
+            stack.push(Frame {
+                segment,
+                height: root.tag(),
+                base: 0,
+                next: 0,
+            });
+        }
+        Iter { stack, guard }
+    }
+
+    /// Returns the smallest index whose slot is not null, or `None` if all the slots are null.
+    /// Never allocates a segment.
+    ///
+    /// As in `iter`, the slots that are concurrently set or cleared may or may not be seen.
+    pub fn min_index(&self, guard: &Guard) -> Option<usize> {
+        let root = self.root.load(Ordering::Acquire, guard);
+        unsafe { root.with_tag(0).as_ref() }?.occupied(root.tag(), 0, false, guard)
+    }
+
+    /// Returns the largest index whose slot is not null, or `None` if all the slots are null.
+    /// Never allocates a segment.
+    ///
+    /// As in `iter`, the slots that are concurrently set or cleared may or may not be seen.
+    pub fn max_index(&self, guard: &Guard) -> Option<usize> {
+        let root = self.root.load(Ordering::Acquire, guard);
+        unsafe { root.with_tag(0).as_ref() }?.occupied(root.tag(), 0, true, guard)
+    }
+
+    /// Returns the indices and the pointers of the non-null slots, in the order of the indices.
+    ///
+    /// As in `iter`, the snapshot may or may not contain the slots that are concurrently set or
+    /// cleared.
+    pub fn snapshot<'g>(&'g self, guard: &'g Guard) -> Vec<(usize, Shared<'g, T>)> {
+        self.iter(guard)
+            .map(|(index, slot)| (index, slot.load(Ordering::Acquire, guard)))
+            .filter(|(_, pointer)| !pointer.is_null())
+            .collect()
+    }
+
+    /// Removes the segments whose indices are all above `max_index`, and lowers the tree to the
+    /// height for `max_index`. The removed segments are retired through `guard`. As on drop, the
+    /// elements are not dropped.
+    ///
+    /// A concurrent `get` of an index above `max_index` may add the segments back, or return a
+    /// slot of a removed segment, which is then removed as if it were set before `truncate`. The
+    /// tree is not lowered if it is concurrently grown. `truncate` should not run concurrently
+    /// with `compact` or another `truncate`.
+    pub fn truncate(&self, max_index: usize, guard: &Guard) {
+        let mut root = self.root.load(Ordering::Acquire, guard);
+        let height = height_for(max_index);
+        if height > root.tag() {
+            return;
+        }
+
+        // Removes the subtrees to the right of the path to `max_index`.
+        let mut segment = root.with_tag(0);
+        for child_height in (1..root.tag()).rev() {
+            let segment_ref = some_or!(unsafe { segment.as_ref() }, break);
+            let child_index = (max_index >> (SEGMENT_LOGSIZE * child_height)) & (SEGMENT_SIZE - 1);
+            for slot in segment_ref.children[child_index + 1..].iter() {
+                let child = slot.swap(Shared::null(), Ordering::AcqRel, guard);
+                if !child.is_null() {
+                    unsafe { self.prune(child, child_height, guard) };
+                }
+            }
+            segment = segment_ref.children[child_index].load(Ordering::Acquire, guard);
+        }
+
+        let frozen = Shared::null().with_tag(FROZEN);
+        while root.tag() > height {
+            // The indices of the slots but the first are above `max_index`, so they are frozen
+            // with the subtrees added in the meantime removed, and the first child becomes the
+            // root. The first slot is frozen too if it is null.
+            let segment = unsafe { root.with_tag(0).deref() };
+            for slot in segment.children[1..].iter() {
+                let child = slot.swap(frozen, Ordering::AcqRel, guard);
+                if !child.is_null() {
+                    unsafe { self.prune(child, root.tag() - 1, guard) };
                 }
-                height = root.tag();
             }
-            else { break; }
-        }
-    
-        let mut curr_seg = root;
-        let mut currIndex = index;
-        let mut root_height = root.tag();
-        loop{
-            let two:usize = 2;
-            let max_bit = two.pow(10 * root_height as u32) - 1;
-            let mut new_index = currIndex & max_bit;
-            new_index = new_index >> (10 * (root_height -1));
-            if(root_height>1){
-                unsafe{
-                    let seg_index = (curr_seg.deref()).get_unchecked(new_index);
-                    let val = Shared::into_usize(Owned::new(Segment::new()).into_shared(guard));
-                    let usize_zero = seg_index.compare_and_swap(0, val, Ordering::AcqRel);
-                    match usize_zero {
-                        0 => curr_seg = Shared::from_usize(val),
-                        _ =>{
-                            let x:Owned<Segment> = Shared::from_usize(val).into_owned();
-                            curr_seg = Shared::from_usize(usize_zero)
-                        }   
+            let new = match segment.children[0].compare_and_set(
+                Shared::null(),
+                frozen,
+                Ordering::AcqRel,
+                guard,
+            ) {
+                Ok(_) => Shared::null(),
+                Err(e) => e.current.with_tag(root.tag() - 1),
+            };
+
+            match self
+                .root
+                .compare_and_set(root, new, Ordering::AcqRel, guard)
+            {
+                Ok(_) => {
+                    gauge!("growable_array.height", new.tag());
+                    unsafe { self.retire(root.with_tag(0), guard) };
+                    root = new;
+                }
+                Err(_) => {
+                    // The segment is under the new root, so its slots are unfrozen.
+                    for slot in segment.children.iter() {
+                        let _ =
+                            slot.compare_and_set(frozen, Shared::null(), Ordering::AcqRel, guard);
                     }
+                    break;
                 }
-                root_height-=1;
             }
-            else{
-                unsafe{ 
-                    let seg = curr_seg.deref();
-                    return &*(seg.get_unchecked(new_index) as *const _ as *const Atomic<T>)
+        }
+    }
+
+    /// Sets all the slots to null, but keeps the segments, so that `get` doesn't allocate them
+    /// again. As on drop, the elements are not dropped.
+    ///
+    /// The slots that are concurrently set may or may not be cleared.
+    pub fn clear(&self, guard: &Guard) {
+        for (_, slot) in self.iter(guard) {
+            slot.store(Shared::null(), Ordering::Release);
+        }
+    }
+
+    /// Removes the segments whose slots are all null, and lowers the tree while its root has
+    /// only the first child. The removed segments are retired through `guard`.
+    ///
+    /// It may run concurrently with the readers, e.g. `get_if_exists` and `iter`, which see the
+    /// removed slots as null.
+    ///
+    /// # Safety
+    ///
+    /// `compact` should not run concurrently with `get`, `try_get`, `swap`, `set_tag`, `reserve`,
+    /// or the threads that set the slots returned by them, since a segment or a slot that is added
+    /// under a segment after it is found empty is removed with the segment, and the element that
+    /// was stored in it is leaked.
+    pub unsafe fn compact(&self, guard: &Guard) {
+        let mut root = self.root.load(Ordering::Acquire, guard);
+        let segment = some_or!(root.with_tag(0).as_ref(), return);
+        if self.compact_segment(segment, root.tag(), guard) {
+            if self
+                .root
+                .compare_and_set(root, Shared::null(), Ordering::AcqRel, guard)
+                .is_ok()
+            {
+                self.retire(root.with_tag(0), guard);
+            }
+            return;
+        }
+
+        while root.tag() > 1 {
+            let segment = root.with_tag(0).deref();
+            if segment.children[1..]
+                .iter()
+                .any(|child| !child.load(Ordering::Acquire, guard).is_null())
+            {
+                break;
+            }
+            // Not null, since the tree is not empty.
+            let new_height = root.tag() - 1;
+            let new = segment.children[0]
+                .load(Ordering::Acquire, guard)
+                .with_tag(new_height);
+            match self
+                .root
+                .compare_and_set(root, new, Ordering::AcqRel, guard)
+            {
+                Ok(_) => {
+                    gauge!("growable_array.height", new_height);
+                    self.retire(root.with_tag(0), guard);
+                    root = new;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<T> GrowableArray<T> {
+    /// See `debug_dump::growable_array`.
+    pub(crate) fn dump(&self, format: Format, guard: &Guard) -> String {
+        fn dump_segment<T>(
+            writer: &mut Writer,
+            segment: &Segment<T>,
+            height: usize,
+            depth: usize,
+            base: usize,
+            guard: &Guard,
+        ) {
+            let id = ("s", segment as *const _ as usize);
+            if height == 1 {
+                let leaf = unsafe { &*(segment as *const Segment<T> as *const Leaf<T>) };
+                let set = || {
+                    leaf.elements
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, e)| !e.load(Ordering::Acquire, guard).is_null())
+                        .map(|(i, _)| base + i)
+                };
+                let mut ranges = String::new();
+                debug_dump::write_ranges(&mut ranges, set());
+                let label = format_args!("leaf from {}: {} set: {}", base, set().count(), ranges);
+                writer.node(id, depth, Kind::Segment, label);
+                return;
+            }
+
+            writer.node(
+                id,
+                depth,
+                Kind::Segment,
+                format_args!("height {} from {}", height, base),
+            );
+            for (i, child) in segment.children.iter().enumerate() {
+                let child = child.load(Ordering::Acquire, guard);
+                if let Some(child) = unsafe { child.as_ref() } {
+                    writer.edge(id, ("s", child as *const _ as usize), Some(i));
+                    let base = base + (i << (SEGMENT_LOGSIZE * (height - 1)));
+                    dump_segment(writer, child, height - 1, depth + 1, base, guard);
                 }
             }
         }
+
+        let root = self.root.load(Ordering::Acquire, guard);
+        let mut writer = Writer::new(format, format_args!("GrowableArray: height {}", root.tag()));
+        if let Some(segment) = unsafe { root.with_tag(0).as_ref() } {
+            dump_segment(&mut writer, segment, root.tag(), 0, 0, guard);
+        }
+        writer.finish()
     }
 }
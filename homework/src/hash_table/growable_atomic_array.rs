@@ -0,0 +1,83 @@
+//! Growable array of inline values.
+
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{Guard, Shared};
+
+use super::growable_array::GrowableArray;
+
+/// Growable array of `T`s stored inline in the slots, e.g. an array of counters.
+///
+/// It is a `GrowableArray` whose slots keep the `usize`s of the values instead of the pointers to
+/// them, so an access doesn't follow a pointer per element. The "pointers" are to `u8`, whose
+/// alignment leaves no bits for the tag, and are never dereferenced.
+///
+/// A value is converted into a `usize` by `Into` and back by `TryFrom`, which should round-trip.
+/// Each slot starts as `0`, so loading a slot that is never stored panics if `0` is not a `T`.
+#[derive(Debug)]
+pub struct GrowableAtomicArray<T> {
+    inner: GrowableArray<u8>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for GrowableAtomicArray<T> {
+    fn default() -> Self {
+        Self {
+            inner: GrowableArray::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy + Into<usize> + TryFrom<usize>> GrowableAtomicArray<T> {
+    /// Creates a new array.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value at `index`. Never allocates a segment.
+    pub fn load(&self, index: usize, ord: Ordering, guard: &Guard) -> T {
+        let raw = match self.inner.get_if_exists(index, guard) {
+            Some(slot) => slot.load(ord, guard).as_raw() as usize,
+            None => 0,
+        };
+        Self::from_raw(raw)
+    }
+
+    /// Stores `value` at `index`. Allocates new segments if necessary.
+    pub fn store(&self, index: usize, value: T, ord: Ordering, guard: &Guard) {
+        self.inner.get(index, guard).store(Self::to_raw(value), ord);
+    }
+
+    /// Stores `new` at `index` if the value is `current`. Returns the previous value on success,
+    /// and the current value on failure. Allocates new segments if necessary.
+    pub fn cas(
+        &self,
+        index: usize,
+        current: T,
+        new: T,
+        ord: Ordering,
+        guard: &Guard,
+    ) -> Result<T, T> {
+        self.inner
+            .get(index, guard)
+            .compare_and_set(Self::to_raw(current), Self::to_raw(new), ord, guard)
+            .map(|_| current)
+            .map_err(|e| Self::from_raw(e.current.as_raw() as usize))
+    }
+
+    /// Returns the underlying array, e.g. for `GrowableArray::memory_usage`. Its non-null slots
+    /// are the nonzero values.
+    pub fn as_array(&self) -> &GrowableArray<u8> {
+        &self.inner
+    }
+
+    fn to_raw<'g>(value: T) -> Shared<'g, u8> {
+        Shared::from(value.into() as *const u8)
+    }
+
+    fn from_raw(raw: usize) -> T {
+        T::try_from(raw).unwrap_or_else(|_| panic!("{} is not a value of the array", raw))
+    }
+}
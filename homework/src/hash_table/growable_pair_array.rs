@@ -0,0 +1,126 @@
+//! Growable array of pairs of words.
+
+use core::sync::atomic::{self, Ordering};
+use crossbeam_epoch::Guard;
+
+use super::growable_atomic_array::GrowableAtomicArray;
+use crate::backoff::ExponentialBackoff;
+
+/// The number of the slots of the underlying array per index: the sequence number and the words.
+const STRIDE: usize = 3;
+
+/// Growable array of pairs of `usize`s that are read and written together, e.g. a pointer and its
+/// version counter for ABA-safe publication.
+///
+/// Each index takes three adjacent slots of a `GrowableAtomicArray`: a sequence number and the two
+/// words. The pairs are protected by seqlocks. A writer makes the sequence number odd while it
+/// writes the words, so the writers of an index wait for each other. A reader doesn't write
+/// anything, and retries if the sequence number is odd or changes while it reads the words.
+///
+/// Each pair starts as `(0, 0)`.
+#[derive(Debug, Default)]
+pub struct GrowablePairArray {
+    inner: GrowableAtomicArray<usize>,
+}
+
+impl GrowablePairArray {
+    /// The largest index.
+    pub const MAX_INDEX: usize = (usize::MAX - 2) / STRIDE;
+
+    /// Creates a new array.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the pair at `index`. Never allocates a segment.
+    pub fn load(&self, index: usize, guard: &Guard) -> (usize, usize) {
+        let seq = Self::seq_index(index);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let before = self.inner.load(seq, Ordering::Acquire, guard);
+            if before & 1 == 0 {
+                let pair = (
+                    self.inner.load(seq + 1, Ordering::Relaxed, guard),
+                    self.inner.load(seq + 2, Ordering::Relaxed, guard),
+                );
+                // Synchronizes with the fence of a writer whose words are read, so that the
+                // sequence number is seen odd or advanced.
+                atomic::fence(Ordering::Acquire);
+                if self.inner.load(seq, Ordering::Relaxed, guard) == before {
+                    return pair;
+                }
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Stores `pair` at `index`. Allocates new segments if necessary.
+    pub fn store(&self, index: usize, pair: (usize, usize), guard: &Guard) {
+        let seq = self.lock(index, guard);
+        self.write(index, seq, pair, guard);
+    }
+
+    /// Stores `new` at `index` if the pair is `current`. Returns the previous pair on success, and
+    /// the current pair on failure. Allocates new segments if necessary.
+    pub fn cas(
+        &self,
+        index: usize,
+        current: (usize, usize),
+        new: (usize, usize),
+        guard: &Guard,
+    ) -> Result<(usize, usize), (usize, usize)> {
+        let seq = self.lock(index, guard);
+        let slot = Self::seq_index(index);
+        let pair = (
+            self.inner.load(slot + 1, Ordering::Relaxed, guard),
+            self.inner.load(slot + 2, Ordering::Relaxed, guard),
+        );
+        if pair != current {
+            // Restores the sequence number, since the words are not changed.
+            self.inner.store(slot, seq, Ordering::Release, guard);
+            return Err(pair);
+        }
+        self.write(index, seq, new, guard);
+        Ok(pair)
+    }
+
+    /// Returns the slot of the sequence number of `index` in the underlying array.
+    fn seq_index(index: usize) -> usize {
+        assert!(
+            index <= Self::MAX_INDEX,
+            "index {} is larger than {}",
+            index,
+            Self::MAX_INDEX
+        );
+        index * STRIDE
+    }
+
+    /// Makes the sequence number of `index` odd, and returns the even one before it.
+    fn lock(&self, index: usize, guard: &Guard) -> usize {
+        let seq = Self::seq_index(index);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let current = self.inner.load(seq, Ordering::Relaxed, guard);
+            if current & 1 == 0
+                && self
+                    .inner
+                    .cas(seq, current, current + 1, Ordering::Acquire, guard)
+                    .is_ok()
+            {
+                // Orders the odd sequence number before the words for the readers.
+                atomic::fence(Ordering::Release);
+                return current;
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Writes `pair` at `index` locked by `lock`, and unlocks it.
+    fn write(&self, index: usize, seq: usize, pair: (usize, usize), guard: &Guard) {
+        let slot = Self::seq_index(index);
+        self.inner.store(slot + 1, pair.0, Ordering::Relaxed, guard);
+        self.inner.store(slot + 2, pair.1, Ordering::Relaxed, guard);
+        self.inner
+            .store(slot, seq.wrapping_add(2), Ordering::Release, guard);
+    }
+}
@@ -1,7 +1,17 @@
 //! Lock-free hash table Based on https://dl.acm.org/doi/abs/10.1145/1147954.1147958
 
-mod growable_array;
-mod split_ordered_list;
+pub mod growable_array;
+pub mod growable_atomic_array;
+pub mod growable_pair_array;
+pub mod split_ordered_list;
+#[cfg(feature = "std")]
+mod split_ordered_map;
+pub mod split_ordered_multimap;
 
 pub use growable_array::GrowableArray;
+pub use growable_atomic_array::GrowableAtomicArray;
+pub use growable_pair_array::GrowablePairArray;
 pub use split_ordered_list::SplitOrderedList;
+#[cfg(feature = "std")]
+pub use split_ordered_map::SplitOrderedMap;
+pub use split_ordered_multimap::SplitOrderedMultimap;
@@ -1,51 +1,702 @@
 //! Split-ordered linked list.
 
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+#[cfg(feature = "std")]
+use core::iter::FromIterator;
 use core::mem;
+use core::ops::{Bound, RangeBounds};
 use core::sync::atomic::{AtomicUsize, Ordering};
-use crossbeam_epoch::{Guard, Owned, Shared, Atomic};
-use lockfree::list::{Cursor, List, Node};
+use crossbeam_epoch::{unprotected, Guard, Shared};
+#[cfg(feature = "std")]
+use std::sync::Arc;
 
-use super::growable_array::GrowableArray;
-use crate::map::NonblockingMap;
+use super::growable_array::{GrowableArray, MemoryUsage};
+use crate::arena::{Arena, ArenaBox, ArenaStats};
+use crate::backoff::ExponentialBackoff;
+use crate::counter::StripedCounter;
+use crate::debug_dump::{Format, Kind, Writer};
+#[cfg(feature = "std")]
+use crate::hello_server::ThreadPool;
+use crate::list::{self, Cursor, List, Node};
+use crate::map::{MapError, NonblockingMap};
 
-/// Lock-free map from `usize` in range [0, 2^63-1] to `V`.
+#[cfg(all(feature = "rayon", feature = "std"))]
+pub use rayon_impl::ParIter;
+
+/// Key of a node: the reversed bits of the hash of the key, or of the index of the bucket for a
+/// sentinel, whether the node is a regular node, and the key. A sentinel is ordered before the
+/// regular nodes of its bucket, including the ones of the same reversed bits.
+type NodeKey = (usize, bool, usize);
+
+/// Cursor of the list of `SplitOrderedList<V>`.
+type ListCursor<'g, V> = Cursor<'g, NodeKey, Option<V>>;
+
+type ListNode<V> = Node<NodeKey, Option<V>>;
+
+/// Returns the node key of the sentinel of the bucket `index`.
+fn sentinel_key(index: usize) -> NodeKey {
+    (index.reverse_bits(), false, 0)
+}
+
+/// Hasher that returns the key as is.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 << 8) | b as u64;
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.0 = i as u64;
+    }
+}
+
+/// The default hasher of `SplitOrderedList`, which puts a key in the bucket of its low bits.
+pub type IdentityState = BuildHasherDefault<IdentityHasher>;
+
+/// Lock-free map from `usize` to `V`.
 ///
-/// NOTE: We don't care about hashing in this homework for simplicity.
+/// The keys are hashed by `S` into the buckets. The default `IdentityState` doesn't mix the bits
+/// of the keys, so the keys that share the low bits, e.g. the multiples of a large power of two,
+/// fall in the same bucket. For such keys, create the list by `with_hasher` with e.g.
+/// `std::collections::hash_map::RandomState`.
 #[derive(Debug)]
-pub struct SplitOrderedList<V> {
+pub struct SplitOrderedList<V, S = IdentityState> {
     /// Lock-free list sorted by recursive-split order. Use `None` sentinel node value.
-    list: List<usize, Option<V>>,
+    list: List<NodeKey, Option<V>>,
     /// array of pointers to the buckets
-    buckets: GrowableArray<Node<usize, Option<V>>>,
+    buckets: GrowableArray<Node<NodeKey, Option<V>>>,
     /// number of buckets
     size: AtomicUsize,
-    /// number of items
-    count: AtomicUsize,
+    /// number of items, which is approximate while the list is modified concurrently
+    count: StripedCounter,
+    /// Allocates the sentinel nodes, which are removed only when `size` is halved.
+    sentinels: Arena<ListNode<V>>,
+    /// Allocates the regular nodes.
+    nodes: Arena<ListNode<V>>,
+    hash_builder: S,
 }
 
-impl<V> Default for SplitOrderedList<V> {
-    fn default() -> Self {
-        Self {
-            list: List::new(),
-            buckets: GrowableArray::new(),
-            size: AtomicUsize::new(2),
-            count: AtomicUsize::new(0),
+/// Iterator over the key-value pairs of a `SplitOrderedList` in the split order.
+#[derive(Debug)]
+pub struct Iter<'g, V> {
+    inner: list::Iter<'g, NodeKey, Option<V>>,
+}
+
+/// Iterator over the key-value pairs of a `SplitOrderedList` whose keys are in a range, returned by
+/// `SplitOrderedList::range`.
+#[derive(Debug)]
+pub struct Range<'g, V, S = IdentityState> {
+    inner: RangeInner<'g, V, S>,
+}
+
+#[derive(Debug)]
+enum RangeInner<'g, V, S> {
+    /// Looks up the keys from `next` to `last` one by one.
+    Lookup {
+        list: &'g SplitOrderedList<V, S>,
+        /// `None` if all the keys are looked up.
+        next: Option<usize>,
+        last: usize,
+        guard: &'g Guard,
+    },
+    /// Scans the whole list for the keys from `first` to `last`.
+    Scan {
+        iter: Iter<'g, V>,
+        first: usize,
+        last: usize,
+    },
+}
+
+/// Iterator over the values of a key, for the lists where multiple nodes may have the same key.
+#[derive(Debug)]
+pub(crate) struct ValuesBy<'g, V> {
+    key: NodeKey,
+    /// At the next node of the key, or `None` if the iteration is over.
+    cursor: Option<ListCursor<'g, V>>,
+    guard: &'g Guard,
+}
+
+/// Occupancy of the buckets of a `SplitOrderedList`, returned by `SplitOrderedList::stats`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MapStats {
+    /// The number of buckets.
+    pub buckets: usize,
+    /// The number of items.
+    pub items: usize,
+    /// The number of initialized buckets, i.e., of the sentinels in the list.
+    pub sentinels: usize,
+    /// `chain_lengths[n]` is the number of initialized buckets that have `n` items until the next
+    /// sentinel.
+    pub chain_lengths: Vec<usize>,
+}
+
+impl MapStats {
+    /// Returns the length of the longest chain.
+    pub fn max_chain_length(&self) -> usize {
+        self.chain_lengths.len().saturating_sub(1)
+    }
+
+    fn add_chain(&mut self, length: usize) {
+        if self.chain_lengths.len() <= length {
+            self.chain_lengths.resize(length + 1, 0);
         }
+        self.chain_lengths[length] += 1;
     }
 }
 
-impl<V> SplitOrderedList<V> {
-    /// `size` is doubled when `count > size * LOAD_FACTOR`.
-    const LOAD_FACTOR: usize = 2;
+/// A key of a `SplitOrderedList`, returned by `SplitOrderedList::entry`.
+///
+/// The operations of an entry start from the position of the key that is found by `entry`, so
+/// they don't search the key again unless the key is concurrently modified by other threads.
+#[derive(Debug)]
+pub enum Entry<'g, V, S = IdentityState> {
+    /// The key has a value.
+    Occupied(OccupiedEntry<'g, V, S>),
+    /// The key doesn't have a value.
+    Vacant(VacantEntry<'g, V, S>),
+}
+
+/// A key that has a value.
+#[derive(Debug)]
+pub struct OccupiedEntry<'g, V, S = IdentityState> {
+    list: &'g SplitOrderedList<V, S>,
+    key: usize,
+    /// At the node of the key.
+    cursor: ListCursor<'g, V>,
+    guard: &'g Guard,
+}
+
+/// A key that doesn't have a value.
+#[derive(Debug)]
+pub struct VacantEntry<'g, V, S = IdentityState> {
+    list: &'g SplitOrderedList<V, S>,
+    key: usize,
+    /// The number of buckets when the key is searched.
+    size: usize,
+    /// At the position to insert the key.
+    cursor: ListCursor<'g, V>,
+    guard: &'g Guard,
+}
+
+impl<V, S: Default> Default for SplitOrderedList<V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<V, S> Drop for SplitOrderedList<V, S> {
+    fn drop(&mut self) {
+        mem::take(&mut self.list).into_nodes(|node| unsafe { Arena::free(node) });
+    }
+}
 
+impl<V> SplitOrderedList<V> {
     /// Creates a new split ordered list.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Creates a new split ordered list with the buckets for `capacity` items. See
+    /// `with_capacity_and_hasher`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, IdentityState::default())
+    }
+}
+
+impl<V, S> SplitOrderedList<V, S> {
+    /// `size` is doubled when `count > size * LOAD_FACTOR`, and halved when
+    /// `count < size / (2 * LOAD_FACTOR)`. The count is checked every `LOAD_FACTOR` updates of a
+    /// stripe of it.
+    const LOAD_FACTOR: usize = 2;
+
+    /// The initial and the smallest `size`.
+    const MIN_SIZE: usize = 2;
+
+    /// Creates a new split ordered list that hashes the keys with `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            list: unsafe { List::with_retire(Arena::retire) },
+            buckets: GrowableArray::new(),
+            size: AtomicUsize::new(Self::MIN_SIZE),
+            count: StripedCounter::new(),
+            sentinels: Arena::new(),
+            nodes: Arena::new(),
+            hash_builder,
+        }
+    }
+
+    /// Returns the hasher of the list.
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Grows `size` so that it is not doubled until the list has `items` items.
+    fn grow_to(&self, items: usize) {
+        let size = (items / Self::LOAD_FACTOR)
+            .next_power_of_two()
+            .max(Self::MIN_SIZE);
+        let _ = self.size.fetch_max(size, Ordering::AcqRel);
+    }
+
+    /// Inserts `node` at `cursor`. Returns the node back if it fails.
+    fn insert_node<'g>(
+        cursor: &mut ListCursor<'g, V>,
+        node: ArenaBox<ListNode<V>>,
+        guard: &'g Guard,
+    ) -> Result<(), ArenaBox<ListNode<V>>> {
+        let node = node.into_raw();
+        unsafe {
+            cursor
+                .insert_shared(Shared::from(node as *const _), guard)
+                .map_err(|()| ArenaBox::from_raw(node))
+        }
+    }
+
+    /// Replaces the node at `cursor` with `node`, and returns the old value. Returns the node back
+    /// if it fails.
+    fn replace_node<'g>(
+        cursor: &mut ListCursor<'g, V>,
+        node: ArenaBox<ListNode<V>>,
+        guard: &'g Guard,
+    ) -> Result<&'g Option<V>, ArenaBox<ListNode<V>>> {
+        let node = node.into_raw();
+        unsafe {
+            cursor
+                .replace_shared(Shared::from(node as *const _), guard)
+                .map_err(|()| ArenaBox::from_raw(node))
+        }
+    }
+}
+
+impl<V, S: BuildHasher> SplitOrderedList<V, S> {
+    /// Creates a new split ordered list that hashes the keys with `hash_builder`, with the buckets
+    /// for `capacity` items.
+    ///
+    /// The sentinels of all the buckets are inserted up front, so the insertions don't initialize
+    /// the buckets until the list has `capacity` items. The deletions may shrink the list and
+    /// remove the buckets again.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let list = Self::with_hasher(hash_builder);
+        list.grow_to(capacity);
+        let size = list.size.load(Ordering::Relaxed);
+        // The list is not shared yet, so no node is unlinked. The parent of a bucket has a smaller
+        // index, so it is initialized before the bucket.
+        let guard = unsafe { unprotected() };
+        for index in 0..size {
+            let _ = list.initialize_bucket(index, size, guard);
+        }
+        list
+    }
+
+    /// Unlinks the deleted nodes that are not unlinked yet, including the sentinels of the buckets
+    /// removed by shrinking, and flushes the garbage of this thread to the collector. See
+    /// `reclamation`.
+    pub fn collect(&self, guard: &Guard) {
+        self.list.collect(guard);
+    }
+
+    /// Returns the entry of `key` for in-place manipulation.
+    pub fn entry<'g>(&'g self, key: &usize, guard: &'g Guard) -> Entry<'g, V, S> {
+        let (size, found, cursor) = self.find(key, guard);
+        if found {
+            Entry::Occupied(OccupiedEntry {
+                list: self,
+                key: *key,
+                cursor,
+                guard,
+            })
+        } else {
+            Entry::Vacant(VacantEntry {
+                list: self,
+                key: *key,
+                size,
+                cursor,
+                guard,
+            })
+        }
+    }
+
+    /// Deletes all the key-value pairs, keeping the sentinels. The nodes are freed after the
+    /// threads currently pinned are unpinned.
+    ///
+    /// The pairs that are concurrently inserted may or may not be deleted.
+    pub fn clear(&self, guard: &Guard) {
+        let deleted = self.list.delete_if(|_, value| value.is_some(), guard);
+        self.count.add(-(deleted as isize));
+    }
+
+    /// Returns an iterator over the key-value pairs in the split order, i.e., the order of the
+    /// reversed bits of the hashes of the keys.
+    ///
+    /// The iterator may or may not see the pairs that are concurrently inserted or deleted.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, V> {
+        Iter {
+            inner: self.list.iter(guard),
+        }
+    }
+
+    /// Returns an iterator over the key-value pairs whose keys are in `range`, in an unspecified
+    /// order.
+    ///
+    /// The keys of a range are scattered over the list in the split order. If the range has fewer
+    /// keys than the list has items, the keys are looked up one by one. Otherwise, the whole list
+    /// is scanned. As in `iter`, the iterator may or may not see the pairs that are concurrently
+    /// inserted or deleted.
+    pub fn range<'g, R: RangeBounds<usize>>(
+        &'g self,
+        range: R,
+        guard: &'g Guard,
+    ) -> Range<'g, V, S> {
+        let first = match range.start_bound() {
+            Bound::Included(&start) => Some(start),
+            Bound::Excluded(&start) => start.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let last = match range.end_bound() {
+            Bound::Included(&end) => Some(end),
+            Bound::Excluded(&end) => end.checked_sub(1),
+            Bound::Unbounded => Some(usize::MAX),
+        };
+        let bounds = match (first, last) {
+            (Some(first), Some(last)) if first <= last => Some((first, last)),
+            _ => None,
+        };
+
+        let inner = match bounds {
+            Some((first, last)) if last - first >= self.count.sum().max(0) as usize => {
+                RangeInner::Scan {
+                    iter: self.iter(guard),
+                    first,
+                    last,
+                }
+            }
+            // An empty range looks up no key.
+            _ => RangeInner::Lookup {
+                list: self,
+                next: bounds.map(|(first, _)| first),
+                last: bounds.map_or(0, |(_, last)| last),
+                guard,
+            },
+        };
+        Range { inner }
+    }
+
+    /// Returns the value of `key`, or inserts the value returned by `f` if the list doesn't have
+    /// `key`.
+    ///
+    /// `f` is called at most once. If another thread inserts `key` after `f` is called, the value
+    /// of the other thread is returned, and the value of `f` is dropped.
+    pub fn get_or_insert_with<'g, F: FnOnce() -> V>(
+        &'g self,
+        key: &usize,
+        f: F,
+        guard: &'g Guard,
+    ) -> &'g V {
+        match self.try_insert_with(key, f, guard) {
+            Ok(value) | Err(value) => value,
+        }
+    }
+
+    /// Inserts the value returned by `f` if the list doesn't have `key`, and returns the inserted
+    /// value. Returns the existing value in `Err` if the list has `key`.
+    ///
+    /// `f` is called at most once, only if `key` is not found. If another thread inserts `key`
+    /// after `f` is called, the value of `f` is dropped.
+    pub fn try_insert_with<'g, F: FnOnce() -> V>(
+        &'g self,
+        key: &usize,
+        f: F,
+        guard: &'g Guard,
+    ) -> Result<&'g V, &'g V> {
+        let mut f = Some(f);
+        let mut new_node = None;
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let (size, found, mut cursor) = self.find(key, guard);
+            if found {
+                return Err(cursor.lookup().unwrap().as_ref().unwrap());
+            }
+            let node = new_node.take().unwrap_or_else(|| {
+                let value = f.take().unwrap()();
+                self.new_node(*key, value, guard)
+            });
+            yield_point!();
+            match Self::insert_node(&mut cursor, node, guard) {
+                Err(n) => {
+                    new_node = Some(n);
+                    backoff.backoff();
+                }
+                Ok(()) => {
+                    self.count_insert(size);
+                    return Ok(cursor.lookup().unwrap().as_ref().unwrap());
+                }
+            }
+        }
+    }
+
+    /// Deletes `key` if its value satisfies `pred`, and returns the value.
+    ///
+    /// If the value is replaced by `update` concurrently, `pred` is called again with the new
+    /// value.
+    pub fn remove_if<'g, P: Fn(&V) -> bool>(
+        &'g self,
+        key: &usize,
+        pred: P,
+        guard: &'g Guard,
+    ) -> Result<&'g V, ()> {
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let (_, found, cursor) = self.find(key, guard);
+            let value = if found { cursor.lookup() } else { None };
+            match value {
+                Some(Some(value)) if pred(value) => {}
+                _ => return Err(()),
+            }
+            yield_point!();
+            match cursor.delete(guard) {
+                Err(()) => backoff.backoff(),
+                Ok(value) => {
+                    self.count_delete(guard);
+                    return Ok(value.as_ref().unwrap());
+                }
+            }
+        }
+    }
+
+    /// Inserts `value` for `key`, or replaces the value if the list already has `key`. Returns the
+    /// replaced value.
+    ///
+    /// As in `update`, the concurrent lookups always find `key` once it is inserted.
+    pub fn insert_or_replace<'g>(
+        &'g self,
+        key: &usize,
+        value: V,
+        guard: &'g Guard,
+    ) -> Option<&'g V> {
+        let mut new_node = self.new_node(*key, value, guard);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let (size, found, mut cursor) = self.find(key, guard);
+            yield_point!();
+            if found {
+                match Self::replace_node(&mut cursor, new_node, guard) {
+                    Ok(value) => return value.as_ref(),
+                    Err(n) => new_node = n,
+                }
+            } else {
+                match Self::insert_node(&mut cursor, new_node, guard) {
+                    Ok(()) => {
+                        self.count_insert(size);
+                        return None;
+                    }
+                    Err(n) => new_node = n,
+                }
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Replaces the value of `key` with `new_value`, and returns the old value. Returns `new_value`
+    /// back if the list doesn't have `key`.
+    ///
+    /// Unlike deleting and inserting `key` again, the concurrent lookups always find `key`.
+    pub fn update<'g>(&'g self, key: &usize, new_value: V, guard: &'g Guard) -> Result<&'g V, V> {
+        self.update_if(key, new_value, |_| true, guard)
+    }
+
+    /// Same as `update`, but only if the value of `key` is equal to `current`.
+    pub fn compare_and_update<'g>(
+        &'g self,
+        key: &usize,
+        current: &V,
+        new_value: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, V>
+    where
+        V: PartialEq,
+    {
+        self.update_if(key, new_value, |value| value == current, guard)
+    }
+
+    /// Deletes `key` only if its value is equal to `expected`, e.g. the value that is read before,
+    /// and returns the value.
+    ///
+    /// The values are compared by `PartialEq`, so the key is deleted even if its value is replaced
+    /// by `update` with an equal value after it is read.
+    pub fn delete_if_eq<'g>(
+        &'g self,
+        key: &usize,
+        expected: &V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, ()>
+    where
+        V: PartialEq,
+    {
+        self.remove_if(key, |value| value == expected, guard)
+    }
+
+    /// Returns the key-value pairs in the split order, e.g. for checkpointing. The list can be
+    /// restored by `collect`ing the pairs.
+    ///
+    /// The snapshot is consistent only if the list is not modified concurrently, as in `iter`.
+    pub fn snapshot(&self, guard: &Guard) -> Vec<(usize, V)>
+    where
+        V: Clone,
+    {
+        self.iter(guard)
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    /// Returns the occupancy of the buckets.
+    ///
+    /// The items of an uninitialized bucket are counted in the chain of its closest initialized
+    /// parent, since the searches for them walk that chain. As in `iter`, the statistics are
+    /// consistent only if the list is not modified concurrently.
+    pub fn stats(&self, guard: &Guard) -> MapStats {
+        let buckets = self.size.load(Ordering::Acquire);
+        let mut stats = MapStats {
+            buckets,
+            ..MapStats::default()
+        };
+        let mut chain = None;
+        for (node, deleted) in self.list.nodes(guard) {
+            if deleted {
+                continue;
+            }
+            if node.value().is_some() {
+                stats.items += 1;
+                if let Some(length) = chain.as_mut() {
+                    *length += 1;
+                }
+                continue;
+            }
+            // Skips the sentinels of the removed buckets that are not marked yet.
+            if node.key().0.reverse_bits() >= buckets {
+                continue;
+            }
+            stats.sentinels += 1;
+            if let Some(length) = chain.replace(0) {
+                stats.add_chain(length);
+            }
+        }
+        if let Some(length) = chain {
+            stats.add_chain(length);
+        }
+        stats
+    }
+
+    /// Checks the invariants of the list, and returns the first violation found, e.g. in the
+    /// tests of the extensions of the list:
+    ///
+    /// - The nodes are sorted in the split order, and the sentinels are not duplicated.
+    /// - The key of each regular node is hashed into its split-order bits.
+    /// - Each initialized bucket points to its sentinel, which is linked in the list.
+    /// - The count equals the number of the regular nodes that are not deleted.
+    ///
+    /// The list should not be modified concurrently, or the checks may fail spuriously.
+    pub fn validate(&self, guard: &Guard) -> Result<(), String> {
+        let mut prev: Option<NodeKey> = None;
+        let mut sentinels = BTreeSet::new();
+        let mut items = 0;
+        for (node, deleted) in self.list.nodes(guard) {
+            let key = *node.key();
+            if let Some(prev) = prev {
+                if prev > key || (prev == key && !key.1) {
+                    return Err(format!("{:?} is followed by {:?}", prev, key));
+                }
+            }
+            prev = Some(key);
+
+            if !key.1 {
+                if node.value().is_some() {
+                    return Err(format!("sentinel {:?} has a value", key));
+                }
+                if !deleted {
+                    let _ = sentinels.insert(node as *const _);
+                }
+                continue;
+            }
+            if node.value().is_none() {
+                return Err(format!("regular node {:?} has no value", key));
+            }
+            if key.0 != self.hash(key.2).reverse_bits() {
+                return Err(format!("regular node {:?} has a wrong hash", key));
+            }
+            if !deleted {
+                items += 1;
+            }
+        }
+
+        let size = self.size.load(Ordering::Acquire);
+        for index in 0..size {
+            let bucket = some_or!(self.buckets.get_if_exists(index, guard), continue);
+            let sentinel = bucket.load(Ordering::Acquire, guard);
+            let node = some_or!(unsafe { sentinel.as_ref() }, continue);
+            if *node.key() != sentinel_key(index) {
+                return Err(format!("bucket {} points to {:?}", index, node.key()));
+            }
+            if !sentinels.contains(&(node as *const _)) {
+                return Err(format!("bucket {} points to a node out of the list", index));
+            }
+        }
+
+        let count = self.count.sum();
+        if count != items {
+            return Err(format!("count is {}, but there are {} items", count, items));
+        }
+        Ok(())
+    }
+
+    /// Returns the memory footprint of the buckets. See `GrowableArray::memory_usage`.
+    pub fn bucket_memory_usage(&self, guard: &Guard) -> MemoryUsage {
+        self.buckets.memory_usage(guard)
+    }
+
+    /// Returns the allocation statistics of the sentinel nodes.
+    pub fn sentinel_stats(&self) -> ArenaStats {
+        self.sentinels.stats()
+    }
+
+    /// Returns the allocation statistics of the regular nodes.
+    pub fn node_stats(&self) -> ArenaStats {
+        self.nodes.stats()
+    }
+
+    /// Returns the hash of `key`.
+    fn hash(&self, key: usize) -> usize {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish() as usize
+    }
+
+    /// Allocates a regular node of `key` from the arena.
+    fn new_node(&self, key: usize, value: V, guard: &Guard) -> ArenaBox<ListNode<V>> {
+        self.nodes
+            .alloc_box(Node::new(self.regular_key(key), Some(value)), guard)
+    }
+
+    /// Returns the node key of `key`.
+    fn regular_key(&self, key: usize) -> NodeKey {
+        (self.hash(key).reverse_bits(), true, key)
+    }
+
     /// Creates a cursor and moves it to the bucket for the given index.  If the bucket doesn't
     /// exist, recursively initializes the buckets.
-    fn lookup_bucket<'s>(&'s self, index: usize, guard: &'s Guard) -> Cursor<'s, usize, Option<V>> {
+    fn lookup_bucket<'s>(&'s self, index: usize, guard: &'s Guard) -> Cursor<'s, NodeKey, Option<V>> {
         
         // bucket list에서 pointer 받아오기
         // pointer가 sentinel_key 가르키기
@@ -67,21 +718,24 @@ impl<V> SplitOrderedList<V> {
         parent
     }
 
-    fn initialize_bucket<'s>(&'s self, index: usize, size: usize, guard: &'s Guard) -> Cursor<'s, usize, Option<V>>{   
+    fn initialize_bucket<'s>(&'s self, index: usize, size: usize, guard: &'s Guard) -> Cursor<'s, NodeKey, Option<V>>{   
         unsafe {
             let bucket_ptr = self.buckets.get(index,guard);
             let mut cursor;
             let parent = Self::get_parent(index, size);
-            let none_value: Option<V> = None;
-            let sentinel_index = index.reverse_bits();
-            let mut sentinel_node = Owned::new(Node::new(sentinel_index, none_value));
+            let sentinel_index = sentinel_key(index);
+            // Allocated from the arena only when it is to be inserted.
+            let mut sentinel_node: Option<ArenaBox<ListNode<V>>> = None;
+            let backoff = ExponentialBackoff::new();
             
             loop {
                 let mut found;
                 loop{
                     let sentinel_ptr = bucket_ptr.load(Ordering::Acquire,guard);
                     if !sentinel_ptr.is_null(){
-                        cursor =  Cursor::from_raw(&Atomic::null(),sentinel_ptr.deref());
+                        // If the sentinel is deleted by shrinking, the cursor fails to modify the
+                        // list, and `find` retries with the new `size`.
+                        cursor = self.list.cursor_after(sentinel_ptr.deref(), guard);
                         found = true;
                         break;
                     }
@@ -95,13 +749,23 @@ impl<V> SplitOrderedList<V> {
                         found = b;
                         break;
                     }
+                    backoff.backoff();
                 }
                 if found {
                     break;
                 }
-                match cursor.insert(sentinel_node, guard){
-                    Err(n) => sentinel_node = n,
+                yield_point!();
+                let node = sentinel_node.take().unwrap_or_else(|| {
+                    self.sentinels.alloc_box(Node::new(sentinel_index, None), guard)
+                });
+                match Self::insert_node(&mut cursor, node, guard){
+                    Err(n) => {
+                        sentinel_node = Some(n);
+                        backoff.backoff();
+                    }
                     Ok(()) => {
+                        // Other threads may find the sentinel in the list before the bucket is set.
+                        yield_point!();
                         bucket_ptr.store(cursor.curr(), Ordering::Release);
                         break;
                     }
@@ -117,32 +781,319 @@ impl<V> SplitOrderedList<V> {
         &'s self,
         key: &usize,
         guard: &'s Guard,
-    ) -> (usize, bool, Cursor<'s, usize, Option<V>>) {
-        let bucket_size = self.size.load(Ordering::Acquire);
-        let bucket_index = (*key) % bucket_size;
-        let mask:usize = 1 << 63;
-        let new_index = ((*key)|mask).reverse_bits();
+    ) -> (usize, bool, Cursor<'s, NodeKey, Option<V>>) {
+        let new_index = self.regular_key(*key);
+        let mut bucket_size;
         let mut cursor;
         let mut found = false;
+        let backoff = ExponentialBackoff::new();
         loop{
-            cursor = self.lookup_bucket(bucket_index,guard);
+            bucket_size = self.size.load(Ordering::Acquire);
+            cursor = self.lookup_bucket(new_index.0.reverse_bits() % bucket_size, guard);
             if let Ok(b) = cursor.find_harris_michael(&new_index, guard){
                 found = b;
                 break;
             }
+            backoff.backoff();
         }
         (bucket_size, found, cursor)
     }
 
-    fn assert_valid_key(key: usize) {
-        assert!(key.leading_zeros() != 0);
+    /// Like `find`, but starts from the closest initialized ancestor of the bucket of `key`
+    /// instead of initializing the bucket, so it allocates neither a sentinel nor a segment of
+    /// `buckets`. Returns `(found, cursor)`.
+    fn find_existing<'s>(&'s self, key: &usize, guard: &'s Guard) -> (bool, ListCursor<'s, V>) {
+        let node_key = self.regular_key(*key);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let size = self.size.load(Ordering::Acquire);
+            let mut index = node_key.0.reverse_bits() % size;
+            let mut cursor = loop {
+                let sentinel = self
+                    .buckets
+                    .get_if_exists(index, guard)
+                    .map(|bucket| bucket.load(Ordering::Acquire, guard));
+                if let Some(sentinel) = sentinel.and_then(|s| unsafe { s.as_ref() }) {
+                    break unsafe { self.list.cursor_after(sentinel, guard) };
+                }
+                if index == 0 {
+                    break self.list.head(guard);
+                }
+                index = Self::get_parent(index, size);
+            };
+            if let Ok(found) = cursor.find_harris_michael(&node_key, guard) {
+                return (found, cursor);
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Counts an inserted item, and doubles `size` if the list is too full for `size` buckets.
+    ///
+    /// Adding up the stripes of the counter on every insertion would contend for all of them, so
+    /// the count is checked only when the stripe of the current thread crosses a multiple of
+    /// `LOAD_FACTOR`.
+    fn count_insert(&self, size: usize) {
+        if self.count.increment() % Self::LOAD_FACTOR as isize == 0
+            && self.count.sum() > (size * Self::LOAD_FACTOR) as isize
+            && self
+                .size
+                .compare_exchange(size, size * 2, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            counter!("split_ordered_list.resize");
+            gauge!("split_ordered_list.buckets", size * 2);
+        }
+    }
+
+    /// Uncounts a deleted item, and halves `size` if the list is too empty for `size` buckets.
+    ///
+    /// Like `count_insert`, checks the count only when the stripe crosses a multiple of
+    /// `LOAD_FACTOR`. Then keeps halving `size` while it is too large, since the count may have
+    /// dropped by more than one halving needs since the last check.
+    fn count_delete(&self, guard: &Guard) {
+        if self.count.decrement() % Self::LOAD_FACTOR as isize != 0 {
+            return;
+        }
+        loop {
+            let size = self.size.load(Ordering::Acquire);
+            if size <= Self::MIN_SIZE
+                || self.count.sum() >= (size / (2 * Self::LOAD_FACTOR)) as isize
+                || self
+                    .size
+                    .compare_exchange(size, size / 2, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+            {
+                return;
+            }
+            counter!("split_ordered_list.shrink");
+            gauge!("split_ordered_list.buckets", size / 2);
+            self.remove_buckets(size / 2, size, guard);
+        }
+    }
+
+    /// Clears the buckets in `[from, to)`, which are out of `size`, and deletes their sentinels.
+    ///
+    /// The sentinels are unlinked and retired lazily by the later traversals, or by `collect`. A
+    /// thread that read the old `size` may initialize a bucket out of `size` again, which is kept
+    /// until `size` grows back or the list is dropped.
+    fn remove_buckets(&self, from: usize, to: usize, guard: &Guard) {
+        for index in from..to {
+            let bucket = some_or!(self.buckets.get_if_exists(index, guard), continue);
+            let sentinel = bucket.swap(Shared::null(), Ordering::AcqRel, guard);
+            if let Some(sentinel) = unsafe { sentinel.as_ref() } {
+                let _ = sentinel.mark(guard);
+            }
+        }
+    }
+
+    /// Replaces the value of `key` with `new_value` if the value satisfies `cond`.
+    fn update_if<'g, C: Fn(&V) -> bool>(
+        &'g self,
+        key: &usize,
+        new_value: V,
+        cond: C,
+        guard: &'g Guard,
+    ) -> Result<&'g V, V> {
+        let mut new_node = self.new_node(*key, new_value, guard);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let (_, found, mut cursor) = self.find(key, guard);
+            let value = if found { cursor.lookup() } else { None };
+            match value {
+                Some(Some(value)) if cond(value) => {}
+                _ => return Err(new_node.into_inner().into_value().unwrap()),
+            }
+            yield_point!();
+            match Self::replace_node(&mut cursor, new_node, guard) {
+                Err(n) => {
+                    new_node = n;
+                    backoff.backoff();
+                }
+                Ok(value) => return Ok(value.as_ref().unwrap()),
+            }
+        }
+    }
+
+    /// Moves the cursor of `find` to the node of `key` whose value satisfies `pred`, for the lists
+    /// where multiple nodes may have the same key. Returns `(size, cursor of find, cursor of the
+    /// node)`.
+    fn find_by<'s, P: Fn(&V) -> bool>(
+        &'s self,
+        key: usize,
+        pred: &P,
+        guard: &'s Guard,
+    ) -> (usize, ListCursor<'s, V>, Option<ListCursor<'s, V>>) {
+        let (size, found, start) = self.find(&key, guard);
+        if !found {
+            return (size, start, None);
+        }
+
+        // The nodes of `key` are next to each other, starting from the one that `find` found.
+        let node_key = self.regular_key(key);
+        let mut cursor = start.clone();
+        while let Some(node) = unsafe { cursor.curr().as_ref() } {
+            if *node.key() != node_key {
+                break;
+            }
+            if pred(node.value().as_ref().unwrap()) {
+                return (size, start, Some(cursor));
+            }
+            cursor.next(guard);
+        }
+        (size, start, None)
+    }
+
+    /// Returns the value of `key` that satisfies `pred`. Multiple nodes may have `key`.
+    pub(crate) fn lookup_by<'g, P: Fn(&V) -> bool>(
+        &'g self,
+        key: usize,
+        pred: P,
+        guard: &'g Guard,
+    ) -> Option<&'g V> {
+        let (_, _, cursor) = self.find_by(key, &pred, guard);
+        cursor?.lookup()?.as_ref()
+    }
+
+    /// Returns the values of `key`. Multiple nodes may have `key`.
+    pub(crate) fn values_by<'g>(&'g self, key: usize, guard: &'g Guard) -> ValuesBy<'g, V> {
+        let (found, cursor) = self.find_existing(&key, guard);
+        ValuesBy {
+            key: self.regular_key(key),
+            cursor: if found { Some(cursor) } else { None },
+            guard,
+        }
+    }
+
+    /// Inserts `value` for `key` unless `eq(existing, &value)` for a value of `key`. Multiple nodes
+    /// may have `key`.
+    pub(crate) fn insert_by<E: Fn(&V, &V) -> bool>(
+        &self,
+        key: usize,
+        value: V,
+        eq: E,
+        guard: &Guard,
+    ) -> Result<(), V> {
+        let mut new_node = self.new_node(key, value, guard);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let (size, mut start, cursor) = {
+                let value = new_node.value().as_ref().unwrap();
+                self.find_by(key, &|existing: &V| eq(existing, value), guard)
+            };
+            if cursor.is_some() {
+                return Err(new_node.into_inner().into_value().unwrap());
+            }
+            // Inserting before the other nodes of `key` fails if another node of `key` is
+            // inserted meanwhile.
+            match Self::insert_node(&mut start, new_node, guard) {
+                Err(n) => {
+                    new_node = n;
+                    backoff.backoff();
+                }
+                Ok(()) => {
+                    self.count_insert(size);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Deletes the value of `key` that satisfies `pred`. Multiple nodes may have `key`.
+    pub(crate) fn delete_by<'g, P: Fn(&V) -> bool>(
+        &'g self,
+        key: usize,
+        pred: P,
+        guard: &'g Guard,
+    ) -> Result<&'g V, ()> {
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let (_, _, cursor) = self.find_by(key, &pred, guard);
+            match cursor.ok_or(())?.delete(guard) {
+                Err(()) => backoff.backoff(),
+                Ok(value) => {
+                    self.count_delete(guard);
+                    return Ok(value.as_ref().unwrap());
+                }
+            }
+        }
+    }
+
+    /// See `debug_dump::split_ordered_list`.
+    pub(crate) fn dump(&self, format: Format, guard: &Guard) -> String
+    where
+        V: fmt::Debug,
+    {
+        let size = self.size.load(Ordering::Acquire);
+        let mut writer = Writer::new(
+            format,
+            format_args!(
+                "SplitOrderedList: {} buckets, {} items",
+                size,
+                self.count.sum()
+            ),
+        );
+
+        // The buckets are shown only in DOT, since each initialized one is next to its sentinel.
+        if writer.format() == Format::Dot {
+            for (index, sentinel) in self.buckets.snapshot(guard) {
+                if index >= size {
+                    break;
+                }
+                let id = ("b", index);
+                writer.node(id, 0, Kind::Segment, format_args!("bucket {}", index));
+                writer.edge(id, ("n", sentinel.as_raw() as usize), None);
+            }
+        }
+
+        let mut prev = None;
+        for (node, deleted) in self.list.nodes(guard) {
+            let id = ("n", node as *const _ as usize);
+            let key = node.key().2;
+            match node.value() {
+                // Only the sentinels have no value.
+                None => {
+                    let index = node.key().0.reverse_bits();
+                    writer.node(id, 0, Kind::Sentinel, format_args!("sentinel {}", index))
+                }
+                Some(value) => {
+                    let kind = if deleted { Kind::Deleted } else { Kind::Element };
+                    writer.node(id, 1, kind, format_args!("{} => {:?}", key, value));
+                }
+            }
+            if let Some(prev) = prev {
+                writer.edge(prev, id, None);
+            }
+            prev = Some(id);
+        }
+        writer.finish()
+    }
+}
+
+/// Operations that pin the current thread by themselves and return the owned values, for the
+/// callers that don't keep a guard.
+#[cfg(feature = "std")]
+impl<V: Clone, S: BuildHasher> SplitOrderedList<V, S> {
+    /// Returns a clone of the value of `key`.
+    pub fn lookup_cloned(&self, key: &usize) -> Option<V> {
+        self.lookup(key, &crossbeam_epoch::pin()).cloned()
+    }
+
+    /// Inserts a key-value pair. If the list already has `key`, returns `value` back in
+    /// `MapError::AlreadyExists`.
+    pub fn insert_owned(&self, key: &usize, value: V) -> Result<(), MapError<V>> {
+        self.insert(key, value, &crossbeam_epoch::pin())
+    }
+
+    /// Deletes `key`, and returns a clone of its value.
+    pub fn delete_owned(&self, key: &usize) -> Option<V> {
+        self.delete(key, &crossbeam_epoch::pin()).ok().cloned()
     }
 }
 
-impl<V> NonblockingMap<usize, V> for SplitOrderedList<V> {
+impl<V, S: BuildHasher> NonblockingMap<usize, V> for SplitOrderedList<V, S> {
     fn lookup<'a>(&'a self, key: &usize, guard: &'a Guard) -> Option<&'a V> {
-        Self::assert_valid_key(*key);
-        let (size,found,cursor) = self.find(key, guard);
+        let (found, cursor) = self.find_existing(key, guard);
         let none_value: Option<&V> = None;
         
         
@@ -156,30 +1107,29 @@ impl<V> NonblockingMap<usize, V> for SplitOrderedList<V> {
         else { none_value }
     }
 
-    fn insert(&self, key: &usize, value: V, guard: &Guard) -> Result<(), V> {
-        Self::assert_valid_key(*key);
-        let mask:usize = 1 << 63;
-        let new_key = ((*key)|mask).reverse_bits();
-        let v:Option<V> = Some(value);
-        let mut new_node = Owned::new(Node::new(new_key,v));
+    fn insert(&self, key: &usize, value: V, guard: &Guard) -> Result<(), MapError<V>> {
+        let mut new_node = self.new_node(*key, value, guard);
+        let backoff = ExponentialBackoff::new();
         loop{
             let (size,found,mut cursor) = self.find(key, guard);
             if found {
-                let error_value = new_node.into_box().into_value();
+                let error_value = new_node.into_inner().into_value();
                 match error_value {
                     Some(t) => {
-                        return Err(t)
+                        return Err(MapError::AlreadyExists(t))
                     },
                     None => unreachable!()
                 }
             }
-            match cursor.insert(new_node, guard){
-                Err(n) => new_node = n,
+            yield_point!();
+            match Self::insert_node(&mut cursor, new_node, guard){
+                Err(n) => {
+                    new_node = n;
+                    backoff.backoff();
+                }
                 Ok(()) => {
-                    let old_count = self.count.fetch_add(1, Ordering::Release);
-                    if (old_count + 1) > (size * 2){
-                        self.size.compare_and_swap(size, size * 2,Ordering::AcqRel);
-                    }
+                    yield_point!();
+                    self.count_insert(size);
                     return Ok(())
                 }
             }
@@ -187,17 +1137,18 @@ impl<V> NonblockingMap<usize, V> for SplitOrderedList<V> {
         // todo!()
     }
 
-    fn delete<'a>(&'a self, key: &usize, guard: &'a Guard) -> Result<&'a V, ()> {
-        Self::assert_valid_key(*key);
+    fn delete<'a>(&'a self, key: &usize, guard: &'a Guard) -> Result<&'a V, MapError<V>> {
+        let backoff = ExponentialBackoff::new();
         loop{
-            let (size,found,cursor) = self.find(key, guard);
+            let (_, found, cursor) = self.find(key, guard);
             if !found {
-                return Err(())
+                return Err(MapError::NotFound)
             }
+            yield_point!();
             match cursor.delete(guard){
-                Err(()) => continue,
+                Err(()) => backoff.backoff(),
                 Ok(value) => {
-                    self.count.fetch_sub(1, Ordering::Release);
+                    self.count_delete(guard);
                     match value {
                         Some(v) => return Ok(v),
                         None => unreachable!()
@@ -207,3 +1158,411 @@ impl<V> NonblockingMap<usize, V> for SplitOrderedList<V> {
         }
     }
 }
+
+impl<'g, V> Iterator for Iter<'g, V> {
+    type Item = (usize, &'g V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skips the sentinels, which have no value.
+        self.inner.find_map(|node| {
+            let value = node.value().as_ref()?;
+            Some((node.key().2, value))
+        })
+    }
+}
+
+impl<'g, V, S: BuildHasher> Iterator for Range<'g, V, S> {
+    type Item = (usize, &'g V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            RangeInner::Lookup {
+                list,
+                next,
+                last,
+                guard,
+            } => loop {
+                let key = (*next)?;
+                *next = if key < *last { Some(key + 1) } else { None };
+                if let Some(value) = list.lookup(&key, guard) {
+                    return Some((key, value));
+                }
+            },
+            RangeInner::Scan { iter, first, last } => {
+                iter.find(|(key, _)| *first <= *key && *key <= *last)
+            }
+        }
+    }
+}
+
+impl<'g, V> Iterator for ValuesBy<'g, V> {
+    type Item = &'g V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor.as_mut()?;
+        match unsafe { cursor.curr().as_ref() } {
+            Some(node) if *node.key() == self.key => {
+                cursor.next(self.guard);
+                node.value().as_ref()
+            }
+            _ => {
+                self.cursor = None;
+                None
+            }
+        }
+    }
+}
+
+/// Collects the key-value pairs into a list. If a key appears more than once, the last value is
+/// kept.
+#[cfg(feature = "std")]
+impl<V, S: BuildHasher + Default> FromIterator<(usize, V)> for SplitOrderedList<V, S> {
+    fn from_iter<I: IntoIterator<Item = (usize, V)>>(iter: I) -> Self {
+        let list = Self::default();
+        let guard = crossbeam_epoch::pin();
+        for (key, value) in iter {
+            if let Err(MapError::AlreadyExists(value)) = list.insert(&key, value, &guard) {
+                let _ = list.update(&key, value, &guard);
+            }
+        }
+        list
+    }
+}
+
+/// Clones the key-value pairs into an independent list with as many buckets.
+///
+/// As in `snapshot`, the clone is consistent only if the list is not modified concurrently.
+#[cfg(feature = "std")]
+impl<V: Clone, S: BuildHasher + Clone> Clone for SplitOrderedList<V, S> {
+    fn clone(&self) -> Self {
+        let list = Self::with_hasher(self.hash_builder.clone());
+        list.size
+            .store(self.size.load(Ordering::Acquire), Ordering::Relaxed);
+        let guard = crossbeam_epoch::pin();
+        for (key, value) in self.iter(&guard) {
+            let _ = list.insert(&key, value.clone(), &guard);
+        }
+        list
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V, S> SplitOrderedList<V, S>
+where
+    V: Send + Sync + 'static,
+    S: BuildHasher + Default + Send + Sync + 'static,
+{
+    /// Collects the key-value pairs into a list as `collect` does, but inserts them in parallel by
+    /// the jobs of `pool`.
+    ///
+    /// The buckets are grown for all the pairs beforehand, and the pairs are split by the hashes of
+    /// the keys into a part for each thread of `pool`. The pairs of a key are inserted in order by
+    /// the same job, so the last value is kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a job panics.
+    pub fn par_from_iter<I: IntoIterator<Item = (usize, V)>>(iter: I, pool: &ThreadPool) -> Self {
+        let list = Self::default();
+        let pairs = iter.into_iter().collect::<Vec<_>>();
+        list.grow_to(pairs.len());
+
+        let mut parts = (0..pool.size()).map(|_| Vec::new()).collect::<Vec<_>>();
+        let num_parts = parts.len();
+        for (key, value) in pairs {
+            parts[list.hash(key) % num_parts].push((key, value));
+        }
+
+        let list = Arc::new(list);
+        let handles = parts
+            .into_iter()
+            .map(|part| {
+                let list = Arc::clone(&list);
+                pool.spawn(move || {
+                    let guard = crossbeam_epoch::pin();
+                    for (key, value) in part {
+                        let _ = list.insert_or_replace(&key, value, &guard);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("a job of `par_from_iter` panicked");
+        }
+        // The jobs have dropped their references.
+        Arc::try_unwrap(list).unwrap_or_else(|_| unreachable!())
+    }
+}
+
+impl<'g, V, S: BuildHasher> Entry<'g, V, S> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> usize {
+        match self {
+            Entry::Occupied(entry) => entry.key,
+            Entry::Vacant(entry) => entry.key,
+        }
+    }
+
+    /// Returns the value of the key, or inserts `default` if the key is vacant.
+    pub fn or_insert(self, default: V) -> &'g V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value of the key, or inserts the value returned by `f` if the key is vacant.
+    ///
+    /// If another thread inserts the key concurrently, returns the value of the other thread.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'g V {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => {
+                let (list, key, guard) = (entry.list, entry.key, entry.guard);
+                match entry.insert(f()) {
+                    Ok(value) => value,
+                    Err(value) => list.get_or_insert_with(&key, || value, guard),
+                }
+            }
+        }
+    }
+
+    /// Replaces the value of an occupied key with the value returned by `f` for the current value.
+    ///
+    /// If the value is concurrently updated, `f` is called again for the new value. If the key is
+    /// concurrently deleted, returns a vacant entry.
+    pub fn and_modify<F: FnMut(&V) -> V>(self, mut f: F) -> Self {
+        let mut entry = match self {
+            Entry::Occupied(entry) => entry,
+            vacant => return vacant,
+        };
+        loop {
+            if entry.replace(f(entry.get())).is_ok() {
+                return Entry::Occupied(entry);
+            }
+            match entry.list.entry(&entry.key, entry.guard) {
+                Entry::Occupied(e) => entry = e,
+                vacant => return vacant,
+            }
+        }
+    }
+}
+
+impl<'g, V, S: BuildHasher> OccupiedEntry<'g, V, S> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Returns the value of the key when the entry is created or last replaced.
+    pub fn get(&self) -> &'g V {
+        self.cursor.lookup().unwrap().as_ref().unwrap()
+    }
+
+    /// Replaces the value with `value`, and returns the old value. Returns `value` back if the key
+    /// is deleted or updated after `get`.
+    pub fn replace(&mut self, value: V) -> Result<&'g V, V> {
+        let node = self.list.new_node(self.key, value, self.guard);
+        yield_point!();
+        match SplitOrderedList::<V, S>::replace_node(&mut self.cursor, node, self.guard) {
+            Ok(value) => Ok(value.as_ref().unwrap()),
+            Err(node) => Err(node.into_inner().into_value().unwrap()),
+        }
+    }
+
+    /// Deletes the key, and returns its value. Returns `Err` if the key is deleted or updated after
+    /// `get`.
+    pub fn remove(self) -> Result<&'g V, ()> {
+        yield_point!();
+        let value = self.cursor.delete(self.guard)?;
+        self.list.count_delete(self.guard);
+        Ok(value.as_ref().unwrap())
+    }
+}
+
+impl<'g, V, S: BuildHasher> VacantEntry<'g, V, S> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Inserts `value` for the key, and returns the reference to it. Returns `value` back if
+    /// another thread inserts the key first.
+    pub fn insert(self, value: V) -> Result<&'g V, V> {
+        let VacantEntry {
+            list,
+            key,
+            mut size,
+            mut cursor,
+            guard,
+        } = self;
+        let mut node = list.new_node(key, value, guard);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            yield_point!();
+            match SplitOrderedList::<V, S>::insert_node(&mut cursor, node, guard) {
+                Ok(()) => {
+                    list.count_insert(size);
+                    return Ok(cursor.lookup().unwrap().as_ref().unwrap());
+                }
+                Err(n) => node = n,
+            }
+            backoff.backoff();
+            let (new_size, found, new_cursor) = list.find(&key, guard);
+            if found {
+                return Err(node.into_inner().into_value().unwrap());
+            }
+            size = new_size;
+            cursor = new_cursor;
+        }
+    }
+}
+
+/// A `SplitOrderedList` is serialized as a map from the keys to the values in the split order.
+///
+/// The serialized view is consistent only if the list is not modified concurrently. Otherwise, it
+/// may or may not contain the concurrently inserted or deleted keys.
+#[cfg(all(feature = "serde", feature = "std"))]
+mod serde_impl {
+    use core::fmt;
+    use core::marker::PhantomData;
+    use crossbeam_epoch as epoch;
+    use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::SplitOrderedList;
+    use crate::map::NonblockingMap;
+
+    impl<V: Serialize> Serialize for SplitOrderedList<V> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let guard = epoch::pin();
+            serializer.collect_map(self.iter(&guard))
+        }
+    }
+
+    impl<'de, V: Deserialize<'de>> Deserialize<'de> for SplitOrderedList<V> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct ListVisitor<V>(PhantomData<V>);
+
+            impl<'de, V: Deserialize<'de>> Visitor<'de> for ListVisitor<V> {
+                type Value = SplitOrderedList<V>;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a map from usize keys")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                    let list = SplitOrderedList::new();
+                    let guard = epoch::pin();
+                    while let Some((key, value)) = map.next_entry::<usize, V>()? {
+                        if list.insert(&key, value, &guard).is_err() {
+                            return Err(de::Error::custom(format_args!("duplicate key {}", key)));
+                        }
+                    }
+                    Ok(list)
+                }
+            }
+
+            deserializer.deserialize_map(ListVisitor(PhantomData))
+        }
+    }
+}
+
+/// `SplitOrderedList::par_iter` splits the list at the sentinels, so that each worker of rayon
+/// traverses the buckets in a contiguous range of the split order.
+#[cfg(all(feature = "rayon", feature = "std"))]
+mod rayon_impl {
+    use core::hash::BuildHasher;
+    use core::mem;
+    use core::sync::atomic::Ordering;
+    use crossbeam_epoch::{self as epoch, Guard};
+    use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+    use rayon::iter::ParallelIterator;
+
+    use super::SplitOrderedList;
+
+    /// Parallel iterator over the key-value pairs of a `SplitOrderedList`, returned by
+    /// `SplitOrderedList::par_iter`.
+    #[derive(Debug)]
+    pub struct ParIter<'g, V, S> {
+        list: &'g SplitOrderedList<V, S>,
+    }
+
+    /// The buckets at the positions `start..end` in the split order, out of `size` buckets.
+    ///
+    /// The position of a bucket is the reversed bits of its index, in `size.trailing_zeros()` bits.
+    struct Segment<'g, V, S> {
+        list: &'g SplitOrderedList<V, S>,
+        size: usize,
+        start: usize,
+        end: usize,
+    }
+
+    impl<V: Send + Sync, S: BuildHasher + Sync> SplitOrderedList<V, S> {
+        /// Returns a parallel iterator over the key-value pairs, in an unspecified order.
+        ///
+        /// The buckets are split into ranges at their sentinels, and each range is traversed by
+        /// a worker of rayon with its own guard, while `guard` keeps the returned values alive. As
+        /// in `iter`, the iterator may or may not see the pairs that are concurrently inserted or
+        /// deleted.
+        pub fn par_iter<'g>(&'g self, _guard: &'g Guard) -> ParIter<'g, V, S> {
+            ParIter { list: self }
+        }
+    }
+
+    impl<'g, V: Send + Sync, S: BuildHasher + Sync> ParallelIterator for ParIter<'g, V, S> {
+        type Item = (usize, &'g V);
+
+        fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+            let size = self.list.size.load(Ordering::Acquire);
+            let segment = Segment {
+                list: self.list,
+                size,
+                start: 0,
+                end: size,
+            };
+            bridge_unindexed(segment, consumer)
+        }
+    }
+
+    impl<'g, V: Send + Sync, S: BuildHasher + Sync> UnindexedProducer for Segment<'g, V, S> {
+        type Item = (usize, &'g V);
+
+        fn split(self) -> (Self, Option<Self>) {
+            if self.end - self.start < 2 {
+                return (self, None);
+            }
+            let mid = self.start + (self.end - self.start) / 2;
+            (
+                Segment { end: mid, ..self },
+                Some(Segment { start: mid, ..self }),
+            )
+        }
+
+        fn fold_with<F: Folder<Self::Item>>(self, mut folder: F) -> F {
+            let guard = epoch::pin();
+            let shift = mem::size_of::<usize>() * 8 - self.size.trailing_zeros() as usize;
+            // The first node key of the bucket at `end`, where the range stops.
+            let end = if self.end < self.size {
+                Some(self.end << shift)
+            } else {
+                None
+            };
+            let index = (self.start << shift).reverse_bits();
+            let cursor = self.list.initialize_bucket(index, self.size, &guard);
+            for node in cursor.iter(&guard) {
+                if matches!(end, Some(end) if node.key().0 >= end) {
+                    break;
+                }
+                // Skips the sentinels, which have no value.
+                if let Some(value) = node.value() {
+                    // The value is retired after the guard of `par_iter` is pinned, so it is not
+                    // freed until the guard is dropped.
+                    let value = unsafe { &*(value as *const V) };
+                    folder = folder.consume((node.key().2, value));
+                    if folder.full() {
+                        break;
+                    }
+                }
+            }
+            folder
+        }
+    }
+}
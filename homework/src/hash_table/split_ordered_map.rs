@@ -0,0 +1,91 @@
+//! Split-ordered hash map of arbitrary keys.
+
+use core::hash::{BuildHasher, Hash, Hasher};
+use crossbeam_epoch::Guard;
+use std::collections::hash_map::RandomState;
+
+use super::split_ordered_list::SplitOrderedList;
+use crate::map::{MapError, NonblockingMap};
+
+/// Lock-free hash map on top of `SplitOrderedList`.
+///
+/// The keys are hashed by `S` into the keys of the list, and each node keeps the original key and
+/// the value. The keys with the same hash share the key in the list, and are told apart by `Eq`.
+#[derive(Debug)]
+pub struct SplitOrderedMap<K, V, S = RandomState> {
+    inner: SplitOrderedList<(K, V)>,
+    hash_builder: S,
+}
+
+impl<K, V> SplitOrderedMap<K, V, RandomState> {
+    /// Creates a new map with a randomly seeded hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V, S: Default> Default for SplitOrderedMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> SplitOrderedMap<K, V, S> {
+    /// Creates a new map that hashes the keys with `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            inner: SplitOrderedList::new(),
+            hash_builder,
+        }
+    }
+
+    /// Returns the hasher of the map.
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> SplitOrderedMap<K, V, S> {
+    /// Hashes `key` into a key of `SplitOrderedList`.
+    fn hash(&self, key: &K) -> usize {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish() as usize
+    }
+
+    /// Lookups the given key to get the reference to its value.
+    pub fn lookup<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        self.inner
+            .lookup_by(self.hash(key), |(k, _)| k == key, guard)
+            .map(|(_, v)| v)
+    }
+
+    /// Inserts a key-value pair. If the map already has the key, returns the pair back in `Err`.
+    pub fn insert(&self, key: K, value: V, guard: &Guard) -> Result<(), (K, V)> {
+        let hash = self.hash(&key);
+        self.inner
+            .insert_by(hash, (key, value), |(k1, _), (k2, _)| k1 == k2, guard)
+    }
+
+    /// Deletes the given key and its value.
+    pub fn delete<'g>(&'g self, key: &K, guard: &'g Guard) -> Result<&'g V, ()> {
+        self.inner
+            .delete_by(self.hash(key), |(k, _)| k == key, guard)
+            .map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, S: BuildHasher> NonblockingMap<K, V> for SplitOrderedMap<K, V, S> {
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        self.lookup(key, guard)
+    }
+
+    fn insert(&self, key: &K, value: V, guard: &Guard) -> Result<(), MapError<V>> {
+        self.insert(key.clone(), value, guard)
+            .map_err(|(_, v)| MapError::AlreadyExists(v))
+    }
+
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, MapError<V>> {
+        self.delete(key, guard).map_err(|()| MapError::NotFound)
+    }
+}
@@ -0,0 +1,81 @@
+//! Split-ordered multimap.
+
+use crossbeam_epoch::Guard;
+
+use super::split_ordered_list::{SplitOrderedList, ValuesBy};
+
+/// Lock-free map from `usize` to multiple `V`s on top of `SplitOrderedList`.
+///
+/// Each value is a regular node of the list. The nodes of the same key are next to each other in
+/// the split order, so the values of a key are found by a single search.
+#[derive(Debug)]
+pub struct SplitOrderedMultimap<V> {
+    inner: SplitOrderedList<V>,
+}
+
+/// Iterator over the values of a key, returned by `SplitOrderedMultimap::get_all`.
+#[derive(Debug)]
+pub struct GetAll<'g, V> {
+    inner: ValuesBy<'g, V>,
+}
+
+impl<V> Default for SplitOrderedMultimap<V> {
+    fn default() -> Self {
+        Self {
+            inner: SplitOrderedList::new(),
+        }
+    }
+}
+
+impl<V> SplitOrderedMultimap<V> {
+    /// Creates a new multimap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` to the values of `key`.
+    pub fn insert(&self, key: &usize, value: V, guard: &Guard) {
+        // No value is equal to `value`, so it is always inserted.
+        self.inner
+            .insert_by(*key, value, |_, _| false, guard)
+            .unwrap_or_else(|_| unreachable!());
+    }
+
+    /// Removes one of the values of `key`, and returns it. Returns `Err` if `key` has no value.
+    pub fn remove_one<'g>(&'g self, key: &usize, guard: &'g Guard) -> Result<&'g V, ()> {
+        self.inner.delete_by(*key, |_| true, guard)
+    }
+
+    /// Removes all the values of `key`, and returns the number of the removed values.
+    ///
+    /// The values that are inserted concurrently may or may not be removed.
+    pub fn remove_all(&self, key: &usize, guard: &Guard) -> usize {
+        let mut removed = 0;
+        while self.remove_one(key, guard).is_ok() {
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Returns the values of `key`, starting from the most recently inserted one.
+    ///
+    /// The values that are inserted or removed concurrently may or may not be returned.
+    pub fn get_all<'g>(&'g self, key: &usize, guard: &'g Guard) -> GetAll<'g, V> {
+        GetAll {
+            inner: self.inner.values_by(*key, guard),
+        }
+    }
+
+    /// See `SplitOrderedList::collect`.
+    pub fn collect(&self, guard: &Guard) {
+        self.inner.collect(guard);
+    }
+}
+
+impl<'g, V> Iterator for GetAll<'g, V> {
+    type Item = &'g V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
@@ -1,14 +1,282 @@
 //! Thead-safe key/value cache.
 
-use std::collections::hash_map::{Entry, HashMap};
-use std::hash::Hash;
-use std::sync::{Arc, Mutex, RwLock};
+use core::any::Any;
+use core::convert::Infallible;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::Instant;
+
+use std::sync::Arc;
+
+use super::clock_cache::Clock;
+use crate::mock::sync::{self as mock, RwLock};
+use crate::sync::OnceCell;
+
+/// The maximum number of shards of `Cache::with_weigher`. The weights may vary widely, e.g. from
+/// hundreds of bytes to megabytes, and the heavy keys should still fit in a shard.
+const WEIGHTED_SHARDS: usize = 4;
 
 /// Cache that remembers the result for each key.
-#[derive(Debug, Default)]
+///
+/// The keys are split into shards by their hashes, and each shard has its own lock, so that the
+/// insertions of the keys of different shards don't serialize each other.
+///
+/// A cache created by `with_capacity` holds at most that many keys, and evicts the keys with the
+/// CLOCK (second-chance) policy with the same ring as `ClockCache`: a hit sets the reference bit of the key under
+/// the shared lock, and to make room for a new key, the clock hand sweeps the keys, clearing their
+/// bits, and evicts the first key whose bit is already cleared. The capacity is divided among the
+/// shards, and each shard evicts its own keys, so a shard may evict a key while the others still
+/// have room.
+///
+/// A cache created by `with_weigher` bounds the total weight of the keys instead, e.g. the sizes of
+/// the values in bytes. The weight of a key is known only when its value is computed, so the keys
+/// whose values are being computed weigh nothing, and the shard evicts the other keys when the
+/// computation finishes. The keys of weight zero are never evicted.
+#[derive(Debug)]
 pub struct Cache<K, V> {
-    // todo! Build your own cache type.
-    inner: RwLock<HashMap<K, Arc<Mutex<Option<V>>>>>,
+    shards: Box<[RwLock<Inner<K, V>>]>,
+    /// Selects the shard of a key. Independent of the hashers of the maps, so that the keys of a
+    /// shard don't share the bits of their hashes in the map.
+    hasher: RandomState,
+    /// The maximum number or total weight of the keys, or `None` if unbounded.
+    capacity: Option<usize>,
+    /// Weighs the keys, if the capacity is the total weight. Otherwise, each key weighs 1.
+    weigher: Option<Weigher<K, V>>,
+    stats: Stats,
+}
+
+/// The weigher of `Cache::with_weigher`.
+struct Weigher<K, V>(Box<WeigherFn<K, V>>);
+
+type WeigherFn<K, V> = dyn Fn(&K, &V) -> usize + Send + Sync;
+
+impl<K, V> fmt::Debug for Weigher<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Weigher")
+    }
+}
+
+/// Statistics of the lookups of a `Cache`, returned by `Cache::stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The lookups that got the value without computing it, including the waits.
+    pub hits: u64,
+    /// The lookups that computed the value, including the failed ones.
+    pub misses: u64,
+    /// The keys evicted to make room for the new keys.
+    pub evictions: u64,
+    /// The lookups that waited for the computation of another lookup.
+    pub in_flight_waits: u64,
+    /// The time spent in the computations that returned, including the failed ones.
+    pub compute_time: Duration,
+}
+
+/// `CacheStats` updated by the lookups. The counters are independent, so `Relaxed` suffices.
+#[derive(Debug, Default)]
+struct Stats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    in_flight_waits: AtomicU64,
+    compute_nanos: AtomicU64,
+}
+
+impl Stats {
+    fn add(counter: &AtomicU64, value: u64) {
+        let _ = counter.fetch_add(value, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct Inner<K, V> {
+    map: HashMap<K, Slot<V>>,
+    /// The keys and their reference bits, if the shard is bounded.
+    clock: Clock<K>,
+    /// The maximum total weight of the keys, or 0 if the shard is unbounded.
+    capacity: usize,
+    /// The total weight of the keys.
+    weight: usize,
+}
+
+/// The error of a failed computation, whose type is known only to the invocations.
+type Failure = Box<dyn Any + Send + Sync>;
+
+/// The outcome of a computation. A failed cell is removed from the map before it is initialized, so
+/// only its waiters see the failure.
+type Cell<V> = mock::Arc<OnceCell<Result<Arc<V>, Failure>>>;
+
+#[derive(Debug)]
+struct Slot<V> {
+    /// Initialized only once, and shared via `Arc` so that the computation runs without holding the
+    /// map lock.
+    cell: Cell<V>,
+    /// The position of the key in `clock`.
+    position: usize,
+    /// The weight of the key, which counts toward the weight of the shard.
+    weight: usize,
+}
+
+impl<K, V> Default for Cache<K, V> {
+    fn default() -> Self {
+        Self::with_shards(Self::default_shards())
+    }
+}
+
+impl<K, V> Cache<K, V> {
+    /// Creates a new unbounded cache with `shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "shards should be positive");
+        Self {
+            shards: (0..shards).map(|_| RwLock::new(Inner::new(0))).collect(),
+            hasher: RandomState::new(),
+            capacity: None,
+            weigher: None,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Creates a new cache that holds at most `capacity` keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_shards(capacity, Self::default_shards())
+    }
+
+    /// Creates a new cache that holds at most `capacity` keys in `shards` shards. If `capacity` is
+    /// smaller than `shards`, there are only `capacity` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` or `shards` is zero.
+    pub fn with_capacity_and_shards(capacity: usize, shards: usize) -> Self {
+        Self::bounded(capacity, shards, None)
+    }
+
+    /// Creates a new cache whose keys weigh at most `capacity` in total, where `weigher` returns
+    /// the weight of a key and its value.
+    ///
+    /// The cache has at most 4 shards regardless of the number of CPUs, each of which holds the
+    /// keys of at most `capacity / 4` in total, so a key heavier than a quarter of `capacity` is
+    /// never cached. Use `with_weigher_and_shards` with fewer shards for the heavier keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_weigher<W>(capacity: usize, weigher: W) -> Self
+    where
+        W: Fn(&K, &V) -> usize + Send + Sync + 'static,
+    {
+        let shards = Self::default_shards().min(WEIGHTED_SHARDS);
+        Self::with_weigher_and_shards(capacity, shards, weigher)
+    }
+
+    /// Creates a new cache whose keys weigh at most `capacity` in total in `shards` shards. If
+    /// `capacity` is smaller than `shards`, there are only `capacity` shards.
+    ///
+    /// Each shard holds the keys of at most `capacity / shards` in total, and a key heavier than
+    /// that is evicted as soon as its value is computed. So the cache of the keys of the widely
+    /// varying weights should have fewer shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` or `shards` is zero.
+    pub fn with_weigher_and_shards<W>(capacity: usize, shards: usize, weigher: W) -> Self
+    where
+        W: Fn(&K, &V) -> usize + Send + Sync + 'static,
+    {
+        Self::bounded(capacity, shards, Some(Weigher(Box::new(weigher))))
+    }
+
+    fn bounded(capacity: usize, shards: usize, weigher: Option<Weigher<K, V>>) -> Self {
+        assert!(capacity > 0, "capacity should be positive");
+        assert!(shards > 0, "shards should be positive");
+        let shards = shards.min(capacity);
+        Self {
+            shards: (0..shards)
+                .map(|i| {
+                    // The first `capacity % shards` shards hold one more.
+                    let capacity = capacity / shards + (i < capacity % shards) as usize;
+                    RwLock::new(Inner::new(capacity))
+                })
+                .collect(),
+            hasher: RandomState::new(),
+            capacity: Some(capacity),
+            weigher,
+            stats: Stats::default(),
+        }
+    }
+
+    /// The number of shards of `default` and `with_capacity`: a few per CPU, so that the threads
+    /// rarely insert into the same shard at the same time.
+    pub(super) fn default_shards() -> usize {
+        (num_cpus::get() * 4).next_power_of_two()
+    }
+
+    /// Returns the maximum number of keys, or the maximum total weight if the cache has a weigher,
+    /// or `None` if the cache is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Returns the statistics of the lookups so far.
+    ///
+    /// The counters are read one by one, so they may be inconsistent with the concurrent lookups.
+    pub fn stats(&self) -> CacheStats {
+        let stats = &self.stats;
+        CacheStats {
+            hits: stats.hits.load(Ordering::Relaxed),
+            misses: stats.misses.load(Ordering::Relaxed),
+            evictions: stats.evictions.load(Ordering::Relaxed),
+            in_flight_waits: stats.in_flight_waits.load(Ordering::Relaxed),
+            compute_time: Duration::from_nanos(stats.compute_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of keys, including the ones whose values are being computed.
+    ///
+    /// The shards are counted one by one, so the result may be inconsistent with the concurrent
+    /// insertions and removals.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().map.len())
+            .sum()
+    }
+
+    /// Returns `true` if the cache has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all keys.
+    ///
+    /// The computations in flight still complete for the invocations that are waiting for them,
+    /// but their values are not stored.
+    ///
+    /// The shards are cleared one by one, so the keys inserted concurrently may remain.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut inner = shard.write().unwrap();
+            let capacity = inner.capacity;
+            *inner = Inner::new(capacity);
+        }
+    }
 }
 
 impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
@@ -22,116 +290,288 @@ impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
     /// On the other hand, since `f` may consume a lot of resource (= money), it's desirable not to
     /// duplicate the work. That is, `f` should be run only once for each key. Specifically, even
     /// for the concurrent invocations of `get_or_insert_with(key, f)`, `f` is called only once.
+    ///
+    /// If `f` panics, one of the invocations waiting for the same key runs its own `f` instead, and
+    /// the others keep waiting for it. The key is never left without a value to compute, so the
+    /// cache is not poisoned by the panic.
+    ///
+    /// If the cache is bounded, the key may be evicted while `f` runs. Then the invocations that
+    /// are already waiting for it still get the value, but the later ones run `f` again.
+    ///
+    /// The value is cloned for each invocation. Use `get_or_insert_with_arc` to share it instead.
     pub fn get_or_insert_with<F: FnOnce(K) -> V>(&self, key: K, f: F) -> V {
-        // vale가 있다면 key return, 또는 excute
-        // let map = self.inner.read().unwrap();
-        // let contain = map.get(&key);
-
-        // if let Some(v) = contain {
-        //     let r = &*v.lock().unwrap();
-        //     r.clone()
-        // }
-        // else{
-        //     drop(map);
-        //     let ff = f(key.clone());
-        //     let value = Arc::new(Mutex::new(ff.clone()));
-        //     let mut map = self.inner.write().unwrap();
-        //     map.insert(key.clone(),Arc::clone(&value));
-        //     drop(map);
-        //     ff
-        // }
-        let map = self.inner.read().unwrap();
-        let contain = map.get(&key);
-
-        if let Some(v) = contain {
-            let r = &*v.lock().unwrap();
-            match r {
-                Some(v) => v.clone(),
-                None => unreachable!(),
+        V::clone(&self.get_or_insert_with_arc(key, f))
+    }
+
+    /// Like `get_or_insert_with`, but the computation may fail.
+    ///
+    /// If `f` fails, the key is released without a value, so that the later invocations compute it
+    /// again, and the invocations waiting for the key get the error. The waiting invocations whose
+    /// error type is not `E`, e.g. those of `get_or_insert_with`, run their own computation
+    /// instead.
+    pub fn get_or_try_insert_with<E, F>(&self, key: K, f: F) -> Result<V, E>
+    where
+        E: Clone + Send + Sync + 'static,
+        F: FnOnce(K) -> Result<V, E>,
+    {
+        self.get_or_try_insert_with_arc(key, f)
+            .map(|value| V::clone(&value))
+    }
+
+    /// Returns the value of `key` if it is computed. Never computes the value, and doesn't wait for
+    /// the value being computed.
+    ///
+    /// The lookup counts as a use of the key for the eviction, but is not counted in `stats`.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_arc(key).map(|value| V::clone(&value))
+    }
+
+    /// Removes `key`, and returns its value if it was computed.
+    ///
+    /// If the value is being computed, the computation still completes for the invocations that
+    /// are waiting for it, but the value is not stored, and the later invocations compute it again.
+    pub fn invalidate(&self, key: &K) -> Option<V> {
+        let slot = self.shard(key).write().unwrap().remove(key)?;
+        let value = slot.cell.get()?.as_ref().ok()?;
+        Some(V::clone(value))
+    }
+}
+
+impl<K: Eq + Hash, V> Cache<K, V> {
+    /// Like `get`, but returns the shared value instead of its clone.
+    pub fn get_arc(&self, key: &K) -> Option<Arc<V>> {
+        let inner = self.shard(key).read().unwrap();
+        let slot = inner.map.get(key)?;
+        let value = slot.cell.get()?.as_ref().ok()?;
+        inner.touch(slot);
+        Some(value.clone())
+    }
+
+    /// Returns `true` if the value of `key` is computed. Unlike `get`, it doesn't count as a use of
+    /// the key for the eviction.
+    pub fn contains_key(&self, key: &K) -> bool {
+        let inner = self.shard(key).read().unwrap();
+        matches!(
+            inner.map.get(key).and_then(|slot| slot.cell.get()),
+            Some(Ok(_))
+        )
+    }
+
+    /// Returns the shard of `key`.
+    fn shard(&self, key: &K) -> &RwLock<Inner<K, V>> {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Cache<K, V> {
+    /// Like `get_or_insert_with`, but returns the shared value instead of its clone, e.g. for the
+    /// large values that are expensive to clone.
+    pub fn get_or_insert_with_arc<F: FnOnce(K) -> V>(&self, key: K, f: F) -> Arc<V> {
+        match self.get_or_try_insert_with_arc(key, |key| Ok::<_, Infallible>(f(key))) {
+            Ok(value) => value,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Like `get_or_try_insert_with`, but returns the shared value instead of its clone.
+    pub fn get_or_try_insert_with_arc<E, F>(&self, key: K, f: F) -> Result<Arc<V>, E>
+    where
+        E: Clone + Send + Sync + 'static,
+        F: FnOnce(K) -> Result<V, E>,
+    {
+        let mut f = Some(f);
+        loop {
+            let cell = self.slot(&key);
+            yield_point!();
+            // If the cell is not initialized, this lookup either computes it or waits for another.
+            let computed = cell.get().is_some();
+            let mut hit = true;
+            let result = cell.get_or_init(|| {
+                hit = false;
+                counter!("cache.miss");
+                Stats::add(&self.stats.misses, 1);
+                let start = Instant::now();
+                let result = (f.take().unwrap())(key.clone());
+                Stats::add(&self.stats.compute_nanos, start.elapsed().as_nanos() as u64);
+                match &result {
+                    Ok(value) => {
+                        if let Some(weigher) = &self.weigher {
+                            let weight = (weigher.0)(&key, value);
+                            let mut inner = self.shard(&key).write().unwrap();
+                            let evicted = inner.charge(&key, &cell, weight);
+                            Stats::add(&self.stats.evictions, evicted);
+                        }
+                    }
+                    Err(_) => {
+                        // Before the waiters are woken up, so that they don't find the failed cell.
+                        self.shard(&key).write().unwrap().remove_cell(&key, &cell);
+                    }
+                }
+                result.map(Arc::new).map_err(|e| Box::new(e) as Failure)
+            });
+            if hit && !computed {
+                Stats::add(&self.stats.in_flight_waits, 1);
             }
-        } else {
-            drop(map);
-            let mut map = self.inner.write().unwrap();
-            let cont = map.get(&key);
-            if let Some(mtx) = cont {
-                let r_mutex = mtx.lock().unwrap();
-                let ff = match &*r_mutex {
-                    Some(v) => v.clone(),
-                    None => unreachable!(),
-                };
-                ff
-            } else {
-                let x: Option<V> = None;
-                let mtx = Arc::new(Mutex::new(x));
-                map.insert(key.clone(), mtx.clone());
-                let mut r_mutex = mtx.lock().unwrap();
-                drop(map);
-                let ff = f(key.clone());
-                let ff: Option<V> = Some(ff);
-                // let mut map = self.inner.write().unwrap();
-                if let None = &*r_mutex {
-                    // let x = *r_mutex;
-                    *r_mutex = ff.clone();
+            match result {
+                Ok(value) => {
+                    if hit {
+                        counter!("cache.hit");
+                        Stats::add(&self.stats.hits, 1);
+                    }
+                    return Ok(value.clone());
                 }
-                // map.insert(key.clone(),Arc::clone(&value));
-                // drop(map);
-                match ff {
-                    Some(v) => v.clone(),
-                    None => panic!(),
+                Err(failure) => {
+                    if let Some(e) = failure.downcast_ref::<E>() {
+                        return Err(e.clone());
+                    }
+                    // A waiter of a computation that failed with another error type.
+                    debug_assert!(hit);
                 }
             }
+        }
+    }
 
-            // drop(map);
-            // let map = self.inner.read().unwrap();
-            // let vv = map.get(&key);
-            // let v = match vv {
-            //     Some(v) => Arc::clone(&v),
-            //     None => unreachable!()
-            // };
-            // // drop(map);
-            // let ff = f(key.clone());
-            // let ff : Option<V> = Some(ff);
-            // let value = Arc::new(Mutex::new(ff.clone()));
-            // let mut map = self.inner.write().unwrap();
-            // map.insert(key.clone(),Arc::clone(&value));
-            // drop(map);
-            // match ff {
-            //     Some(v) => v.clone(),
-            //     None => unreachable!()
-            // }
-        }
-        // let map = self.inner.read().unwrap();
-        // let contain = map.get(&key);
-
-        // if let Some(v) = contain {
-        //     let x = Arc::clone(&v);
-        //     let x = &*x.lock().unwrap();
-        //     x.clone()
-        // }
-        // else{
-        //     drop(map);
-        //     let kk = key.clone();
-        //     let k = Mutex::new(key);
-        //     // let kk = Arc::new(Mutex::new(key.clone()));
-        //     // let k = &*kk.lock().unwrap();
-        //     let ff = f(k.lock().unwrap().clone());
-        //     let value = ff.clone();
-        //     let mut map = self.inner.write().unwrap();
-        //     map.insert(kk.clone(),Arc::new(Mutex::new(value)));
-        //     drop(map);
-        //     ff
-
-        //RwLock은 많은 reader가 읽을 수 있음.
-    }
-}
-
-#[cfg(test)]
+    /// Returns the slot for `key`, creating an empty one if it doesn't exist.
+    fn slot(&self, key: &K) -> Cell<V> {
+        let shard = self.shard(key);
+        {
+            let inner = shard.read().unwrap();
+            if let Some(slot) = inner.map.get(key) {
+                inner.touch(slot);
+                return slot.cell.clone();
+            }
+        }
+        // Another thread may create the slot between the locks.
+        yield_point!();
+        let mut inner = shard.write().unwrap();
+        if let Some(slot) = inner.map.get(key) {
+            return slot.cell.clone();
+        }
+
+        // The weight of a key is charged when its value is computed if the cache has a weigher.
+        let weight = if self.weigher.is_some() { 0 } else { 1 };
+        let evicted = inner.make_room(weight);
+        Stats::add(&self.stats.evictions, evicted);
+        let cell = mock::Arc::new(OnceCell::new());
+        inner.insert(key.clone(), cell.clone(), weight);
+        cell
+    }
+}
+
+impl<K, V> Inner<K, V> {
+    /// Creates a new shard whose keys weigh at most `capacity` in total, or unbounded if `capacity`
+    /// is zero.
+    fn new(capacity: usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            clock: Clock::new(),
+            capacity,
+            weight: 0,
+        }
+    }
+
+    /// Sets the reference bit of the key of `slot`.
+    fn touch(&self, slot: &Slot<V>) {
+        if self.capacity > 0 {
+            self.clock.touch(slot.position);
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Inner<K, V> {
+    /// Inserts `key` of `weight` with `cell`.
+    fn insert(&mut self, key: K, cell: Cell<V>, weight: usize) {
+        // An unbounded shard has no clock, and the positions are unused.
+        let mut position = 0;
+        if self.capacity > 0 {
+            position = self.clock.insert(key.clone());
+        }
+        self.weight += weight;
+        let _ = self.map.insert(
+            key,
+            Slot {
+                cell,
+                position,
+                weight,
+            },
+        );
+    }
+}
+
+impl<K: Eq + Hash, V> Inner<K, V> {
+    /// Removes `key`, freeing its position in `clock`.
+    fn remove(&mut self, key: &K) -> Option<Slot<V>> {
+        let slot = self.map.remove(key)?;
+        if self.capacity > 0 {
+            let _ = self.clock.remove(slot.position);
+        }
+        self.weight -= slot.weight;
+        Some(slot)
+    }
+
+    /// Sets the weight of `key` if its slot is `cell`, and evicts the keys until the shard is not
+    /// overweight. Returns the number of the evicted keys, which may include `key` itself.
+    fn charge(&mut self, key: &K, cell: &Cell<V>, weight: usize) -> u64 {
+        match self.map.get_mut(key) {
+            Some(slot) if ptr::eq(&*slot.cell, &**cell) => {
+                slot.weight = weight;
+                self.weight += weight;
+                self.make_room(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Evicts the keys until `weight` more fits in the shard, or no key can be evicted. Returns the
+    /// number of the evicted keys.
+    fn make_room(&mut self, weight: usize) -> u64 {
+        let mut evicted = 0;
+        while self.capacity > 0 && self.weight + weight > self.capacity && self.evict() {
+            evicted += 1;
+        }
+        evicted
+    }
+
+    /// Removes `key` if its slot is `cell`.
+    fn remove_cell(&mut self, key: &K, cell: &Cell<V>) {
+        if let Some(slot) = self.map.get(key) {
+            if ptr::eq(&*slot.cell, &**cell) {
+                let _ = self.remove(key);
+            }
+        }
+    }
+
+    /// Evicts the victim of the clock among the keys of positive weight. The keys being computed
+    /// weigh zero, so they are never evicted. Returns `false` if there is no such key.
+    fn evict(&mut self) -> bool {
+        // Then every key weighs zero.
+        if self.weight == 0 {
+            return false;
+        }
+        let map = &self.map;
+        let position = match self.clock.victim(|key| map[key].weight > 0) {
+            Some(position) => position,
+            None => return false,
+        };
+        let key = self.clock.remove(position);
+        let slot = self.map.remove(&key).unwrap();
+        self.weight -= slot.weight;
+        counter!("cache.evict");
+        true
+    }
+}
+
+#[cfg(all(test, not(feature = "check-loom")))]
 mod test {
     use super::Cache;
-    use crossbeam_channel::bounded;
+    use crate::channel::oneshot;
+    use crate::sync::Latch;
     use crossbeam_utils::thread::scope;
     use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Barrier;
+    use std::sync::Arc;
+    use std::thread;
     use std::time::Duration;
 
     const NUM_THREADS: usize = 8;
@@ -148,17 +588,281 @@ mod test {
         assert_eq!(cache.get_or_insert_with(3, |_| panic!()), 3);
     }
 
+    #[test]
+    fn cache_eviction() {
+        let cache = Cache::with_capacity_and_shards(3, 1);
+        for key in 1..=3 {
+            assert_eq!(cache.get_or_insert_with(key, |k| k * 10), key * 10);
+        }
+        assert_eq!(cache.get_or_insert_with(1, |_| panic!()), 10);
+
+        // 1 is referenced, so 2 is evicted, and then 3.
+        assert_eq!(cache.get_or_insert_with(4, |k| k * 10), 40);
+        assert_eq!(cache.get_or_insert_with(5, |k| k * 10), 50);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get_or_insert_with(1, |_| panic!()), 10);
+        assert_eq!(cache.get_or_insert_with(2, |k| k * 11), 22);
+    }
+
+    #[test]
+    fn cache_bounded_concurrent() {
+        const CAPACITY: usize = 16;
+
+        let cache = Cache::with_capacity(CAPACITY);
+        scope(|s| {
+            for _ in 0..NUM_THREADS {
+                s.spawn(|_| {
+                    for key in 0..NUM_KEYS {
+                        assert_eq!(cache.get_or_insert_with(key % 40, |k| k + 1), key % 40 + 1);
+                        assert!(cache.len() <= CAPACITY);
+                    }
+                });
+            }
+        })
+        .unwrap();
+        // The shards may not be full.
+        assert!(cache.len() <= CAPACITY);
+    }
+
+    #[test]
+    fn cache_stats() {
+        let cache = &Cache::with_capacity_and_shards(2, 1);
+        for key in 0..3 {
+            cache.get_or_insert_with(key, |k| {
+                thread::sleep(Duration::from_millis(10));
+                k
+            });
+        }
+        cache.get_or_insert_with(2, |_| panic!());
+        assert_eq!(cache.get_or_try_insert_with(3, |_| Err(())), Err(()));
+
+        // T1 waits for the computation of 4.
+        scope(|s| {
+            let (started_sender, started_receiver) = oneshot::channel();
+            let t1 = s.spawn(move |_| {
+                started_receiver.recv().unwrap();
+                cache.get_or_insert_with(4, |_| panic!())
+            });
+            cache.get_or_insert_with(4, |k| {
+                started_sender.send(()).unwrap();
+                thread::sleep(Duration::from_millis(100));
+                k
+            });
+            assert_eq!(t1.join().unwrap(), 4);
+        })
+        .unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 5);
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.in_flight_waits, 1);
+        assert!(stats.compute_time >= Duration::from_millis(130));
+    }
+
+    #[test]
+    fn cache_get() {
+        let cache = &Cache::with_capacity_and_shards(2, 1);
+        assert_eq!(cache.get(&1), None);
+        assert!(!cache.contains_key(&1));
+        assert!(cache.is_empty());
+
+        cache.get_or_insert_with(1, |_| 10);
+        cache.get_or_insert_with(2, |_| 20);
+        assert_eq!(cache.get(&1), Some(10));
+        assert!(cache.contains_key(&2));
+        assert_eq!(cache.stats().hits, 0);
+
+        // `get` referenced 1, so 2 is evicted.
+        cache.get_or_insert_with(3, |_| 30);
+        assert!(cache.contains_key(&1));
+        assert!(!cache.contains_key(&2));
+
+        // The value being computed is not returned.
+        scope(|s| {
+            let (started_sender, started_receiver) = oneshot::channel();
+            let (quit_sender, quit_receiver) = oneshot::channel();
+            let t1 = s.spawn(move |_| {
+                cache.get_or_insert_with(4, |_| {
+                    started_sender.send(()).unwrap();
+                    quit_receiver.recv().unwrap();
+                    40
+                })
+            });
+            started_receiver.recv().unwrap();
+            assert_eq!(cache.get(&4), None);
+            assert!(!cache.contains_key(&4));
+            quit_sender.send(()).unwrap();
+            assert_eq!(t1.join().unwrap(), 40);
+        })
+        .unwrap();
+        assert_eq!(cache.get_arc(&4).as_deref(), Some(&40));
+    }
+
+    #[test]
+    fn cache_arc() {
+        // The values are shared without `Clone`.
+        #[derive(Debug, PartialEq)]
+        struct Body(Vec<u8>);
+
+        let cache = Cache::default();
+        let body = cache.get_or_insert_with_arc(1, |_| Body(vec![1; 1 << 20]));
+        let hit = cache.get_or_insert_with_arc(1, |_| panic!());
+        assert!(Arc::ptr_eq(&body, &hit));
+        assert_eq!(
+            cache.get_or_try_insert_with_arc(2, |_| Err(())),
+            Err::<Arc<Body>, _>(())
+        );
+        assert_eq!(
+            *cache
+                .get_or_try_insert_with_arc(2, |_| Ok::<_, ()>(Body(vec![2])))
+                .unwrap(),
+            Body(vec![2])
+        );
+    }
+
+    #[test]
+    fn cache_weigher() {
+        let cache = Cache::with_weigher_and_shards(10, 1, |_: &usize, v: &usize| *v);
+        cache.get_or_insert_with(1, |_| 4);
+        cache.get_or_insert_with(2, |_| 4);
+        cache.get_or_insert_with(2, |_| panic!());
+
+        // 1 is evicted to fit 3, but 2 is referenced.
+        cache.get_or_insert_with(3, |_| 4);
+        assert!(!cache.contains_key(&1));
+        assert!(cache.contains_key(&2));
+        assert_eq!(cache.stats().evictions, 1);
+
+        // The keys of weight zero are never evicted, and a key heavier than the capacity is evicted
+        // as soon as it is computed.
+        cache.get_or_insert_with(4, |_| 0);
+        assert_eq!(cache.get_or_insert_with(5, |_| 11), 11);
+        assert!(!cache.contains_key(&5));
+        assert_eq!(cache.get(&4), Some(0));
+        assert!((1..=5).filter_map(|key| cache.get(&key)).sum::<usize>() <= 10);
+
+        cache.get_or_insert_with(6, |_| 10);
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        cache.get_or_insert_with(7, |_| 10);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn cache_weigher_concurrent() {
+        const CAPACITY: usize = 1000;
+
+        let cache = Cache::with_weigher_and_shards(CAPACITY, 4, |_: &usize, v: &usize| *v);
+        scope(|s| {
+            for _ in 0..NUM_THREADS {
+                s.spawn(|_| {
+                    for key in 0..NUM_KEYS {
+                        let weight = key % 7 * 50;
+                        assert_eq!(cache.get_or_insert_with(key, |_| weight), weight);
+                    }
+                });
+            }
+        })
+        .unwrap();
+
+        let weight = (0..NUM_KEYS)
+            .filter_map(|key| cache.get(&key))
+            .sum::<usize>();
+        assert!(weight <= CAPACITY);
+    }
+
+    #[test]
+    fn cache_weigher_shards() {
+        // A key of a quarter of the capacity fits in a shard however many CPUs there are.
+        let cache = Cache::with_weigher(1 << 30, |_: &usize, v: &usize| *v);
+        assert!(cache.shards() <= 4);
+        assert_eq!(cache.get_or_insert_with(1, |_| 1 << 28), 1 << 28);
+        assert!(cache.contains_key(&1));
+    }
+
+    #[test]
+    fn cache_shards() {
+        let cache = Cache::with_capacity_and_shards(10, 4);
+        assert_eq!(cache.shards(), 4);
+        for key in 0..100 {
+            assert_eq!(cache.get_or_insert_with(key, |k| k + 1), key + 1);
+            assert!(cache.len() <= 10);
+        }
+        assert_eq!(
+            Cache::<usize, usize>::with_capacity_and_shards(2, 4).shards(),
+            2
+        );
+
+        let cache = Cache::with_shards(4);
+        for key in 0..100 {
+            assert_eq!(cache.get_or_insert_with(key, |k| k + 1), key + 1);
+        }
+        assert_eq!(cache.len(), 100);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_invalidate() {
+        let cache = Cache::with_capacity_and_shards(2, 1);
+        cache.get_or_insert_with(1, |_| 10);
+        cache.get_or_insert_with(2, |_| 20);
+        assert_eq!(cache.invalidate(&1), Some(10));
+        assert_eq!(cache.invalidate(&1), None);
+        assert_eq!(cache.len(), 1);
+
+        // The position of 1 is reused without evicting 2.
+        cache.get_or_insert_with(3, |_| 30);
+        assert_eq!(cache.get_or_insert_with(2, |_| panic!()), 20);
+        assert_eq!(cache.get_or_insert_with(1, |_| 11), 11);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.get_or_insert_with(2, |_| 21), 21);
+        assert_eq!(cache.get_or_insert_with(3, |_| 31), 31);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn cache_invalidate_in_flight() {
+        let cache = &Cache::default();
+
+        scope(|s| {
+            // T1 computes 1 until it is invalidated.
+            let (t1_started_sender, t1_started_receiver) = oneshot::channel();
+            let (t1_quit_sender, t1_quit_receiver) = oneshot::channel();
+            let t1 = s.spawn(move |_| {
+                cache.get_or_insert_with(1, |k| {
+                    t1_started_sender.send(()).unwrap();
+                    t1_quit_receiver.recv().unwrap();
+                    k + 1
+                })
+            });
+            t1_started_receiver.recv().unwrap();
+
+            assert_eq!(cache.invalidate(&1), None);
+            t1_quit_sender.send(()).unwrap();
+            assert_eq!(t1.join().unwrap(), 2);
+        })
+        .unwrap();
+
+        // The value of T1 is not stored.
+        assert!(cache.is_empty());
+        assert_eq!(cache.get_or_insert_with(1, |k| k + 2), 3);
+    }
+
     #[test]
     fn cache_no_duplicate_concurrent() {
         for _ in 0..8 {
             let cache = Cache::default();
-            let barrier = Barrier::new(NUM_THREADS);
+            let start = Latch::new(NUM_THREADS);
             // Count the number of times the computation is run.
             let num_compute = AtomicUsize::new(0);
             scope(|s| {
                 for _ in 0..NUM_THREADS {
                     s.spawn(|_| {
-                        barrier.wait();
+                        start.arrive_and_wait();
                         for key in 0..NUM_KEYS {
                             cache.get_or_insert_with(key, |k| {
                                 num_compute.fetch_add(1, Ordering::Relaxed);
@@ -179,7 +883,7 @@ mod test {
 
         scope(|s| {
             // T1 blocks while inserting 1.
-            let (t1_quit_sender, t1_quit_receiver) = bounded(0);
+            let (t1_quit_sender, t1_quit_receiver) = oneshot::channel();
             s.spawn(move |_| {
                 cache.get_or_insert_with(1, |k| {
                     t1_quit_receiver.recv().unwrap();
@@ -188,7 +892,7 @@ mod test {
             });
 
             // T2 must not be blocked by T1 when inserting 2.
-            let (t2_done_sender, t2_done_receiver) = bounded(0);
+            let (t2_done_sender, mut t2_done_receiver) = oneshot::channel();
             s.spawn(move |_| {
                 cache.get_or_insert_with(2, |k| k);
                 t2_done_sender.send(()).unwrap();
@@ -203,4 +907,214 @@ mod test {
         })
         .unwrap();
     }
+
+    #[test]
+    fn cache_retry_after_panic() {
+        let cache = &Cache::default();
+
+        scope(|s| {
+            // T1 panics while inserting 1, possibly after T2 started waiting for it.
+            let (t1_started_sender, t1_started_receiver) = oneshot::channel();
+            let (t1_panic_sender, t1_panic_receiver) = oneshot::channel::<()>();
+            let t1 = s.spawn(move |_| {
+                cache.get_or_insert_with(1, |_| {
+                    t1_started_sender.send(()).unwrap();
+                    let _ = t1_panic_receiver.recv();
+                    panic!("computation failed");
+                })
+            });
+            t1_started_receiver.recv().unwrap();
+
+            // T2 must not see the failed computation, and computes the value by itself.
+            let t2 = s.spawn(move |_| cache.get_or_insert_with(1, |k| k + 1));
+            thread::sleep(Duration::from_millis(100));
+
+            drop(t1_panic_sender);
+            assert!(t1.join().is_err());
+            assert_eq!(t2.join().unwrap(), 2);
+        })
+        .unwrap();
+
+        assert_eq!(cache.get_or_insert_with(1, |_| panic!()), 2);
+    }
+
+    #[test]
+    fn cache_retry_after_panic_once() {
+        let cache = &Cache::default();
+        let num_compute = &AtomicUsize::new(0);
+
+        scope(|s| {
+            // T1 panics while inserting 1 after the others started waiting for it.
+            let (t1_started_sender, t1_started_receiver) = oneshot::channel();
+            let (t1_panic_sender, t1_panic_receiver) = oneshot::channel::<()>();
+            let t1 = s.spawn(move |_| {
+                cache.get_or_insert_with(1, |_| {
+                    t1_started_sender.send(()).unwrap();
+                    let _ = t1_panic_receiver.recv();
+                    panic!("computation failed");
+                })
+            });
+            t1_started_receiver.recv().unwrap();
+
+            // Only one of the waiters computes the value, and the others get it.
+            let waiters = (0..NUM_THREADS)
+                .map(|_| {
+                    s.spawn(move |_| {
+                        cache.get_or_insert_with(1, |k| {
+                            num_compute.fetch_add(1, Ordering::Relaxed);
+                            thread::sleep(Duration::from_millis(10));
+                            k + 1
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            thread::sleep(Duration::from_millis(100));
+
+            drop(t1_panic_sender);
+            assert!(t1.join().is_err());
+            for waiter in waiters {
+                assert_eq!(waiter.join().unwrap(), 2);
+            }
+        })
+        .unwrap();
+
+        assert_eq!(num_compute.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn cache_try_insert() {
+        let cache = Cache::default();
+        assert_eq!(cache.get_or_try_insert_with(1, |_| Err(500)), Err(500));
+        assert!(cache.is_empty());
+        assert_eq!(
+            cache.get_or_try_insert_with(1, |k| Ok::<_, i32>(k + 1)),
+            Ok(2)
+        );
+        assert_eq!(cache.get_or_try_insert_with(1, |_| Err(500)), Ok(2));
+    }
+
+    #[test]
+    fn cache_try_insert_waiters() {
+        let cache = &Cache::default();
+
+        scope(|s| {
+            // T1 fails to compute 1 after T2 and T3 started waiting for it.
+            let (t1_started_sender, t1_started_receiver) = oneshot::channel();
+            let (t1_fail_sender, t1_fail_receiver) = oneshot::channel();
+            let t1 = s.spawn(move |_| {
+                cache.get_or_try_insert_with(1, |_| {
+                    t1_started_sender.send(()).unwrap();
+                    t1_fail_receiver.recv().unwrap();
+                    Err("upstream error")
+                })
+            });
+            t1_started_receiver.recv().unwrap();
+
+            // T2 gets the error of T1, but T3 can't, so it computes the value by itself.
+            let t2 = s.spawn(move |_| cache.get_or_try_insert_with(1, |_| Ok::<_, &str>(2)));
+            let t3 = s.spawn(move |_| cache.get_or_insert_with(1, |k| k + 2));
+            thread::sleep(Duration::from_millis(100));
+
+            t1_fail_sender.send(()).unwrap();
+            assert_eq!(t1.join().unwrap(), Err("upstream error"));
+            assert_eq!(t2.join().unwrap(), Err("upstream error"));
+            assert_eq!(t3.join().unwrap(), 3);
+        })
+        .unwrap();
+
+        assert_eq!(cache.get_or_insert_with(1, |_| panic!()), 3);
+    }
+}
+
+#[cfg(all(test, feature = "fault-injection"))]
+mod fault_test {
+    use super::Cache;
+    use crate::testing::fault::explore;
+    use crossbeam_utils::thread::scope;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// The threads racing on the missing keys run the computation only once per key, however they
+    /// are delayed between looking up and creating the slot.
+    #[test]
+    fn fault_single_flight() {
+        const KEYS: usize = 8;
+        explore(100, 0, || {
+            let cache = Cache::default();
+            let num_compute = AtomicUsize::new(0);
+            scope(|s| {
+                for _ in 0..4 {
+                    let _ = s.spawn(|_| {
+                        for key in 0..KEYS {
+                            let v = cache.get_or_insert_with(key, |k| {
+                                let _ = num_compute.fetch_add(1, Ordering::Relaxed);
+                                k + 1
+                            });
+                            assert_eq!(v, key + 1);
+                        }
+                    });
+                }
+            })
+            .unwrap();
+            assert_eq!(num_compute.load(Ordering::Relaxed), KEYS);
+        });
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod metrics_test {
+    use super::Cache;
+    use crate::metrics::{self, MemoryRecorder};
+
+    /// The first lookup of a key is a miss, and the rest are hits.
+    #[test]
+    fn metrics_hit_miss() {
+        let recorder: &'static MemoryRecorder = Box::leak(Box::new(MemoryRecorder::new()));
+        metrics::set_recorder(recorder).unwrap();
+
+        let cache = Cache::default();
+        for _ in 0..3 {
+            for key in 0..4 {
+                assert_eq!(cache.get_or_insert_with(key, |k| k * 2), key * 2);
+            }
+        }
+        // Other tests may use caches at the same time.
+        assert!(recorder.counter("cache.miss") >= 4);
+        assert!(recorder.counter("cache.hit") >= 8);
+    }
+}
+
+#[cfg(all(test, feature = "check-loom"))]
+mod loom_test {
+    use super::Cache;
+    use loom::sync::atomic::{AtomicUsize, Ordering};
+    use loom::sync::Arc;
+    use loom::thread;
+
+    /// Two threads racing on the same missing key run the computation only once, and both see its
+    /// result.
+    #[test]
+    fn loom_single_flight() {
+        loom::model(|| {
+            let cache = Arc::new(Cache::default());
+            let num_compute = Arc::new(AtomicUsize::new(0));
+
+            let handles = (0..2)
+                .map(|_| {
+                    let cache = cache.clone();
+                    let num_compute = num_compute.clone();
+                    thread::spawn(move || {
+                        cache.get_or_insert_with(1, |k| {
+                            let _ = num_compute.fetch_add(1, Ordering::Relaxed);
+                            k + 1
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), 2);
+            }
+            assert_eq!(num_compute.load(Ordering::Relaxed), 1);
+        });
+    }
 }
@@ -0,0 +1,297 @@
+//! Bounded key/value cache with CLOCK eviction.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::RwLock;
+
+use super::cache::Cache;
+
+/// Bounded cache that evicts entries with the CLOCK (second-chance) policy, an approximation of
+/// LRU.
+///
+/// The keys are split into shards by their hashes like `Cache`, and the entries of each shard are
+/// kept in a `Clock`, a ring of keys, each with a reference bit. The cache is not lock-free: the map
+/// and the ring of a shard are behind a `RwLock`, and an insertion takes the write lock of its
+/// shard. But a hit only takes the shared lock and sets the reference bit of the key, so unlike LRU,
+/// hits never contend on a list. The capacity is divided among the shards, and each shard evicts its
+/// own keys, so a shard may evict a key while the others still have room. `Cache` evicts the keys
+/// of its shards with the same `Clock`.
+#[derive(Debug)]
+pub struct ClockCache<K, V> {
+    shards: Box<[RwLock<Inner<K, V>>]>,
+    /// Selects the shard of a key.
+    hasher: RandomState,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+struct Inner<K, V> {
+    /// The position in `clock` and the value of each key.
+    index: HashMap<K, (usize, V)>,
+    clock: Clock<K>,
+    /// The maximum number of the keys of the shard.
+    capacity: usize,
+}
+
+/// The CLOCK (second-chance) ring of the keys of a cache.
+///
+/// The owner keeps the ring behind its lock. The ring is changed with the exclusive lock, but the
+/// reference bits are set with the shared lock. To make room for a new key, the clock hand sweeps
+/// the ring, clearing the reference bits, and picks the first key whose bit is already cleared.
+#[derive(Debug)]
+pub(crate) struct Clock<K> {
+    /// The keys in the order of the clock.
+    ring: Vec<Option<K>>,
+    /// The reference bit of each position of `ring`.
+    referenced: Vec<AtomicBool>,
+    /// The empty positions of `ring`.
+    free: Vec<usize>,
+    /// The next position of `ring` to look at.
+    hand: usize,
+}
+
+impl<K> Clock<K> {
+    /// Creates a new empty ring.
+    pub(crate) fn new() -> Self {
+        Self {
+            ring: Vec::new(),
+            referenced: Vec::new(),
+            free: Vec::new(),
+            hand: 0,
+        }
+    }
+
+    /// Inserts `key`, and returns its position. A new key gets its reference bit only when it is
+    /// used again.
+    pub(crate) fn insert(&mut self, key: K) -> usize {
+        let position = self.free.pop().unwrap_or_else(|| {
+            self.ring.push(None);
+            self.referenced.push(AtomicBool::new(false));
+            self.ring.len() - 1
+        });
+        self.ring[position] = Some(key);
+        self.referenced[position].store(false, Ordering::Relaxed);
+        position
+    }
+
+    /// Removes the key at `position`.
+    pub(crate) fn remove(&mut self, position: usize) -> K {
+        let key = self.ring[position].take().unwrap();
+        self.free.push(position);
+        key
+    }
+
+    /// Sets the reference bit of the key at `position`.
+    pub(crate) fn touch(&self, position: usize) {
+        self.referenced[position].store(true, Ordering::Relaxed);
+    }
+
+    /// Moves the clock hand to the first key that is `evictable` and whose reference bit is
+    /// cleared, giving the evictable keys with the bit set a second chance. Returns its position,
+    /// or `None` if no key is evictable.
+    pub(crate) fn victim<F: FnMut(&K) -> bool>(&mut self, mut evictable: F) -> Option<usize> {
+        // The first round clears the bits of the evictable keys, so the second finds one if any.
+        for _ in 0..2 * self.ring.len() {
+            let position = self.hand;
+            self.hand = (position + 1) % self.ring.len();
+            if let Some(key) = &self.ring[position] {
+                if evictable(key) && !self.referenced[position].swap(false, Ordering::Relaxed) {
+                    return Some(position);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ClockCache<K, V> {
+    /// Creates a new cache that holds at most `capacity` entries, with as many shards as
+    /// `Cache::with_capacity`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_shards(capacity, Cache::<K, V>::default_shards())
+    }
+
+    /// Creates a new cache that holds at most `capacity` entries in `shards` shards. If `capacity`
+    /// is smaller than `shards`, there are only `capacity` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` or `shards` is zero.
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        assert!(capacity > 0, "capacity should be positive");
+        assert!(shards > 0, "shards should be positive");
+        let shards = shards.min(capacity);
+        Self {
+            shards: (0..shards)
+                .map(|i| {
+                    // The first `capacity % shards` shards hold one more.
+                    let capacity = capacity / shards + (i < capacity % shards) as usize;
+                    RwLock::new(Inner {
+                        index: HashMap::with_capacity(capacity),
+                        clock: Clock::new(),
+                        capacity,
+                    })
+                })
+                .collect(),
+            hasher: RandomState::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries.
+    ///
+    /// The shards are counted one by one, so the result may be inconsistent with the concurrent
+    /// insertions and removals.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().index.len())
+            .sum()
+    }
+
+    /// Returns `true` if the cache has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value for `key`, marking it as recently used.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let inner = self.shard(key).read().unwrap();
+        let (position, value) = inner.index.get(key)?;
+        inner.clock.touch(*position);
+        Some(value.clone())
+    }
+
+    /// Inserts a key-value pair, replacing the old value of `key` if any. Returns the entry of the
+    /// same shard evicted to make room for it.
+    pub fn insert(&self, key: K, value: V) -> Option<(K, V)> {
+        let mut inner = self.shard(&key).write().unwrap();
+        let inner = &mut *inner;
+        if let Some((position, old)) = inner.index.get_mut(&key) {
+            inner.clock.touch(*position);
+            *old = value;
+            return None;
+        }
+
+        let evicted = if inner.index.len() == inner.capacity {
+            let position = inner.clock.victim(|_| true).unwrap();
+            let key = inner.clock.remove(position);
+            let (_, value) = inner.index.remove(&key).unwrap();
+            Some((key, value))
+        } else {
+            None
+        };
+
+        let position = inner.clock.insert(key.clone());
+        let _ = inner.index.insert(key, (position, value));
+        evicted
+    }
+
+    /// Returns the value for `key`, or inserts the one created by `f`.
+    ///
+    /// Unlike `Cache::get_or_insert_with`, concurrent invocations for the same key may run `f`
+    /// more than once, and the last one wins.
+    pub fn get_or_insert_with<F: FnOnce(&K) -> V>(&self, key: K, f: F) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = f(&key);
+        let _ = self.insert(key, value.clone());
+        value
+    }
+
+    /// Removes `key` and returns its value.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let mut inner = self.shard(key).write().unwrap();
+        let (position, value) = inner.index.remove(key)?;
+        let _ = inner.clock.remove(position);
+        Some(value)
+    }
+
+    fn shard(&self, key: &K) -> &RwLock<Inner<K, V>> {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ClockCache;
+    use crossbeam_utils::thread::scope;
+
+    #[test]
+    fn clock_cache_second_chance() {
+        let cache = ClockCache::with_shards(3, 1);
+        for key in 1..=3 {
+            assert_eq!(cache.insert(key, key * 10), None);
+        }
+        assert_eq!(cache.get(&1), Some(10));
+
+        // 1 is referenced, so 2 is evicted, and then 3.
+        assert_eq!(cache.insert(4, 40), Some((2, 20)));
+        assert_eq!(cache.insert(5, 50), Some((3, 30)));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.len(), 3);
+
+        // Replacing a value does not evict anything.
+        assert_eq!(cache.insert(4, 41), None);
+        assert_eq!(cache.get(&4), Some(41));
+
+        assert_eq!(cache.remove(&5), Some(50));
+        assert_eq!(cache.insert(6, 60), None);
+        assert_eq!(cache.get_or_insert_with(6, |_| panic!()), 60);
+    }
+
+    #[test]
+    fn clock_cache_shards() {
+        // There are only as many shards as the capacity, each holding one key.
+        let cache = ClockCache::with_shards(4, 8);
+        assert_eq!(cache.shards.len(), 4);
+        for key in 0..100 {
+            let _ = cache.insert(key, key);
+        }
+        assert_eq!(cache.capacity(), 4);
+        assert!(cache.len() <= 4);
+        assert!(cache
+            .shards
+            .iter()
+            .all(|shard| shard.read().unwrap().index.len() == 1));
+    }
+
+    #[test]
+    fn clock_cache_concurrent() {
+        const NUM_THREADS: usize = 8;
+        const NUM_KEYS: usize = 1024;
+        const CAPACITY: usize = 128;
+
+        // With 2 shards, each shard gets more keys than it holds.
+        let cache = ClockCache::with_shards(CAPACITY, 2);
+        scope(|s| {
+            for _ in 0..NUM_THREADS {
+                s.spawn(|_| {
+                    for key in 0..NUM_KEYS {
+                        assert_eq!(
+                            cache.get_or_insert_with(key % 200, |k| k * 2),
+                            key % 200 * 2
+                        );
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(cache.len(), CAPACITY);
+    }
+}
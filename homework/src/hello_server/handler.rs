@@ -8,7 +8,7 @@ use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-use super::cache::Cache;
+use super::cache::{Cache, CacheStats};
 use super::statistics::Report;
 
 /// Computes the result for the given key. So expensive, much wow.
@@ -19,12 +19,26 @@ fn very_expensive_computation_that_takes_a_few_seconds(key: String) -> String {
 }
 
 /// Hello handler with a cache.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct Handler {
     cache: Arc<Cache<String, String>>,
 }
 
+impl Default for Handler {
+    fn default() -> Self {
+        Self {
+            cache: Arc::new(Cache::with_weigher(
+                Self::CACHE_CAPACITY,
+                |key: &String, result: &String| key.len() + result.len(),
+            )),
+        }
+    }
+}
+
 impl Handler {
+    /// The maximum total size of the keys and the results that are cached, in bytes.
+    const CACHE_CAPACITY: usize = 1 << 30;
+
     const OK: &'static str = "<!DOCTYPE html>
 <html lang=\"en\">
   <head>
@@ -48,6 +62,11 @@ impl Handler {
   </body>
 </html>";
 
+    /// Returns the statistics of the cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
     /// Process the request and generate report.
     pub fn handle_conn(&self, request_id: usize, mut stream: TcpStream) -> Report {
         let mut buf = [0; 512];
@@ -63,7 +82,7 @@ impl Handler {
             .map(|key| String::from_utf8_lossy(key.as_bytes()));
 
         let resp = if let Some(ref key) = key {
-            let result = self.cache.get_or_insert_with(
+            let result = self.cache.get_or_insert_with_arc(
                 key.to_string(),
                 very_expensive_computation_that_takes_a_few_seconds,
             );
@@ -1,12 +1,15 @@
 //! Hello server with a cache.
 
 mod cache;
+mod clock_cache;
 mod handler;
 mod statistics;
 mod tcp;
 mod thread_pool;
 
+pub use cache::{Cache, CacheStats};
+pub use clock_cache::ClockCache;
 pub use handler::Handler;
 pub use statistics::{Report, Statistics};
 pub use tcp::CancellableTcpListener;
-pub use thread_pool::ThreadPool;
+pub use thread_pool::{JoinHandle, LimitedExecutor, ThreadPool};
@@ -70,7 +70,7 @@ impl<'a> Iterator for Incoming<'a> {
 #[cfg(test)]
 mod test {
     use super::CancellableTcpListener;
-    use crossbeam_channel::bounded;
+    use crate::channel::oneshot;
     use crossbeam_utils::thread::scope;
     use std::io::prelude::*;
     use std::net::TcpStream;
@@ -88,7 +88,7 @@ mod test {
             port += 1;
         };
 
-        let (done_sender, done_receiver) = bounded(0);
+        let (done_sender, mut done_receiver) = oneshot::channel();
         scope(|s| {
             s.spawn(|_| {
                 for stream in listener.incoming() {
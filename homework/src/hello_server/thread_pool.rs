@@ -2,14 +2,25 @@
 
 #![allow(clippy::mutex_atomic)]
 
-// NOTE: Crossbeam channels are MPMC, which means that you don't need to wrap the receiver in
-// Arc<Mutex<..>>. Just clone the receiver and give it to each worker thread.
-use crossbeam_channel::{unbounded, Sender};
-use std::sync::{Arc, Condvar, Mutex};
-use std::thread;
+// NOTE: The job channel is MPSC, so the workers share the receiver through `Arc<Mutex<..>>`. A
+// worker holds the lock only while waiting for a job, not while running it.
+use std::sync::Arc;
+
+use crate::channel::{mpsc, oneshot};
+use crate::mock::sync::{Condvar, Mutex};
+use crate::mock::thread;
+use crate::pool::{OwnedPooledGuard, Pool};
+use crate::sync::Semaphore;
 
 struct Job(Box<dyn FnOnce() + Send + 'static>);
 
+/// Carries a job through the job channel. Envelopes are recycled through a pool instead of being
+/// allocated for each job.
+type Envelope = OwnedPooledGuard<Option<Job>>;
+
+/// The maximum number of queued jobs. `execute` blocks while the queue is full.
+const JOB_QUEUE_CAPACITY: usize = 1 << 16;
+
 #[derive(Debug)]
 struct Worker {
     id: usize,
@@ -28,7 +39,7 @@ impl Drop for Worker {
 
 /// Internal data structure for tracking the current job status. This is shared by the worker
 /// closures via `Arc` so that the workers can report to the pool that it started/finished a job.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct ThreadPoolInner {
     job_count: Mutex<usize>,
     empty_condvar: Condvar,
@@ -37,13 +48,17 @@ struct ThreadPoolInner {
 impl ThreadPoolInner {
     /// Increment the job count.
     fn start_job(&self) {
-        *self.job_count.lock().unwrap() += 1;
+        let mut v = self.job_count.lock().unwrap();
+        *v += 1;
+        gauge!("thread_pool.jobs", *v);
+        histogram!("thread_pool.queue_depth", *v);
     }
 
     /// Decrement the job count.
     fn finish_job(&self) {
         let mut v = self.job_count.lock().unwrap();
         *v -= 1;
+        gauge!("thread_pool.jobs", *v);
         if *v == 0 {
             self.empty_condvar.notify_one();
         }
@@ -66,7 +81,8 @@ impl ThreadPoolInner {
 #[derive(Debug)]
 pub struct ThreadPool {
     workers: Vec<Worker>,
-    job_sender: Option<Sender<Job>>,
+    job_sender: Option<mpsc::Sender<Envelope>>,
+    envelopes: Arc<Pool<Option<Job>>>,
     pool_inner: Arc<ThreadPoolInner>,
 }
 
@@ -75,7 +91,8 @@ impl ThreadPool {
     pub fn new(size: usize) -> Self {
         assert!(size > 0);
         // 스레드들을 생성하고 백터 내에 보관
-        let (sender, receiver) = unbounded();
+        let (sender, receiver) = mpsc::channel::<Envelope>(JOB_QUEUE_CAPACITY);
+        let receiver = Arc::new(Mutex::new(receiver));
 
         let mut workers = Vec::with_capacity(size);
 
@@ -90,10 +107,14 @@ impl ThreadPool {
             let r = receiver.clone();
             let p = Arc::clone(&pool);
             let thread = thread::spawn(move || loop {
-                let job = r.recv();
+                let job = r.lock().unwrap().recv();
+                yield_point!();
                 match job {
-                    Ok(Job(job)) => {
+                    Ok(mut envelope) => {
+                        let Job(job) = envelope.take().unwrap();
+                        drop(envelope);
                         job();
+                        yield_point!();
                     }
                     Err(_) => break,
                 }
@@ -112,6 +133,7 @@ impl ThreadPool {
         ThreadPool {
             workers,
             job_sender,
+            envelopes: Arc::new(Pool::new(|| None)),
             pool_inner,
         }
     }
@@ -122,7 +144,9 @@ impl ThreadPool {
         F: FnOnce() + Send + 'static,
     {
         self.pool_inner.start_job();
-        let job = Job(Box::new(f));
+        yield_point!();
+        let mut job = self.envelopes.clone().get_owned();
+        *job = Some(Job(Box::new(f)));
 
         let x = &self.job_sender;
 
@@ -131,11 +155,93 @@ impl ThreadPool {
         }
     }
 
+    /// Execute a new job in the thread pool, and returns the handle to wait for its result.
+    pub fn spawn<F, T>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.execute(move || {
+            // The handle may have been dropped.
+            let _ = sender.send(f());
+        });
+        JoinHandle { receiver }
+    }
+
+    /// Returns the number of the worker threads.
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
     /// Block the current thread until all jobs in the pool have been executed.  NOTE: This method
     /// has nothing to do with `JoinHandle::join`.
     pub fn join(&self) {
         self.pool_inner.wait_empty();
     }
+
+    /// Returns the process-wide thread pool with one thread per CPU, creating it on the first call.
+    ///
+    /// NOTE: The global pool is never dropped, so its worker threads are never joined. Not
+    /// available under loom, whose primitives can't be created in statics.
+    #[cfg(not(feature = "check-loom"))]
+    pub fn global() -> &'static ThreadPool {
+        use crate::sync::Lazy;
+
+        static GLOBAL: Lazy<ThreadPool> = Lazy::new(|| ThreadPool::new(num_cpus::get()));
+        &GLOBAL
+    }
+}
+
+/// Handle to the result of a job spawned by `ThreadPool::spawn`.
+#[derive(Debug)]
+pub struct JoinHandle<T> {
+    receiver: oneshot::Receiver<T>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Blocks until the job is finished, and returns its result. Returns `Err` if the job
+    /// panicked.
+    pub fn join(self) -> Result<T, oneshot::RecvError> {
+        self.receiver.recv()
+    }
+}
+
+/// Executor that runs at most `limit` jobs of a thread pool at the same time.
+///
+/// `execute` blocks the caller while `limit` jobs are running or queued, which gives backpressure
+/// to the producer of the jobs.
+#[derive(Debug)]
+pub struct LimitedExecutor<'a> {
+    pool: &'a ThreadPool,
+    permits: Arc<Semaphore>,
+}
+
+impl ThreadPool {
+    /// Creates an executor that runs at most `limit` jobs in this pool at the same time. Panics if
+    /// the limit is 0.
+    pub fn limited(&self, limit: usize) -> LimitedExecutor<'_> {
+        assert!(limit > 0);
+        LimitedExecutor {
+            pool: self,
+            permits: Arc::new(Semaphore::new(limit)),
+        }
+    }
+}
+
+impl LimitedExecutor<'_> {
+    /// Execute a new job in the thread pool, blocking until the number of jobs of this executor
+    /// gets below the limit.
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let permit = self.permits.clone().acquire_owned();
+        self.pool.execute(move || {
+            f();
+            drop(permit);
+        });
+    }
 }
 
 impl Drop for ThreadPool {
@@ -149,12 +255,12 @@ impl Drop for ThreadPool {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "check-loom")))]
 mod test {
     use super::ThreadPool;
-    use crossbeam_channel::bounded;
+    use crate::sync::Latch;
     use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::{Arc, Barrier};
+    use std::sync::Arc;
     use std::thread::sleep;
     use std::time::Duration;
 
@@ -164,19 +270,17 @@ mod test {
     #[test]
     fn thread_pool_parallel() {
         let pool = ThreadPool::new(NUM_THREADS);
-        let barrier = Arc::new(Barrier::new(NUM_THREADS));
-        let (done_sender, done_receiver) = bounded(NUM_THREADS);
+        let start = Arc::new(Latch::new(NUM_THREADS));
+        let done = Arc::new(Latch::new(NUM_THREADS));
         for _ in 0..NUM_THREADS {
-            let barrier = barrier.clone();
-            let done_sender = done_sender.clone();
+            let start = start.clone();
+            let done = done.clone();
             pool.execute(move || {
-                barrier.wait();
-                done_sender.send(()).unwrap();
+                start.arrive_and_wait();
+                done.count_down();
             });
         }
-        for _ in 0..NUM_THREADS {
-            done_receiver.recv_timeout(Duration::from_secs(3)).unwrap();
-        }
+        assert!(done.wait_timeout(Duration::from_secs(3)));
     }
 
     // Run jobs that take NUM_JOBS milliseconds as a whole.
@@ -210,6 +314,68 @@ mod test {
         assert_eq!(counter.load(Ordering::Relaxed), NUM_JOBS);
     }
 
+    /// `LimitedExecutor` runs at most `limit` jobs at the same time.
+    #[test]
+    fn thread_pool_limited() {
+        const LIMIT: usize = 2;
+        let pool = ThreadPool::new(NUM_THREADS);
+        let executor = pool.limited(LIMIT);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        for _ in 0..64 {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            executor.execute(move || {
+                let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(n, Ordering::SeqCst);
+                sleep(Duration::from_millis(1));
+                running.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        pool.join();
+        assert!(max_running.load(Ordering::SeqCst) <= LIMIT);
+    }
+
+    /// `spawn` returns the result of the job through the handle.
+    #[test]
+    fn thread_pool_spawn() {
+        let pool = ThreadPool::new(NUM_THREADS);
+        let handles = (0..NUM_JOBS)
+            .map(|i| pool.spawn(move || i * 2))
+            .collect::<Vec<_>>();
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join(), Ok(i * 2));
+        }
+    }
+
+    /// The envelopes of the finished jobs carry the next jobs.
+    #[test]
+    fn thread_pool_envelope_reuse() {
+        let pool = ThreadPool::new(NUM_THREADS);
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..NUM_JOBS {
+            let counter = counter.clone();
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+            pool.join();
+        }
+        assert_eq!(counter.load(Ordering::Relaxed), NUM_JOBS);
+        assert_eq!(pool.envelopes.created(), 1);
+    }
+
+    /// `global` returns the same pool for every call.
+    #[test]
+    fn thread_pool_global() {
+        let pool = ThreadPool::global();
+        assert!(std::ptr::eq(pool, ThreadPool::global()));
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        run_jobs(pool, &counter);
+        pool.join();
+        assert_eq!(counter.load(Ordering::Relaxed), NUM_JOBS);
+    }
+
     /// This indirectly tests if the worker threads' `JoinHandle`s are joined when the pool is
     /// dropped.
     #[test]
@@ -221,3 +387,33 @@ mod test {
         });
     }
 }
+
+#[cfg(all(test, feature = "check-loom"))]
+mod loom_test {
+    use super::ThreadPool;
+    use loom::sync::atomic::{AtomicUsize, Ordering};
+    use loom::sync::Arc;
+
+    /// Dropping the pool closes the job channel while the workers may be waiting for a job or
+    /// running one. Every job queued before the drop is still run, and the drop joins all workers.
+    ///
+    /// The interleavings are bounded to 3 preemptions: the job channel has too many atomics for the
+    /// three threads to be explored exhaustively.
+    #[test]
+    fn loom_shutdown() {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(|| {
+            let pool = ThreadPool::new(2);
+            let counter = Arc::new(AtomicUsize::new(0));
+            for _ in 0..2 {
+                let counter = counter.clone();
+                pool.execute(move || {
+                    let _ = counter.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+            drop(pool);
+            assert_eq!(counter.load(Ordering::Relaxed), 2);
+        });
+    }
+}
@@ -1,29 +1,133 @@
 //! Homeworks
+//!
+//! Without the default `std` feature, the crate is `no_std` and needs `alloc`. Only the lock-free
+//! structures that don't block, e.g. `GrowableArray`, `SplitOrderedList`, and `BoundedStack`, are
+//! available then.
 
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 #[macro_use]
 mod utils;
 
+#[cfg(feature = "std")]
+mod append_vec;
+#[cfg(feature = "std")]
 mod arc;
+mod arena;
+#[cfg(feature = "std")]
 mod art;
+#[cfg(feature = "std")]
+mod atomic_arc;
+mod backoff;
+mod bounded_stack;
+#[cfg(feature = "std")]
 mod bst;
+#[cfg(feature = "std")]
+pub mod channel;
+mod counter;
+pub mod debug_dump;
+#[cfg(feature = "std")]
 mod elim_stack;
+#[cfg(feature = "std")]
+mod hamt;
 mod hash_table;
+#[cfg(feature = "std")]
 pub mod hazard_pointer;
+#[cfg(feature = "std")]
 pub mod hello_server;
+#[cfg(feature = "std")]
 mod linked_list;
+pub mod list;
+#[cfg(feature = "std")]
 mod list_set;
+#[cfg(feature = "std")]
+mod lock_coupling_bst;
 mod map;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "std")]
+mod mock;
+#[cfg(feature = "std")]
+pub mod mcas;
+#[cfg(feature = "std")]
+mod nm_tree;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+mod radix_tree;
+#[cfg(feature = "std")]
+mod rate_limiter;
+#[cfg(feature = "std")]
+pub mod reclamation;
+#[cfg(feature = "std")]
+mod rcu;
+mod set;
+#[cfg(feature = "std")]
+mod stm;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
+mod thread_registry;
 
+#[cfg(feature = "std")]
+pub use append_vec::AppendVec;
+#[cfg(feature = "std")]
 pub use arc::Arc;
+pub use arena::{Arena, ArenaBox, ArenaStats};
+#[cfg(feature = "std")]
 pub use art::{Art, Entry};
+#[cfg(feature = "std")]
+pub use atomic_arc::{AtomicArc, CompareExchangeError};
+pub use backoff::{BackoffStats, ExponentialBackoff};
+pub use bounded_stack::BoundedStack;
+#[cfg(feature = "std")]
 pub use bst::Bst;
-pub use elim_stack::ElimStack;
-pub use hash_table::{GrowableArray, SplitOrderedList};
+pub use counter::StripedCounter;
+#[cfg(feature = "std")]
+pub use elim_stack::{ElimStack, Stack};
+#[cfg(feature = "std")]
+pub use hamt::{AtomicHamt, Hamt};
+pub use hash_table::{
+    growable_array, growable_atomic_array, split_ordered_list, split_ordered_multimap,
+    GrowableArray, GrowableAtomicArray, GrowablePairArray, SplitOrderedList, SplitOrderedMultimap,
+};
+#[cfg(feature = "std")]
+pub use hash_table::SplitOrderedMap;
+#[cfg(feature = "std")]
 pub use linked_list::LinkedList;
+#[cfg(feature = "std")]
 pub use list_set::OrderedListSet;
+#[cfg(feature = "std")]
+pub use lock_coupling_bst::LockCouplingBst;
+pub use lockfree::{Stamped, StampedAtomic};
+#[cfg(feature = "std")]
+pub use map::RandGen;
 pub use map::{
-    ConcurrentMap, NonblockingConcurrentMap, NonblockingMap, RandGen, SequentialMap, StrStringMap,
+    ConcurrentMap, MapError, NonblockingConcurrentMap, NonblockingMap, SequentialMap, StrStringMap,
+};
+#[cfg(feature = "std")]
+pub use nm_tree::NmTree;
+#[cfg(feature = "std")]
+pub use pool::{OwnedPooledGuard, Pool, PoolBox, PooledGuard};
+#[cfg(feature = "std")]
+pub use radix_tree::RadixTree;
+#[cfg(feature = "std")]
+pub use rate_limiter::RateLimiter;
+#[cfg(feature = "std")]
+pub use rcu::{RcuCell, RcuList};
+pub use set::ConcurrentSet;
+#[cfg(feature = "std")]
+pub use stm::{atomically, Abort, StmResult, TVar, Transaction};
+#[cfg(feature = "std")]
+pub use sync::{
+    BarrierWaitResult, Exchanger, Latch, Lazy, OnceCell, OwnedSemaphorePermit, Phaser,
+    Semaphore, SemaphorePermit, SenseBarrier, TreeBarrier, WaitGroup,
 };
+#[cfg(feature = "std")]
+pub use thread_registry::{current_thread_id, ThreadRegistry};
@@ -0,0 +1,685 @@
+//! Lock-free sorted singly linked list by Harris and by Michael.
+//!
+//! A node is deleted in two steps. It is first logically deleted by marking (tagging) its `next`
+//! pointer, and then physically unlinked by swinging the `next` pointer of its predecessor. A
+//! traversal that sees a marked node unlinks it on the way, so the second step may be done by
+//! another thread. Unlinked nodes are reclaimed by the epoch GC, or by the function given to
+//! `List::with_retire`.
+
+use alloc::string::String;
+use core::cmp::Ordering::{Equal, Greater, Less};
+use core::fmt;
+use core::iter;
+use core::mem;
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
+
+use crate::debug_dump::{Format, Kind, Writer};
+use crate::map::{MapError, NonblockingMap};
+
+/// Linked list node.
+#[derive(Debug)]
+pub struct Node<K, V> {
+    /// The tag is set if the node is logically deleted.
+    next: Atomic<Node<K, V>>,
+    key: K,
+    value: V,
+}
+
+/// Sorted singly linked list.
+#[derive(Debug)]
+pub struct List<K, V> {
+    head: Atomic<Node<K, V>>,
+    retire: Retire<K, V>,
+}
+
+/// Frees an unlinked node after the threads currently pinned are unpinned.
+struct Retire<K, V>(unsafe fn(*mut Node<K, V>, &Guard));
+
+/// Linked list cursor.
+///
+/// `prev` is the `next` field of the predecessor of `curr`, or the head of the list.
+#[derive(Debug)]
+pub struct Cursor<'g, K, V> {
+    prev: &'g Atomic<Node<K, V>>,
+    curr: Shared<'g, Node<K, V>>,
+    retire: Retire<K, V>,
+}
+
+/// Iterator over the nodes of a list that are not logically deleted.
+#[derive(Debug)]
+pub struct Iter<'g, K, V> {
+    curr: Shared<'g, Node<K, V>>,
+    guard: &'g Guard,
+}
+
+impl<K, V> Clone for Retire<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for Retire<K, V> {}
+
+impl<K, V> fmt::Debug for Retire<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Retire")
+    }
+}
+
+impl<K, V> Default for Retire<K, V> {
+    fn default() -> Self {
+        unsafe fn destroy<K, V>(node: *mut Node<K, V>, guard: &Guard) {
+            guard.defer_destroy(Shared::from(node as *const Node<K, V>));
+        }
+        Self(destroy)
+    }
+}
+
+impl<K, V> Retire<K, V> {
+    unsafe fn retire(self, node: Shared<'_, Node<K, V>>, guard: &Guard) {
+        (self.0)(node.as_raw() as *mut _, guard)
+    }
+}
+
+impl<K, V> Clone for Cursor<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            prev: self.prev,
+            curr: self.curr,
+            retire: self.retire,
+        }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    /// Creates a new node.
+    pub fn new(key: K, value: V) -> Self {
+        Self {
+            next: Atomic::null(),
+            key,
+            value,
+        }
+    }
+
+    /// Returns the key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the value.
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Extracts the inner value.
+    pub fn into_value(self) -> V {
+        self.value
+    }
+
+    /// Logically deletes the node, and leaves it to be unlinked by a later traversal. Returns
+    /// `false` if it is already deleted.
+    pub fn mark(&self, guard: &Guard) -> bool {
+        self.next.fetch_or(1, Ordering::AcqRel, guard).tag() == 0
+    }
+}
+
+impl<'g, K: Ord, V> Cursor<'g, K, V> {
+    /// Creates a cursor from raw pointers.
+    ///
+    /// # Safety
+    ///
+    /// `curr` should be null or a node of a list that is protected by the guard of `'g`, and
+    /// `prev` should be valid for `'g`. If `prev` is not the `next` field of the predecessor of
+    /// `curr`, the cursor should be moved forward by `find_*` before `insert` or `delete`. The
+    /// list should not be created by `List::with_retire`.
+    pub unsafe fn from_raw(prev: *const Atomic<Node<K, V>>, curr: *const Node<K, V>) -> Self {
+        Self {
+            prev: &*prev,
+            curr: Shared::from(curr),
+            retire: Retire::default(),
+        }
+    }
+
+    /// Returns the current node.
+    pub fn curr(&self) -> Shared<'g, Node<K, V>> {
+        self.curr
+    }
+
+    /// Returns an iterator over the nodes from the current node. See `List::iter`.
+    pub fn iter(&self, guard: &'g Guard) -> Iter<'g, K, V> {
+        Iter {
+            curr: self.curr.with_tag(0),
+            guard,
+        }
+    }
+
+    /// Moves the cursor to the first node whose key is not less than `key`, and returns whether
+    /// its key is equal to `key`.
+    ///
+    /// Unlinks all the marked nodes between `prev` and the found node with a single CAS. Returns
+    /// `Err` if the CAS fails, in which case the search should be restarted from the head.
+    pub fn find_harris(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
+        // The successor of `prev` at the time it was read. If it differs from the found node, the
+        // nodes in between are marked.
+        let mut prev_next = self.curr;
+        let found = loop {
+            let curr_node = some_or!(unsafe { self.curr.as_ref() }, break false);
+            let next = curr_node.next.load(Ordering::Acquire, guard);
+
+            // Skip the marked node without moving `prev`.
+            if next.tag() != 0 {
+                self.curr = next.with_tag(0);
+                continue;
+            }
+
+            match curr_node.key.cmp(key) {
+                Less => {
+                    self.prev = &curr_node.next;
+                    self.curr = next;
+                    prev_next = next;
+                }
+                Equal => break true,
+                Greater => break false,
+            }
+        };
+
+        if prev_next == self.curr {
+            return Ok(found);
+        }
+
+        self.prev
+            .compare_and_set(prev_next, self.curr, Ordering::Release, guard)
+            .map_err(|_| ())?;
+
+        let mut node = prev_next;
+        while node != self.curr {
+            unsafe {
+                let next = node.deref().next.load(Ordering::Acquire, guard);
+                self.retire.retire(node, guard);
+                node = next.with_tag(0);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Same as `find_harris`, but unlinks the marked nodes one by one.
+    pub fn find_harris_michael(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
+        loop {
+            debug_assert_eq!(self.curr.tag(), 0);
+            let curr_node = some_or!(unsafe { self.curr.as_ref() }, return Ok(false));
+            let next = curr_node.next.load(Ordering::Acquire, guard);
+
+            if next.tag() != 0 {
+                let next = next.with_tag(0);
+                self.prev
+                    .compare_and_set(self.curr, next, Ordering::Release, guard)
+                    .map_err(|_| ())?;
+                unsafe { self.retire.retire(self.curr, guard) };
+                self.curr = next;
+                continue;
+            }
+
+            match curr_node.key.cmp(key) {
+                Less => {
+                    self.prev = &curr_node.next;
+                    self.curr = next;
+                }
+                Equal => return Ok(true),
+                Greater => return Ok(false),
+            }
+        }
+    }
+
+    /// Moves the cursor to the next node that is not logically deleted. Unlike `find_harris` and
+    /// `find_harris_michael`, doesn't unlink the deleted nodes on the way.
+    pub fn next(&mut self, guard: &'g Guard) {
+        loop {
+            let curr_node = some_or!(unsafe { self.curr.as_ref() }, return);
+            self.prev = &curr_node.next;
+            self.curr = curr_node.next.load(Ordering::Acquire, guard).with_tag(0);
+            let next_node = some_or!(unsafe { self.curr.as_ref() }, return);
+            if next_node.next.load(Ordering::Acquire, guard).tag() == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Returns the value of the current node.
+    pub fn lookup(&self) -> Option<&'g V> {
+        unsafe { self.curr.as_ref() }.map(|n| &n.value)
+    }
+
+    /// Inserts `node` between `prev` and `curr`, and moves the cursor to it. Returns the node
+    /// back if `prev` is changed.
+    pub fn insert(
+        &mut self,
+        node: Owned<Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Result<(), Owned<Node<K, V>>> {
+        let node = node.into_shared(guard);
+        unsafe { self.insert_shared(node, guard) }.map_err(|()| unsafe { node.into_owned() })
+    }
+
+    /// Same as `insert`, but for a node that is not allocated by `Owned`, e.g. by an `Arena`. The
+    /// node is still owned by the caller if it fails.
+    ///
+    /// # Safety
+    ///
+    /// `node` should be valid and not shared with the other threads yet, and the list should be able
+    /// to free it once it is inserted. See `List::with_retire`.
+    pub unsafe fn insert_shared(
+        &mut self,
+        node: Shared<'g, Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Result<(), ()> {
+        node.deref().next.store(self.curr, Ordering::Relaxed);
+        let _ = self
+            .prev
+            .compare_and_set(self.curr, node, Ordering::Release, guard)
+            .map_err(|_| ())?;
+        self.curr = node;
+        Ok(())
+    }
+
+    /// Deletes the current node, and returns its value. Returns `Err` if the node is already
+    /// deleted by another thread.
+    pub fn delete(self, guard: &'g Guard) -> Result<&'g V, ()> {
+        let curr_node = unsafe { self.curr.as_ref() }.unwrap();
+
+        let next = curr_node.next.fetch_or(1, Ordering::AcqRel, guard);
+        if next.tag() != 0 {
+            return Err(());
+        }
+
+        // If the unlinking fails, a later traversal unlinks the node.
+        if self
+            .prev
+            .compare_and_set(self.curr, next, Ordering::Release, guard)
+            .is_ok()
+        {
+            unsafe { self.retire.retire(self.curr, guard) };
+        }
+        Ok(&curr_node.value)
+    }
+
+    /// Replaces the current node with `node` of the same key, moves the cursor to `node`, and
+    /// returns the value of the replaced node. Returns the node back if the current node is deleted
+    /// or its successor is changed.
+    ///
+    /// The current node is marked and `node` is linked after it with a single CAS, so the
+    /// traversals see either of them, but never neither.
+    pub fn replace(
+        &mut self,
+        node: Owned<Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Result<&'g V, Owned<Node<K, V>>> {
+        let node = node.into_shared(guard);
+        unsafe { self.replace_shared(node, guard) }.map_err(|()| unsafe { node.into_owned() })
+    }
+
+    /// Same as `replace`, but for a node that is not allocated by `Owned`, e.g. by an `Arena`. The
+    /// node is still owned by the caller if it fails.
+    ///
+    /// # Safety
+    ///
+    /// Same as `insert_shared`.
+    pub unsafe fn replace_shared(
+        &mut self,
+        node: Shared<'g, Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Result<&'g V, ()> {
+        let curr_node = self.curr.as_ref().unwrap();
+
+        let next = curr_node.next.load(Ordering::Acquire, guard);
+        if next.tag() != 0 {
+            return Err(());
+        }
+        node.deref().next.store(next, Ordering::Relaxed);
+        let _ = curr_node
+            .next
+            .compare_and_set(next, node.with_tag(1), Ordering::AcqRel, guard)
+            .map_err(|_| ())?;
+
+        // If the unlinking fails, a later traversal unlinks the node.
+        if self
+            .prev
+            .compare_and_set(self.curr, node, Ordering::Release, guard)
+            .is_ok()
+        {
+            self.retire.retire(self.curr, guard);
+        }
+        // If the unlinking failed, `prev` may not point to `node`, but the modifications through
+        // `prev` fail anyway.
+        self.curr = node;
+        Ok(&curr_node.value)
+    }
+}
+
+impl<K: Ord, V> Default for List<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for List<K, V> {
+    fn drop(&mut self) {
+        unsafe {
+            let mut curr = self.head.load(Ordering::Relaxed, unprotected());
+            while !curr.is_null() {
+                let next = curr.deref().next.load(Ordering::Relaxed, unprotected());
+                drop(curr.into_owned());
+                curr = next.with_tag(0);
+            }
+        }
+    }
+}
+
+impl<K, V> List<K, V> {
+    /// Consumes the list, and passes each of its nodes to `free` instead of freeing them. Used when
+    /// the nodes are not allocated by `Owned`.
+    pub fn into_nodes<F: FnMut(*mut Node<K, V>)>(self, mut free: F) {
+        unsafe {
+            let mut curr = self.head.load(Ordering::Relaxed, unprotected());
+            while !curr.is_null() {
+                let next = curr.deref().next.load(Ordering::Relaxed, unprotected());
+                free(curr.as_raw() as *mut _);
+                curr = next.with_tag(0);
+            }
+        }
+        mem::forget(self);
+    }
+
+    /// Returns the nodes in order, including the logically deleted ones, each with whether it is
+    /// deleted.
+    pub(crate) fn nodes<'g>(
+        &'g self,
+        guard: &'g Guard,
+    ) -> impl Iterator<Item = (&'g Node<K, V>, bool)> + 'g {
+        let mut curr = self.head.load(Ordering::Acquire, guard);
+        iter::from_fn(move || {
+            let node = unsafe { curr.as_ref() }?;
+            let next = node.next.load(Ordering::Acquire, guard);
+            curr = next.with_tag(0);
+            Some((node, next.tag() != 0))
+        })
+    }
+
+    /// Unlinks the logically deleted nodes that are not unlinked yet, and flushes the garbage of
+    /// this thread to the collector. See `reclamation`.
+    pub fn collect(&self, guard: &Guard) {
+        'restart: loop {
+            let mut prev = &self.head;
+            let mut curr = prev.load(Ordering::Acquire, guard);
+            while let Some(curr_node) = unsafe { curr.as_ref() } {
+                let next = curr_node.next.load(Ordering::Acquire, guard);
+                if next.tag() == 0 {
+                    prev = &curr_node.next;
+                    curr = next;
+                    continue;
+                }
+
+                let next = next.with_tag(0);
+                if prev
+                    .compare_and_set(curr, next, Ordering::Release, guard)
+                    .is_err()
+                {
+                    continue 'restart;
+                }
+                unsafe { self.retire.retire(curr, guard) };
+                curr = next;
+            }
+            break;
+        }
+        guard.flush();
+    }
+
+    /// Deletes and unlinks the nodes for which `f` returns `true`, and returns the number of them.
+    ///
+    /// `f` may be called more than once for a node, and the nodes that are concurrently inserted may
+    /// or may not be visited.
+    pub fn delete_if<F: FnMut(&K, &V) -> bool>(&self, mut f: F, guard: &Guard) -> usize {
+        let mut deleted = 0;
+        'restart: loop {
+            let mut prev = &self.head;
+            let mut curr = prev.load(Ordering::Acquire, guard);
+            while let Some(curr_node) = unsafe { curr.as_ref() } {
+                let next = curr_node.next.load(Ordering::Acquire, guard);
+                if next.tag() == 0 {
+                    if !f(&curr_node.key, &curr_node.value) {
+                        prev = &curr_node.next;
+                        curr = next;
+                        continue;
+                    }
+                    if curr_node.next.fetch_or(1, Ordering::AcqRel, guard).tag() == 0 {
+                        deleted += 1;
+                    }
+                    // Unlinks it with the successor at the time of marking.
+                    continue;
+                }
+
+                let next = next.with_tag(0);
+                if prev
+                    .compare_and_set(curr, next, Ordering::Release, guard)
+                    .is_err()
+                {
+                    continue 'restart;
+                }
+                unsafe { self.retire.retire(curr, guard) };
+                curr = next;
+            }
+            return deleted;
+        }
+    }
+
+    /// See `debug_dump::list`.
+    pub(crate) fn dump(&self, format: Format, guard: &Guard) -> String
+    where
+        K: fmt::Debug,
+        V: fmt::Debug,
+    {
+        let mut writer = Writer::new(format, format_args!("List"));
+        let mut prev = None;
+        for (node, deleted) in self.nodes(guard) {
+            let id = ("n", node as *const _ as usize);
+            let kind = if deleted {
+                Kind::Deleted
+            } else {
+                Kind::Element
+            };
+            writer.node(
+                id,
+                0,
+                kind,
+                format_args!("{:?} => {:?}", node.key, node.value),
+            );
+            if let Some(prev) = prev {
+                writer.edge(prev, id, None);
+            }
+            prev = Some(id);
+        }
+        writer.finish()
+    }
+}
+
+impl<K: Ord, V> List<K, V> {
+    /// Creates a new list.
+    pub fn new() -> Self {
+        Self {
+            head: Atomic::null(),
+            retire: Retire::default(),
+        }
+    }
+
+    /// Creates a new list whose unlinked nodes are freed by `retire` instead of
+    /// `Guard::defer_destroy`. Used with `into_nodes` when the nodes are not allocated by `Owned`.
+    ///
+    /// # Safety
+    ///
+    /// `retire` should free the node after all the threads currently pinned are unpinned, e.g. by
+    /// `Guard::defer_unchecked`, and it should be able to free all the nodes inserted to the list.
+    pub unsafe fn with_retire(retire: unsafe fn(*mut Node<K, V>, &Guard)) -> Self {
+        Self {
+            head: Atomic::null(),
+            retire: Retire(retire),
+        }
+    }
+
+    /// Creates a cursor at the head.
+    pub fn head<'g>(&'g self, guard: &'g Guard) -> Cursor<'g, K, V> {
+        Cursor {
+            prev: &self.head,
+            curr: self.head.load(Ordering::Acquire, guard),
+            retire: self.retire,
+        }
+    }
+
+    /// Creates a cursor right after `node`.
+    ///
+    /// If `node` is logically deleted, `insert` and `delete` of the cursor fail, and so does
+    /// `find_*` if it has to unlink a node, since `prev` is marked.
+    ///
+    /// # Safety
+    ///
+    /// `node` should be a node of this list.
+    pub unsafe fn cursor_after<'g>(
+        &'g self,
+        node: &'g Node<K, V>,
+        guard: &'g Guard,
+    ) -> Cursor<'g, K, V> {
+        Cursor {
+            prev: &node.next,
+            curr: node.next.load(Ordering::Acquire, guard).with_tag(0),
+            retire: self.retire,
+        }
+    }
+
+    /// Returns an iterator over the nodes in the order of their keys.
+    ///
+    /// The iterator doesn't unlink the marked nodes, and may or may not see the nodes that are
+    /// concurrently inserted or deleted.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        Iter {
+            curr: self.head.load(Ordering::Acquire, guard),
+            guard,
+        }
+    }
+
+    /// Finds `key` from the head, restarting on failure.
+    fn find<'g, F>(&'g self, key: &K, find: &F, guard: &'g Guard) -> (bool, Cursor<'g, K, V>)
+    where
+        F: Fn(&mut Cursor<'g, K, V>, &K, &'g Guard) -> Result<bool, ()>,
+    {
+        loop {
+            let mut cursor = self.head(guard);
+            if let Ok(found) = find(&mut cursor, key, guard) {
+                return (found, cursor);
+            }
+        }
+    }
+
+    fn lookup_with<'g, F>(&'g self, key: &K, find: F, guard: &'g Guard) -> Option<&'g V>
+    where
+        F: Fn(&mut Cursor<'g, K, V>, &K, &'g Guard) -> Result<bool, ()>,
+    {
+        let (found, cursor) = self.find(key, &find, guard);
+        if found {
+            cursor.lookup()
+        } else {
+            None
+        }
+    }
+
+    fn insert_with<'g, F>(&'g self, key: K, value: V, find: F, guard: &'g Guard) -> Result<(), V>
+    where
+        F: Fn(&mut Cursor<'g, K, V>, &K, &'g Guard) -> Result<bool, ()>,
+    {
+        let mut node = Owned::new(Node::new(key, value));
+        loop {
+            let (found, mut cursor) = self.find(&node.key, &find, guard);
+            if found {
+                return Err(node.into_box().into_value());
+            }
+            match cursor.insert(node, guard) {
+                Ok(()) => return Ok(()),
+                Err(n) => node = n,
+            }
+        }
+    }
+
+    fn delete_with<'g, F>(&'g self, key: &K, find: F, guard: &'g Guard) -> Result<&'g V, ()>
+    where
+        F: Fn(&mut Cursor<'g, K, V>, &K, &'g Guard) -> Result<bool, ()>,
+    {
+        loop {
+            let (found, cursor) = self.find(key, &find, guard);
+            if !found {
+                return Err(());
+            }
+            if let Ok(value) = cursor.delete(guard) {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Lookups `key` using `find_harris`.
+    pub fn harris_lookup<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        self.lookup_with(key, Cursor::find_harris, guard)
+    }
+
+    /// Inserts a key-value pair using `find_harris`.
+    pub fn harris_insert(&self, key: K, value: V, guard: &Guard) -> Result<(), V> {
+        self.insert_with(key, value, Cursor::find_harris, guard)
+    }
+
+    /// Deletes `key` using `find_harris`.
+    pub fn harris_delete<'g>(&'g self, key: &K, guard: &'g Guard) -> Result<&'g V, ()> {
+        self.delete_with(key, Cursor::find_harris, guard)
+    }
+
+    /// Lookups `key` using `find_harris_michael`.
+    pub fn harris_michael_lookup<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        self.lookup_with(key, Cursor::find_harris_michael, guard)
+    }
+
+    /// Inserts a key-value pair using `find_harris_michael`.
+    pub fn harris_michael_insert(&self, key: K, value: V, guard: &Guard) -> Result<(), V> {
+        self.insert_with(key, value, Cursor::find_harris_michael, guard)
+    }
+
+    /// Deletes `key` using `find_harris_michael`.
+    pub fn harris_michael_delete<'g>(&'g self, key: &K, guard: &'g Guard) -> Result<&'g V, ()> {
+        self.delete_with(key, Cursor::find_harris_michael, guard)
+    }
+}
+
+impl<'g, K, V> Iterator for Iter<'g, K, V> {
+    type Item = &'g Node<K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = unsafe { self.curr.as_ref() }?;
+            let next = node.next.load(Ordering::Acquire, self.guard);
+            self.curr = next.with_tag(0);
+            if next.tag() == 0 {
+                return Some(node);
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> NonblockingMap<K, V> for List<K, V> {
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        self.harris_michael_lookup(key, guard)
+    }
+
+    fn insert(&self, key: &K, value: V, guard: &Guard) -> Result<(), MapError<V>> {
+        self.harris_michael_insert(key.clone(), value, guard)
+            .map_err(MapError::AlreadyExists)
+    }
+
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, MapError<V>> {
+        self.harris_michael_delete(key, guard)
+            .map_err(|()| MapError::NotFound)
+    }
+}
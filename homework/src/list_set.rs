@@ -1,7 +1,15 @@
 #![allow(clippy::mutex_atomic)]
+#[cfg(feature = "list-set-arena")]
+use core::mem::MaybeUninit;
 use std::cmp;
+use std::fmt;
 use std::ptr;
-use std::sync::{Mutex, MutexGuard};
+
+use crate::debug_dump::{Format, Kind, Writer};
+use crate::mock::sync::{Mutex, MutexGuard};
+#[cfg(feature = "list-set-arena")]
+use crate::pool::{Pool, PoolBox};
+use crate::ConcurrentSet;
 
 #[derive(Debug)]
 struct Node<T> {
@@ -16,6 +24,9 @@ unsafe impl<T> Sync for Node<T> {}
 #[derive(Debug)]
 pub struct OrderedListSet<T> {
     head: Mutex<*mut Node<T>>,
+    /// Recycles the memory of the removed nodes.
+    #[cfg(feature = "list-set-arena")]
+    arena: Pool<MaybeUninit<Node<T>>>,
 }
 
 unsafe impl<T> Send for OrderedListSet<T> {}
@@ -25,11 +36,11 @@ unsafe impl<T> Sync for OrderedListSet<T> {}
 struct Cursor<'l, T>(MutexGuard<'l, *mut Node<T>>);
 
 impl<T> Node<T> {
-    fn new(data: T, next: *mut Self) -> *mut Self {
-        Box::into_raw(Box::new(Self {
+    fn new(data: T, next: *mut Self) -> Self {
+        Self {
             data,
             next: Mutex::new(next),
-        }))
+        }
     }
 }
 
@@ -68,8 +79,36 @@ impl<T> OrderedListSet<T> {
     pub fn new() -> Self {
         Self {
             head: Mutex::new(ptr::null_mut()),
+            #[cfg(feature = "list-set-arena")]
+            arena: Pool::new(MaybeUninit::uninit),
         }
     }
+
+    #[cfg(not(feature = "list-set-arena"))]
+    fn alloc_node(&self, node: Node<T>) -> *mut Node<T> {
+        Box::into_raw(Box::new(node))
+    }
+
+    #[cfg(feature = "list-set-arena")]
+    fn alloc_node(&self, node: Node<T>) -> *mut Node<T> {
+        let mut slot = self.arena.take();
+        unsafe { slot.as_mut_ptr().write(node) };
+        slot.into_raw() as *mut Node<T>
+    }
+
+    /// Frees an unlinked node, and returns its data.
+    #[cfg(not(feature = "list-set-arena"))]
+    unsafe fn free_node(&self, node: *mut Node<T>) -> T {
+        Box::from_raw(node).data
+    }
+
+    /// Frees an unlinked node, and returns its data.
+    #[cfg(feature = "list-set-arena")]
+    unsafe fn free_node(&self, node: *mut Node<T>) -> T {
+        let Node { data, .. } = ptr::read(node);
+        self.arena.put(PoolBox::from_raw(node as *mut MaybeUninit<Node<T>>));
+        data
+    }
 }
 
 impl<T: Ord> OrderedListSet<T> {
@@ -96,7 +135,7 @@ impl<T: Ord> OrderedListSet<T> {
         }
         else{
             let next = *cursor.0;
-            let new = Node::new(key,next);
+            let new = self.alloc_node(Node::new(key,next));
             *cursor.0 = new;
             Ok(())
         }
@@ -108,11 +147,10 @@ impl<T: Ord> OrderedListSet<T> {
             let head = self.head.lock().unwrap();
             let mut cursor = Cursor(head);
             if cursor.find(key) {
-                let remove = Box::from_raw(*cursor.0);
-                let data = remove.data;
-                let next = (*remove).next.lock().unwrap();
-                *cursor.0 = *next;
-                Ok(data)
+                let remove = *cursor.0;
+                let next = *(*remove).next.lock().unwrap();
+                *cursor.0 = next;
+                Ok(self.free_node(remove))
             }
             else{
                 Err(())
@@ -158,26 +196,79 @@ impl<'l, T> Iterator for Iter<'l, T> {
     }
 }
 
+impl<T> OrderedListSet<T> {
+    /// Locks the nodes from the head to the tail without releasing them, and calls `f` with the
+    /// elements. The elements are a snapshot at the moment the last node is locked.
+    fn with_snapshot<R>(&self, f: impl FnOnce(Vec<&T>) -> R) -> R {
+        let mut locked = Vec::new();
+        let mut elements = Vec::new();
+        let mut guard = self.head.lock().unwrap();
+        while !guard.is_null() {
+            let node = unsafe { &**guard };
+            elements.push(&node.data);
+            locked.push(guard);
+            guard = node.next.lock().unwrap();
+        }
+        f(elements)
+    }
+
+    /// See `debug_dump::ordered_list_set`.
+    pub(crate) fn dump(&self, format: Format) -> String
+    where
+        T: fmt::Debug,
+    {
+        self.with_snapshot(|elements| {
+            let mut writer = Writer::new(format, format_args!("OrderedListSet"));
+            let mut prev = None;
+            for data in elements {
+                let id = ("n", data as *const T as usize);
+                writer.node(id, 0, Kind::Element, format_args!("{:?}", data));
+                if let Some(prev) = prev {
+                    writer.edge(prev, id, None);
+                }
+                prev = Some(id);
+            }
+            writer.finish()
+        })
+    }
+}
+
+impl<T: Ord> ConcurrentSet<T> for OrderedListSet<T> {
+    fn contains(&self, key: &T) -> bool {
+        self.contains(key)
+    }
+
+    fn insert(&self, key: T) -> Result<(), T> {
+        self.insert(key)
+    }
+
+    fn remove(&self, key: &T) -> Result<T, ()> {
+        self.remove(key)
+    }
+
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns the keys in order.
+    fn snapshot(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.with_snapshot(|elements| elements.into_iter().cloned().collect())
+    }
+}
+
 impl<T> Drop for OrderedListSet<T> {
     fn drop(&mut self) {
+        // NOTE: loom's `Mutex` has neither `get_mut` nor `into_inner`, so the pointers are read by
+        // locking, which never blocks here.
         unsafe {
-            let mut head = *self.head.get_mut().unwrap();
-            if head.is_null(){
-                return;
-            }
-            loop{
-                let next = Box::from_raw(head);
-                let next = (*next).next;
-                let x = next.into_inner();
-                match x {
-                    Ok(n) => {
-                        if n.is_null() {
-                            break;
-                        }
-                        head = n;
-                    }
-                    _ => break
-                }
+            let mut node = *self.head.lock().unwrap_or_else(|e| e.into_inner());
+            while !node.is_null() {
+                let next = *(*node).next.lock().unwrap_or_else(|e| e.into_inner());
+                drop(self.free_node(node));
+                node = next;
             }
         }
     }
@@ -188,3 +279,108 @@ impl<T> Default for OrderedListSet<T> {
         Self::new()
     }
 }
+
+/// An `OrderedListSet` is serialized as a sequence of its elements in order.
+///
+/// The serialization takes a snapshot of the set, as `ConcurrentSet::snapshot` does. Deserialization sorts the
+/// elements and links the nodes from the tail at once.
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use core::fmt;
+    use core::marker::PhantomData;
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+    use std::ptr;
+
+    use super::{Node, OrderedListSet};
+
+    impl<T: Serialize> Serialize for OrderedListSet<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.with_snapshot(|elements| serializer.collect_seq(elements))
+        }
+    }
+
+    impl<'de, T: Ord + Deserialize<'de>> Deserialize<'de> for OrderedListSet<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct SetVisitor<T>(PhantomData<T>);
+
+            impl<'de, T: Ord + Deserialize<'de>> Visitor<'de> for SetVisitor<T> {
+                type Value = OrderedListSet<T>;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a sequence of distinct elements")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                    let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                    while let Some(element) = seq.next_element()? {
+                        elements.push(element);
+                    }
+                    elements.sort_unstable();
+                    if elements.windows(2).any(|w| w[0] == w[1]) {
+                        return Err(de::Error::custom("duplicate elements"));
+                    }
+
+                    let set = OrderedListSet::new();
+                    let mut next = ptr::null_mut();
+                    for element in elements.into_iter().rev() {
+                        next = set.alloc_node(Node::new(element, next));
+                    }
+                    *set.head.lock().unwrap() = next;
+                    Ok(set)
+                }
+            }
+
+            deserializer.deserialize_seq(SetVisitor(PhantomData))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "list-set-arena", not(feature = "check-loom")))]
+mod arena_test {
+    use super::OrderedListSet;
+
+    /// The removed nodes are reused by the next insertions.
+    #[test]
+    fn arena_reuse() {
+        let set = OrderedListSet::new();
+        for i in 0..100 {
+            assert_eq!(set.insert(i), Ok(()));
+            assert_eq!(set.insert(i + 1), Ok(()));
+            assert_eq!(set.remove(&i), Ok(i));
+            assert_eq!(set.remove(&(i + 1)), Ok(i + 1));
+        }
+        assert_eq!(set.arena.created(), 2);
+    }
+}
+
+#[cfg(all(test, feature = "check-loom"))]
+mod loom_test {
+    use super::OrderedListSet;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    /// An insertion and a removal next to each other hand the locks over in either order. Neither
+    /// loses the other's update nor touches a freed node.
+    #[test]
+    fn loom_lock_coupling_hand_off() {
+        loom::model(|| {
+            let set = Arc::new(OrderedListSet::new());
+            assert_eq!(set.insert(1), Ok(()));
+            assert_eq!(set.insert(3), Ok(()));
+
+            let t1 = {
+                let set = set.clone();
+                thread::spawn(move || assert_eq!(set.insert(2), Ok(())))
+            };
+            let t2 = {
+                let set = set.clone();
+                thread::spawn(move || assert_eq!(set.remove(&3), Ok(3)))
+            };
+            t1.join().unwrap();
+            t2.join().unwrap();
+
+            assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+        });
+    }
+}
@@ -0,0 +1,181 @@
+//! Concurrent binary search tree using lock-coupling.
+
+#![allow(clippy::mutex_atomic)]
+
+use core::cmp;
+use core::ptr;
+use crossbeam_epoch::Guard;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::map::ConcurrentMap;
+
+#[derive(Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    left: Mutex<*mut Node<K, V>>,
+    right: Mutex<*mut Node<K, V>>,
+}
+
+/// Concurrent internal binary search tree using lock-coupling (a.k.a. hand-over-hand locking).
+///
+/// Each link to a node is protected by its own lock, and a node is only accessed while holding the
+/// lock of the link to it. A thread going down the tree locks the next link before unlocking the
+/// current one, so the links are always locked from the top to the bottom. The tree is not
+/// rebalanced.
+#[derive(Debug)]
+pub struct LockCouplingBst<K, V> {
+    root: Mutex<*mut Node<K, V>>,
+}
+
+unsafe impl<K: Send, V: Send> Send for LockCouplingBst<K, V> {}
+unsafe impl<K: Send, V: Send> Sync for LockCouplingBst<K, V> {}
+
+/// The locked link to the current node.
+struct Cursor<'l, K, V>(MutexGuard<'l, *mut Node<K, V>>);
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, value: V) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            key,
+            value,
+            left: Mutex::new(ptr::null_mut()),
+            right: Mutex::new(ptr::null_mut()),
+        }))
+    }
+}
+
+impl<'l, K: Ord, V> Cursor<'l, K, V> {
+    /// Moves the cursor down to the link to the node of `key`, or to the null link where `key`
+    /// should be inserted. Returns `true` if `key` is found.
+    fn find(&mut self, key: &K) -> bool {
+        loop {
+            let node = some_or!(unsafe { self.0.as_ref() }, return false);
+            self.0 = match key.cmp(&node.key) {
+                cmp::Ordering::Equal => return true,
+                cmp::Ordering::Less => node.left.lock().unwrap(),
+                cmp::Ordering::Greater => node.right.lock().unwrap(),
+            };
+        }
+    }
+
+    /// Unlinks the current node and returns it.
+    ///
+    /// If the node has two children, it is replaced with its successor, i.e. the leftmost node of
+    /// its right subtree.
+    fn unlink(mut self) -> Box<Node<K, V>> {
+        let node = *self.0;
+        let node_ref = unsafe { &*node };
+        let left = node_ref.left.lock().unwrap();
+        let mut right = node_ref.right.lock().unwrap();
+
+        let replacement = if left.is_null() {
+            *right
+        } else if right.is_null() {
+            *left
+        } else {
+            let successor = unsafe { Self::pop_leftmost(&mut right) };
+            let successor_ref = unsafe { &*successor };
+            *successor_ref.left.lock().unwrap() = *left;
+            *successor_ref.right.lock().unwrap() = *right;
+            successor
+        };
+
+        // Nobody else can reach the node now because we hold the link to it, and the threads
+        // below it have moved on.
+        drop((left, right));
+        *self.0 = replacement;
+        unsafe { Box::from_raw(node) }
+    }
+
+    /// Unlinks the leftmost node of the non-empty subtree at `link`, and returns it.
+    ///
+    /// # Safety
+    ///
+    /// `link` should be locked.
+    unsafe fn pop_leftmost(link: &mut *mut Node<K, V>) -> *mut Node<K, V> {
+        let first = &**link;
+        let mut left = first.left.lock().unwrap();
+        if left.is_null() {
+            let node = *link;
+            *link = *first.right.lock().unwrap();
+            return node;
+        }
+
+        loop {
+            let node = &**left;
+            let next = node.left.lock().unwrap();
+            if next.is_null() {
+                let popped = *left;
+                *left = *node.right.lock().unwrap();
+                return popped;
+            }
+            left = next;
+        }
+    }
+}
+
+impl<K, V> Default for LockCouplingBst<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> LockCouplingBst<K, V> {
+    /// Creates a new tree.
+    pub fn new() -> Self {
+        Self {
+            root: Mutex::new(ptr::null_mut()),
+        }
+    }
+
+    fn root(&self) -> Cursor<'_, K, V> {
+        Cursor(self.root.lock().unwrap())
+    }
+}
+
+impl<K, V> Drop for LockCouplingBst<K, V> {
+    fn drop(&mut self) {
+        let mut stack = vec![*self.root.get_mut().unwrap()];
+        while let Some(node) = stack.pop() {
+            if node.is_null() {
+                continue;
+            }
+            let mut node = unsafe { Box::from_raw(node) };
+            stack.push(*node.left.get_mut().unwrap());
+            stack.push(*node.right.get_mut().unwrap());
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> ConcurrentMap<K, V> for LockCouplingBst<K, V> {
+    fn lookup<'a, F, R>(&'a self, key: &'a K, _guard: &'a Guard, f: F) -> R
+    where
+        F: FnOnce(Option<&V>) -> R,
+    {
+        let mut cursor = self.root();
+        let found = cursor.find(key);
+        f(if found {
+            Some(unsafe { &(**cursor.0).value })
+        } else {
+            None
+        })
+    }
+
+    fn insert<'a>(&'a self, key: &'a K, value: V, _guard: &'a Guard) -> Result<(), V> {
+        let mut cursor = self.root();
+        if cursor.find(key) {
+            return Err(value);
+        }
+        *cursor.0 = Node::new(key.clone(), value);
+        Ok(())
+    }
+
+    fn delete(&self, key: &K, _guard: &Guard) -> Result<V, ()> {
+        let mut cursor = self.root();
+        if !cursor.find(key) {
+            return Err(());
+        }
+        Ok(cursor.unlink().value)
+    }
+}
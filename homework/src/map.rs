@@ -1,16 +1,23 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
 use core::marker::PhantomData;
 use crossbeam_epoch::Guard;
 use lock::{Lock, RawLock};
+#[cfg(feature = "std")]
 use rand::{distributions::Alphanumeric, rngs::ThreadRng, Rng};
 
 /// Types that has random generator
+#[cfg(feature = "std")]
 pub trait RandGen {
     /// Randomly generates a value.
     fn rand_gen(rng: &mut ThreadRng) -> Self;
 }
 
+#[cfg(feature = "std")]
 const KEY_MAX_LENGTH: usize = 4;
 
+#[cfg(feature = "std")]
 impl RandGen for String {
     fn rand_gen(rng: &mut ThreadRng) -> Self {
         let length = rng.gen::<usize>() % KEY_MAX_LENGTH;
@@ -18,6 +25,7 @@ impl RandGen for String {
     }
 }
 
+#[cfg(feature = "std")]
 impl RandGen for usize {
     /// pick only 16 bits, MSB=0
     fn rand_gen(rng: &mut ThreadRng) -> Self {
@@ -26,6 +34,7 @@ impl RandGen for usize {
     }
 }
 
+#[cfg(feature = "std")]
 impl RandGen for u32 {
     /// pick only 16 bits
     fn rand_gen(rng: &mut ThreadRng) -> Self {
@@ -34,6 +43,30 @@ impl RandGen for u32 {
     }
 }
 
+/// Error of a map operation, returned by `NonblockingMap::insert` and `NonblockingMap::delete`.
+///
+/// There is no error for an invalid key: every key of the maps is valid, e.g. `SplitOrderedList`
+/// accepts the full `usize` range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError<V> {
+    /// The map already has the key. The value to insert is returned back.
+    AlreadyExists(V),
+    /// The map doesn't have the key.
+    NotFound,
+}
+
+impl<V> fmt::Display for MapError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyExists(_) => f.write_str("the key already exists"),
+            Self::NotFound => f.write_str("the key is not found"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V: fmt::Debug> std::error::Error for MapError<V> {}
+
 /// Trait for a sequential key-value map.
 pub trait SequentialMap<K: ?Sized, V> {
     /// Lookups a key.
@@ -65,11 +98,13 @@ pub trait NonblockingMap<K: ?Sized, V> {
     /// Lookups the given key to get the reference to its value.
     fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V>;
 
-    /// Inserts a key-value pair.
-    fn insert(&self, key: &K, value: V, guard: &Guard) -> Result<(), V>;
+    /// Inserts a key-value pair. If the map already has the key, returns the value back in
+    /// `MapError::AlreadyExists`.
+    fn insert(&self, key: &K, value: V, guard: &Guard) -> Result<(), MapError<V>>;
 
-    /// Deletes the given key and its value.
-    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, ()>;
+    /// Deletes the given key and its value. Returns `MapError::NotFound` if the map doesn't have
+    /// the key.
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, MapError<V>>;
 }
 
 /// Converts str sequential map into string sequential map
@@ -134,10 +169,16 @@ impl<K: ?Sized, V: Clone, M: NonblockingMap<K, V>> ConcurrentMap<K, V>
     }
 
     fn insert<'a>(&'a self, key: &'a K, value: V, guard: &'a Guard) -> Result<(), V> {
-        self.inner.insert(key, value, guard)
+        self.inner.insert(key, value, guard).map_err(|e| match e {
+            MapError::AlreadyExists(value) => value,
+            MapError::NotFound => unreachable!(),
+        })
     }
 
     fn delete(&self, key: &K, guard: &Guard) -> Result<V, ()> {
-        self.inner.delete(key, guard).map(|v| v.clone())
+        self.inner
+            .delete(key, guard)
+            .map(|v| v.clone())
+            .map_err(|_| ())
     }
 }
@@ -0,0 +1,278 @@
+//! Multi-word compare-and-swap.
+//!
+//! - From Harris, Fraser, Pratt. A Practical Multi-Word Compare-and-Swap Operation. DISC 2002
+//!   (https://www.cl.cam.ac.uk/research/srg/netos/papers/2002-casn.pdf)
+//!
+//! An MCAS operation is described by a descriptor, which is installed in each of its cells in the
+//! address order. Once installed in all cells, the operation succeeds and the descriptor is
+//! replaced with the new values. Any thread that meets a descriptor helps the operation to finish
+//! instead of waiting for it.
+//!
+//! A descriptor is installed in a cell only if the operation is not decided yet, which needs a
+//! double-compare single-swap (RDCSS). It is implemented with another kind of descriptor that is
+//! installed in the cell while checking the status of the operation.
+//!
+//! Cells that are accessed with MCAS should be read with `load`, which helps the operation in
+//! progress, rather than with `Atomic::load`. The values should not be tagged, and `T` should be
+//! aligned to at least 4 bytes, because the two lowest bits mark the descriptors.
+
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{Atomic, Guard, Pointer, Shared};
+use std::sync::Arc;
+
+/// Marks an RDCSS descriptor.
+const RDCSS_TAG: usize = 1;
+/// Marks an MCAS descriptor.
+const MCAS_TAG: usize = 2;
+const TAGS: usize = RDCSS_TAG | MCAS_TAG;
+
+const UNDECIDED: usize = 0;
+const SUCCEEDED: usize = 1;
+const FAILED: usize = 2;
+
+struct Entry<T> {
+    cell: *const Atomic<T>,
+    old: usize,
+    new: usize,
+}
+
+/// MCAS descriptor.
+///
+/// It is reference-counted: the owner, each RDCSS descriptor for it, and each cell in which it is
+/// installed hold a count. The count of a cell is dropped by the thread that uninstalls it, after
+/// the other threads that may have read it are unpinned.
+struct McasDescriptor<T> {
+    status: AtomicUsize,
+    entries: Vec<Entry<T>>,
+}
+
+/// RDCSS descriptor that installs `mcas` in `cell` if `cell` is `old` and `mcas` is undecided.
+struct RdcssDescriptor<T> {
+    mcas: Arc<McasDescriptor<T>>,
+    cell: *const Atomic<T>,
+    old: usize,
+}
+
+/// Multi-word compare-and-swap operation.
+///
+/// ```
+/// use crossbeam_epoch::{self as epoch, Atomic, Owned};
+/// use cs492_concur_homework::mcas::{self, Mcas};
+///
+/// let a = Atomic::new(1);
+/// let b = Atomic::new(2);
+/// let guard = epoch::pin();
+/// let (old_a, old_b) = (mcas::load(&a, &guard), mcas::load(&b, &guard));
+///
+/// let mut op = Mcas::new();
+/// let _ = op
+///     .add(&a, old_a, Owned::new(3).into_shared(&guard))
+///     .add(&b, old_b, Owned::new(4).into_shared(&guard));
+/// assert!(op.execute(&guard));
+/// assert_eq!(unsafe { *mcas::load(&a, &guard).deref() }, 3);
+/// assert_eq!(unsafe { *mcas::load(&b, &guard).deref() }, 4);
+/// # unsafe {
+/// #     guard.defer_destroy(old_a);
+/// #     guard.defer_destroy(old_b);
+/// #     drop(a.into_owned());
+/// #     drop(b.into_owned());
+/// # }
+/// ```
+pub struct Mcas<'g, T> {
+    entries: Vec<Entry<T>>,
+    _marker: PhantomData<&'g Atomic<T>>,
+}
+
+impl<T> core::fmt::Debug for Mcas<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Mcas")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+impl<T> Default for Mcas<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'g, T> Mcas<'g, T> {
+    /// Creates an empty operation.
+    pub fn new() -> Self {
+        assert!(
+            mem::align_of::<T>() >= 4,
+            "MCAS needs two tag bits in the pointers"
+        );
+        Self {
+            entries: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Adds a cell that should be changed from `old` to `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `old` or `new` is tagged.
+    pub fn add(
+        &mut self,
+        cell: &'g Atomic<T>,
+        old: Shared<'g, T>,
+        new: Shared<'g, T>,
+    ) -> &mut Self {
+        assert_eq!(old.tag(), 0, "MCAS values should not be tagged");
+        assert_eq!(new.tag(), 0, "MCAS values should not be tagged");
+        self.entries.push(Entry {
+            cell,
+            old: old.into_usize(),
+            new: new.into_usize(),
+        });
+        self
+    }
+
+    /// Executes the operation. Returns `true` if all the cells were changed, and `false` if none
+    /// was changed because one of them was not the expected value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a cell is added twice.
+    pub fn execute(self, guard: &'g Guard) -> bool {
+        let mut entries = self.entries;
+        // Installing in the address order prevents two operations from blocking each other.
+        entries.sort_by_key(|e| e.cell as usize);
+        assert!(
+            entries.windows(2).all(|w| w[0].cell != w[1].cell),
+            "a cell is added twice"
+        );
+
+        let descriptor = Arc::new(McasDescriptor {
+            status: AtomicUsize::new(UNDECIDED),
+            entries,
+        });
+        unsafe { help(&descriptor, guard) }
+    }
+}
+
+/// Reads the value of `cell`, helping the operations in progress.
+pub fn load<'g, T>(cell: &Atomic<T>, guard: &'g Guard) -> Shared<'g, T> {
+    loop {
+        let word = cell.load(Ordering::SeqCst, guard).into_usize();
+        match word & TAGS {
+            RDCSS_TAG => unsafe { complete(untag::<RdcssDescriptor<T>>(word), guard) },
+            MCAS_TAG => {
+                let _ = unsafe { help(&borrow::<T>(word), guard) };
+            }
+            _ => return unsafe { Shared::from_usize(word) },
+        }
+    }
+}
+
+fn untag<D>(word: usize) -> *const D {
+    (word & !TAGS) as *const D
+}
+
+/// Borrows the MCAS descriptor in a cell without taking a count.
+///
+/// # Safety
+///
+/// `word` should be read from a cell by the current thread while pinned.
+unsafe fn borrow<T>(word: usize) -> ManuallyDrop<Arc<McasDescriptor<T>>> {
+    ManuallyDrop::new(Arc::from_raw(untag(word)))
+}
+
+/// Compares and swaps the raw words. Returns the previous word.
+unsafe fn cas<T>(cell: *const Atomic<T>, current: usize, new: usize, guard: &Guard) -> usize {
+    match (*cell).compare_and_set(
+        Shared::<T>::from_usize(current),
+        Shared::<T>::from_usize(new),
+        Ordering::SeqCst,
+        guard,
+    ) {
+        Ok(_) => current,
+        Err(e) => e.current.into_usize(),
+    }
+}
+
+/// Installs `mcas` in the cell of `entry` if the cell is `entry.old` and `mcas` is undecided.
+/// Returns the previous word of the cell.
+unsafe fn rdcss<T>(mcas: &Arc<McasDescriptor<T>>, entry: &Entry<T>, guard: &Guard) -> usize {
+    let descriptor = Box::into_raw(Box::new(RdcssDescriptor {
+        mcas: mcas.clone(),
+        cell: entry.cell,
+        old: entry.old,
+    }));
+    let word = descriptor as usize | RDCSS_TAG;
+
+    let prev = loop {
+        let prev = cas(entry.cell, entry.old, word, guard);
+        if prev & TAGS != RDCSS_TAG {
+            break prev;
+        }
+        complete(untag::<RdcssDescriptor<T>>(prev), guard);
+    };
+
+    if prev == entry.old {
+        complete(descriptor, guard);
+        guard.defer_unchecked(move || drop(Box::from_raw(descriptor)));
+    } else {
+        drop(Box::from_raw(descriptor));
+    }
+    prev
+}
+
+/// Replaces the installed RDCSS descriptor with its MCAS descriptor if it is undecided, or with
+/// the old value otherwise.
+unsafe fn complete<T>(descriptor: *const RdcssDescriptor<T>, guard: &Guard) {
+    let d = &*descriptor;
+    let word = descriptor as usize | RDCSS_TAG;
+    if d.mcas.status.load(Ordering::SeqCst) == UNDECIDED {
+        let mcas = Arc::into_raw(d.mcas.clone()) as usize | MCAS_TAG;
+        if cas(d.cell, word, mcas, guard) != word {
+            drop(Arc::from_raw(untag::<McasDescriptor<T>>(mcas)));
+        }
+    } else {
+        let _ = cas(d.cell, word, d.old, guard);
+    }
+}
+
+/// Runs the MCAS operation to the end. Returns `true` if it succeeded.
+unsafe fn help<T>(mcas: &Arc<McasDescriptor<T>>, guard: &Guard) -> bool {
+    let word = Arc::as_ptr(mcas) as usize | MCAS_TAG;
+
+    // Installs the descriptor in the cells.
+    if mcas.status.load(Ordering::SeqCst) == UNDECIDED {
+        let mut status = SUCCEEDED;
+        'entries: for entry in &mcas.entries {
+            loop {
+                let prev = rdcss(mcas, entry, guard);
+                if prev & TAGS == MCAS_TAG {
+                    if prev != word {
+                        let _ = help(&borrow::<T>(prev), guard);
+                        continue;
+                    }
+                } else if prev != entry.old {
+                    status = FAILED;
+                    break 'entries;
+                }
+                break;
+            }
+        }
+        let _ = mcas
+            .status
+            .compare_exchange(UNDECIDED, status, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    // Replaces the descriptor with the new or the old values.
+    let succeeded = mcas.status.load(Ordering::SeqCst) == SUCCEEDED;
+    for entry in &mcas.entries {
+        let value = if succeeded { entry.new } else { entry.old };
+        if cas(entry.cell, word, value, guard) == word {
+            let descriptor = untag::<McasDescriptor<T>>(word);
+            guard.defer_unchecked(move || drop(Arc::from_raw(descriptor)));
+        }
+    }
+    succeeded
+}
@@ -0,0 +1,185 @@
+//! Instrumentation of the data structures.
+//!
+//! With the `metrics` feature, the data structures report their events to the global `Recorder`:
+//!
+//! - `growable_array.grow` (counter) and `growable_array.height` (gauge) when a `GrowableArray`
+//!   adds a level, and `growable_array.height` when `GrowableArray::compact` removes one.
+//! - `split_ordered_list.resize` (counter) and `split_ordered_list.buckets` (gauge) when a
+//!   `SplitOrderedList` doubles its buckets, and `split_ordered_list.shrink` (counter) and
+//!   `split_ordered_list.buckets` when it halves them.
+//! - `cache.hit` and `cache.miss` (counters) for each lookup of the cache of the hello server, and
+//!   `cache.evict` (counter) when a bounded cache evicts a key.
+//! - `backoff.spin`, `backoff.yield` and `backoff.park` (counters) for each wait of an
+//!   `ExponentialBackoff` in the retry loops.
+//! - `thread_pool.jobs` (gauge) and `thread_pool.queue_depth` (histogram) for the jobs that are
+//!   queued or running in a `ThreadPool`.
+//!
+//! The recorder is a no-op until `set_recorder` is called. Without the feature, the reports are
+//! compiled away.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Receives the metrics of the data structures.
+///
+/// The names are `'static` strings of the form `structure.metric`.
+pub trait Recorder: Sync {
+    /// Adds `value` to the counter `name`.
+    fn increment_counter(&self, name: &'static str, value: u64);
+
+    /// Sets the gauge `name` to `value`.
+    fn set_gauge(&self, name: &'static str, value: i64);
+
+    /// Records `value` as a sample of the histogram `name`.
+    fn record_histogram(&self, name: &'static str, value: u64);
+}
+
+/// Recorder that ignores everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopRecorder;
+
+impl Recorder for NoopRecorder {
+    fn increment_counter(&self, _: &'static str, _: u64) {}
+
+    fn set_gauge(&self, _: &'static str, _: i64) {}
+
+    fn record_histogram(&self, _: &'static str, _: u64) {}
+}
+
+const UNINITIALIZED: usize = 0;
+const INITIALIZING: usize = 1;
+const INITIALIZED: usize = 2;
+
+static STATE: AtomicUsize = AtomicUsize::new(UNINITIALIZED);
+static mut RECORDER: &dyn Recorder = &NoopRecorder;
+
+/// Error returned by `set_recorder` when the recorder is already set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetRecorderError;
+
+impl fmt::Display for SetRecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the metrics recorder is already set")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SetRecorderError {}
+
+/// Sets the global recorder. It can be set only once.
+pub fn set_recorder(recorder: &'static dyn Recorder) -> Result<(), SetRecorderError> {
+    if STATE
+        .compare_exchange(
+            UNINITIALIZED,
+            INITIALIZING,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_err()
+    {
+        return Err(SetRecorderError);
+    }
+    // Only this thread writes it, and the readers don't read it until `INITIALIZED` is published.
+    unsafe { RECORDER = recorder };
+    STATE.store(INITIALIZED, Ordering::Release);
+    Ok(())
+}
+
+/// Returns the global recorder, or `NoopRecorder` if it is not set yet.
+pub fn recorder() -> &'static dyn Recorder {
+    if STATE.load(Ordering::Acquire) == INITIALIZED {
+        unsafe { RECORDER }
+    } else {
+        &NoopRecorder
+    }
+}
+
+#[cfg(feature = "std")]
+pub use memory::{Histogram, MemoryRecorder};
+
+#[cfg(feature = "std")]
+mod memory {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::Recorder;
+
+    /// Summary of the samples of a histogram.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Histogram {
+        /// The number of samples.
+        pub count: u64,
+        /// The sum of the samples.
+        pub sum: u64,
+        /// The smallest sample.
+        pub min: u64,
+        /// The largest sample.
+        pub max: u64,
+    }
+
+    #[derive(Debug, Default)]
+    struct Metrics {
+        counters: HashMap<&'static str, u64>,
+        gauges: HashMap<&'static str, i64>,
+        histograms: HashMap<&'static str, Histogram>,
+    }
+
+    /// Recorder that keeps the metrics in memory, e.g. for tests.
+    #[derive(Debug, Default)]
+    pub struct MemoryRecorder {
+        metrics: Mutex<Metrics>,
+    }
+
+    impl MemoryRecorder {
+        /// Creates a new recorder without metrics.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns the counter `name`, or 0 if it is never incremented.
+        pub fn counter(&self, name: &str) -> u64 {
+            let metrics = self.metrics.lock().unwrap();
+            metrics.counters.get(name).copied().unwrap_or(0)
+        }
+
+        /// Returns the last value of the gauge `name`.
+        pub fn gauge(&self, name: &str) -> Option<i64> {
+            self.metrics.lock().unwrap().gauges.get(name).copied()
+        }
+
+        /// Returns the summary of the histogram `name`.
+        pub fn histogram(&self, name: &str) -> Option<Histogram> {
+            self.metrics.lock().unwrap().histograms.get(name).copied()
+        }
+    }
+
+    impl Recorder for MemoryRecorder {
+        fn increment_counter(&self, name: &'static str, value: u64) {
+            *self
+                .metrics
+                .lock()
+                .unwrap()
+                .counters
+                .entry(name)
+                .or_insert(0) += value;
+        }
+
+        fn set_gauge(&self, name: &'static str, value: i64) {
+            let _ = self.metrics.lock().unwrap().gauges.insert(name, value);
+        }
+
+        fn record_histogram(&self, name: &'static str, value: u64) {
+            let mut metrics = self.metrics.lock().unwrap();
+            let histogram = metrics.histograms.entry(name).or_insert(Histogram {
+                count: 0,
+                sum: 0,
+                min: u64::MAX,
+                max: 0,
+            });
+            histogram.count += 1;
+            histogram.sum += value;
+            histogram.min = histogram.min.min(value);
+            histogram.max = histogram.max.max(value);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Synchronization primitives that are replaced with loom's by the `check-loom` feature.
+//!
+//! Loom explores every interleaving of the threads at the operations on its own primitives, so the
+//! modules that block on these primitives, e.g. `Cache`, `ThreadPool`, and `OrderedListSet`, can be
+//! model-checked with `loom::model`. Run the loom tests with
+//! `cargo test --release --features check-loom --lib loom`. Loom 0.3 runs each thread on a fixed
+//! small stack, which the debug build overflows.
+//!
+//! The channels (`mpsc`, `oneshot` and `select`) and `OnceCell` are built on these primitives too:
+//! their atomics are loom's, and a blocked thread waits on an `Event` instead of parking, which
+//! loom 0.3 can't model. So the tests of `Cache` and `ThreadPool` explore the hand-offs through them
+//! as well as the locks. Their values are still kept in `core`'s `UnsafeCell`s, so loom explores the
+//! orderings of the atomics that guard the values, but doesn't check the accesses to the values
+//! themselves.
+//!
+//! The rest of the code stays `std`'s, e.g. `Pool`, which recycles the job envelopes of
+//! `ThreadPool`. Loom neither switches the threads at it nor explores its weak behaviors. Under loom
+//! it acts as sequentially consistent memory that is accessed atomically between two operations on
+//! loom's primitives.
+
+use std::time::Duration;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "check-loom")] {
+        pub(crate) use loom::sync;
+        pub(crate) use loom::thread;
+
+        /// `fence`, which loom 0.3 supports only for `Acquire`. A `SeqCst` fence is emulated by an
+        /// `AcqRel` read-modify-write of a single location: the fences are totally ordered by its
+        /// modification order, and each one synchronizes with the fences before it.
+        pub(crate) fn fence(order: sync::atomic::Ordering) {
+            use sync::atomic::{AtomicUsize, Ordering};
+
+            loom::lazy_static! {
+                static ref FENCE: AtomicUsize = AtomicUsize::new(0);
+            }
+
+            match order {
+                Ordering::Acquire => sync::atomic::fence(order),
+                Ordering::SeqCst => {
+                    let _ = FENCE.fetch_add(0, Ordering::AcqRel);
+                }
+                _ => panic!("only Acquire and SeqCst fences are supported"),
+            }
+        }
+
+        /// Waits on `cvar` for at most `timeout`.
+        ///
+        /// Loom has no time, so this waits until notified.
+        pub(crate) fn wait_timeout<'a, T>(
+            cvar: &sync::Condvar,
+            guard: sync::MutexGuard<'a, T>,
+            _timeout: Duration,
+        ) -> sync::MutexGuard<'a, T> {
+            cvar.wait(guard).unwrap()
+        }
+
+        /// `crossbeam_utils::Backoff` that lets the other threads run on every step, since loom
+        /// doesn't preempt a spinning thread.
+        #[derive(Debug, Default)]
+        pub(crate) struct Backoff;
+
+        impl Backoff {
+            pub(crate) fn new() -> Self {
+                Self
+            }
+
+            pub(crate) fn spin(&self) {
+                thread::yield_now();
+            }
+
+            pub(crate) fn snooze(&self) {
+                thread::yield_now();
+            }
+
+            pub(crate) fn is_completed(&self) -> bool {
+                true
+            }
+        }
+    } else {
+        pub(crate) use core::sync::atomic::fence;
+        pub(crate) use crossbeam_utils::Backoff;
+        pub(crate) use std::sync;
+        pub(crate) use std::thread;
+
+
+        /// Waits on `cvar` for at most `timeout`.
+        pub(crate) fn wait_timeout<'a, T>(
+            cvar: &sync::Condvar,
+            guard: sync::MutexGuard<'a, T>,
+            timeout: Duration,
+        ) -> sync::MutexGuard<'a, T> {
+            cvar.wait_timeout(guard, timeout).unwrap().0
+        }
+    }
+}
+
+/// A flag that a thread waits on until another thread sets it. Used instead of `park` and
+/// `unpark`, which loom 0.3 can't model.
+#[derive(Debug, Default)]
+pub(crate) struct Event {
+    set: sync::Mutex<bool>,
+    cvar: sync::Condvar,
+}
+
+impl Event {
+    /// Creates a new unset event.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the event, and wakes up the waiting threads.
+    pub(crate) fn set(&self) {
+        let mut set = self.set.lock().unwrap();
+        *set = true;
+        self.cvar.notify_all();
+    }
+
+    /// Blocks until the event is set.
+    pub(crate) fn wait(&self) {
+        let mut set = self.set.lock().unwrap();
+        while !*set {
+            set = self.cvar.wait(set).unwrap();
+        }
+    }
+
+    /// Blocks until the event is set or the timeout expires. Returns `true` if the event is set.
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> bool {
+        let set = self.set.lock().unwrap();
+        if *set {
+            return true;
+        }
+        *wait_timeout(&self.cvar, set, timeout)
+    }
+}
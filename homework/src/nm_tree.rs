@@ -0,0 +1,382 @@
+//! Lock-free external binary search tree.
+//!
+//! - From Natarajan, Mittal. Fast Concurrent Lock-Free Binary Search Trees. PPoPP 2014
+//!   (https://dl.acm.org/doi/10.1145/2555243.2555256)
+//!
+//! The key-value pairs are stored in the leaves, and the internal nodes only route the searches.
+//! Deletion operates on edges rather than on nodes. A leaf is deleted by flagging the edge to it,
+//! and then its parent is removed by tagging the edge to its sibling and swinging the edge from
+//! the last untagged ancestor to the sibling. Flagged and tagged edges are never changed again, so
+//! any thread that meets them can finish the deletion. Removed nodes are reclaimed by the epoch
+//! GC.
+
+use core::cmp;
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
+
+use crate::backoff::ExponentialBackoff;
+use crate::map::{MapError, NonblockingMap};
+
+/// The edge points to a leaf that is being deleted.
+const FLAG: usize = 1;
+/// The edge points to the sibling of a leaf that is being deleted, and it is not changed anymore.
+const TAG: usize = 2;
+
+/// Node key. The infinite keys are for the sentinel nodes, so that every real key has a parent and
+/// a grandparent.
+#[derive(Debug, Clone)]
+enum Key<K> {
+    Fin(K),
+    Inf0,
+    Inf1,
+    Inf2,
+}
+
+impl<K: Ord> Key<K> {
+    fn cmp(&self, key: &K) -> cmp::Ordering {
+        match self {
+            Key::Fin(k) => k.cmp(key),
+            _ => cmp::Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Node<K, V> {
+    key: Key<K>,
+    /// `None` for the internal nodes and the sentinel leaves.
+    value: Option<V>,
+    left: Atomic<Node<K, V>>,
+    right: Atomic<Node<K, V>>,
+}
+
+/// Lock-free external binary search tree by Natarajan and Mittal.
+#[derive(Debug)]
+pub struct NmTree<K, V> {
+    /// The sentinel node with the key `Inf2`. Its left child is the sentinel node with the key
+    /// `Inf1`, under whose left child all the real keys are.
+    root: Atomic<Node<K, V>>,
+}
+
+unsafe impl<K: Send, V: Send> Send for NmTree<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for NmTree<K, V> {}
+
+/// The result of `seek`.
+///
+/// `parent` is the parent of `leaf`. `successor` is the topmost node on the path to `parent` that
+/// is not yet being removed, and `ancestor` is its parent. The edges between `successor` and
+/// `parent` are tagged.
+struct SeekRecord<'g, K, V> {
+    ancestor: Shared<'g, Node<K, V>>,
+    successor: Shared<'g, Node<K, V>>,
+    parent: Shared<'g, Node<K, V>>,
+    leaf: Shared<'g, Node<K, V>>,
+}
+
+impl<K, V> Node<K, V> {
+    fn leaf(key: Key<K>, value: Option<V>) -> Self {
+        Self {
+            key,
+            value,
+            left: Atomic::null(),
+            right: Atomic::null(),
+        }
+    }
+
+    fn internal(key: Key<K>, left: Atomic<Self>, right: Atomic<Self>) -> Self {
+        Self {
+            key,
+            value: None,
+            left,
+            right,
+        }
+    }
+}
+
+impl<K: Ord, V> Node<K, V> {
+    /// Returns the edge to the child in the direction of `key`.
+    fn child(&self, key: &K) -> &Atomic<Self> {
+        if self.key.cmp(key) == cmp::Ordering::Greater {
+            &self.left
+        } else {
+            &self.right
+        }
+    }
+
+    /// Returns the edge to the child in the opposite direction of `key`.
+    fn other_child(&self, key: &K) -> &Atomic<Self> {
+        if self.key.cmp(key) == cmp::Ordering::Greater {
+            &self.right
+        } else {
+            &self.left
+        }
+    }
+}
+
+impl<K: Ord, V> Default for NmTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for NmTree<K, V> {
+    fn drop(&mut self) {
+        unsafe {
+            let mut stack = vec![self.root.load(Ordering::Relaxed, unprotected())];
+            while let Some(node) = stack.pop() {
+                if node.is_null() {
+                    continue;
+                }
+                let node = node.into_owned();
+                stack.push(node.left.load(Ordering::Relaxed, unprotected()).with_tag(0));
+                stack.push(
+                    node.right
+                        .load(Ordering::Relaxed, unprotected())
+                        .with_tag(0),
+                );
+            }
+        }
+    }
+}
+
+impl<K: Ord, V> NmTree<K, V> {
+    /// Creates a new tree.
+    pub fn new() -> Self {
+        let inner = Node::internal(
+            Key::Inf1,
+            Atomic::new(Node::leaf(Key::Inf0, None)),
+            Atomic::new(Node::leaf(Key::Inf1, None)),
+        );
+        let root = Node::internal(
+            Key::Inf2,
+            Atomic::new(inner),
+            Atomic::new(Node::leaf(Key::Inf2, None)),
+        );
+        Self {
+            root: Atomic::new(root),
+        }
+    }
+
+    /// Finds the leaf where `key` is or should be.
+    fn seek<'g>(&'g self, key: &K, guard: &'g Guard) -> SeekRecord<'g, K, V> {
+        let root = self.root.load(Ordering::Relaxed, guard);
+        let inner = unsafe { root.deref() }.left.load(Ordering::Relaxed, guard);
+        let mut record = SeekRecord {
+            ancestor: root,
+            successor: inner,
+            parent: inner,
+            leaf: Shared::null(),
+        };
+
+        let mut parent_field = unsafe { inner.deref() }.left.load(Ordering::Acquire, guard);
+        record.leaf = parent_field.with_tag(0);
+        let mut current_field = unsafe { record.leaf.deref() }
+            .child(key)
+            .load(Ordering::Acquire, guard);
+
+        while let Some(current) = unsafe { current_field.with_tag(0).as_ref() } {
+            // An untagged edge is not being removed.
+            if parent_field.tag() & TAG == 0 {
+                record.ancestor = record.parent;
+                record.successor = record.leaf;
+            }
+            record.parent = record.leaf;
+            record.leaf = current_field.with_tag(0);
+            parent_field = current_field;
+            current_field = current.child(key).load(Ordering::Acquire, guard);
+        }
+        record
+    }
+
+    /// Removes the flagged leaf under `record.parent` together with `record.parent`, by swinging
+    /// the edge from `record.ancestor` to the sibling of the leaf. Returns `false` if the edge is
+    /// changed by another thread.
+    fn cleanup<'g>(&'g self, key: &K, record: &SeekRecord<'g, K, V>, guard: &'g Guard) -> bool {
+        let ancestor = unsafe { record.ancestor.deref() };
+        let parent = unsafe { record.parent.deref() };
+
+        // The flagged edge may be on either side of the parent.
+        let child_field = parent.child(key);
+        let sibling_field = if child_field.load(Ordering::Acquire, guard).tag() & FLAG == 0 {
+            child_field
+        } else {
+            parent.other_child(key)
+        };
+
+        // Freezes the sibling edge, and moves the sibling up while keeping its flag.
+        let sibling = sibling_field.fetch_or(TAG, Ordering::AcqRel, guard);
+        let sibling = sibling.with_tag(sibling.tag() & FLAG);
+        if ancestor
+            .child(key)
+            .compare_and_set(record.successor, sibling, Ordering::AcqRel, guard)
+            .is_err()
+        {
+            return false;
+        }
+
+        unsafe { Self::retire(key, record, sibling.with_tag(0), guard) };
+        true
+    }
+
+    /// Retires the nodes removed by `cleanup`, i.e. the nodes from `record.successor` to
+    /// `record.parent` and their flagged leaves.
+    ///
+    /// # Safety
+    ///
+    /// The nodes should have been just unlinked by the current thread.
+    unsafe fn retire<'g>(
+        key: &K,
+        record: &SeekRecord<'g, K, V>,
+        sibling: Shared<'g, Node<K, V>>,
+        guard: &'g Guard,
+    ) {
+        // All the edges below the removed nodes are flagged or tagged, so they do not change.
+        let mut node = record.successor;
+        while node != record.parent {
+            let node_ref = node.deref();
+            let next = node_ref.child(key).load(Ordering::Acquire, guard);
+            let leaf = node_ref.other_child(key).load(Ordering::Acquire, guard);
+            guard.defer_destroy(leaf.with_tag(0));
+            guard.defer_destroy(node);
+            node = next.with_tag(0);
+        }
+
+        let parent = record.parent.deref();
+        let left = parent.left.load(Ordering::Acquire, guard).with_tag(0);
+        let right = parent.right.load(Ordering::Acquire, guard).with_tag(0);
+        guard.defer_destroy(if left == sibling { right } else { left });
+        guard.defer_destroy(record.parent);
+    }
+
+    /// Lookups `key`.
+    pub fn lookup<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        let mut node = unsafe { self.root.load(Ordering::Acquire, guard).deref() };
+        loop {
+            let next = node.child(key).load(Ordering::Acquire, guard);
+            node = some_or!(unsafe { next.with_tag(0).as_ref() }, break);
+        }
+
+        if node.key.cmp(key) == cmp::Ordering::Equal {
+            node.value.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a key-value pair.
+    pub fn insert(&self, key: K, value: V, guard: &Guard) -> Result<(), V>
+    where
+        K: Clone,
+    {
+        let mut new_leaf = Owned::new(Node::leaf(Key::Fin(key.clone()), Some(value)));
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let record = self.seek(&key, guard);
+            let leaf = unsafe { record.leaf.deref() };
+            let ordering = leaf.key.cmp(&key);
+            if ordering == cmp::Ordering::Equal {
+                return Err(new_leaf.into_box().value.unwrap());
+            }
+
+            // The new internal node has the larger key of the two leaves.
+            let new_is_left = ordering == cmp::Ordering::Greater;
+            let internal = if new_is_left {
+                Node::internal(
+                    leaf.key.clone(),
+                    Atomic::from(new_leaf),
+                    Atomic::from(record.leaf),
+                )
+            } else {
+                Node::internal(
+                    Key::Fin(key.clone()),
+                    Atomic::from(record.leaf),
+                    Atomic::from(new_leaf),
+                )
+            };
+
+            let parent = unsafe { record.parent.deref() };
+            match parent.child(&key).compare_and_set(
+                record.leaf,
+                Owned::new(internal),
+                Ordering::AcqRel,
+                guard,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(e) => {
+                    let internal = e.new.into_box();
+                    let field = if new_is_left {
+                        &internal.left
+                    } else {
+                        &internal.right
+                    };
+                    new_leaf = unsafe { field.load(Ordering::Relaxed, unprotected()).into_owned() };
+
+                    // Helps the deletion that is in the way.
+                    if e.current.with_tag(0) == record.leaf && e.current.tag() != 0 {
+                        let _ = self.cleanup(&key, &record, guard);
+                    }
+                    backoff.backoff();
+                }
+            }
+        }
+    }
+
+    /// Deletes `key`, and returns its value.
+    pub fn delete<'g>(&'g self, key: &K, guard: &'g Guard) -> Result<&'g V, ()> {
+        // The leaf flagged by the current thread.
+        let mut flagged: Option<Shared<'g, Node<K, V>>> = None;
+        let backoff = ExponentialBackoff::new();
+        loop {
+            let record = self.seek(key, guard);
+
+            if let Some(leaf) = flagged {
+                // If the leaf is not there anymore, another thread has finished the deletion.
+                if record.leaf != leaf || self.cleanup(key, &record, guard) {
+                    return Ok(unsafe { leaf.deref() }.value.as_ref().unwrap());
+                }
+                continue;
+            }
+
+            let leaf = unsafe { record.leaf.deref() };
+            if leaf.key.cmp(key) != cmp::Ordering::Equal {
+                return Err(());
+            }
+
+            let parent = unsafe { record.parent.deref() };
+            match parent.child(key).compare_and_set(
+                record.leaf,
+                record.leaf.with_tag(FLAG),
+                Ordering::AcqRel,
+                guard,
+            ) {
+                Ok(_) => {
+                    flagged = Some(record.leaf);
+                    if self.cleanup(key, &record, guard) {
+                        return Ok(leaf.value.as_ref().unwrap());
+                    }
+                }
+                Err(e) => {
+                    if e.current.with_tag(0) == record.leaf && e.current.tag() != 0 {
+                        let _ = self.cleanup(key, &record, guard);
+                    }
+                    backoff.backoff();
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> NonblockingMap<K, V> for NmTree<K, V> {
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        self.lookup(key, guard)
+    }
+
+    fn insert(&self, key: &K, value: V, guard: &Guard) -> Result<(), MapError<V>> {
+        self.insert(key.clone(), value, guard)
+            .map_err(MapError::AlreadyExists)
+    }
+
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, MapError<V>> {
+        self.delete(key, guard).map_err(|()| MapError::NotFound)
+    }
+}
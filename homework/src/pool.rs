@@ -0,0 +1,288 @@
+//! Object pool.
+
+use core::fmt;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use crossbeam_utils::Backoff;
+use std::sync::Arc;
+
+/// An object of a pool, linked in the free list while it is in the pool. The pointer to the object
+/// is the pointer to the slot.
+#[repr(C)]
+struct Slot<T> {
+    value: T,
+    next: *mut Slot<T>,
+}
+
+/// Pool of reusable objects.
+///
+/// The free objects are kept in an intrusive Treiber stack: each object is allocated with the link
+/// to the next free object, so `put` pushes the object itself and never allocates. `get` pops a
+/// free object, or creates a new one if there is none, and the returned guard pushes it back when
+/// dropped. The objects are not reset when returned, so the user should clear the state it does
+/// not want to carry over.
+///
+/// The pushes are lock-free, but the pops take turns. A popped object is reused at once rather
+/// than reclaimed, so with concurrent pops, an object could be popped and pushed back between
+/// another pop's read of the head and its CAS, which would then install a stale link (ABA). With a
+/// single pop at a time, the head can only be replaced by pushes in the meantime, which don't
+/// change its link.
+pub struct Pool<T> {
+    /// The top of the stack of the free objects.
+    head: AtomicPtr<Slot<T>>,
+    /// Whether a thread is popping.
+    popping: AtomicBool,
+    /// The number of objects created.
+    created: AtomicUsize,
+    create: fn() -> T,
+}
+
+// The free objects are only accessed by the thread that took them.
+unsafe impl<T: Send> Send for Pool<T> {}
+unsafe impl<T: Send> Sync for Pool<T> {}
+
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("created", &self.created())
+            .field("create", &self.create)
+            .finish()
+    }
+}
+
+/// An object taken out of a `Pool`. Like `Box`, the object is freed when dropped, unless it is put
+/// back into a pool.
+pub struct PoolBox<T> {
+    slot: *mut Slot<T>,
+}
+
+unsafe impl<T: Send> Send for PoolBox<T> {}
+unsafe impl<T: Sync> Sync for PoolBox<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for PoolBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// An object taken from a `Pool`. The object is returned to the pool when dropped.
+#[derive(Debug)]
+pub struct PooledGuard<'p, T> {
+    pool: &'p Pool<T>,
+    value: Option<PoolBox<T>>,
+}
+
+/// An object taken from a `Pool` shared by `Arc`. The object is returned to the pool when dropped.
+#[derive(Debug)]
+pub struct OwnedPooledGuard<T> {
+    pool: Arc<Pool<T>>,
+    value: Option<PoolBox<T>>,
+}
+
+impl<T> Pool<T> {
+    /// Creates a new empty pool that creates objects with `create`.
+    pub fn new(create: fn() -> T) -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            popping: AtomicBool::new(false),
+            created: AtomicUsize::new(0),
+            create,
+        }
+    }
+
+    /// Creates a new pool with `capacity` objects created in advance.
+    pub fn with_capacity(capacity: usize, create: fn() -> T) -> Self {
+        let pool = Self::new(create);
+        for _ in 0..capacity {
+            pool.put(pool.create());
+        }
+        pool
+    }
+
+    /// Returns the number of objects the pool has created, including the ones freed since.
+    pub fn created(&self) -> usize {
+        self.created.load(Ordering::Relaxed)
+    }
+
+    fn create(&self) -> PoolBox<T> {
+        let _ = self.created.fetch_add(1, Ordering::Relaxed);
+        PoolBox {
+            slot: Box::into_raw(Box::new(Slot {
+                value: (self.create)(),
+                next: ptr::null_mut(),
+            })),
+        }
+    }
+
+    /// Pops a free object.
+    fn pop(&self) -> Option<*mut Slot<T>> {
+        if self.head.load(Ordering::Relaxed).is_null() {
+            return None;
+        }
+
+        let backoff = Backoff::new();
+        while self
+            .popping
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            backoff.snooze();
+        }
+
+        let mut head = self.head.load(Ordering::Acquire);
+        while !head.is_null() {
+            // Nobody else pops `head`, so it is not freed and its link doesn't change.
+            let next = unsafe { (*head).next };
+            match self
+                .head
+                .compare_exchange_weak(head, next, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+        self.popping.store(false, Ordering::Release);
+
+        if head.is_null() {
+            None
+        } else {
+            Some(head)
+        }
+    }
+
+    /// Takes an object out of the pool, creating a new one if the pool is empty. The object is
+    /// not returned to the pool unless `put` is called.
+    pub fn take(&self) -> PoolBox<T> {
+        match self.pop() {
+            Some(slot) => PoolBox { slot },
+            None => self.create(),
+        }
+    }
+
+    /// Puts an object into the pool. It may have been taken out of another pool with the same
+    /// type of objects.
+    pub fn put(&self, value: PoolBox<T>) {
+        let slot = value.slot;
+        mem::forget(value);
+
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*slot).next = head };
+            match self
+                .head
+                .compare_exchange_weak(head, slot, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Takes an object out of the pool, and returns the guard that returns it to the pool.
+    pub fn get(&self) -> PooledGuard<'_, T> {
+        PooledGuard {
+            pool: self,
+            value: Some(self.take()),
+        }
+    }
+
+    /// Same as `get`, but the guard holds the pool by `Arc` instead of borrowing it.
+    pub fn get_owned(self: Arc<Self>) -> OwnedPooledGuard<T> {
+        let value = Some(self.take());
+        OwnedPooledGuard { pool: self, value }
+    }
+}
+
+impl<T> Drop for Pool<T> {
+    fn drop(&mut self) {
+        let mut slot = *self.head.get_mut();
+        while !slot.is_null() {
+            let next = unsafe { (*slot).next };
+            drop(PoolBox { slot });
+            slot = next;
+        }
+    }
+}
+
+impl<T> PoolBox<T> {
+    /// Consumes the box, and returns the pointer to the object. The object should be turned back
+    /// into a box by `from_raw`.
+    pub fn into_raw(self) -> *mut T {
+        let slot = self.slot;
+        mem::forget(self);
+        slot as *mut T
+    }
+
+    /// Creates a box from the pointer returned by `into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// `value` should have been returned by `into_raw`, and not turned back into a box yet.
+    pub unsafe fn from_raw(value: *mut T) -> Self {
+        Self {
+            slot: value as *mut Slot<T>,
+        }
+    }
+}
+
+impl<T> Deref for PoolBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &(*self.slot).value }
+    }
+}
+
+impl<T> DerefMut for PoolBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.slot).value }
+    }
+}
+
+impl<T> Drop for PoolBox<T> {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.slot)) };
+    }
+}
+
+impl<T> Deref for PooledGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for PooledGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for PooledGuard<'_, T> {
+    fn drop(&mut self) {
+        self.pool.put(self.value.take().unwrap());
+    }
+}
+
+impl<T> Deref for OwnedPooledGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for OwnedPooledGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for OwnedPooledGuard<T> {
+    fn drop(&mut self) {
+        self.pool.put(self.value.take().unwrap());
+    }
+}
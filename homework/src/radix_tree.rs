@@ -0,0 +1,264 @@
+//! Concurrent radix tree for byte-string keys.
+
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
+
+use crate::backoff::ExponentialBackoff;
+use crate::map::{MapError, NonblockingMap};
+
+#[derive(Debug)]
+struct Node<V> {
+    /// The value of the key that ends at this node.
+    value: Atomic<V>,
+    /// Null if the node has no child.
+    children: Atomic<Children<V>>,
+}
+
+/// The children of a node, sorted by the byte. It is never modified once published, but replaced
+/// with a copy that has one more child.
+#[derive(Debug)]
+struct Children<V> {
+    entries: Vec<(u8, Atomic<Node<V>>)>,
+}
+
+impl<V> Node<V> {
+    fn new() -> Self {
+        Self {
+            value: Atomic::null(),
+            children: Atomic::null(),
+        }
+    }
+
+    /// Returns the children, sorted by the byte.
+    fn children<'g>(&self, guard: &'g Guard) -> &'g [(u8, Atomic<Node<V>>)] {
+        let children = self.children.load(Ordering::Acquire, guard);
+        unsafe { children.as_ref() }.map_or(&[], |children| &children.entries)
+    }
+
+    fn child<'g>(&self, byte: u8, guard: &'g Guard) -> Option<&'g Node<V>> {
+        let children = self.children(guard);
+        let index = children.binary_search_by_key(&byte, |(b, _)| *b).ok()?;
+        Some(unsafe { children[index].1.load(Ordering::Acquire, guard).deref() })
+    }
+
+    /// Returns the child for `byte`, inserting a new one if there is none.
+    fn child_or_insert<'g>(&self, byte: u8, guard: &'g Guard) -> &'g Node<V> {
+        let backoff = ExponentialBackoff::new();
+        let mut new_child = None;
+        loop {
+            let children = self.children.load(Ordering::Acquire, guard);
+            let entries = unsafe { children.as_ref() }.map_or(&[][..], |c| &c.entries);
+            let index = match entries.binary_search_by_key(&byte, |(b, _)| *b) {
+                Ok(index) => {
+                    // `new_child` is not published, so it is dropped right away.
+                    return unsafe { entries[index].1.load(Ordering::Acquire, guard).deref() };
+                }
+                Err(index) => index,
+            };
+
+            let child = new_child
+                .take()
+                .unwrap_or_else(|| Owned::new(Node::new()))
+                .into_shared(guard);
+            let mut new_entries = Vec::with_capacity(entries.len() + 1);
+            new_entries.extend(
+                entries[..index]
+                    .iter()
+                    .map(|(b, n)| (*b, Atomic::from(n.load(Ordering::Relaxed, guard)))),
+            );
+            new_entries.push((byte, Atomic::from(child)));
+            new_entries.extend(
+                entries[index..]
+                    .iter()
+                    .map(|(b, n)| (*b, Atomic::from(n.load(Ordering::Relaxed, guard)))),
+            );
+
+            match self.children.compare_and_set(
+                children,
+                Owned::new(Children {
+                    entries: new_entries,
+                }),
+                Ordering::AcqRel,
+                guard,
+            ) {
+                Ok(_) => unsafe {
+                    // The old array shares the children with the new one, so only the array is
+                    // destroyed.
+                    if !children.is_null() {
+                        guard.defer_destroy(children);
+                    }
+                    return child.deref();
+                },
+                Err(_) => {
+                    new_child = Some(unsafe { child.into_owned() });
+                    backoff.backoff();
+                }
+            }
+        }
+    }
+}
+
+/// Concurrent radix tree from byte strings to `V`.
+///
+/// Each node stands for a prefix of the keys and branches by the next byte, so the keys are kept
+/// in the lexicographic order, and the keys with a common prefix are in the same subtree. A node's
+/// children are kept in a sorted array that is replaced by a CAS when a child is added, so a node
+/// takes memory proportional to its actual fanout rather than 256 pointers. Lookups, iterations,
+/// and prefix scans don't write to shared memory at all, and updates are lock-free.
+///
+/// Deleting a key only removes its value, which is reclaimed by the epoch GC. The nodes are never
+/// removed until the tree is dropped, so this is for key sets that don't keep growing, e.g. the
+/// paths of a server.
+#[derive(Debug)]
+pub struct RadixTree<V> {
+    root: Node<V>,
+}
+
+/// Iterator over the entries of a `RadixTree` in the order of the keys.
+#[derive(Debug)]
+pub struct Iter<'g, V> {
+    /// The nodes to visit, with their keys. The top is the next node.
+    stack: Vec<(Vec<u8>, &'g Node<V>)>,
+    guard: &'g Guard,
+}
+
+impl<V> Default for RadixTree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Drop for RadixTree<V> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = unprotected();
+            let mut stack = vec![Shared::from(&self.root as *const Node<V>)];
+            let mut is_root = true;
+            while let Some(node) = stack.pop() {
+                let node_ref = node.deref();
+                let value = node_ref.value.load(Ordering::Relaxed, guard);
+                if !value.is_null() {
+                    drop(value.into_owned());
+                }
+                let children = node_ref.children.load(Ordering::Relaxed, guard);
+                if !children.is_null() {
+                    let children = children.into_owned();
+                    stack.extend(
+                        children
+                            .entries
+                            .iter()
+                            .map(|(_, child)| child.load(Ordering::Relaxed, guard)),
+                    );
+                }
+                if !is_root {
+                    drop(node.into_owned());
+                }
+                is_root = false;
+            }
+        }
+    }
+}
+
+impl<V> RadixTree<V> {
+    /// Creates a new empty tree.
+    pub fn new() -> Self {
+        Self { root: Node::new() }
+    }
+
+    /// Returns the node of `key`, if there is one.
+    fn find<'g>(&'g self, key: &[u8], guard: &'g Guard) -> Option<&'g Node<V>> {
+        let mut node = &self.root;
+        for byte in key {
+            node = node.child(*byte, guard)?;
+        }
+        Some(node)
+    }
+
+    /// Lookups `key`.
+    pub fn lookup<'g>(&'g self, key: &[u8], guard: &'g Guard) -> Option<&'g V> {
+        let node = self.find(key, guard)?;
+        unsafe { node.value.load(Ordering::Acquire, guard).as_ref() }
+    }
+
+    /// Inserts a key-value pair. Returns the value back if `key` is already in the tree.
+    pub fn insert(&self, key: &[u8], value: V, guard: &Guard) -> Result<(), V> {
+        let mut node = &self.root;
+        for byte in key {
+            node = node.child_or_insert(*byte, guard);
+        }
+        node.value
+            .compare_and_set(Shared::null(), Owned::new(value), Ordering::AcqRel, guard)
+            .map(|_| ())
+            .map_err(|e| *e.new.into_box())
+    }
+
+    /// Deletes `key`, and returns its value.
+    pub fn delete<'g>(&'g self, key: &[u8], guard: &'g Guard) -> Result<&'g V, ()> {
+        let node = self.find(key, guard).ok_or(())?;
+        let value = node.value.swap(Shared::null(), Ordering::AcqRel, guard);
+        if value.is_null() {
+            return Err(());
+        }
+        unsafe {
+            guard.defer_destroy(value);
+            Ok(value.deref())
+        }
+    }
+
+    /// Returns the iterator over all the entries in the order of the keys.
+    ///
+    /// The iterator sees the entries that are in the tree during the whole iteration, and may or
+    /// may not see the others.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, V> {
+        self.scan_prefix(&[], guard)
+    }
+
+    /// Returns the iterator over the entries whose keys start with `prefix`, in the order of the
+    /// keys.
+    pub fn scan_prefix<'g>(&'g self, prefix: &[u8], guard: &'g Guard) -> Iter<'g, V> {
+        Iter {
+            stack: self
+                .find(prefix, guard)
+                .map(|node| (prefix.to_vec(), node))
+                .into_iter()
+                .collect(),
+            guard,
+        }
+    }
+}
+
+impl<'g, V> Iterator for Iter<'g, V> {
+    type Item = (Vec<u8>, &'g V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, node) = self.stack.pop()?;
+            // Visits the children after the node itself, in the order of the bytes.
+            for (byte, child) in node.children(self.guard).iter().rev() {
+                let mut child_key = key.clone();
+                child_key.push(*byte);
+                let child = unsafe { child.load(Ordering::Acquire, self.guard).deref() };
+                self.stack.push((child_key, child));
+            }
+            let value = node.value.load(Ordering::Acquire, self.guard);
+            if let Some(value) = unsafe { value.as_ref() } {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+impl<V> NonblockingMap<[u8], V> for RadixTree<V> {
+    fn lookup<'a>(&'a self, key: &[u8], guard: &'a Guard) -> Option<&'a V> {
+        self.lookup(key, guard)
+    }
+
+    fn insert(&self, key: &[u8], value: V, guard: &Guard) -> Result<(), MapError<V>> {
+        self.insert(key, value, guard)
+            .map_err(MapError::AlreadyExists)
+    }
+
+    fn delete<'a>(&'a self, key: &[u8], guard: &'a Guard) -> Result<&'a V, MapError<V>> {
+        self.delete(key, guard).map_err(|()| MapError::NotFound)
+    }
+}
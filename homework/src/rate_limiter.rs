@@ -0,0 +1,82 @@
+//! Token-bucket rate limiter.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::backoff::ExponentialBackoff;
+
+/// Lock-free token-bucket rate limiter.
+///
+/// Tokens are refilled at `rate` tokens per second, up to `burst` tokens. Instead of a token count,
+/// the limiter keeps the time at which the bucket becomes full again (the generic cell rate
+/// algorithm), so that acquiring tokens is a single CAS on that timestamp.
+#[derive(Debug)]
+pub struct RateLimiter {
+    start: Instant,
+    /// The time to refill a token, in nanoseconds.
+    interval: u64,
+    /// The time to refill the whole bucket, in nanoseconds.
+    capacity: u64,
+    /// The time since `start` at which the bucket becomes full, in nanoseconds.
+    full_at: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Creates a new rate limiter that allows `rate` tokens per second on average, and up to
+    /// `burst` tokens at once. The bucket is initially full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` or `burst` is zero, or if `rate` is more than 10^9, since the time to
+    /// refill a token would be less than a nanosecond.
+    pub fn new(rate: u32, burst: u32) -> Self {
+        assert!(rate > 0, "rate should be positive");
+        assert!(burst > 0, "burst should be positive");
+        let interval = Duration::from_secs(1).as_nanos() as u64 / u64::from(rate);
+        assert!(interval > 0, "rate should be at most 10^9 per second");
+        Self {
+            start: Instant::now(),
+            interval,
+            capacity: interval * u64::from(burst),
+            full_at: AtomicU64::new(0),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_nanos() as u64
+    }
+
+    /// Tries to take `n` tokens. Returns `false` without taking any token if there are not enough.
+    pub fn try_acquire(&self, n: u32) -> bool {
+        let cost = self.interval * u64::from(n);
+        let now = self.now();
+        let mut full_at = self.full_at.load(Ordering::Relaxed);
+        let backoff = ExponentialBackoff::new();
+        loop {
+            // The bucket is never fuller than full.
+            let new_full_at = full_at.max(now) + cost;
+            if new_full_at - now > self.capacity {
+                return false;
+            }
+            match self.full_at.compare_exchange_weak(
+                full_at,
+                new_full_at,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => {
+                    full_at = current;
+                    backoff.backoff();
+                }
+            }
+        }
+    }
+
+    /// Returns the number of tokens that can be taken now.
+    pub fn available(&self) -> u32 {
+        let now = self.now();
+        let full_at = self.full_at.load(Ordering::Relaxed).max(now);
+        ((self.capacity - (full_at - now)) / self.interval) as u32
+    }
+}
@@ -0,0 +1,79 @@
+//! Read-copy-update cell.
+
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{pin, unprotected, Atomic, Guard, Owned};
+
+/// Cell that holds a value which is read without blocking and replaced by a new copy.
+///
+/// Readers only need an epoch guard to get a reference to the current value. Writers build a new
+/// value from the current one and install it with a CAS. The old value is destroyed through the
+/// epoch GC after all the readers that may have seen it are unpinned.
+///
+/// This is useful for read-mostly data like a server configuration or a routing table that should
+/// be hot-swapped without blocking the request threads.
+#[derive(Debug)]
+pub struct RcuCell<T> {
+    inner: Atomic<T>,
+}
+
+impl<T: Default> Default for RcuCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> RcuCell<T> {
+    /// Creates a new cell with the given value.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Atomic::new(value),
+        }
+    }
+
+    /// Returns the reference to the current value. The reference is valid while `guard` is alive.
+    pub fn read<'g>(&'g self, guard: &'g Guard) -> &'g T {
+        let curr = self.inner.load(Ordering::Acquire, guard);
+        // The pointer is never null and is only destroyed after `guard` is unpinned.
+        unsafe { curr.deref() }
+    }
+
+    /// Installs the value created by `f` from the current value.
+    ///
+    /// If another writer installs a value in the meantime, `f` is called again with the newer
+    /// value. Hence `f` may be called more than once.
+    pub fn update<F: FnMut(&T) -> T>(&self, mut f: F) {
+        let guard = pin();
+        let mut curr = self.inner.load(Ordering::Acquire, &guard);
+        let mut new = Owned::new(f(unsafe { curr.deref() }));
+        loop {
+            match self
+                .inner
+                .compare_and_set(curr, new, Ordering::AcqRel, &guard)
+            {
+                Ok(_) => break,
+                Err(e) => {
+                    curr = e.current;
+                    new = e.new;
+                    *new = f(unsafe { curr.deref() });
+                }
+            }
+        }
+        unsafe { guard.defer_destroy(curr) };
+    }
+
+    /// Replaces the current value with `value` regardless of the current value.
+    pub fn store(&self, value: T) {
+        let guard = pin();
+        let old = self.inner.swap(Owned::new(value), Ordering::AcqRel, &guard);
+        unsafe { guard.defer_destroy(old) };
+    }
+}
+
+impl<T> Drop for RcuCell<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let curr = self.inner.load(Ordering::Relaxed, unprotected());
+            drop(curr.into_owned());
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Read-copy-update linked list.
+
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{pin, unprotected, Atomic, Guard, Owned, Shared};
+use std::sync::Mutex;
+
+#[derive(Debug)]
+struct Node<T> {
+    value: T,
+    next: Atomic<Node<T>>,
+}
+
+/// Singly linked list whose readers traverse it without blocking.
+///
+/// Readers only need an epoch guard to iterate over the list. Writers are serialized by a lock,
+/// and never modify a node that readers may see: a node is inserted after it is fully built, and
+/// a node is updated by linking a modified copy in its place. A reader that is on an unlinked node
+/// still follows its `next` pointer, which is valid until the reader is unpinned.
+///
+/// This is useful for read-mostly registries that should be modified without blocking the request
+/// threads, e.g. the list of handlers of a server.
+#[derive(Debug)]
+pub struct RcuList<T> {
+    head: Atomic<Node<T>>,
+    writer: Mutex<()>,
+}
+
+/// Iterator over the values of an `RcuList`.
+#[derive(Debug)]
+pub struct Iter<'g, T> {
+    curr: Shared<'g, Node<T>>,
+    guard: &'g Guard,
+}
+
+impl<T> Default for RcuList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> RcuList<T> {
+    /// Creates a new empty list.
+    pub fn new() -> Self {
+        Self {
+            head: Atomic::null(),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns the iterator over the values. The values are valid while `guard` is alive.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T> {
+        Iter {
+            curr: self.head.load(Ordering::Acquire, guard),
+            guard,
+        }
+    }
+
+    /// Returns `true` if the list is empty.
+    pub fn is_empty(&self, guard: &Guard) -> bool {
+        self.head.load(Ordering::Acquire, guard).is_null()
+    }
+
+    /// Finds the link to the first node whose value satisfies `pred`, or to the end of the list.
+    /// The caller should hold the writer lock.
+    fn find<'g, F: FnMut(&T) -> bool>(
+        &'g self,
+        mut pred: F,
+        guard: &'g Guard,
+    ) -> (&'g Atomic<Node<T>>, Shared<'g, Node<T>>) {
+        let mut link = &self.head;
+        loop {
+            let curr = link.load(Ordering::Acquire, guard);
+            let curr_ref = some_or!(unsafe { curr.as_ref() }, return (link, curr));
+            if pred(&curr_ref.value) {
+                return (link, curr);
+            }
+            link = &curr_ref.next;
+        }
+    }
+
+    /// Appends `value` at the end of the list.
+    pub fn insert(&self, value: T) {
+        let _lock = self.writer.lock().unwrap();
+        let guard = &pin();
+        let (link, _) = self.find(|_| false, guard);
+        link.store(
+            Owned::new(Node {
+                value,
+                next: Atomic::null(),
+            }),
+            Ordering::Release,
+        );
+    }
+
+    /// Removes the first value that satisfies `pred`. Returns `true` if a value is removed.
+    ///
+    /// The value is dropped after the readers that may see it are unpinned.
+    pub fn remove<F: FnMut(&T) -> bool>(&self, pred: F) -> bool {
+        let _lock = self.writer.lock().unwrap();
+        let guard = &pin();
+        let (link, curr) = self.find(pred, guard);
+        let curr_ref = some_or!(unsafe { curr.as_ref() }, return false);
+        link.store(
+            curr_ref.next.load(Ordering::Acquire, guard),
+            Ordering::Release,
+        );
+        unsafe { guard.defer_destroy(curr) };
+        true
+    }
+
+    /// Replaces the first value that satisfies `pred` with the one created by `f` from it. Returns
+    /// `true` if a value is replaced.
+    ///
+    /// The old value is dropped after the readers that may see it are unpinned.
+    pub fn update<F, G>(&self, pred: F, f: G) -> bool
+    where
+        F: FnMut(&T) -> bool,
+        G: FnOnce(&T) -> T,
+    {
+        let _lock = self.writer.lock().unwrap();
+        let guard = &pin();
+        let (link, curr) = self.find(pred, guard);
+        let curr_ref = some_or!(unsafe { curr.as_ref() }, return false);
+        let copy = Owned::new(Node {
+            value: f(&curr_ref.value),
+            next: Atomic::from(curr_ref.next.load(Ordering::Acquire, guard)),
+        });
+        link.store(copy, Ordering::Release);
+        unsafe { guard.defer_destroy(curr) };
+        true
+    }
+}
+
+impl<'g, T> Iterator for Iter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let curr = unsafe { self.curr.as_ref() }?;
+        self.curr = curr.next.load(Ordering::Acquire, self.guard);
+        Some(&curr.value)
+    }
+}
+
+impl<T> Drop for RcuList<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = unprotected();
+            let mut curr = self.head.load(Ordering::Relaxed, guard);
+            while !curr.is_null() {
+                let node = curr.into_owned();
+                curr = node.next.load(Ordering::Relaxed, guard);
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! Read-copy-update primitives on top of `crossbeam_epoch`.
+
+mod cell;
+mod list;
+
+pub use cell::RcuCell;
+pub use list::RcuList;
@@ -0,0 +1,61 @@
+//! Reclamation of the garbage deferred to the default collector of `crossbeam_epoch`.
+//!
+//! The collector destroys a garbage only after the global epoch advances twice, and the epoch only
+//! advances as a side effect of pinning. So the garbage of a thread that stops pinning, e.g. a test
+//! that has finished its operations or an idle server, may stay indefinitely. `flush` drives the
+//! collection until the garbage retired so far is destroyed, and the structures with the lazily
+//! unlinked nodes have `collect` methods that retire them first:
+//!
+//! ```
+//! use crossbeam_epoch as epoch;
+//! use cs492_concur_homework::{reclamation, NonblockingMap, SplitOrderedList};
+//!
+//! let list = SplitOrderedList::new();
+//! {
+//!     let guard = epoch::pin();
+//!     assert_eq!(list.insert(&1, "one".to_string(), &guard), Ok(()));
+//!     assert!(list.delete(&1, &guard).is_ok());
+//!     list.collect(&guard);
+//! }
+//! // The node of 1 is destroyed here.
+//! reclamation::flush();
+//! ```
+
+use crossbeam_epoch as epoch;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::backoff::ExponentialBackoff;
+
+/// Blocks until the garbage that this thread has deferred, and the garbage that the other threads
+/// have flushed to the global queue, is destroyed.
+///
+/// The garbage that the other threads keep in their thread-local bags is not flushed. Since the
+/// epoch can't advance while a thread is pinned in an older epoch, this blocks while another thread
+/// holds a guard.
+///
+/// # Panics
+///
+/// Panics if the current thread is pinned, which would block forever.
+pub fn flush() {
+    assert!(!epoch::is_pinned(), "`flush` is called while pinned");
+
+    // The collector defers the nodes of the global queue that it pops to the bag of this thread,
+    // so the second round destroys the ones that the first round pops.
+    for _ in 0..2 {
+        let done = Arc::new(AtomicBool::new(false));
+        {
+            // The bags are destroyed in order, so the others are destroyed once this one is.
+            let guard = epoch::pin();
+            let done = done.clone();
+            guard.defer(move || done.store(true, Ordering::Release));
+            guard.flush();
+        }
+
+        let backoff = ExponentialBackoff::new();
+        while !done.load(Ordering::Acquire) {
+            epoch::pin().flush();
+            backoff.backoff();
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use alloc::vec::Vec;
+
+/// Trait for a concurrent set.
+///
+/// The conformance tests of `tests/set/mod.rs` are written against this trait, so a new set gets
+/// them by implementing it.
+pub trait ConcurrentSet<T> {
+    /// Returns `true` if the set contains the key.
+    fn contains(&self, key: &T) -> bool;
+
+    /// Inserts a key. If the set already has the key, returns the provided key in `Err`.
+    fn insert(&self, key: T) -> Result<(), T>;
+
+    /// Removes the key from the set and returns it.
+    fn remove(&self, key: &T) -> Result<T, ()>;
+
+    /// Returns the number of the keys. If other threads modify the set at the same time, the result
+    /// may not correspond to any moment.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the set has no keys.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the keys in the set at a moment during the call. The order depends on the set.
+    fn snapshot(&self) -> Vec<T>
+    where
+        T: Clone;
+}
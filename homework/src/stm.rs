@@ -0,0 +1,309 @@
+//! Software transactional memory.
+//!
+//! The implementation follows TL2. There is a global version clock, and each `TVar` has a version
+//! that is the clock value at its last commit, together with a lock bit.
+//!
+//! - A transaction reads the clock when it starts. Reading a `TVar` checks that its version is not
+//!   newer than that and that it is not locked, so all the reads see a consistent snapshot.
+//!
+//! - Writes are buffered in a write log. At commit time, the transaction locks the `TVar`s in the
+//!   write log, advances the clock, checks that the `TVar`s it read are not changed, and then
+//!   installs the new values.
+//!
+//! If any of the checks fails, the transaction is aborted and run again from the start.
+
+use core::any::Any;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{pin, unprotected, Atomic, Guard, Owned};
+use crossbeam_utils::Backoff;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The global version clock. Always even.
+static CLOCK: AtomicUsize = AtomicUsize::new(0);
+
+/// The lowest bit of a version is the lock bit.
+const LOCKED: usize = 1;
+
+/// The error that aborts a transaction because of a conflict with another transaction.
+///
+/// `atomically` runs the transaction again when it returns `Abort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Abort;
+
+/// The result of a transactional operation.
+pub type StmResult<T> = Result<T, Abort>;
+
+struct VarInner<T> {
+    /// The commit version and the lock bit.
+    version: AtomicUsize,
+    value: Atomic<T>,
+}
+
+/// A `TVar` with its type erased, so that logs can store `TVar`s of different types.
+trait AnyVar: Send + Sync {
+    fn version(&self) -> &AtomicUsize;
+
+    /// Replaces the value with `value`, which should be a `Box<T>`.
+    ///
+    /// # Safety
+    ///
+    /// The current transaction should hold the lock of the `TVar`.
+    unsafe fn install(&self, value: Box<dyn Any + Send>, guard: &Guard);
+}
+
+impl<T: Send + Sync + 'static> AnyVar for VarInner<T> {
+    fn version(&self) -> &AtomicUsize {
+        &self.version
+    }
+
+    unsafe fn install(&self, value: Box<dyn Any + Send>, guard: &Guard) {
+        let value = *value.downcast::<T>().unwrap();
+        let old = self.value.swap(Owned::new(value), Ordering::AcqRel, guard);
+        guard.defer_destroy(old);
+    }
+}
+
+/// Transactional variable.
+///
+/// A `TVar` is a shared handle, and its clones refer to the same variable.
+pub struct TVar<T> {
+    inner: Arc<VarInner<T>>,
+}
+
+impl<T> Clone for TVar<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + fmt::Debug + 'static> fmt::Debug for TVar<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TVar").field(&self.load()).finish()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> TVar<T> {
+    /// Creates a new variable.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(VarInner {
+                version: AtomicUsize::new(0),
+                value: Atomic::new(value),
+            }),
+        }
+    }
+
+    /// Reads the value in a transaction of its own.
+    pub fn load(&self) -> T {
+        atomically(|tx| tx.read(self))
+    }
+
+    /// Writes the value in a transaction of its own.
+    pub fn store(&self, value: T) {
+        atomically(|tx| tx.write(self, value.clone()))
+    }
+
+    fn key(&self) -> usize {
+        Arc::as_ptr(&self.inner) as usize
+    }
+}
+
+impl<T> Drop for VarInner<T> {
+    fn drop(&mut self) {
+        unsafe {
+            drop(
+                self.value
+                    .load(Ordering::Relaxed, unprotected())
+                    .into_owned(),
+            )
+        };
+    }
+}
+
+struct WriteEntry {
+    var: Arc<dyn AnyVar>,
+    value: Box<dyn Any + Send>,
+}
+
+/// A running transaction.
+pub struct Transaction {
+    /// The clock value at the start of the transaction.
+    read_version: usize,
+    /// The variables read by the transaction, by their addresses.
+    reads: HashMap<usize, Arc<dyn AnyVar>>,
+    /// The values written by the transaction, by the addresses of the variables.
+    writes: HashMap<usize, WriteEntry>,
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("read_version", &self.read_version)
+            .field("reads", &self.reads.len())
+            .field("writes", &self.writes.len())
+            .finish()
+    }
+}
+
+impl Transaction {
+    fn new() -> Self {
+        Self {
+            read_version: CLOCK.load(Ordering::Acquire),
+            reads: HashMap::new(),
+            writes: HashMap::new(),
+        }
+    }
+
+    /// Reads the value of `var`.
+    ///
+    /// Returns `Abort` if `var` is changed after the transaction started.
+    pub fn read<T: Clone + Send + Sync + 'static>(&mut self, var: &TVar<T>) -> StmResult<T> {
+        let key = var.key();
+        if let Some(entry) = self.writes.get(&key) {
+            return Ok(entry.value.downcast_ref::<T>().unwrap().clone());
+        }
+
+        let inner = &*var.inner;
+        let before = inner.version.load(Ordering::Acquire);
+        if before & LOCKED != 0 || before > self.read_version {
+            return Err(Abort);
+        }
+        let value = {
+            let guard = pin();
+            let value = inner.value.load(Ordering::Acquire, &guard);
+            unsafe { value.deref() }.clone()
+        };
+        if inner.version.load(Ordering::Acquire) != before {
+            return Err(Abort);
+        }
+
+        let _ = self
+            .reads
+            .entry(key)
+            .or_insert_with(|| var.inner.clone() as Arc<dyn AnyVar>);
+        Ok(value)
+    }
+
+    /// Writes `value` to `var`. The value is visible to the other transactions only after the
+    /// transaction commits.
+    pub fn write<T: Clone + Send + Sync + 'static>(
+        &mut self,
+        var: &TVar<T>,
+        value: T,
+    ) -> StmResult<()> {
+        let _ = self.writes.insert(
+            var.key(),
+            WriteEntry {
+                var: var.inner.clone(),
+                value: Box::new(value),
+            },
+        );
+        Ok(())
+    }
+
+    /// Applies `f` to the value of `var`.
+    pub fn modify<T, F>(&mut self, var: &TVar<T>, f: F) -> StmResult<()>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce(T) -> T,
+    {
+        let value = self.read(var)?;
+        self.write(var, f(value))
+    }
+
+    /// Tries to commit the transaction. Returns `false` if it conflicts with another transaction.
+    fn commit(self) -> bool {
+        // A read-only transaction has seen a consistent snapshot.
+        if self.writes.is_empty() {
+            return true;
+        }
+
+        // Lock the written variables. Give up on a locked one instead of waiting for it, so that
+        // two transactions never wait for each other.
+        let mut locked = Vec::with_capacity(self.writes.len());
+        for entry in self.writes.values() {
+            let version = entry.var.version();
+            let current = version.load(Ordering::Relaxed);
+            if current & LOCKED != 0
+                || version
+                    .compare_exchange(
+                        current,
+                        current | LOCKED,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_err()
+            {
+                Self::unlock(&locked);
+                return false;
+            }
+            locked.push((version, current));
+        }
+
+        let write_version = CLOCK.fetch_add(2, Ordering::AcqRel) + 2;
+
+        // Validate the reads. If nobody committed since the transaction started, they are valid.
+        if write_version != self.read_version + 2 {
+            for (key, var) in &self.reads {
+                let current = var.version().load(Ordering::Acquire);
+                let locked_by_me = self.writes.contains_key(key);
+                if (current & LOCKED != 0 && !locked_by_me) || current & !LOCKED > self.read_version
+                {
+                    Self::unlock(&locked);
+                    return false;
+                }
+            }
+        }
+
+        let guard = pin();
+        for (_, entry) in self.writes {
+            unsafe { entry.var.install(entry.value, &guard) };
+            entry.var.version().store(write_version, Ordering::Release);
+        }
+        true
+    }
+
+    /// Restores the versions of the locked variables.
+    fn unlock(locked: &[(&AtomicUsize, usize)]) {
+        for (version, current) in locked {
+            version.store(*current, Ordering::Release);
+        }
+    }
+}
+
+/// Runs `f` as a transaction, and returns its result.
+///
+/// `f` is run again until it commits without conflicts, so it should not have side effects other
+/// than through the transaction.
+///
+/// ```
+/// use cs492_concur_homework::{atomically, TVar};
+///
+/// let from = TVar::new(100);
+/// let to = TVar::new(0);
+/// atomically(|tx| {
+///     let amount = tx.read(&from)?;
+///     tx.write(&from, 0)?;
+///     tx.modify(&to, |v| v + amount)
+/// });
+/// assert_eq!((from.load(), to.load()), (0, 100));
+/// ```
+pub fn atomically<R, F>(mut f: F) -> R
+where
+    F: FnMut(&mut Transaction) -> StmResult<R>,
+{
+    let backoff = Backoff::new();
+    loop {
+        let mut tx = Transaction::new();
+        if let Ok(result) = f(&mut tx) {
+            if tx.commit() {
+                return result;
+            }
+        }
+        backoff.snooze();
+    }
+}
@@ -0,0 +1,209 @@
+//! Reusable barriers.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crossbeam_utils::{Backoff, CachePadded};
+use std::sync::{Condvar, Mutex};
+
+/// The result of waiting on a barrier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if the current thread is the leader of the round. Exactly one thread is the
+    /// leader in each round.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+/// Global sense of a barrier that is flipped at the end of each round.
+///
+/// Waiting threads spin for a while, and then block on a condition variable. This makes the
+/// barrier fast for tight loops where all the threads arrive at almost the same time, while not
+/// wasting CPU when some thread is late.
+#[derive(Debug, Default)]
+struct Sense {
+    sense: AtomicBool,
+    lock: Mutex<()>,
+    cvar: Condvar,
+}
+
+impl Sense {
+    /// Returns the sense of the current round.
+    ///
+    /// The sense can't be flipped before the current thread arrives, so it is the same for all the
+    /// threads of the round.
+    fn current(&self) -> bool {
+        self.sense.load(Ordering::Relaxed)
+    }
+
+    /// Ends the round whose sense is `sense`, waking up all the waiting threads.
+    fn flip(&self, sense: bool) {
+        self.sense.store(!sense, Ordering::Release);
+
+        // Taking the lock guarantees that a waiter is either not yet checking the sense or is
+        // already waiting on the condvar.
+        drop(self.lock.lock().unwrap());
+        self.cvar.notify_all();
+    }
+
+    /// Blocks until the round whose sense is `sense` ends.
+    fn wait(&self, sense: bool) {
+        let backoff = Backoff::new();
+        while !backoff.is_completed() {
+            if self.sense.load(Ordering::Acquire) != sense {
+                return;
+            }
+            backoff.snooze();
+        }
+
+        let mut lock = self.lock.lock().unwrap();
+        while self.sense.load(Ordering::Acquire) == sense {
+            lock = self.cvar.wait(lock).unwrap();
+        }
+    }
+}
+
+/// Reusable barrier with the same interface as `std::sync::Barrier`.
+///
+/// The barrier has a global sense that is flipped by the last thread arriving in each round. A
+/// thread remembers the sense at its arrival and waits until it is flipped, so the barrier can be
+/// reused for the next round right away without reallocation.
+#[derive(Debug)]
+pub struct SenseBarrier {
+    /// The number of threads that have not arrived in the current round.
+    count: AtomicUsize,
+    num_threads: usize,
+    sense: Sense,
+}
+
+impl SenseBarrier {
+    /// Creates a new barrier for `n` threads. A barrier for 0 threads behaves as the one for 1.
+    pub fn new(n: usize) -> Self {
+        let n = n.max(1);
+        Self {
+            count: AtomicUsize::new(n),
+            num_threads: n,
+            sense: Sense::default(),
+        }
+    }
+
+    /// Blocks until all the `n` threads have called `wait` in the current round.
+    ///
+    /// The last thread to arrive is the leader.
+    pub fn wait(&self) -> BarrierWaitResult {
+        let sense = self.sense.current();
+
+        if self.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.count.store(self.num_threads, Ordering::Relaxed);
+            self.sense.flip(sense);
+            return BarrierWaitResult(true);
+        }
+
+        self.sense.wait(sense);
+        BarrierWaitResult(false)
+    }
+}
+
+/// A node of the combining tree.
+#[derive(Debug)]
+struct TreeNode {
+    /// The number of children that have not arrived in the current round.
+    count: CachePadded<AtomicUsize>,
+    /// The number of children.
+    num_children: usize,
+    parent: Option<usize>,
+}
+
+/// Combining-tree barrier.
+///
+/// Instead of a single counter shared by all the threads, the threads are statically divided into
+/// groups of `fan_in` threads, each of which shares the counter of a leaf node. The last thread to
+/// arrive at a node goes up and arrives at its parent, and the last thread to arrive at the root
+/// ends the round. Since each counter is touched by at most `fan_in` threads, this reduces the
+/// contention for high thread counts.
+///
+/// Each thread should call `wait` with a distinct id in `0..n`.
+#[derive(Debug)]
+pub struct TreeBarrier {
+    /// Leaves come first, and the root comes last.
+    nodes: Vec<TreeNode>,
+    num_threads: usize,
+    fan_in: usize,
+    sense: Sense,
+}
+
+impl TreeBarrier {
+    /// Creates a new barrier for `n` threads where each node has at most `fan_in` children.
+    ///
+    /// Panics if `fan_in < 2`.
+    pub fn new(n: usize, fan_in: usize) -> Self {
+        assert!(fan_in >= 2);
+        let n = n.max(1);
+
+        let mut nodes = Vec::new();
+        // The start index and the length of the previous level.
+        let mut prev_level: Option<(usize, usize)> = None;
+        let mut num_children = n;
+        loop {
+            let start = nodes.len();
+            let len = (num_children - 1) / fan_in + 1;
+            for i in 0..len {
+                let children = (num_children - i * fan_in).min(fan_in);
+                nodes.push(TreeNode {
+                    count: CachePadded::new(AtomicUsize::new(children)),
+                    num_children: children,
+                    parent: None,
+                });
+            }
+
+            if let Some((prev_start, prev_len)) = prev_level {
+                for child in 0..prev_len {
+                    nodes[prev_start + child].parent = Some(start + child / fan_in);
+                }
+            }
+
+            if len == 1 {
+                break;
+            }
+            prev_level = Some((start, len));
+            num_children = len;
+        }
+
+        Self {
+            nodes,
+            num_threads: n,
+            fan_in,
+            sense: Sense::default(),
+        }
+    }
+
+    /// Blocks until all the `n` threads have called `wait` in the current round.
+    ///
+    /// The thread that arrives at the root last is the leader. Panics if `id >= n`.
+    pub fn wait(&self, id: usize) -> BarrierWaitResult {
+        assert!(id < self.num_threads);
+        let sense = self.sense.current();
+
+        let mut index = id / self.fan_in;
+        loop {
+            let node = &self.nodes[index];
+            if node.count.fetch_sub(1, Ordering::AcqRel) != 1 {
+                break;
+            }
+
+            // No child arrives at this node again until the round ends.
+            node.count.store(node.num_children, Ordering::Relaxed);
+            match node.parent {
+                Some(parent) => index = parent,
+                None => {
+                    self.sense.flip(sense);
+                    return BarrierWaitResult(true);
+                }
+            }
+        }
+
+        self.sense.wait(sense);
+        BarrierWaitResult(false)
+    }
+}
@@ -0,0 +1,155 @@
+//! Exchanger.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_epoch::{pin, Atomic, Guard, Owned, Shared};
+use crossbeam_utils::Backoff;
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+struct Node<T> {
+    thread: Thread,
+    /// The value offered by the waiting thread. Taken by the matching thread.
+    offer: UnsafeCell<Option<T>>,
+    /// The value given by the matching thread before setting `matched`.
+    response: UnsafeCell<Option<T>>,
+    matched: AtomicBool,
+}
+
+/// Rendezvous point where two threads swap values.
+///
+/// The first thread publishes its value in the slot and waits. The second thread removes it from
+/// the slot with a CAS, leaves its own value in return, and wakes the first one up. A waiting
+/// thread that times out takes its value back by removing it from the slot, unless a matching
+/// thread has removed it first.
+pub struct Exchanger<T> {
+    slot: Atomic<Node<T>>,
+}
+
+unsafe impl<T: Send> Send for Exchanger<T> {}
+unsafe impl<T: Send> Sync for Exchanger<T> {}
+
+impl<T> fmt::Debug for Exchanger<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exchanger").finish()
+    }
+}
+
+impl<T> Default for Exchanger<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Exchanger<T> {
+    /// Creates a new exchanger.
+    pub fn new() -> Self {
+        Self {
+            slot: Atomic::null(),
+        }
+    }
+
+    /// Blocks until another thread arrives, and swaps `value` with its value.
+    pub fn exchange(&self, value: T) -> T {
+        match self.exchange_deadline(value, None) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Same as `exchange`, but gives up after `timeout`. Returns `value` back on timeout.
+    pub fn exchange_timeout(&self, value: T, timeout: Duration) -> Result<T, T> {
+        self.exchange_deadline(value, Some(Instant::now() + timeout))
+    }
+
+    fn exchange_deadline(&self, value: T, deadline: Option<Instant>) -> Result<T, T> {
+        let guard = &pin();
+        let mut node = Owned::new(Node {
+            thread: thread::current(),
+            offer: UnsafeCell::new(Some(value)),
+            response: UnsafeCell::new(None),
+            matched: AtomicBool::new(false),
+        });
+
+        loop {
+            let slot = self.slot.load(Ordering::Acquire, guard);
+            if let Some(waiter) = unsafe { slot.as_ref() } {
+                if self
+                    .slot
+                    .compare_and_set(slot, Shared::null(), Ordering::AcqRel, guard)
+                    .is_ok()
+                {
+                    // The waiter is ours now.
+                    unsafe {
+                        let theirs = (*waiter.offer.get()).take().unwrap();
+                        *waiter.response.get() = node.offer.get_mut().take();
+                        let thread = waiter.thread.clone();
+                        waiter.matched.store(true, Ordering::Release);
+                        thread.unpark();
+                        return Ok(theirs);
+                    }
+                }
+                continue;
+            }
+
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return Err(node.offer.get_mut().take().unwrap());
+                }
+            }
+
+            match self
+                .slot
+                .compare_and_set(Shared::null(), node, Ordering::AcqRel, guard)
+            {
+                Ok(node) => return self.wait(node, deadline, guard),
+                Err(e) => node = e.new,
+            }
+        }
+    }
+
+    /// Waits for a thread to match `node` in the slot.
+    fn wait(
+        &self,
+        node: Shared<'_, Node<T>>,
+        deadline: Option<Instant>,
+        guard: &Guard,
+    ) -> Result<T, T> {
+        let node_ref = unsafe { node.deref() };
+        let backoff = Backoff::new();
+        loop {
+            if node_ref.matched.load(Ordering::Acquire) {
+                let theirs = unsafe { (*node_ref.response.get()).take().unwrap() };
+                unsafe { guard.defer_destroy(node) };
+                return Ok(theirs);
+            }
+
+            // Exchanges are usually quick, so spin for a while before parking.
+            if !backoff.is_completed() {
+                backoff.snooze();
+                continue;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now < deadline {
+                        thread::park_timeout(deadline - now);
+                    } else if self
+                        .slot
+                        .compare_and_set(node, Shared::null(), Ordering::AcqRel, guard)
+                        .is_ok()
+                    {
+                        let mine = unsafe { (*node_ref.offer.get()).take().unwrap() };
+                        unsafe { guard.defer_destroy(node) };
+                        return Err(mine);
+                    } else {
+                        // A thread has matched the node just before the timeout.
+                        thread::yield_now();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Count-down latch.
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Latch that opens when its counter reaches zero.
+///
+/// Unlike `std::sync::Barrier`, the threads that count down and the threads that wait may be
+/// different, and a latch is not reusable: once opened, it stays open.
+#[derive(Debug)]
+pub struct Latch {
+    count: Mutex<usize>,
+    cvar: Condvar,
+}
+
+impl Latch {
+    /// Creates a new latch that opens after `count` calls to `count_down`.
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: Mutex::new(count),
+            cvar: Condvar::new(),
+        }
+    }
+
+    /// Decrements the counter, opening the latch if it reaches zero. Does nothing if the latch is
+    /// already open.
+    pub fn count_down(&self) {
+        let mut count = self.count.lock().unwrap();
+        if *count == 0 {
+            return;
+        }
+        *count -= 1;
+        if *count == 0 {
+            self.cvar.notify_all();
+        }
+    }
+
+    /// Decrements the counter and blocks until the latch is open.
+    pub fn arrive_and_wait(&self) {
+        self.count_down();
+        self.wait();
+    }
+
+    /// Returns the current value of the counter.
+    pub fn count(&self) -> usize {
+        *self.count.lock().unwrap()
+    }
+
+    /// Blocks until the latch is open.
+    pub fn wait(&self) {
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            count = self.cvar.wait(count).unwrap();
+        }
+    }
+
+    /// Blocks until the latch is open or `timeout` elapses. Returns `true` if the latch is open.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            count = self.cvar.wait_timeout(count, deadline - now).unwrap().0;
+        }
+        true
+    }
+}
@@ -0,0 +1,17 @@
+//! Synchronization primitives.
+
+mod barrier;
+mod exchanger;
+mod latch;
+mod once_cell;
+mod phaser;
+mod semaphore;
+mod wait_group;
+
+pub use barrier::{BarrierWaitResult, SenseBarrier, TreeBarrier};
+pub use exchanger::Exchanger;
+pub use latch::Latch;
+pub use once_cell::{Lazy, OnceCell};
+pub use phaser::Phaser;
+pub use semaphore::{OwnedSemaphorePermit, Semaphore, SemaphorePermit};
+pub use wait_group::WaitGroup;
@@ -0,0 +1,268 @@
+//! Cells that are initialized only once.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::ops::Deref;
+use core::ptr;
+
+use crate::mock::sync::atomic::{AtomicUsize, Ordering};
+use crate::mock::sync::Arc;
+use crate::mock::Event;
+
+/// The lowest two bits of `OnceCell::state` store the state. The other bits store the pointer to
+/// the head of the queue of threads waiting for the initialization.
+const INCOMPLETE: usize = 0x0;
+const RUNNING: usize = 0x1;
+const COMPLETE: usize = 0x2;
+const STATE_MASK: usize = 0x3;
+
+/// A thread waiting for the initialization. Lives on the stack of the waiting thread.
+struct Waiter {
+    /// Shared with the thread finishing the initialization, which may still be setting it when the
+    /// waiter returns.
+    signaled: Arc<Event>,
+    next: Cell<*const Waiter>,
+}
+
+/// Cell that can be written to only once.
+///
+/// Reading an initialized cell is a single `Acquire` load. If multiple threads try to initialize
+/// the cell at the same time, only one of them runs its initializer, and the others are blocked
+/// until the initialization is finished.
+///
+/// If the initializer panics, the cell goes back to the uninitialized state and one of the waiting
+/// threads runs its own initializer. Hence the cell is never poisoned.
+pub struct OnceCell<T> {
+    state: AtomicUsize,
+    value: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceCell<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+/// Wakes up the waiting threads when the initialization is finished or aborted by a panic.
+struct Finish<'a> {
+    state: &'a AtomicUsize,
+    new_state: usize,
+}
+
+impl Drop for Finish<'_> {
+    fn drop(&mut self) {
+        let queue = self.state.swap(self.new_state, Ordering::AcqRel);
+        assert_eq!(queue & STATE_MASK, RUNNING);
+
+        let mut waiter = (queue & !STATE_MASK) as *const Waiter;
+        while !waiter.is_null() {
+            unsafe {
+                // `waiter` may be deallocated as soon as `signaled` is set, so read it first.
+                let next = (*waiter).next.get();
+                let signaled = (*waiter).signaled.clone();
+                signaled.set();
+                waiter = next;
+            }
+        }
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(v) => f.debug_tuple("OnceCell").field(v).finish(),
+            None => f.write_str("OnceCell(<uninit>)"),
+        }
+    }
+}
+
+impl<T> OnceCell<T> {
+    /// Creates a new uninitialized cell.
+    #[cfg(not(feature = "check-loom"))]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(INCOMPLETE),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Creates a new uninitialized cell.
+    ///
+    /// NOTE: loom's atomics can't be created in constants, so neither can the cells under loom.
+    #[cfg(feature = "check-loom")]
+    pub fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(INCOMPLETE),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Returns the value if the cell is initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == COMPLETE {
+            unsafe { (*self.value.get()).as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the mutable reference to the value if the cell is initialized.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        unsafe { (*self.value.get()).as_mut() }
+    }
+
+    /// Initializes the cell with `value`. Returns `Err(value)` if the cell is already initialized.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        let _ = self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Returns the value, initializing the cell with `f` if it is not initialized yet.
+    ///
+    /// If another thread is running its initializer, blocks until it is finished.
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        self.initialize(f);
+        self.get().unwrap()
+    }
+
+    /// Consumes the cell and returns the value if it is initialized.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    /// Slow path of `get_or_init`.
+    #[cold]
+    fn initialize<F: FnOnce() -> T>(&self, f: F) {
+        let mut f = Some(f);
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            match state & STATE_MASK {
+                COMPLETE => return,
+                INCOMPLETE => {
+                    if let Err(s) = self.state.compare_exchange(
+                        state,
+                        (state & !STATE_MASK) | RUNNING,
+                        Ordering::Acquire,
+                        Ordering::Acquire,
+                    ) {
+                        state = s;
+                        continue;
+                    }
+
+                    // If `f` panics, `finish` resets the state so that a waiter can retry.
+                    let mut finish = Finish {
+                        state: &self.state,
+                        new_state: INCOMPLETE,
+                    };
+                    let value = (f.take().unwrap())();
+                    unsafe { *self.value.get() = Some(value) };
+                    finish.new_state = COMPLETE;
+                    return;
+                }
+                _ => {
+                    self.wait(state);
+                    state = self.state.load(Ordering::Acquire);
+                }
+            }
+        }
+    }
+
+    /// Blocks the current thread until the running initialization is finished.
+    fn wait(&self, mut state: usize) {
+        let waiter = Waiter {
+            signaled: Arc::new(Event::new()),
+            next: Cell::new(ptr::null()),
+        };
+        let me = &waiter as *const Waiter as usize;
+
+        loop {
+            if state & STATE_MASK != RUNNING {
+                return;
+            }
+
+            waiter.next.set((state & !STATE_MASK) as *const Waiter);
+            match self.state.compare_exchange(
+                state,
+                me | RUNNING,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+
+        waiter.signaled.wait();
+    }
+}
+
+/// Value that is initialized on the first access.
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceCell<T>,
+    init: Cell<Option<F>>,
+}
+
+// `init` is only accessed by the thread running the initialization.
+unsafe impl<T, F: Send> Sync for Lazy<T, F> where OnceCell<T>: Sync {}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lazy").field("cell", &self.cell).finish()
+    }
+}
+
+impl<T, F> Lazy<T, F> {
+    /// Creates a new lazy value with the given initializer.
+    #[cfg(not(feature = "check-loom"))]
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: OnceCell::new(),
+            init: Cell::new(Some(init)),
+        }
+    }
+
+    /// Creates a new lazy value with the given initializer.
+    #[cfg(feature = "check-loom")]
+    pub fn new(init: F) -> Self {
+        Self {
+            cell: OnceCell::new(),
+            init: Cell::new(Some(init)),
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /// Forces the evaluation and returns the value.
+    ///
+    /// Panics if the initializer panicked on a previous access.
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| match this.init.take() {
+            Some(f) => f(),
+            None => panic!("Lazy instance has previously been poisoned"),
+        })
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        Self::force(self)
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Self {
+        Self::new(T::default)
+    }
+}
@@ -0,0 +1,163 @@
+//! Phaser.
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct State {
+    phase: usize,
+    /// The number of registered parties.
+    parties: usize,
+    /// The number of parties that have arrived in the current phase.
+    arrived: usize,
+}
+
+impl State {
+    fn arrive(&mut self, cvar: &Condvar) -> usize {
+        assert!(
+            self.arrived < self.parties,
+            "more arrivals than the registered parties"
+        );
+        self.arrived += 1;
+        let phase = self.phase;
+        self.try_advance(cvar);
+        phase
+    }
+
+    /// Advances to the next phase if all the registered parties have arrived.
+    fn try_advance(&mut self, cvar: &Condvar) {
+        if self.arrived == self.parties {
+            self.phase = self.phase.wrapping_add(1);
+            self.arrived = 0;
+            cvar.notify_all();
+        }
+    }
+}
+
+/// Reusable barrier whose number of parties may change from phase to phase.
+///
+/// A party registers with `register`, arrives at the end of each phase, and leaves with
+/// `arrive_and_deregister`. A phase ends when all the registered parties have arrived, and then
+/// the phase number is incremented. Unlike `SenseBarrier`, a party may arrive without waiting for
+/// the others, and a thread that is not a party may wait for a phase to end.
+///
+/// When the last party deregisters, the phase ends and the phaser is empty, but new parties may
+/// register again.
+#[derive(Debug)]
+pub struct Phaser {
+    state: Mutex<State>,
+    cvar: Condvar,
+}
+
+impl Default for Phaser {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Phaser {
+    /// Creates a new phaser with `parties` registered parties, at the phase 0.
+    pub fn new(parties: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                phase: 0,
+                parties,
+                arrived: 0,
+            }),
+            cvar: Condvar::new(),
+        }
+    }
+
+    /// Returns the current phase number.
+    pub fn phase(&self) -> usize {
+        self.state.lock().unwrap().phase
+    }
+
+    /// Returns the number of registered parties.
+    pub fn registered_parties(&self) -> usize {
+        self.state.lock().unwrap().parties
+    }
+
+    /// Returns the number of parties that have arrived in the current phase.
+    pub fn arrived_parties(&self) -> usize {
+        self.state.lock().unwrap().arrived
+    }
+
+    /// Registers a new party in the current phase. Returns the phase number.
+    pub fn register(&self) -> usize {
+        self.bulk_register(1)
+    }
+
+    /// Registers `parties` new parties in the current phase. Returns the phase number.
+    pub fn bulk_register(&self, parties: usize) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.parties += parties;
+        state.phase
+    }
+
+    /// Arrives at the current phase without waiting for the others. Returns the phase number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no party is registered.
+    pub fn arrive(&self) -> usize {
+        self.state.lock().unwrap().arrive(&self.cvar)
+    }
+
+    /// Arrives at the current phase and deregisters a party. Returns the phase number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no party is registered.
+    pub fn arrive_and_deregister(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        assert!(
+            state.arrived < state.parties,
+            "more arrivals than the registered parties"
+        );
+        state.parties -= 1;
+        let phase = state.phase;
+        state.try_advance(&self.cvar);
+        phase
+    }
+
+    /// Arrives at the current phase and blocks until the phase ends. Returns the new phase
+    /// number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no party is registered.
+    pub fn arrive_and_await_advance(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let phase = state.arrive(&self.cvar);
+        while state.phase == phase {
+            state = self.cvar.wait(state).unwrap();
+        }
+        state.phase
+    }
+
+    /// Blocks until the phase `phase` ends. Returns the current phase number, immediately if it
+    /// is not `phase`.
+    pub fn await_advance(&self, phase: usize) -> usize {
+        let mut state = self.state.lock().unwrap();
+        while state.phase == phase {
+            state = self.cvar.wait(state).unwrap();
+        }
+        state.phase
+    }
+
+    /// Blocks until the phase `phase` ends or `timeout` elapses. Returns the current phase number,
+    /// or `None` on timeout.
+    pub fn await_advance_timeout(&self, phase: usize, timeout: Duration) -> Option<usize> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        while state.phase == phase {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            state = self.cvar.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        Some(state.phase)
+    }
+}
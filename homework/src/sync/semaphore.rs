@@ -0,0 +1,146 @@
+//! Counting semaphore.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Counting semaphore with RAII permits.
+///
+/// Acquiring a permit is a CAS on the number of available permits. Only when no permit is
+/// available does the thread block on a condition variable.
+#[derive(Debug)]
+pub struct Semaphore {
+    permits: AtomicUsize,
+    lock: Mutex<()>,
+    cvar: Condvar,
+}
+
+/// A permit borrowed from a `Semaphore`. The permit is released when dropped.
+#[derive(Debug)]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+/// A permit from a `Semaphore` shared by `Arc`. The permit is released when dropped.
+///
+/// Unlike `SemaphorePermit`, this can be moved into a `'static` closure.
+#[derive(Debug)]
+pub struct OwnedSemaphorePermit {
+    semaphore: Arc<Semaphore>,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given number of permits.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: AtomicUsize::new(permits),
+            lock: Mutex::new(()),
+            cvar: Condvar::new(),
+        }
+    }
+
+    /// Returns the number of available permits.
+    pub fn available_permits(&self) -> usize {
+        self.permits.load(Ordering::Relaxed)
+    }
+
+    /// Acquires a permit if one is available without blocking.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        if self.try_take() {
+            Some(SemaphorePermit { semaphore: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires a permit, blocking until one is available.
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        let acquired = self.take(None);
+        debug_assert!(acquired);
+        SemaphorePermit { semaphore: self }
+    }
+
+    /// Acquires a permit, blocking until one is available or `timeout` elapses.
+    pub fn acquire_timeout(&self, timeout: Duration) -> Option<SemaphorePermit<'_>> {
+        if self.take(Some(Instant::now() + timeout)) {
+            Some(SemaphorePermit { semaphore: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires a permit that keeps the semaphore alive, blocking until one is available.
+    pub fn acquire_owned(self: Arc<Self>) -> OwnedSemaphorePermit {
+        let acquired = self.take(None);
+        debug_assert!(acquired);
+        OwnedSemaphorePermit { semaphore: self }
+    }
+
+    /// Adds `n` permits to the semaphore.
+    pub fn add_permits(&self, n: usize) {
+        self.permits.fetch_add(n, Ordering::Release);
+
+        // Taking the lock guarantees that a waiter is either not yet checking the permits or is
+        // already waiting on the condvar.
+        drop(self.lock.lock().unwrap());
+        if n == 1 {
+            self.cvar.notify_one();
+        } else {
+            self.cvar.notify_all();
+        }
+    }
+
+    /// Takes a permit if one is available.
+    fn try_take(&self) -> bool {
+        let mut permits = self.permits.load(Ordering::Relaxed);
+        while permits > 0 {
+            match self.permits.compare_exchange_weak(
+                permits,
+                permits - 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(p) => permits = p,
+            }
+        }
+        false
+    }
+
+    /// Takes a permit, blocking until one is available or `deadline` passes. Returns `true` if a
+    /// permit is taken.
+    fn take(&self, deadline: Option<Instant>) -> bool {
+        if self.try_take() {
+            return true;
+        }
+
+        let mut lock = self.lock.lock().unwrap();
+        loop {
+            if self.try_take() {
+                return true;
+            }
+            lock = match deadline {
+                None => self.cvar.wait(lock).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    self.cvar.wait_timeout(lock, deadline - now).unwrap().0
+                }
+            };
+        }
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.add_permits(1);
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        self.semaphore.add_permits(1);
+    }
+}
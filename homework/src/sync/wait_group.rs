@@ -0,0 +1,88 @@
+//! Wait group.
+
+use core::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Waits for an unknown number of participants to finish.
+///
+/// Each clone of a `WaitGroup` is a participant. A participant finishes when it is dropped, and
+/// `wait` blocks until all the other participants are finished.
+///
+/// ```
+/// use cs492_concur_homework::WaitGroup;
+/// use std::thread;
+///
+/// let wg = WaitGroup::new();
+/// for _ in 0..4 {
+///     let wg = wg.clone();
+///     thread::spawn(move || {
+///         // do some work
+///         drop(wg);
+///     });
+/// }
+/// wg.wait();
+/// ```
+pub struct WaitGroup {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// The number of participants.
+    count: Mutex<usize>,
+    cvar: Condvar,
+}
+
+impl Default for WaitGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaitGroup {
+    /// Creates a new wait group with a single participant.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                count: Mutex::new(1),
+                cvar: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Finishes the current participant and blocks until all the other participants are finished.
+    pub fn wait(self) {
+        let inner = self.inner.clone();
+        drop(self);
+
+        let mut count = inner.count.lock().unwrap();
+        while *count > 0 {
+            count = inner.cvar.wait(count).unwrap();
+        }
+    }
+}
+
+impl Clone for WaitGroup {
+    fn clone(&self) -> Self {
+        *self.inner.count.lock().unwrap() += 1;
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl Drop for WaitGroup {
+    fn drop(&mut self) {
+        let mut count = self.inner.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.inner.cvar.notify_all();
+        }
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = *self.inner.count.lock().unwrap();
+        f.debug_struct("WaitGroup").field("count", &count).finish()
+    }
+}
@@ -0,0 +1,178 @@
+//! Counting global allocator for finding leaks in the tests.
+//!
+//! A test binary installs `CountingAllocator` as its global allocator, and wraps each test in
+//! `assert_no_leaks`:
+//!
+//! ```ignore
+//! use cs492_concur_homework::testing::allocation::{assert_no_leaks, CountingAllocator};
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! #[test]
+//! fn no_leak() {
+//!     assert_no_leaks(|| drop(Box::new(42)));
+//! }
+//! ```
+//!
+//! The counters are global, so the allocations of the other threads of the binary are counted as
+//! well. `assert_no_leaks` runs one at a time, but a test binary that uses it should not have tests
+//! that don't.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::lazy_static;
+use std::alloc::System;
+use std::sync::Mutex;
+
+use crate::reclamation;
+
+/// The number of the live allocations.
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+/// The size of the live allocations.
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+/// The number of all the allocations so far.
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+/// The size from which the allocations fail, or 0 if none fails. See `fail_allocations`.
+static FAILING_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// Serializes the runs of `assert_no_leaks`, since the counters are global.
+    static ref CHECKING: Mutex<()> = Mutex::new(());
+}
+
+/// Global allocator that forwards to `System` and counts the allocations.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if fails(layout.size()) {
+            return ptr::null_mut();
+        }
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if fails(layout.size()) {
+            return ptr::null_mut();
+        }
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        let _ = ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+        let _ = BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            let _ = BYTES.fetch_add(new_size, Ordering::Relaxed);
+            let _ = BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new
+    }
+}
+
+fn fails(size: usize) -> bool {
+    let failing = FAILING_SIZE.load(Ordering::Relaxed);
+    failing != 0 && size >= failing
+}
+
+fn record_alloc(size: usize) {
+    let _ = ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let _ = BYTES.fetch_add(size, Ordering::Relaxed);
+    let _ = TOTAL.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counters of `CountingAllocator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocStats {
+    /// The number of the live allocations.
+    pub allocations: usize,
+    /// The size of the live allocations in bytes.
+    pub bytes: usize,
+    /// The number of all the allocations so far, including the freed ones.
+    pub total: usize,
+}
+
+/// Returns the current counters of `CountingAllocator`.
+pub fn stats() -> AllocStats {
+    AllocStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+        total: TOTAL.load(Ordering::Relaxed),
+    }
+}
+
+/// Runs `f`, and asserts that everything it allocates is freed by the time it returns, including
+/// the garbage that it retires to `crossbeam_epoch`'s default collector.
+///
+/// # Panics
+///
+/// Panics if some allocations are not freed, or if `CountingAllocator` is not the global allocator.
+pub fn assert_no_leaks<F: FnOnce()>(f: F) {
+    let _checking = CHECKING.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Registers this thread to the collector, and empties it before taking the baseline.
+    reclamation::flush();
+    let before = stats();
+    drop(Box::new(0u8));
+    assert!(
+        stats().total > before.total,
+        "`CountingAllocator` is not the global allocator"
+    );
+
+    f();
+
+    reclamation::flush();
+    let after = stats();
+    if after.allocations > before.allocations {
+        panic!(
+            "leaked {} allocations ({} bytes)",
+            after.allocations - before.allocations,
+            after.bytes.wrapping_sub(before.bytes) as isize
+        );
+    }
+}
+
+/// Runs `f` without checking, but one at a time with `assert_no_leaks`.
+///
+/// It is for warming up the global states that allocate once and are kept, e.g. the pool of the
+/// IDs released by the exited threads (see `current_thread_id`), so that `assert_no_leaks` doesn't
+/// count them as leaks.
+pub fn warm_up<F: FnOnce()>(f: F) {
+    let _checking = CHECKING.lock().unwrap_or_else(|e| e.into_inner());
+    f();
+}
+
+/// Runs `f` while the allocations of at least `size` bytes by `CountingAllocator` fail, e.g. to
+/// test the handling of the allocation failures. The smaller allocations, e.g. of the test
+/// harness, succeed.
+///
+/// The allocations of all the threads fail, so it should run inside `assert_no_leaks`.
+pub fn fail_allocations<R, F: FnOnce() -> R>(size: usize, f: F) -> R {
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            FAILING_SIZE.store(0, Ordering::Relaxed);
+        }
+    }
+
+    assert_ne!(size, 0);
+    FAILING_SIZE.store(size, Ordering::Relaxed);
+    let _reset = Reset;
+    f()
+}
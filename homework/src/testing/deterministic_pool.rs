@@ -0,0 +1,135 @@
+//! Thread pool that runs the jobs one at a time in a seeded order and in virtual time.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Drop-in replacement of `ThreadPool` for tests, where the order of the jobs and the time are
+/// under the control of the test.
+///
+/// The jobs are queued by `execute` and `execute_after`, and run on the thread that calls `join` or
+/// `advance`. Of the jobs that are due at the same time, the next one is chosen by a generator
+/// seeded with the seed of the pool, so a run is reproduced by its seed. The clock is virtual: it
+/// starts at zero, and jumps to the due time of the next delayed job when no other job is ready,
+/// so the tests of delays and timeouts don't sleep.
+///
+/// The pool is a handle to shared state, so the jobs can clone it to queue more jobs or to read the
+/// clock. Jobs that haven't run when the last handle is dropped are dropped without running.
+#[derive(Clone)]
+pub struct DeterministicPool {
+    inner: Arc<Mutex<Scheduler>>,
+}
+
+struct Scheduler {
+    rng: StdRng,
+    now: Duration,
+    /// The pending jobs, ordered by their due time and then by the order of queueing.
+    jobs: BTreeMap<(Duration, u64), Job>,
+    /// The number of the jobs queued so far.
+    queued: u64,
+}
+
+impl fmt::Debug for DeterministicPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheduler = self.inner.lock().unwrap();
+        f.debug_struct("DeterministicPool")
+            .field("now", &scheduler.now)
+            .field("pending", &scheduler.jobs.len())
+            .finish()
+    }
+}
+
+impl Scheduler {
+    /// Removes the next job that is due by `until`, and moves the clock to its due time.
+    fn next(&mut self, until: Option<Duration>) -> Option<Job> {
+        let &(due, _) = self.jobs.keys().next()?;
+        match until {
+            Some(until) if due > until => return None,
+            _ => {}
+        }
+        if due > self.now {
+            self.now = due;
+        }
+
+        // Chooses one of the jobs that are due now.
+        let ready = self.jobs.range(..(self.now, u64::MAX)).count();
+        let i = self.rng.gen_range(0, ready);
+        let key = *self.jobs.keys().nth(i).unwrap();
+        self.jobs.remove(&key)
+    }
+}
+
+impl DeterministicPool {
+    /// Creates a new pool whose order of the jobs is determined by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Scheduler {
+                rng: StdRng::seed_from_u64(seed),
+                now: Duration::default(),
+                jobs: BTreeMap::new(),
+                queued: 0,
+            })),
+        }
+    }
+
+    /// Queues a new job that is ready now.
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.execute_after(Duration::default(), f);
+    }
+
+    /// Queues a new job that is ready after `delay` from now.
+    pub fn execute_after<F>(&self, delay: Duration, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut scheduler = self.inner.lock().unwrap();
+        let key = (scheduler.now + delay, scheduler.queued);
+        scheduler.queued += 1;
+        let _ = scheduler.jobs.insert(key, Box::new(f));
+    }
+
+    /// Returns the virtual time since the pool is created.
+    pub fn now(&self) -> Duration {
+        self.inner.lock().unwrap().now
+    }
+
+    /// Returns the number of the jobs that haven't run yet.
+    pub fn pending(&self) -> usize {
+        self.inner.lock().unwrap().jobs.len()
+    }
+
+    /// Runs the jobs until none is left, including the ones queued by the jobs. The clock moves to
+    /// the due time of the last delayed job.
+    ///
+    /// A panic of a job is propagated to the caller, and the remaining jobs stay queued.
+    pub fn join(&self) {
+        self.run(None);
+    }
+
+    /// Runs the jobs that are due within `duration` from now, and moves the clock forward by
+    /// `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let until = self.now() + duration;
+        self.run(Some(until));
+        self.inner.lock().unwrap().now = until;
+    }
+
+    fn run(&self, until: Option<Duration>) {
+        loop {
+            // Releases the lock before running the job so that it can queue more jobs.
+            let job = self.inner.lock().unwrap().next(until);
+            match job {
+                Some(job) => job(),
+                None => return,
+            }
+        }
+    }
+}
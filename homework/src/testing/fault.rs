@@ -0,0 +1,101 @@
+//! Randomized scheduler that delays the threads at the yield points of the data structures.
+//!
+//! With the `fault-injection` feature, `Cache`, `ThreadPool`, and `SplitOrderedList` call
+//! `yield_point` at the steps where the interleaving of the threads matters, e.g. between finding
+//! the position of a node and linking it. While `explore` runs a test, each yield point randomly
+//! does nothing, yields the thread, or sleeps for a few microseconds. This stretches the windows
+//! between the steps, so the interleavings that the OS scheduler rarely produces come up within a
+//! few hundred runs.
+//!
+//! Run the tests with `cargo test --features fault-injection --test fault`.
+//!
+//! The delays of each run are drawn from a seed, which is printed when the test fails. The threads
+//! are still scheduled by the OS, so rerunning the seed makes the failure likely, but not certain,
+//! to happen again.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+lazy_static! {
+    /// Serializes the runs of `explore`, since the scheduler is global.
+    static ref EXPLORING: Mutex<()> = Mutex::new(());
+}
+
+/// Whether `explore` is running.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// The seed of the current run.
+static SEED: AtomicU64 = AtomicU64::new(0);
+/// Incremented on each run so that the threads reseed their generators.
+static RUN: AtomicUsize = AtomicUsize::new(0);
+/// The number of threads that hit a yield point in the current run.
+static THREADS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The run that the generator of this thread is seeded for, and the generator.
+    static RNG: RefCell<(usize, Option<StdRng>)> = RefCell::new((0, None));
+}
+
+/// Out of 100, the chance that a yield point does nothing. The rest yields or sleeps.
+const PASS_PERCENT: u32 = 50;
+/// Out of 100, the chance that a yield point yields the thread.
+const YIELD_PERCENT: u32 = 40;
+/// The longest sleep at a yield point, in microseconds.
+const MAX_SLEEP_MICROS: u64 = 100;
+
+/// Delays the current thread at random if `explore` is running.
+pub fn yield_point() {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let (dice, micros) = RNG.with(|rng| {
+        let mut rng = rng.borrow_mut();
+        let run = RUN.load(Ordering::Relaxed);
+        if rng.0 != run || rng.1.is_none() {
+            let thread = THREADS.fetch_add(1, Ordering::Relaxed);
+            let seed = SEED.load(Ordering::Relaxed) ^ thread.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            *rng = (run, Some(StdRng::seed_from_u64(seed)));
+        }
+        let rng = rng.1.as_mut().unwrap();
+        (
+            rng.gen_range(0, 100),
+            rng.gen_range(1, MAX_SLEEP_MICROS + 1),
+        )
+    });
+
+    if dice >= PASS_PERCENT + YIELD_PERCENT {
+        thread::sleep(Duration::from_micros(micros));
+    } else if dice >= PASS_PERCENT {
+        thread::yield_now();
+    }
+}
+
+/// Runs `f` `iterations` times with the delays drawn from the seeds `seed`, `seed + 1`, ....
+///
+/// If `f` panics, prints the seed of the run and resumes the panic. `explore(1, seed, f)` reruns
+/// it.
+pub fn explore<F: Fn()>(iterations: usize, seed: u64, f: F) {
+    let _exploring = EXPLORING.lock().unwrap_or_else(|e| e.into_inner());
+
+    for i in 0..iterations as u64 {
+        let seed = seed.wrapping_add(i);
+        SEED.store(seed, Ordering::Relaxed);
+        THREADS.store(0, Ordering::Relaxed);
+        let _ = RUN.fetch_add(1, Ordering::Relaxed);
+        ACTIVE.store(true, Ordering::Relaxed);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(&f));
+        ACTIVE.store(false, Ordering::Relaxed);
+        if let Err(e) = result {
+            eprintln!("fault injection: failed with seed {}", seed);
+            panic::resume_unwind(e);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Linearizability checker for recorded histories.
+//!
+//! The threads run the operations on the concurrent object through a shared `Recorder`, which
+//! timestamps the invocation and the return of each operation. `check` then searches for a
+//! sequential order of the operations that respects their real-time order and gives the same
+//! results when replayed on a `Specification`.
+//!
+//! The search is the algorithm of Wing and Gong with the memoization of Lowe ("Testing for
+//! linearizability", 2017). It tries to linearize the pending operations one at a time in the order
+//! of their invocations, and backtracks when it reaches the return of an operation that is not
+//! linearized yet. The pairs of the linearized operations and the resulting state are memoized, so
+//! the same configuration is never explored twice.
+//!
+//! The search takes exponential time in the worst case. Keep the histories to at most a few hundred
+//! operations with a handful of threads, and check the independent parts of an object separately,
+//! e.g. each key of a map.
+
+use core::fmt;
+use core::hash::Hash;
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashSet;
+
+/// Sequential specification of a concurrent object.
+///
+/// The value of this type is the state of the object.
+pub trait Specification: Clone + Eq + Hash {
+    /// The operation with its arguments.
+    type Op;
+    /// The result of an operation.
+    type Ret: PartialEq;
+
+    /// Applies `op` to the state, and returns its result.
+    fn apply(&mut self, op: &Self::Op) -> Self::Ret;
+}
+
+/// An operation that is run on the concurrent object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation<O, R> {
+    /// The operation with its arguments.
+    pub op: O,
+    /// The result of the operation.
+    pub ret: R,
+    /// The timestamp at the invocation.
+    pub invoked: usize,
+    /// The timestamp at the return.
+    pub returned: usize,
+}
+
+/// Records the operations of multiple threads with a shared clock.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    clock: AtomicUsize,
+}
+
+/// Error returned by `check` when the history has no linearization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotLinearizable;
+
+impl fmt::Display for NotLinearizable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("history is not linearizable")
+    }
+}
+
+impl std::error::Error for NotLinearizable {}
+
+impl Recorder {
+    /// Creates a new recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` as the operation `op`, and returns the record of it.
+    ///
+    /// The records of all threads should be collected into one history after the threads finish.
+    pub fn record<O, R, F: FnOnce(&O) -> R>(&self, op: O, f: F) -> Operation<O, R> {
+        // `SeqCst` orders the timestamps of all threads in real time.
+        let invoked = self.clock.fetch_add(1, Ordering::SeqCst);
+        let ret = f(&op);
+        let returned = self.clock.fetch_add(1, Ordering::SeqCst);
+        Operation {
+            op,
+            ret,
+            invoked,
+            returned,
+        }
+    }
+}
+
+/// Marks the end of the list of events.
+const NIL: usize = usize::MAX;
+
+/// The invocation and return events that are not linearized yet, in the order of time.
+///
+/// Event `2 * i` is the invocation of the `i`-th operation, and event `2 * i + 1` is its return.
+/// The list is doubly linked through the indices so that an operation is removed and put back in
+/// constant time.
+struct Events {
+    next: Vec<usize>,
+    prev: Vec<usize>,
+    /// The sentinel before the first event.
+    head: usize,
+}
+
+impl Events {
+    fn new<O, R>(history: &[Operation<O, R>]) -> Self {
+        let mut events = (0..2 * history.len()).collect::<Vec<_>>();
+        events.sort_by_key(|e| {
+            let op = &history[e / 2];
+            if e % 2 == 0 {
+                op.invoked
+            } else {
+                op.returned
+            }
+        });
+
+        let head = events.len();
+        let mut next = vec![NIL; head + 1];
+        let mut prev = vec![NIL; head + 1];
+        let mut last = head;
+        for e in events {
+            next[last] = e;
+            prev[e] = last;
+            last = e;
+        }
+        Self { next, prev, head }
+    }
+
+    fn first(&self) -> usize {
+        self.next[self.head]
+    }
+
+    fn unlink(&mut self, e: usize) {
+        let (prev, next) = (self.prev[e], self.next[e]);
+        self.next[prev] = next;
+        if next != NIL {
+            self.prev[next] = prev;
+        }
+    }
+
+    /// Puts back `e` where it was unlinked. Must be called in the reverse order of `unlink`.
+    fn relink(&mut self, e: usize) {
+        let (prev, next) = (self.prev[e], self.next[e]);
+        self.next[prev] = e;
+        if next != NIL {
+            self.prev[next] = e;
+        }
+    }
+
+    /// Removes the invocation and the return of the `op`-th operation.
+    fn lift(&mut self, op: usize) {
+        self.unlink(2 * op);
+        self.unlink(2 * op + 1);
+    }
+
+    fn unlift(&mut self, op: usize) {
+        self.relink(2 * op + 1);
+        self.relink(2 * op);
+    }
+}
+
+/// Checks if `history` is linearizable with respect to the specification starting from `init`.
+///
+/// Returns the indices of the operations in `history` in the order of a linearization.
+pub fn check<S: Specification>(
+    init: S,
+    history: &[Operation<S::Op, S::Ret>],
+) -> Result<Vec<usize>, NotLinearizable> {
+    let mut events = Events::new(history);
+    let mut linearized = vec![0u64; history.len() / 64 + 1];
+    let mut cache = HashSet::new();
+    // The linearized operations, with the states before them.
+    let mut stack = Vec::<(usize, S)>::new();
+    let mut state = init;
+
+    let mut event = events.first();
+    while event != NIL {
+        let op = event / 2;
+        if event % 2 == 0 {
+            let mut new_state = state.clone();
+            if new_state.apply(&history[op].op) == history[op].ret {
+                linearized[op / 64] |= 1 << (op % 64);
+                if cache.insert((linearized.clone(), new_state.clone())) {
+                    stack.push((op, mem::replace(&mut state, new_state)));
+                    events.lift(op);
+                    event = events.first();
+                    continue;
+                }
+                linearized[op / 64] &= !(1 << (op % 64));
+            }
+            event = events.next[event];
+        } else {
+            // An operation returned before being linearized, so undo the last choice.
+            let (op, prev_state) = stack.pop().ok_or(NotLinearizable)?;
+            linearized[op / 64] &= !(1 << (op % 64));
+            state = prev_state;
+            events.unlift(op);
+            event = events.next[2 * op];
+        }
+    }
+
+    Ok(stack.into_iter().map(|(op, _)| op).collect())
+}
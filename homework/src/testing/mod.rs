@@ -0,0 +1,12 @@
+//! Utilities for testing the concurrent data structures.
+
+#[cfg(feature = "alloc-tracking")]
+pub mod allocation;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+pub mod linearizability;
+pub mod workload;
+
+mod deterministic_pool;
+
+pub use deterministic_pool::DeterministicPool;
@@ -0,0 +1,377 @@
+//! Standardized workloads for benchmarking the concurrent sets.
+//!
+//! A `Workload` is a mix of `contains`, `insert`, and `remove` on `usize` keys drawn from a fixed
+//! range, either uniformly or from a Zipfian distribution. The structures under test implement
+//! `Target`, which gives them a hook to set up per-thread state, e.g. an epoch handle, before the
+//! measurement starts.
+
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{self as epoch, LocalHandle, Owned, Shared};
+use crossbeam_utils::thread::scope;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
+use std::sync::{Barrier, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::hamt::AtomicHamt;
+use crate::hash_table::{GrowableArray, SplitOrderedList};
+use crate::list_set::OrderedListSet;
+use crate::lock_coupling_bst::LockCouplingBst;
+use crate::map::{ConcurrentMap, NonblockingMap};
+use crate::nm_tree::NmTree;
+use crate::radix_tree::RadixTree;
+
+/// Concurrent set of `usize` keys that is benchmarked by a `Workload`.
+pub trait Target: Sync {
+    /// Per-thread state, created by each thread before it runs the operations.
+    type Handle;
+
+    /// Creates the state of the current thread.
+    fn handle(&self) -> Self::Handle;
+
+    /// Returns `true` if the set contains `key`.
+    fn contains(&self, handle: &mut Self::Handle, key: usize) -> bool;
+
+    /// Inserts `key`. Returns `false` if it is already in the set.
+    fn insert(&self, handle: &mut Self::Handle, key: usize) -> bool;
+
+    /// Removes `key`. Returns `false` if it is not in the set.
+    fn remove(&self, handle: &mut Self::Handle, key: usize) -> bool;
+}
+
+/// The distribution of the keys of a workload.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyDistribution {
+    /// Every key is equally likely.
+    Uniform,
+    /// The `i`-th most popular key is drawn with the probability proportional to `1 / i^theta`.
+    /// `theta` should be in `(0, 1)`.
+    Zipfian(f64),
+}
+
+/// Mix of operations on a range of keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Workload {
+    /// The name shown in the reports.
+    pub name: &'static str,
+    /// The percentage of `contains`. The rest is split evenly between `insert` and `remove`, so the
+    /// size of the set stays around half of the key range.
+    pub read_percent: u32,
+    /// The keys are in `0..key_range`. Must be a power of two.
+    pub key_range: usize,
+    /// The distribution of the keys.
+    pub distribution: KeyDistribution,
+}
+
+impl Workload {
+    /// 90% reads on uniform keys.
+    pub const READ_HEAVY: Self = Self {
+        name: "read-heavy",
+        read_percent: 90,
+        key_range: 1 << 10,
+        distribution: KeyDistribution::Uniform,
+    };
+
+    /// 10% reads on uniform keys.
+    pub const WRITE_HEAVY: Self = Self {
+        name: "write-heavy",
+        read_percent: 10,
+        key_range: 1 << 10,
+        distribution: KeyDistribution::Uniform,
+    };
+
+    /// 50% reads on Zipfian keys, where a few keys take most of the operations.
+    pub const ZIPFIAN: Self = Self {
+        name: "zipfian",
+        read_percent: 50,
+        key_range: 1 << 10,
+        distribution: KeyDistribution::Zipfian(0.99),
+    };
+
+    /// Inserts every other key, which is the expected size of the set under the workload.
+    pub fn prefill<T: Target>(&self, target: &T) {
+        let mut handle = target.handle();
+        for key in (0..self.key_range).step_by(2) {
+            let _ = target.insert(&mut handle, key);
+        }
+    }
+
+    /// Runs `ops` operations on each of `threads` threads, and returns the time from when all
+    /// threads are set up until the last one finishes.
+    pub fn run<T: Target>(&self, target: &T, threads: usize, ops: usize) -> Duration {
+        let barrier = Barrier::new(threads + 1);
+        scope(|s| {
+            for t in 0..threads {
+                let barrier = &barrier;
+                let _ = s.spawn(move |_| {
+                    let mut handle = target.handle();
+                    let mut keys = KeyGenerator::new(self, t as u64);
+                    let mut rng = StdRng::seed_from_u64(!(t as u64));
+                    let _ = barrier.wait();
+                    for _ in 0..ops {
+                        let key = keys.next_key();
+                        let dice = rng.gen_range(0, 200);
+                        let _ = if dice < self.read_percent * 2 {
+                            target.contains(&mut handle, key)
+                        } else if dice % 2 == 0 {
+                            target.insert(&mut handle, key)
+                        } else {
+                            target.remove(&mut handle, key)
+                        };
+                    }
+                });
+            }
+            let _ = barrier.wait();
+            Instant::now()
+        })
+        .unwrap()
+        .elapsed()
+    }
+}
+
+/// Generates the keys of a workload.
+#[derive(Debug)]
+pub struct KeyGenerator {
+    rng: StdRng,
+    mask: usize,
+    zipf: Option<Zipf>,
+}
+
+/// Zipfian generator of Gray et al., "Quickly generating billion-record synthetic databases".
+#[derive(Debug)]
+struct Zipf {
+    n: f64,
+    theta: f64,
+    zeta_n: f64,
+    alpha: f64,
+    eta: f64,
+}
+
+impl Zipf {
+    fn new(n: usize, theta: f64) -> Self {
+        let zeta = |n: usize| (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum::<f64>();
+        let zeta_n = zeta(n);
+        Self {
+            n: n as f64,
+            theta,
+            zeta_n,
+            alpha: 1.0 / (1.0 - theta),
+            eta: (1.0 - (2.0 / n as f64).powf(1.0 - theta)) / (1.0 - zeta(2) / zeta_n),
+        }
+    }
+
+    /// Returns the rank of a key, where 0 is the most popular.
+    fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        let u = rng.gen::<f64>();
+        let uz = u * self.zeta_n;
+        if uz < 1.0 {
+            0
+        } else if uz < 1.0 + 0.5f64.powf(self.theta) {
+            1
+        } else {
+            (self.n * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as usize
+        }
+    }
+}
+
+impl KeyGenerator {
+    /// Creates a generator of the keys of `workload`, seeded with `seed`.
+    pub fn new(workload: &Workload, seed: u64) -> Self {
+        assert!(workload.key_range.is_power_of_two());
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            mask: workload.key_range - 1,
+            zipf: match workload.distribution {
+                KeyDistribution::Uniform => None,
+                KeyDistribution::Zipfian(theta) => Some(Zipf::new(workload.key_range, theta)),
+            },
+        }
+    }
+
+    /// Returns the next key.
+    pub fn next_key(&mut self) -> usize {
+        match &self.zipf {
+            None => self.rng.gen::<usize>() & self.mask,
+            // Scatters the popular keys over the range with an odd multiplier, which is a
+            // bijection modulo a power of two.
+            Some(zipf) => {
+                zipf.sample(&mut self.rng)
+                    .wrapping_mul(0x9e37_79b9_7f4a_7c15u64 as usize)
+                    & self.mask
+            }
+        }
+    }
+}
+
+/// Coarse-grained locking baseline.
+impl Target for Mutex<BTreeSet<usize>> {
+    type Handle = ();
+
+    fn handle(&self) {}
+
+    fn contains(&self, _: &mut (), key: usize) -> bool {
+        self.lock().unwrap().contains(&key)
+    }
+
+    fn insert(&self, _: &mut (), key: usize) -> bool {
+        self.lock().unwrap().insert(key)
+    }
+
+    fn remove(&self, _: &mut (), key: usize) -> bool {
+        self.lock().unwrap().remove(&key)
+    }
+}
+
+impl Target for OrderedListSet<usize> {
+    type Handle = ();
+
+    fn handle(&self) {}
+
+    fn contains(&self, _: &mut (), key: usize) -> bool {
+        self.contains(&key)
+    }
+
+    fn insert(&self, _: &mut (), key: usize) -> bool {
+        self.insert(key).is_ok()
+    }
+
+    fn remove(&self, _: &mut (), key: usize) -> bool {
+        self.remove(&key).is_ok()
+    }
+}
+
+impl Target for SplitOrderedList<()> {
+    type Handle = LocalHandle;
+
+    fn handle(&self) -> LocalHandle {
+        epoch::default_collector().register()
+    }
+
+    fn contains(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        NonblockingMap::lookup(self, &key, &handle.pin()).is_some()
+    }
+
+    fn insert(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        NonblockingMap::insert(self, &key, (), &handle.pin()).is_ok()
+    }
+
+    fn remove(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        NonblockingMap::delete(self, &key, &handle.pin()).is_ok()
+    }
+}
+
+/// The keys are in big endian so that they are ordered as numbers.
+impl Target for RadixTree<()> {
+    type Handle = LocalHandle;
+
+    fn handle(&self) -> LocalHandle {
+        epoch::default_collector().register()
+    }
+
+    fn contains(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.lookup(&key.to_be_bytes(), &handle.pin()).is_some()
+    }
+
+    fn insert(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.insert(&key.to_be_bytes(), (), &handle.pin()).is_ok()
+    }
+
+    fn remove(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.delete(&key.to_be_bytes(), &handle.pin()).is_ok()
+    }
+}
+
+impl Target for AtomicHamt<usize, ()> {
+    type Handle = LocalHandle;
+
+    fn handle(&self) -> LocalHandle {
+        epoch::default_collector().register()
+    }
+
+    fn contains(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.snapshot_with(&handle.pin()).contains_key(&key)
+    }
+
+    fn insert(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        ConcurrentMap::insert(self, &key, (), &handle.pin()).is_ok()
+    }
+
+    fn remove(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        ConcurrentMap::delete(self, &key, &handle.pin()).is_ok()
+    }
+}
+
+impl Target for NmTree<usize, ()> {
+    type Handle = LocalHandle;
+
+    fn handle(&self) -> LocalHandle {
+        epoch::default_collector().register()
+    }
+
+    fn contains(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.lookup(&key, &handle.pin()).is_some()
+    }
+
+    fn insert(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.insert(key, (), &handle.pin()).is_ok()
+    }
+
+    fn remove(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        self.delete(&key, &handle.pin()).is_ok()
+    }
+}
+
+impl Target for LockCouplingBst<usize, ()> {
+    type Handle = LocalHandle;
+
+    fn handle(&self) -> LocalHandle {
+        epoch::default_collector().register()
+    }
+
+    fn contains(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        ConcurrentMap::lookup(self, &key, &handle.pin(), |value| value.is_some())
+    }
+
+    fn insert(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        ConcurrentMap::insert(self, &key, (), &handle.pin()).is_ok()
+    }
+
+    fn remove(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        ConcurrentMap::delete(self, &key, &handle.pin()).is_ok()
+    }
+}
+
+/// The key is in the set if its slot is not null. The array doesn't own the elements, so the keys
+/// should be removed before it is dropped.
+impl Target for GrowableArray<usize> {
+    type Handle = LocalHandle;
+
+    fn handle(&self) -> LocalHandle {
+        epoch::default_collector().register()
+    }
+
+    fn contains(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        !self
+            .get_with_tag(key, Ordering::Acquire, &handle.pin())
+            .0
+            .is_null()
+    }
+
+    fn insert(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        let guard = handle.pin();
+        self.get(key, &guard)
+            .compare_and_set(Shared::null(), Owned::new(key), Ordering::AcqRel, &guard)
+            .is_ok()
+    }
+
+    fn remove(&self, handle: &mut LocalHandle, key: usize) -> bool {
+        let guard = handle.pin();
+        let element = self.take(key, &guard);
+        if element.is_null() {
+            return false;
+        }
+        unsafe { guard.defer_destroy(element) };
+        true
+    }
+}
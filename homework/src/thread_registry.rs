@@ -0,0 +1,209 @@
+//! Thread registry.
+
+use core::cmp::Reverse;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{unprotected, Owned};
+use lazy_static::lazy_static;
+use std::collections::BinaryHeap;
+use std::sync::Mutex;
+
+use crate::hash_table::GrowableArray;
+
+#[derive(Debug, Default)]
+struct Ids {
+    /// The smallest ID that has never been assigned.
+    next: usize,
+    /// The IDs released by the exited threads.
+    free: BinaryHeap<Reverse<usize>>,
+}
+
+lazy_static! {
+    static ref IDS: Mutex<Ids> = Mutex::new(Ids::default());
+}
+
+/// Owns the ID of a thread, and releases it when the thread exits.
+struct ThreadId(usize);
+
+impl ThreadId {
+    fn new() -> Self {
+        let mut ids = IDS.lock().unwrap();
+        if let Some(Reverse(id)) = ids.free.pop() {
+            return Self(id);
+        }
+        let id = ids.next;
+        ids.next += 1;
+        Self(id)
+    }
+}
+
+impl Drop for ThreadId {
+    fn drop(&mut self) {
+        IDS.lock().unwrap().free.push(Reverse(self.0));
+    }
+}
+
+thread_local! {
+    static THREAD_ID: ThreadId = ThreadId::new();
+}
+
+/// Returns the ID of the current thread.
+///
+/// The IDs of the running threads are distinct and dense: a new thread takes the smallest ID that
+/// is not used by a running thread, so the IDs are less than the largest number of threads that
+/// have run at the same time. The ID of an exited thread is reused by a later thread.
+///
+/// # Panics
+///
+/// Panics if called by a thread-local destructor after the ID of the thread is released.
+pub fn current_thread_id() -> usize {
+    THREAD_ID.with(|id| id.0)
+}
+
+/// Per-thread slots indexed by the thread IDs.
+///
+/// Each thread lazily creates its own value with `get_or`, and any thread may iterate over the
+/// values of all the threads, e.g. to scan the hazard pointers of the other threads or to sum up
+/// the shards of a counter. The values are dropped only with the registry.
+///
+/// When a thread exits, its value stays in the registry, and the next thread that takes the same
+/// ID (see `current_thread_id`) finds it instead of creating a new one. Since a running thread
+/// owns its ID exclusively, no two running threads share a value, and the value can be reused as
+/// is, e.g. the hazard pointer array that the exited thread has cleared, or the shard that keeps
+/// the counts of the exited thread.
+pub struct ThreadRegistry<T: Send> {
+    slots: GrowableArray<T>,
+    /// Greater than the IDs of the threads that have created a value.
+    len: AtomicUsize,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for ThreadRegistry<T> {}
+// The values are only created, and accessed by `&T`, by their owner threads, except for `iter`
+// which requires `T: Sync`.
+unsafe impl<T: Send> Sync for ThreadRegistry<T> {}
+
+/// Iterator over the values of a `ThreadRegistry`.
+#[derive(Debug)]
+pub struct Iter<'r, T: Send> {
+    registry: &'r ThreadRegistry<T>,
+    index: usize,
+    len: usize,
+}
+
+impl<T: Send> Default for ThreadRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for ThreadRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadRegistry")
+            .field("local", &self.get())
+            .field("len", &self.len.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<T: Send> ThreadRegistry<T> {
+    /// Creates a new registry without any value.
+    pub fn new() -> Self {
+        Self {
+            slots: GrowableArray::new(),
+            len: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the value of the current thread, if it is created.
+    pub fn get(&self) -> Option<&T> {
+        let id = current_thread_id();
+        if id >= self.len.load(Ordering::Acquire) {
+            return None;
+        }
+        unsafe {
+            // The segments of `slots` are freed only when it is dropped.
+            let guard = unprotected();
+            self.slots
+                .get(id, guard)
+                .load(Ordering::Acquire, guard)
+                .as_ref()
+        }
+    }
+
+    /// Returns the value of the current thread, creating it with `f` if there is none.
+    pub fn get_or<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        let id = current_thread_id();
+        unsafe {
+            let guard = unprotected();
+            // Only the current thread writes to its slot.
+            let value = Owned::new(f()).into_shared(guard);
+            self.slots.get(id, guard).store(value, Ordering::Release);
+            let _ = self.len.fetch_max(id + 1, Ordering::Release);
+            value.deref()
+        }
+    }
+
+    /// Returns the value of the current thread, creating the default value if there is none.
+    pub fn get_or_default(&self) -> &T
+    where
+        T: Default,
+    {
+        self.get_or(T::default)
+    }
+
+    /// Returns the iterator over the values of all the threads, including the exited ones.
+    pub fn iter(&self) -> Iter<'_, T>
+    where
+        T: Sync,
+    {
+        Iter {
+            registry: self,
+            index: 0,
+            len: self.len.load(Ordering::Acquire),
+        }
+    }
+
+    /// Drops all the values.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<'r, T: Send> Iterator for Iter<'r, T> {
+    type Item = &'r T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.len {
+            let index = self.index;
+            self.index += 1;
+            unsafe {
+                let guard = unprotected();
+                let slot = self.registry.slots.get(index, guard);
+                if let Some(value) = slot.load(Ordering::Acquire, guard).as_ref() {
+                    return Some(value);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T: Send> Drop for ThreadRegistry<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = unprotected();
+            for index in 0..*self.len.get_mut() {
+                let value = self.slots.get(index, guard).load(Ordering::Relaxed, guard);
+                if !value.is_null() {
+                    drop(value.into_owned());
+                }
+            }
+        }
+    }
+}
@@ -19,3 +19,41 @@ macro_rules! some_or {
         }
     }};
 }
+
+/// Gives the randomized scheduler of `testing::fault` a chance to delay the current thread here.
+/// Expands to nothing without the `fault-injection` feature.
+macro_rules! yield_point {
+    () => {
+        #[cfg(feature = "fault-injection")]
+        crate::testing::fault::yield_point();
+    };
+}
+
+/// Adds to a counter of `metrics::recorder()`. Expands to nothing without the `metrics` feature.
+macro_rules! counter {
+    ($name:expr) => {
+        counter!($name, 1)
+    };
+    ($name:expr, $value:expr) => {
+        #[cfg(feature = "metrics")]
+        crate::metrics::recorder().increment_counter($name, $value);
+    };
+}
+
+/// Sets a gauge of `metrics::recorder()`. Expands to nothing without the `metrics` feature.
+macro_rules! gauge {
+    ($name:expr, $value:expr) => {
+        #[cfg(feature = "metrics")]
+        crate::metrics::recorder().set_gauge($name, $value as i64);
+    };
+}
+
+/// Records a sample to a histogram of `metrics::recorder()`. Expands to nothing without the
+/// `metrics` feature.
+#[allow(unused_macros)] // Only the modules that need `std` record histograms.
+macro_rules! histogram {
+    ($name:expr, $value:expr) => {
+        #[cfg(feature = "metrics")]
+        crate::metrics::recorder().record_histogram($name, $value as u64);
+    };
+}
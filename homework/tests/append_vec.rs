@@ -0,0 +1,87 @@
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::AppendVec;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let vec = AppendVec::new();
+    let guard = epoch::pin();
+    assert!(vec.is_empty());
+    assert_eq!(vec.get(0, &guard), None);
+
+    for i in 0..3000 {
+        assert_eq!(vec.push(i * 2, &guard), i);
+    }
+    assert_eq!(vec.len(), 3000);
+    assert_eq!(vec.get(1234, &guard), Some(&2468));
+    assert_eq!(vec.get(3000, &guard), None);
+    assert!(vec.iter(&guard).copied().eq((0..3000).map(|i| i * 2)));
+}
+
+#[test]
+fn concurrent_push() {
+    const THREADS: usize = 8;
+    const PUSHES: usize = 10_000;
+    let vec = AppendVec::new();
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let vec = &vec;
+            let _ = s.spawn(move |_| {
+                let guard = epoch::pin();
+                let mut last = None;
+                for i in 0..PUSHES {
+                    let index = vec.push((t, i), &guard);
+                    // The indices of a thread increase, and its values stay where they are pushed.
+                    assert!(last < Some(index));
+                    assert_eq!(vec.get(index, &guard), Some(&(t, i)));
+                    last = Some(index);
+                }
+            });
+        }
+        // Reads while pushing.
+        let _ = s.spawn(|_| {
+            let guard = epoch::pin();
+            while vec.len() < THREADS * PUSHES {
+                let mut next = [0; THREADS];
+                for &(t, i) in vec.iter(&guard) {
+                    assert!(i >= next[t]);
+                    next[t] = i + 1;
+                }
+            }
+        });
+    })
+    .unwrap();
+
+    let guard = epoch::pin();
+    assert_eq!(vec.len(), THREADS * PUSHES);
+    let mut values = vec.iter(&guard).copied().collect::<Vec<_>>();
+    values.sort_unstable();
+    let expected = (0..THREADS)
+        .flat_map(|t| (0..PUSHES).map(move |i| (t, i)))
+        .collect::<Vec<_>>();
+    assert_eq!(values, expected);
+}
+
+#[test]
+fn drop_values() {
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            let _ = self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let vec = AppendVec::new();
+    let guard = epoch::pin();
+    for _ in 0..2000 {
+        let _ = vec.push(Counted(drops.clone()), &guard);
+    }
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    drop(vec);
+    assert_eq!(drops.load(Ordering::Relaxed), 2000);
+}
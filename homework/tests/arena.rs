@@ -0,0 +1,94 @@
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{Arena, ArenaStats};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct DropCounter<'a>(&'a AtomicUsize);
+
+impl Drop for DropCounter<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn smoke() {
+    let drops = AtomicUsize::new(0);
+    let arena = Arena::new();
+    let guard = epoch::pin();
+    let nodes = (0..100)
+        .map(|_| arena.alloc(DropCounter(&drops), &guard))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        arena.stats(),
+        ArenaStats {
+            chunks_allocated: 2,
+            chunks_freed: 0,
+            nodes_allocated: 100,
+            nodes_freed: 0,
+        }
+    );
+
+    // The first chunk is freed when all its nodes are freed.
+    for node in nodes {
+        unsafe { Arena::free(node) };
+    }
+    drop(guard);
+    assert_eq!(drops.load(Ordering::Relaxed), 100);
+    for _ in 0..128 {
+        epoch::pin().flush();
+    }
+    assert_eq!(arena.stats().chunks_freed, 1);
+    assert_eq!(arena.stats().nodes_freed, 100);
+}
+
+#[test]
+fn arena_box() {
+    let drops = AtomicUsize::new(0);
+    let arena = Arena::new();
+    let guard = epoch::pin();
+
+    // The value taken out is not dropped by the arena.
+    let value = arena.alloc_box(DropCounter(&drops), &guard).into_inner();
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    drop(value);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+    drop(arena.alloc_box(DropCounter(&drops), &guard));
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+    assert_eq!(arena.stats().nodes_freed, 2);
+}
+
+#[test]
+fn retire_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 10_000;
+
+    let drops = AtomicUsize::new(0);
+    let arena = Arena::new();
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..STEPS {
+                    let guard = epoch::pin();
+                    let node = arena.alloc(DropCounter(&drops), &guard);
+                    unsafe { Arena::retire(node, &guard) };
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // The retired nodes are freed eventually.
+    for _ in 0..1024 {
+        if drops.load(Ordering::Relaxed) == THREADS * STEPS {
+            break;
+        }
+        epoch::pin().flush();
+    }
+    assert_eq!(drops.load(Ordering::Relaxed), THREADS * STEPS);
+    let stats = arena.stats();
+    assert_eq!(stats.nodes_allocated, THREADS * STEPS);
+    assert_eq!(stats.nodes_freed, THREADS * STEPS);
+    assert!(stats.chunks_allocated >= THREADS * STEPS / 64);
+}
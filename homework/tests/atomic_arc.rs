@@ -0,0 +1,69 @@
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::AtomicArc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let cell = AtomicArc::new(Arc::new(1));
+    assert_eq!(*cell.load(), 1);
+
+    cell.store(Arc::new(2));
+    assert_eq!(*cell.load(), 2);
+
+    let old = cell.swap(Arc::new(3));
+    assert_eq!(*old, 2);
+    assert_eq!(*cell.load_with(&pin()), 3);
+    assert_eq!(*cell.into_inner(), 3);
+}
+
+#[test]
+fn compare_exchange() {
+    let cell = AtomicArc::new(Arc::new(1));
+    let current = cell.load();
+
+    let stale = Arc::new(1);
+    let err = cell.compare_exchange(&stale, Arc::new(2)).unwrap_err();
+    assert!(Arc::ptr_eq(&err.current, &current));
+    assert_eq!(*err.new, 2);
+
+    let old = cell.compare_exchange(&current, Arc::new(3)).unwrap();
+    assert!(Arc::ptr_eq(&old, &current));
+    assert_eq!(*cell.load(), 3);
+}
+
+struct DropCounter<'a>(&'a AtomicUsize);
+
+impl Drop for DropCounter<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn no_leak() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    const THREADS: usize = 8;
+    const STEPS: usize = 1024;
+
+    let cell = AtomicArc::new(Arc::new(DropCounter(&DROPS)));
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..STEPS {
+                    let _value = cell.load();
+                    cell.store(Arc::new(DropCounter(&DROPS)));
+                }
+            });
+        }
+    })
+    .unwrap();
+    drop(cell);
+
+    // Flush the deferred decrements.
+    for _ in 0..1024 {
+        pin().flush();
+    }
+    assert_eq!(DROPS.load(Ordering::Relaxed), THREADS * STEPS + 1);
+}
@@ -0,0 +1,61 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{BackoffStats, ExponentialBackoff};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[test]
+fn escalation() {
+    let backoff = ExponentialBackoff::new();
+    assert_eq!(backoff.stats(), BackoffStats::default());
+    for _ in 0..20 {
+        backoff.backoff();
+    }
+    let stats = backoff.stats();
+    assert!(stats.spins > 0 && stats.yields > 0 && stats.parks > 0);
+    assert_eq!(stats.total(), 20);
+
+    // Spins again after reset.
+    backoff.reset();
+    backoff.backoff();
+    assert_eq!(backoff.stats().spins, stats.spins + 1);
+}
+
+#[test]
+fn bounded_wait() {
+    let backoff = ExponentialBackoff::new();
+    for _ in 0..100 {
+        backoff.backoff();
+    }
+    let start = Instant::now();
+    backoff.backoff();
+    // A park is at most about a millisecond.
+    assert!(start.elapsed() < Duration::from_millis(100));
+}
+
+#[test]
+fn cas_counter() {
+    const THREADS: usize = 8;
+    const COUNT: usize = 10_000;
+    let counter = AtomicUsize::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|_| {
+                for _ in 0..COUNT {
+                    let backoff = ExponentialBackoff::new();
+                    let mut current = counter.load(Ordering::Relaxed);
+                    while let Err(c) = counter.compare_exchange_weak(
+                        current,
+                        current + 1,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        current = c;
+                        backoff.backoff();
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(counter.load(Ordering::Relaxed), THREADS * COUNT);
+}
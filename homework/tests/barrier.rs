@@ -0,0 +1,96 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{SenseBarrier, TreeBarrier};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const THREADS: usize = 16;
+const ROUNDS: usize = 1024;
+
+#[test]
+fn single_thread() {
+    let barrier = SenseBarrier::new(1);
+    for _ in 0..ROUNDS {
+        assert!(barrier.wait().is_leader());
+    }
+}
+
+#[test]
+fn rounds() {
+    let barrier = SenseBarrier::new(THREADS);
+    let arrived = AtomicUsize::new(0);
+    let leaders = AtomicUsize::new(0);
+
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for round in 0..ROUNDS {
+                    arrived.fetch_add(1, Ordering::Relaxed);
+                    if barrier.wait().is_leader() {
+                        leaders.fetch_add(1, Ordering::Relaxed);
+                    }
+                    // Every thread has arrived in this round, and no thread can arrive in the
+                    // next round before this thread.
+                    let n = arrived.load(Ordering::Relaxed);
+                    assert!(n >= (round + 1) * THREADS && n <= (round + 2) * THREADS);
+                    barrier.wait();
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(leaders.load(Ordering::Relaxed), ROUNDS);
+}
+
+#[test]
+fn late_thread() {
+    let barrier = SenseBarrier::new(THREADS);
+
+    scope(|s| {
+        for i in 0..THREADS {
+            let barrier = &barrier;
+            s.spawn(move |_| {
+                for _ in 0..4 {
+                    // The other threads give up spinning and block.
+                    if i == 0 {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    barrier.wait();
+                }
+            });
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn tree_rounds() {
+    for &(threads, fan_in) in &[(1, 2), (THREADS, 2), (THREADS, 4), (THREADS - 3, 3), (5, 8)] {
+        let barrier = TreeBarrier::new(threads, fan_in);
+        let arrived = AtomicUsize::new(0);
+        let leaders = AtomicUsize::new(0);
+
+        scope(|s| {
+            for id in 0..threads {
+                let barrier = &barrier;
+                let arrived = &arrived;
+                let leaders = &leaders;
+                s.spawn(move |_| {
+                    for round in 0..ROUNDS {
+                        arrived.fetch_add(1, Ordering::Relaxed);
+                        if barrier.wait(id).is_leader() {
+                            leaders.fetch_add(1, Ordering::Relaxed);
+                        }
+                        let n = arrived.load(Ordering::Relaxed);
+                        assert!(n >= (round + 1) * threads && n <= (round + 2) * threads);
+                        barrier.wait(id);
+                    }
+                });
+            }
+        })
+        .unwrap();
+
+        assert_eq!(leaders.load(Ordering::Relaxed), ROUNDS);
+    }
+}
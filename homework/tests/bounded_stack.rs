@@ -0,0 +1,82 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::BoundedStack;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let stack = BoundedStack::new(3);
+    assert!(stack.is_empty());
+    assert_eq!(stack.pop(), None);
+
+    for i in 0..3 {
+        assert_eq!(stack.push(i), Ok(()));
+    }
+    assert_eq!(stack.push(3), Err(3));
+    assert_eq!(stack.len(), 3);
+
+    assert_eq!(stack.pop(), Some(2));
+    assert_eq!(stack.push(4), Ok(()));
+    assert_eq!(stack.pop(), Some(4));
+    assert_eq!(stack.pop(), Some(1));
+    assert_eq!(stack.pop(), Some(0));
+    assert_eq!(stack.pop(), None);
+}
+
+#[test]
+fn zero_capacity() {
+    let stack = BoundedStack::new(0);
+    assert_eq!(stack.push(0), Err(0));
+    assert_eq!(stack.pop(), None);
+}
+
+#[test]
+fn drop_values() {
+    let value = Arc::new(());
+    let stack = BoundedStack::new(4);
+    for _ in 0..3 {
+        stack.push(value.clone()).unwrap();
+    }
+    drop(stack.pop());
+    assert_eq!(Arc::strong_count(&value), 3);
+    drop(stack);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+fn concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 10_000;
+    const CAPACITY: usize = 16;
+
+    // Every value pushed is popped exactly once, either concurrently or at the end.
+    let stack = BoundedStack::new(CAPACITY);
+    let popped = (0..THREADS * STEPS)
+        .map(|_| AtomicUsize::new(0))
+        .collect::<Vec<_>>();
+    scope(|s| {
+        for t in 0..THREADS {
+            let (stack, popped) = (&stack, &popped);
+            let _ = s.spawn(move |_| {
+                for i in 0..STEPS {
+                    let mut value = t * STEPS + i;
+                    while let Err(v) = stack.push(value) {
+                        value = v;
+                        if let Some(v) = stack.pop() {
+                            let _ = popped[v].fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    if let Some(v) = stack.pop() {
+                        let _ = popped[v].fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    while let Some(v) = stack.pop() {
+        let _ = popped[v].fetch_add(1, Ordering::Relaxed);
+    }
+    assert!(popped.iter().all(|p| p.load(Ordering::Relaxed) == 1));
+}
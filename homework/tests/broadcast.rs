@@ -0,0 +1,130 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::channel::broadcast::{
+    channel, channel_with_policy, LagPolicy, RecvError, SendError, TryRecvError,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let (tx, mut rx1) = channel(4);
+    let mut rx2 = tx.subscribe();
+    assert_eq!(tx.receiver_count(), 2);
+    assert_eq!(rx1.try_recv(), Err(TryRecvError::Empty));
+
+    assert_eq!(tx.send(1), Ok(2));
+    assert_eq!(tx.send(2), Ok(2));
+    assert_eq!(rx1.recv(), Ok(1));
+    assert_eq!(rx1.recv(), Ok(2));
+    assert_eq!(rx2.try_recv(), Ok(1));
+    assert_eq!(rx2.try_recv(), Ok(2));
+
+    // A new subscriber only sees the messages sent after subscribing.
+    let mut rx3 = tx.subscribe();
+    tx.send(3).unwrap();
+    assert_eq!(rx3.recv(), Ok(3));
+
+    drop(tx);
+    assert_eq!(rx1.recv(), Ok(3));
+    assert_eq!(rx1.recv(), Err(RecvError::Closed));
+    assert_eq!(rx2.try_recv(), Ok(3));
+    assert_eq!(rx2.try_recv(), Err(TryRecvError::Closed));
+}
+
+#[test]
+fn no_receivers() {
+    let (tx, rx) = channel(1);
+    drop(rx);
+    assert_eq!(tx.send(42), Err(SendError(42)));
+}
+
+#[test]
+fn lagged() {
+    let (tx, mut rx) = channel(2);
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv(), Err(RecvError::Lagged(3)));
+    assert_eq!(rx.recv(), Ok(3));
+    assert_eq!(rx.recv(), Ok(4));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn clone_receiver() {
+    let (tx, mut rx1) = channel(4);
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(rx1.recv(), Ok(1));
+
+    // The clone starts at the same position.
+    let mut rx2 = rx1.clone();
+    assert_eq!(rx1.recv(), Ok(2));
+    assert_eq!(rx2.recv(), Ok(2));
+}
+
+#[test]
+fn block_policy() {
+    let (tx, mut rx) = channel_with_policy(2, LagPolicy::Block);
+    let sent = AtomicBool::new(false);
+    tx.send(0).unwrap();
+    tx.send(1).unwrap();
+
+    scope(|s| {
+        s.spawn(|_| {
+            // Blocks until the receiver reads the oldest message.
+            tx.send(2).unwrap();
+            sent.store(true, Ordering::SeqCst);
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(!sent.load(Ordering::SeqCst));
+        assert_eq!(rx.recv(), Ok(0));
+    })
+    .unwrap();
+
+    assert!(sent.load(Ordering::SeqCst));
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Ok(2));
+}
+
+#[test]
+fn block_policy_dropped_receiver() {
+    let (tx, rx1) = channel_with_policy(1, LagPolicy::Block);
+    let mut rx2 = tx.subscribe();
+    tx.send(0).unwrap();
+    assert_eq!(rx2.recv(), Ok(0));
+
+    // The slow receiver is dropped, so the sender doesn't wait for it.
+    drop(rx1);
+    tx.send(1).unwrap();
+    assert_eq!(rx2.recv(), Ok(1));
+}
+
+#[test]
+fn fan_out() {
+    const RECEIVERS: usize = 8;
+    const MESSAGES: usize = 1000;
+
+    let (tx, rx) = channel_with_policy(16, LagPolicy::Block);
+    let receivers = (0..RECEIVERS).map(|_| tx.subscribe()).collect::<Vec<_>>();
+    drop(rx);
+
+    scope(|s| {
+        for mut rx in receivers {
+            s.spawn(move |_| {
+                for i in 0..MESSAGES {
+                    assert_eq!(rx.recv(), Ok(i));
+                }
+                assert_eq!(rx.recv(), Err(RecvError::Closed));
+            });
+        }
+
+        for i in 0..MESSAGES {
+            assert_eq!(tx.send(i), Ok(RECEIVERS));
+        }
+        drop(tx);
+    })
+    .unwrap();
+}
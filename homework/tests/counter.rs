@@ -0,0 +1,39 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::StripedCounter;
+
+#[test]
+fn smoke() {
+    let counter = StripedCounter::new();
+    assert_eq!(counter.sum(), 0);
+    counter.increment();
+    counter.increment();
+    counter.decrement();
+    assert_eq!(counter.sum(), 1);
+    counter.add(-3);
+    assert_eq!(counter.sum(), -2);
+}
+
+#[test]
+fn concurrent() {
+    const THREADS: usize = 32;
+    const STEPS: usize = 4096;
+
+    let counter = StripedCounter::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let counter = &counter;
+            s.spawn(move |_| {
+                for _ in 0..STEPS {
+                    if t % 4 == 0 {
+                        counter.decrement();
+                    } else {
+                        counter.increment();
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(counter.sum(), (THREADS / 2 * STEPS) as isize);
+}
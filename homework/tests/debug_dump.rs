@@ -0,0 +1,102 @@
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::{self as epoch, Owned};
+use cs492_concur_homework::debug_dump::{self, Format};
+use cs492_concur_homework::list::List;
+use cs492_concur_homework::{
+    GrowableArray, MapError, NonblockingMap, OrderedListSet, SplitOrderedList,
+};
+
+#[test]
+fn split_ordered_list() {
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    // The count is checked every other insertion, so the sixth one doubles the buckets.
+    for &key in &[1, 2, 3, 5, 6, 7] {
+        assert_eq!(list.insert(&key, key * 10, &guard), Ok(()));
+    }
+    assert_eq!(list.delete(&2, &guard), Ok(&20));
+    assert_eq!(list.delete(&5, &guard), Ok(&50));
+    // Initializes the buckets 2 and 3 after the resize. Unlike the lookups, the failed insertions
+    // initialize the buckets.
+    assert_eq!(list.insert(&6, 0, &guard), Err(MapError::AlreadyExists(0)));
+    assert_eq!(list.insert(&3, 0, &guard), Err(MapError::AlreadyExists(0)));
+
+    assert_eq!(
+        debug_dump::split_ordered_list(&list, Format::Text, &guard),
+        "SplitOrderedList: 4 buckets, 4 items\n\
+        \x20 sentinel 0\n\
+        \x20 sentinel 2\n\
+        \x20   6 => 60\n\
+        \x20 sentinel 1\n\
+        \x20   1 => 10\n\
+        \x20 sentinel 3\n\
+        \x20   3 => 30\n\
+        \x20   7 => 70\n"
+    );
+
+    let dot = debug_dump::split_ordered_list(&list, Format::Dot, &guard);
+    assert!(dot.starts_with("digraph {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("b3 -> n"));
+    assert_eq!(dot.matches("label=\"sentinel").count(), 4);
+}
+
+#[test]
+fn growable_array() {
+    let array = GrowableArray::<usize>::new();
+    let guard = epoch::pin();
+    for &index in &[0, 1, 2, 3, 7, 1 << 20] {
+        let element = Owned::new(index).into_shared(&guard);
+        array.get(index, &guard).store(element, Ordering::Relaxed);
+    }
+
+    assert_eq!(
+        debug_dump::growable_array(&array, Format::Text, &guard),
+        "GrowableArray: height 3\n\
+        \x20 height 3 from 0\n\
+        \x20   height 2 from 0\n\
+        \x20     leaf from 0: 5 set: 0-3, 7\n\
+        \x20   height 2 from 1048576\n\
+        \x20     leaf from 1048576: 1 set: 1048576\n"
+    );
+
+    for &index in &[0, 1, 2, 3, 7, 1 << 20] {
+        let element = array.get(index, &guard).load(Ordering::Relaxed, &guard);
+        unsafe { guard.defer_destroy(element) };
+    }
+}
+
+#[test]
+fn list() {
+    let list = List::new();
+    let guard = epoch::pin();
+    for &key in &[3, 1, 2] {
+        assert_eq!(list.harris_insert(key, key.to_string(), &guard), Ok(()));
+    }
+
+    assert_eq!(
+        debug_dump::list(&list, Format::Text, &guard),
+        "List\n  1 => \"1\"\n  2 => \"2\"\n  3 => \"3\"\n"
+    );
+    let dot = debug_dump::list(&list, Format::Dot, &guard);
+    assert!(dot.contains("[label=\"1 => \\\"1\\\"\", shape=ellipse];"));
+}
+
+#[test]
+fn ordered_list_set() {
+    let set = OrderedListSet::new();
+    for &key in &[3, 1, 2] {
+        assert_eq!(set.insert(key), Ok(()));
+    }
+
+    assert_eq!(
+        debug_dump::ordered_list_set(&set, Format::Text),
+        "OrderedListSet\n  1\n  2\n  3\n"
+    );
+    assert_eq!(
+        debug_dump::ordered_list_set(&set, Format::Dot)
+            .matches(" -> ")
+            .count(),
+        2
+    );
+}
@@ -0,0 +1,80 @@
+use cs492_concur_homework::testing::DeterministicPool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Runs 8 jobs on a pool with `seed`, and returns the order they ran in.
+fn order(seed: u64) -> Vec<usize> {
+    let pool = DeterministicPool::new(seed);
+    let order = Arc::new(Mutex::new(Vec::new()));
+    for i in 0..8 {
+        let order = order.clone();
+        pool.execute(move || order.lock().unwrap().push(i));
+    }
+    pool.join();
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[test]
+fn seeded_order() {
+    assert_eq!(order(0), order(0));
+    assert!((1..10).any(|seed| order(seed) != order(0)));
+
+    let mut sorted = order(0);
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..8).collect::<Vec<_>>());
+}
+
+#[test]
+fn virtual_time() {
+    let pool = DeterministicPool::new(0);
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    for &ms in &[30, 10, 20] {
+        let fired = fired.clone();
+        let handle = pool.clone();
+        pool.execute_after(Duration::from_millis(ms), move || {
+            fired.lock().unwrap().push((ms, handle.now()));
+        });
+    }
+
+    pool.advance(Duration::from_millis(15));
+    assert_eq!(*fired.lock().unwrap(), [(10, Duration::from_millis(10))]);
+    assert_eq!(pool.now(), Duration::from_millis(15));
+    assert_eq!(pool.pending(), 2);
+
+    pool.join();
+    assert_eq!(
+        *fired.lock().unwrap(),
+        [
+            (10, Duration::from_millis(10)),
+            (20, Duration::from_millis(20)),
+            (30, Duration::from_millis(30)),
+        ]
+    );
+    assert_eq!(pool.now(), Duration::from_millis(30));
+}
+
+/// A job races with the timeout that is queued by a job, which is decided by the virtual time
+/// alone.
+#[test]
+fn timeout() {
+    for &(work, expected) in &[(50, "done"), (150, "timed out")] {
+        let pool = DeterministicPool::new(0);
+        let result = Arc::new(Mutex::new(None));
+
+        let handle = pool.clone();
+        let r = result.clone();
+        pool.execute(move || {
+            handle.execute_after(Duration::from_millis(100), move || {
+                let _ = r.lock().unwrap().get_or_insert("timed out");
+            });
+        });
+        let r = result.clone();
+        pool.execute_after(Duration::from_millis(work), move || {
+            let _ = r.lock().unwrap().get_or_insert("done");
+        });
+
+        pool.join();
+        assert_eq!(*result.lock().unwrap(), Some(expected));
+    }
+}
@@ -0,0 +1,78 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::Exchanger;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let exchanger = Exchanger::new();
+    scope(|s| {
+        let other = s.spawn(|_| exchanger.exchange(String::from("world")));
+        assert_eq!(exchanger.exchange(String::from("hello")), "world");
+        assert_eq!(other.join().unwrap(), "hello");
+    })
+    .unwrap();
+}
+
+#[test]
+fn timeout() {
+    let exchanger = Exchanger::new();
+    assert_eq!(
+        exchanger.exchange_timeout(1, Duration::from_millis(50)),
+        Err(1)
+    );
+
+    // The slot is empty again after the timeout.
+    scope(|s| {
+        let _ = s.spawn(|_| {
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(exchanger.exchange(3), 2);
+        });
+        assert_eq!(
+            exchanger.exchange_timeout(2, Duration::from_secs(10)),
+            Ok(3)
+        );
+    })
+    .unwrap();
+}
+
+#[test]
+fn stress() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1_000;
+
+    // The exchanges form pairs: if `a` receives `b`'s value, `b` receives `a`'s value.
+    let exchanger = Exchanger::new();
+    let received = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let exchanger = &exchanger;
+                s.spawn(move |_| {
+                    let mut received = Vec::new();
+                    for i in 0..STEPS {
+                        if let Ok(v) = exchanger.exchange_timeout((t, i), Duration::from_millis(1))
+                        {
+                            received.push(((t, i), v));
+                        }
+                    }
+                    received
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    let mut sent = received.iter().map(|&(mine, _)| mine).collect::<Vec<_>>();
+    let mut got = received
+        .iter()
+        .map(|&(_, theirs)| theirs)
+        .collect::<Vec<_>>();
+    sent.sort_unstable();
+    got.sort_unstable();
+    assert_eq!(sent, got);
+    assert!(received.iter().all(|&((t, _), (u, _))| t != u));
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "fault-injection")]
+
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::hello_server::ThreadPool;
+use cs492_concur_homework::testing::fault::{explore, yield_point};
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A read-modify-write split by a yield point loses updates under the scheduler.
+#[test]
+#[should_panic]
+fn lost_update() {
+    explore(100, 0, || {
+        let counter = AtomicUsize::new(0);
+        scope(|s| {
+            for _ in 0..2 {
+                let _ = s.spawn(|_| {
+                    let v = counter.load(Ordering::SeqCst);
+                    yield_point();
+                    counter.store(v + 1, Ordering::SeqCst);
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    });
+}
+
+#[test]
+fn split_ordered_list() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 64;
+    explore(50, 0, || {
+        let map = SplitOrderedList::<usize>::new();
+        scope(|s| {
+            for t in 0..THREADS {
+                let map = &map;
+                let _ = s.spawn(move |_| {
+                    let guard = epoch::pin();
+                    for key in (t..KEYS).step_by(THREADS) {
+                        assert!(map.insert(&key, key, &guard).is_ok());
+                    }
+                    for key in (t..KEYS).step_by(THREADS * 2) {
+                        assert_eq!(map.delete(&key, &guard), Ok(&key));
+                    }
+                });
+            }
+        })
+        .unwrap();
+
+        let guard = epoch::pin();
+        for key in 0..KEYS {
+            let deleted = key % (THREADS * 2) < THREADS;
+            assert_eq!(map.lookup(&key, &guard).is_some(), !deleted);
+        }
+    });
+}
+
+#[test]
+fn thread_pool() {
+    const JOBS: usize = 16;
+    explore(50, 0, || {
+        let pool = ThreadPool::new(4);
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..JOBS {
+            let counter = counter.clone();
+            pool.execute(move || {
+                yield_point();
+                let _ = counter.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        pool.join();
+        assert_eq!(counter.load(Ordering::Relaxed), JOBS);
+    });
+}
@@ -1,7 +1,10 @@
-use core::mem::{replace, ManuallyDrop};
+use core::mem::{self, replace, ManuallyDrop};
 use core::sync::atomic::Ordering;
 use crossbeam_epoch::{pin, unprotected, Atomic, Guard, Owned, Shared};
-use cs492_concur_homework::{GrowableArray, NonblockingConcurrentMap, NonblockingMap};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::debug_dump::{self, Format};
+use cs492_concur_homework::growable_array::{MemoryUsage, Reserve};
+use cs492_concur_homework::{GrowableArray, MapError, NonblockingConcurrentMap, NonblockingMap};
 
 mod map;
 
@@ -21,7 +24,7 @@ impl<V> NonblockingMap<u32, V> for ArrayMap<V> {
         unsafe { ptr.as_ref().map(|n| &*n.data) }
     }
 
-    fn insert(&self, key: &u32, value: V, guard: &Guard) -> Result<(), V> {
+    fn insert(&self, key: &u32, value: V, guard: &Guard) -> Result<(), MapError<V>> {
         let slot = self.array.get(*key as usize, guard);
         let node = Owned::new(Node {
             data: ManuallyDrop::new(value),
@@ -32,20 +35,22 @@ impl<V> NonblockingMap<u32, V> for ArrayMap<V> {
                 self.storage.push_node(unsafe { n.into_owned() });
                 Ok(())
             }
-            Err(e) => Err(ManuallyDrop::into_inner(e.new.into_box().data)),
+            Err(e) => Err(MapError::AlreadyExists(ManuallyDrop::into_inner(
+                e.new.into_box().data,
+            ))),
         }
     }
 
-    fn delete<'g>(&self, key: &u32, guard: &'g Guard) -> Result<&'g V, ()> {
+    fn delete<'g>(&self, key: &u32, guard: &'g Guard) -> Result<&'g V, MapError<V>> {
         let slot = self.array.get(*key as usize, guard);
         let curr = slot.load(Ordering::Relaxed, guard);
         // no entry
         if curr.is_null() {
-            return Err(());
+            return Err(MapError::NotFound);
         }
         match slot.compare_and_set(curr, Shared::null(), Ordering::AcqRel, guard) {
             Ok(_) => Ok(unsafe { &*curr.as_ref().unwrap().data }),
-            Err(_) => Err(()), // already removed
+            Err(_) => Err(MapError::NotFound), // already removed
         }
     }
 }
@@ -119,7 +124,7 @@ fn smoke() {
     assert_eq!(list.lookup(&42, &guard), Some(&42));
     assert_eq!(list.lookup(&37, &guard), None);
 
-    assert_eq!(list.delete(&37, &guard), Err(()));
+    assert_eq!(list.delete(&37, &guard), Err(MapError::NotFound));
     assert_eq!(list.lookup(&42, &guard), Some(&42));
     assert_eq!(list.lookup(&37, &guard), None);
 }
@@ -145,3 +150,469 @@ fn log_concurrent() {
     const STEPS: usize = 4096 * 12;
     map::log_concurrent::<u32, NonblockingConcurrentMap<_, _, ArrayMap<usize>>>(THREADS, STEPS);
 }
+
+#[test]
+fn heights() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    // Each index needs one more level of segments than the previous one, so the slots written
+    // before must survive the growth of the tree.
+    let indices = [
+        0,
+        1 << 10,
+        1 << 20,
+        1 << 30,
+        1 << 40,
+        1 << 50,
+        1 << 60,
+        usize::MAX,
+    ];
+    for &index in indices.iter() {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    for &index in indices.iter() {
+        let value = array
+            .get(index, &guard)
+            .swap(Shared::null(), Ordering::Relaxed, &guard);
+        assert_eq!(unsafe { value.into_owned() }.into_box(), Box::new(index));
+    }
+}
+
+#[test]
+fn get_if_exists() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert!(array.get_if_exists(0, &guard).is_none());
+
+    array.get(5, &guard).store(Owned::new(5), Ordering::Relaxed);
+    let slot = array.get_if_exists(5, &guard).unwrap();
+    assert_eq!(
+        unsafe { slot.load(Ordering::Relaxed, &guard).as_ref() },
+        Some(&5)
+    );
+    assert!(array
+        .get_if_exists(6, &guard)
+        .unwrap()
+        .load(Ordering::Relaxed, &guard)
+        .is_null());
+    assert!(array.get_if_exists(1 << 10, &guard).is_none());
+
+    // Growing the tree for `1 << 20` allocates only the segments on its path.
+    array
+        .get(1 << 20, &guard)
+        .store(Owned::new(1 << 20), Ordering::Relaxed);
+    assert!(array.get_if_exists(5, &guard).is_some());
+    assert!(array.get_if_exists(1 << 10, &guard).is_none());
+    assert!(array.get_if_exists(usize::MAX, &guard).is_none());
+
+    for &index in [5, 1 << 20].iter() {
+        let value = array.get_if_exists(index, &guard).unwrap().swap(
+            Shared::null(),
+            Ordering::Relaxed,
+            &guard,
+        );
+        assert_eq!(unsafe { value.into_owned() }.into_box(), Box::new(index));
+    }
+}
+
+#[test]
+fn iter() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.iter(&guard).count(), 0);
+
+    let indices = [0, 5, 1023, 1024, 1 << 20, 1 << 40, usize::MAX];
+    for &index in indices.iter().rev() {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    // The slots that are allocated but null are skipped.
+    assert!(array
+        .get(6, &guard)
+        .load(Ordering::Relaxed, &guard)
+        .is_null());
+
+    let iterated = array
+        .iter(&guard)
+        .map(|(index, slot)| {
+            let value = slot.swap(Shared::null(), Ordering::Relaxed, &guard);
+            assert_eq!(unsafe { value.into_owned() }.into_box(), Box::new(index));
+            index
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(iterated, indices);
+    assert_eq!(array.iter(&guard).count(), 0);
+}
+
+#[test]
+fn drop_max_height() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    // Allocates a segment on every level, and a path of segments for each of the last indices.
+    for index in (0..64).map(|i| usize::MAX - (i << 10)) {
+        let _ = array.get(index, &guard);
+    }
+    let slot = array.get(usize::MAX, &guard);
+    slot.store(Owned::new(usize::MAX), Ordering::Relaxed);
+    let value = slot.swap(Shared::null(), Ordering::Relaxed, &guard);
+    assert_eq!(
+        unsafe { value.into_owned() }.into_box(),
+        Box::new(usize::MAX)
+    );
+    drop(array);
+}
+
+#[test]
+fn compact() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    unsafe { array.compact(&guard) };
+
+    for &index in [5, 1 << 20].iter() {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    let height = |array: &GrowableArray<usize>| {
+        let dump = debug_dump::growable_array(array, Format::Text, &pin());
+        dump.lines().next().unwrap().to_string()
+    };
+    assert_eq!(height(&array), "GrowableArray: height 3");
+
+    // Removes the path to `1 << 20`, and then the levels above the leaf of `5`.
+    let value = array
+        .get(1 << 20, &guard)
+        .swap(Shared::null(), Ordering::Relaxed, &guard);
+    drop(unsafe { value.into_owned() });
+    unsafe { array.compact(&guard) };
+    assert_eq!(height(&array), "GrowableArray: height 1");
+    assert!(array.get_if_exists(1 << 20, &guard).is_none());
+    assert_eq!(array.iter(&guard).map(|(i, _)| i).collect::<Vec<_>>(), [5]);
+
+    let value = array
+        .get(5, &guard)
+        .swap(Shared::null(), Ordering::Relaxed, &guard);
+    assert_eq!(unsafe { value.into_owned() }.into_box(), Box::new(5));
+    unsafe { array.compact(&guard) };
+    assert_eq!(height(&array), "GrowableArray: height 0");
+    assert!(array.get_if_exists(5, &guard).is_none());
+
+    // The array grows again.
+    array.get(7, &guard).store(Owned::new(7), Ordering::Relaxed);
+    let value = array
+        .get(7, &guard)
+        .swap(Shared::null(), Ordering::Relaxed, &guard);
+    assert_eq!(unsafe { value.into_owned() }.into_box(), Box::new(7));
+}
+
+#[test]
+fn introspection() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.height(), 0);
+    assert_eq!(array.capacity(), 0);
+    assert_eq!(array.allocated_segments(), 0);
+
+    let _ = array.get(5, &guard);
+    assert_eq!(array.height(), 1);
+    assert_eq!(array.capacity(), 1 << 10);
+    assert_eq!(array.allocated_segments(), 1);
+
+    // Two levels are added above the leaf of `5`, and a path of two segments below the root.
+    let _ = array.get(1 << 20, &guard);
+    assert_eq!(array.height(), 3);
+    assert_eq!(array.capacity(), 1 << 30);
+    assert_eq!(array.allocated_segments(), 5);
+
+    unsafe { array.compact(&guard) };
+    assert_eq!(array.height(), 0);
+    assert_eq!(array.allocated_segments(), 0);
+
+    // The levels are added above the leaf of index 0, and the path to `usize::MAX` is separate.
+    let _ = array.get(usize::MAX, &guard);
+    assert_eq!(array.height(), 7);
+    assert_eq!(array.capacity(), usize::MAX);
+    assert_eq!(array.allocated_segments(), 13);
+}
+
+#[test]
+fn min_max_index() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.min_index(&guard), None);
+    assert_eq!(array.max_index(&guard), None);
+
+    // The segments of `1 << 20` and `usize::MAX` are allocated, but their slots are null.
+    let _ = array.get(1 << 20, &guard);
+    let _ = array.get(usize::MAX, &guard);
+    assert_eq!(array.min_index(&guard), None);
+    assert_eq!(array.max_index(&guard), None);
+
+    for &index in [5, 3000, 1 << 30].iter() {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    assert_eq!(array.min_index(&guard), Some(5));
+    assert_eq!(array.max_index(&guard), Some(1 << 30));
+
+    for &index in [5, 1 << 30].iter() {
+        let value = array.take(index, &guard);
+        drop(unsafe { value.into_owned() });
+    }
+    assert_eq!(array.min_index(&guard), Some(3000));
+    assert_eq!(array.max_index(&guard), Some(3000));
+    drop(unsafe { array.take(3000, &guard).into_owned() });
+}
+
+#[test]
+fn clear() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    array.clear(&guard);
+
+    for &index in [5, 3000, 1 << 20].iter() {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    let values = array.snapshot(&guard);
+    let usage = array.memory_usage(&guard);
+    array.clear(&guard);
+    assert_eq!(array.iter(&guard).count(), 0);
+    assert_eq!(array.memory_usage(&guard), usage);
+    for (_, value) in values {
+        drop(unsafe { value.into_owned() });
+    }
+
+    array
+        .get(3000, &guard)
+        .store(Owned::new(1), Ordering::Relaxed);
+    assert_eq!(array.min_index(&guard), Some(3000));
+    assert_eq!(array.memory_usage(&guard), usage);
+    drop(unsafe { array.take(3000, &guard).into_owned() });
+}
+
+#[test]
+fn tags() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(
+        GrowableArray::<usize>::TAG_MASK,
+        mem::align_of::<usize>() - 1
+    );
+    assert_eq!(GrowableArray::<u8>::TAG_MASK, 0);
+    assert_eq!(
+        array.get_with_tag(5, Ordering::Relaxed, &guard),
+        (Shared::null(), 0)
+    );
+
+    // The tags of the slots are independent of the height of the tree.
+    let value = Owned::new(5).into_shared(&guard);
+    array.get(5, &guard).store(value, Ordering::Relaxed);
+    assert_eq!(array.set_tag(5, 3, Ordering::Relaxed, &guard), value);
+    let _ = array.get(1 << 20, &guard);
+    assert_eq!(array.get_with_tag(5, Ordering::Relaxed, &guard), (value, 3));
+    assert_eq!(
+        array.set_tag(5, 0, Ordering::Relaxed, &guard),
+        value.with_tag(3)
+    );
+    assert_eq!(array.get_with_tag(5, Ordering::Relaxed, &guard), (value, 0));
+
+    // A tag can be set on a null pointer.
+    assert_eq!(
+        array.set_tag(6, 1, Ordering::Relaxed, &guard),
+        Shared::null()
+    );
+    assert_eq!(
+        array.get_with_tag(6, Ordering::Relaxed, &guard),
+        (Shared::null(), 1)
+    );
+    drop(unsafe { value.into_owned() });
+}
+
+#[test]
+#[should_panic(expected = "does not fit in the tag bits")]
+fn tag_out_of_mask() {
+    let array = GrowableArray::<usize>::new();
+    let _ = array.set_tag(0, mem::align_of::<usize>(), Ordering::Relaxed, &pin());
+}
+
+#[test]
+fn truncate() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    array.truncate(0, &guard);
+
+    let indices = [5, 1000, 3000, 1 << 20, (1 << 20) + 5, 1 << 30];
+    for &index in indices.iter() {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    let values = array.snapshot(&guard);
+    assert_eq!(array.height(), 4);
+
+    // Keeps the leaf of 1000, which also has 5, and removes the others.
+    array.truncate(100, &guard);
+    assert_eq!(array.height(), 1);
+    assert_eq!(array.allocated_segments(), 1);
+    assert_eq!(array.memory_usage(&guard).segments, 1);
+    assert_eq!(
+        array.iter(&guard).map(|(i, _)| i).collect::<Vec<_>>(),
+        [5, 1000]
+    );
+    assert!(array.get_if_exists(3000, &guard).is_none());
+
+    // The removed indices can be set again.
+    array
+        .get(1 << 20, &guard)
+        .store(Owned::new(0), Ordering::Relaxed);
+    assert_eq!(array.max_index(&guard), Some(1 << 20));
+    drop(unsafe { array.take(1 << 20, &guard).into_owned() });
+    for (_, value) in values {
+        drop(unsafe { value.into_owned() });
+    }
+}
+
+/// The threads get the indices on both sides of `MAX_INDEX` while it is truncated, and the slots
+/// up to `MAX_INDEX` are kept.
+#[test]
+fn truncate_concurrent_get() {
+    const THREADS: usize = 4;
+    const MAX_INDEX: usize = 1 << 15;
+    const STEPS: usize = 200;
+
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    for t in 0..THREADS {
+        array
+            .get(t * 1000, &guard)
+            .store(Owned::new(t), Ordering::Relaxed);
+    }
+    let values = array.snapshot(&guard);
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let array = &array;
+            let _ = s.spawn(move |_| {
+                for step in 0..STEPS {
+                    let guard = pin();
+                    let _ = array.get((step << 20) + t, &guard);
+                    let _ = array.get(MAX_INDEX + 1 + step, &guard);
+                    let value = array.get(t * 1000, &guard).load(Ordering::Acquire, &guard);
+                    assert_eq!(unsafe { value.as_ref() }, Some(&t));
+                }
+            });
+        }
+        for _ in 0..STEPS {
+            array.truncate(MAX_INDEX, &pin());
+        }
+    })
+    .unwrap();
+
+    array.truncate(MAX_INDEX, &guard);
+    assert_eq!(array.max_index(&guard), Some((THREADS - 1) * 1000));
+    assert_eq!(array.height(), 2);
+    assert_eq!(
+        array.memory_usage(&guard).segments,
+        array.allocated_segments()
+    );
+    for (_, value) in values {
+        drop(unsafe { value.into_owned() });
+    }
+}
+
+#[test]
+fn memory_usage() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.memory_usage(&guard), MemoryUsage::default());
+
+    let _ = array.get(5, &guard);
+    let _ = array.get(1 << 20, &guard);
+    let usage = array.memory_usage(&guard);
+    assert_eq!(usage.segments, 5);
+    assert_eq!(usage.segments, array.allocated_segments());
+    assert_eq!(usage.bytes, 5 * 1024 * mem::size_of::<usize>());
+    assert_eq!(usage.height, 3);
+
+    unsafe { array.compact(&guard) };
+    assert_eq!(array.memory_usage(&guard), MemoryUsage::default());
+}
+
+#[test]
+fn reserve() {
+    let guard = pin();
+
+    let array = GrowableArray::<usize>::new();
+    array.reserve(1 << 20, Reserve::Path, &guard);
+    assert_eq!(array.height(), 3);
+    assert_eq!(array.allocated_segments(), 5);
+    let _ = array.get(1 << 20, &guard);
+    let _ = array.get(0, &guard);
+    assert_eq!(array.allocated_segments(), 5);
+
+    let array = GrowableArray::<usize>::new();
+    array.reserve(3000, Reserve::All, &guard);
+    assert_eq!(array.height(), 2);
+    assert_eq!(array.allocated_segments(), 4);
+    for index in 0..=3000 {
+        let _ = array.get(index, &guard);
+    }
+    assert_eq!(array.allocated_segments(), 4);
+    assert_eq!(array.iter(&guard).count(), 0);
+}
+
+#[test]
+fn swap_take() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert!(array.take(5, &guard).is_null());
+    assert_eq!(array.allocated_segments(), 0);
+
+    assert!(array
+        .swap(5, Owned::new(5), Ordering::AcqRel, &guard)
+        .is_null());
+    let old = array.swap(5, Owned::new(6), Ordering::AcqRel, &guard);
+    assert_eq!(unsafe { old.into_owned() }.into_box(), Box::new(5));
+    let slot = array.get(5, &guard);
+    assert_eq!(
+        unsafe { slot.load(Ordering::Acquire, &guard).as_ref() },
+        Some(&6)
+    );
+
+    let taken = array.take(5, &guard);
+    assert_eq!(unsafe { taken.into_owned() }.into_box(), Box::new(6));
+    assert!(array.take(5, &guard).is_null());
+    assert!(array.take(1 << 20, &guard).is_null());
+    assert_eq!(array.height(), 1);
+}
+
+#[test]
+fn snapshot() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert!(array.snapshot(&guard).is_empty());
+
+    let indices = [3, 1 << 10, 1 << 20];
+    for &index in indices.iter().rev() {
+        let _ = array.swap(index, Owned::new(index * 2), Ordering::AcqRel, &guard);
+    }
+    let snapshot = array.snapshot(&guard);
+    assert_eq!(
+        snapshot
+            .iter()
+            .map(|&(index, pointer)| (index, unsafe { *pointer.deref() }))
+            .collect::<Vec<_>>(),
+        [(3, 6), (1 << 10, 1 << 11), (1 << 20, 1 << 21)]
+    );
+
+    for &index in indices.iter() {
+        drop(unsafe { array.take(index, &guard).into_owned() });
+    }
+    assert!(array.snapshot(&guard).is_empty());
+}
@@ -0,0 +1,70 @@
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::GrowableAtomicArray;
+
+#[test]
+fn smoke() {
+    let array = GrowableAtomicArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.load(1 << 20, Ordering::Relaxed, &guard), 0);
+    assert_eq!(array.as_array().allocated_segments(), 0);
+
+    for &index in [0, 5, 1 << 20, usize::MAX].iter() {
+        array.store(index, !index, Ordering::Relaxed, &guard);
+    }
+    for &index in [0, 5, 1 << 20, usize::MAX].iter() {
+        assert_eq!(array.load(index, Ordering::Relaxed, &guard), !index);
+    }
+    assert_eq!(array.load(6, Ordering::Relaxed, &guard), 0);
+
+    assert_eq!(array.cas(5, !5, 1, Ordering::Relaxed, &guard), Ok(!5));
+    assert_eq!(array.cas(5, !5, 2, Ordering::Relaxed, &guard), Err(1));
+    assert_eq!(array.cas(7, 0, 3, Ordering::Relaxed, &guard), Ok(0));
+    assert_eq!(array.load(7, Ordering::Relaxed, &guard), 3);
+}
+
+#[test]
+fn small_values() {
+    let array = GrowableAtomicArray::<u16>::new();
+    let guard = pin();
+    array.store(3, u16::MAX, Ordering::Relaxed, &guard);
+    assert_eq!(array.load(3, Ordering::Relaxed, &guard), u16::MAX);
+    assert_eq!(array.as_array().min_index(&guard), Some(3));
+}
+
+#[test]
+fn counters_concurrent() {
+    const THREADS: usize = 8;
+    const COUNTERS: usize = 4096;
+    const STEPS: usize = 4;
+
+    let array = GrowableAtomicArray::<usize>::new();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let array = &array;
+            let _ = s.spawn(move |_| {
+                let guard = pin();
+                for _ in 0..STEPS {
+                    for index in 0..COUNTERS {
+                        let mut current = array.load(index, Ordering::Relaxed, &guard);
+                        while let Err(actual) =
+                            array.cas(index, current, current + 1, Ordering::Relaxed, &guard)
+                        {
+                            current = actual;
+                        }
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    for index in 0..COUNTERS {
+        assert_eq!(
+            array.load(index, Ordering::Relaxed, &guard),
+            THREADS * STEPS
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::GrowablePairArray;
+
+#[test]
+fn smoke() {
+    let array = GrowablePairArray::new();
+    let guard = pin();
+    assert_eq!(array.load(5, &guard), (0, 0));
+
+    array.store(5, (1, 2), &guard);
+    array.store(GrowablePairArray::MAX_INDEX, (3, 4), &guard);
+    assert_eq!(array.load(5, &guard), (1, 2));
+    assert_eq!(array.load(6, &guard), (0, 0));
+    assert_eq!(array.load(GrowablePairArray::MAX_INDEX, &guard), (3, 4));
+
+    assert_eq!(array.cas(5, (1, 2), (1, 3), &guard), Ok((1, 2)));
+    assert_eq!(array.cas(5, (1, 2), (5, 6), &guard), Err((1, 3)));
+    assert_eq!(array.load(5, &guard), (1, 3));
+}
+
+#[test]
+#[should_panic(expected = "is larger than")]
+fn index_out_of_range() {
+    let array = GrowablePairArray::new();
+    let _ = array.load(GrowablePairArray::MAX_INDEX + 1, &pin());
+}
+
+/// The writers keep the words of each pair equal, so a torn read would see them differ.
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const INDICES: usize = 4;
+    const STEPS: usize = 1000;
+
+    let array = GrowablePairArray::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let array = &array;
+            let _ = s.spawn(move |_| {
+                let guard = pin();
+                for step in 0..STEPS {
+                    let index = step % INDICES;
+                    if t % 2 == 0 {
+                        let (first, second) = array.load(index, &guard);
+                        assert_eq!(first, second);
+                        let _ = array.cas(index, (first, second), (first + 1, second + 1), &guard);
+                    } else {
+                        let (first, second) = array.load(index, &guard);
+                        assert_eq!(first, second);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    for index in 0..INDICES {
+        let (first, second) = array.load(index, &guard);
+        assert_eq!(first, second);
+        assert!(first > 0);
+    }
+}
@@ -0,0 +1,147 @@
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{AtomicHamt, Hamt};
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+pub mod map;
+
+/// Hashes the keys to a few values, so that many keys collide.
+#[derive(Default)]
+struct CollidingHasher(u64);
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        self.0 % 3
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(u64::from(*byte));
+        }
+    }
+}
+
+#[test]
+fn smoke() {
+    let empty = Hamt::new();
+    let one = empty.insert(1, "one");
+    let two = one.insert(2, "two");
+    let replaced = two.insert(1, "uno");
+    let removed = replaced.remove(&2);
+
+    // The old versions are not changed.
+    assert!(empty.is_empty());
+    assert_eq!(one.get(&1), Some(&"one"));
+    assert_eq!(one.get(&2), None);
+    assert_eq!((two.len(), two.get(&1)), (2, Some(&"one")));
+    assert_eq!((replaced.len(), replaced.get(&1)), (2, Some(&"uno")));
+    assert_eq!((removed.len(), removed.get(&2)), (1, None));
+    assert_eq!(removed.remove(&3).len(), 1);
+    assert!(removed.remove(&1).is_empty());
+}
+
+fn against_hash_map<S: std::hash::BuildHasher + Clone + Default>() {
+    const KEYS: usize = 2000;
+    let mut map = Hamt::<usize, usize, S>::default();
+    let mut reference = HashMap::new();
+    for i in 0..KEYS * 2 {
+        let key = (i * 7919) % KEYS;
+        if i % 3 == 2 {
+            map = map.remove(&key);
+            let _ = reference.remove(&key);
+        } else {
+            map = map.insert(key, i);
+            let _ = reference.insert(key, i);
+        }
+        assert_eq!(map.len(), reference.len());
+    }
+    for key in 0..KEYS {
+        assert_eq!(map.get(&key), reference.get(&key));
+    }
+    let mut entries = map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    entries.sort_unstable();
+    let mut expected = reference.into_iter().collect::<Vec<_>>();
+    expected.sort_unstable();
+    assert_eq!(entries, expected);
+
+    for key in 0..KEYS {
+        map = map.remove(&key);
+    }
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+}
+
+#[test]
+fn sequential() {
+    against_hash_map::<std::collections::hash_map::RandomState>();
+}
+
+#[test]
+fn collisions() {
+    against_hash_map::<BuildHasherDefault<CollidingHasher>>();
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_concurrent_sequential::<usize, AtomicHamt<usize, usize>>(STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<usize, AtomicHamt<usize, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::log_concurrent::<usize, AtomicHamt<usize, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn consistent_snapshots() {
+    const ACCOUNTS: usize = 16;
+    const TOTAL: usize = 1000 * ACCOUNTS;
+    const TRANSFERS: usize = 2000;
+    let map =
+        AtomicHamt::from((0..ACCOUNTS).fold(Hamt::new(), |map, account| map.insert(account, 1000)));
+
+    scope(|s| {
+        for t in 0..4 {
+            let map = &map;
+            let _ = s.spawn(move |_| {
+                for i in 0..TRANSFERS {
+                    let from = (t + i) % ACCOUNTS;
+                    let to = (t + i * 3 + 1) % ACCOUNTS;
+                    let _ = map.fetch_update(|accounts| {
+                        let balance = *accounts.get(&from).unwrap();
+                        if balance == 0 || from == to {
+                            return None;
+                        }
+                        let accounts = accounts.insert(from, balance - 1);
+                        let balance = *accounts.get(&to).unwrap();
+                        Some(accounts.insert(to, balance + 1))
+                    });
+                }
+            });
+        }
+        for _ in 0..4 {
+            let _ = s.spawn(|_| {
+                for _ in 0..TRANSFERS {
+                    let guard = epoch::pin();
+                    let accounts = map.snapshot_with(&guard);
+                    assert_eq!(accounts.iter().map(|(_, v)| v).sum::<usize>(), TOTAL);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let accounts = map.snapshot();
+    assert_eq!(accounts.len(), ACCOUNTS);
+    assert_eq!(accounts.iter().map(|(_, v)| v).sum::<usize>(), TOTAL);
+}
@@ -0,0 +1,43 @@
+use cs492_concur_homework::Latch;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn count_down() {
+    let latch = Latch::new(2);
+    assert!(!latch.wait_timeout(Duration::from_millis(10)));
+    latch.count_down();
+    assert_eq!(latch.count(), 1);
+    latch.count_down();
+    assert_eq!(latch.count(), 0);
+    latch.wait();
+
+    // Counting down an open latch does nothing.
+    latch.count_down();
+    assert_eq!(latch.count(), 0);
+    assert!(latch.wait_timeout(Duration::from_millis(10)));
+}
+
+#[test]
+fn wait_for_other_threads() {
+    const THREADS: usize = 16;
+    let latch = Arc::new(Latch::new(THREADS));
+
+    let waiters = (0..4)
+        .map(|_| {
+            let latch = latch.clone();
+            thread::spawn(move || latch.wait())
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..THREADS {
+        let latch = latch.clone();
+        thread::spawn(move || latch.count_down());
+    }
+
+    assert!(latch.wait_timeout(Duration::from_secs(3)));
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+#![cfg(feature = "alloc-tracking")]
+
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::growable_array::AllocError;
+use cs492_concur_homework::hello_server::ClockCache;
+use cs492_concur_homework::testing::allocation::{
+    assert_no_leaks, fail_allocations, warm_up, CountingAllocator,
+};
+use cs492_concur_homework::{
+    current_thread_id, growable_array, reclamation, GrowableArray, NonblockingMap, SplitOrderedList,
+};
+use std::sync::Barrier;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+#[should_panic(expected = "leaked 1 allocations")]
+fn detects_leak() {
+    assert_no_leaks(|| std::mem::forget(Box::new(42)));
+}
+
+/// The threads race to add the same levels, so most of them lose the CAS and free their segment.
+#[test]
+fn growable_array_lost_cas() {
+    assert_no_leaks(|| {
+        for _ in 0..500 {
+            let array = GrowableArray::<usize>::new();
+            let barrier = Barrier::new(8);
+            scope(|s| {
+                for t in 0..8 {
+                    let (array, barrier) = (&array, &barrier);
+                    let _ = s.spawn(move |_| {
+                        let guard = epoch::pin();
+                        let _ = barrier.wait();
+                        let _ = array.get((1 << 30) + t, &guard);
+                    });
+                }
+            })
+            .unwrap();
+        }
+    });
+}
+
+/// Every segment that is allocated, including the ones that lose the race, is deallocated.
+#[test]
+fn growable_array_segment_stats() {
+    assert_no_leaks(|| {
+        let before = growable_array::debug_allocation_stats();
+        for _ in 0..100 {
+            let array = GrowableArray::<usize>::new();
+            let barrier = Barrier::new(8);
+            scope(|s| {
+                for t in 0..8 {
+                    let (array, barrier) = (&array, &barrier);
+                    let _ = s.spawn(move |_| {
+                        let guard = epoch::pin();
+                        let _ = barrier.wait();
+                        let _ = array.get((t << 20) + t, &guard);
+                    });
+                }
+            })
+            .unwrap();
+            unsafe { array.compact(&epoch::pin()) };
+        }
+        reclamation::flush();
+
+        let after = growable_array::debug_allocation_stats();
+        assert!(after.allocated > before.allocated);
+        assert_eq!(
+            after.allocated - before.allocated,
+            after.deallocated - before.deallocated
+        );
+    });
+}
+
+/// The segments removed by `compact` are reused by `get` instead of allocating new ones.
+#[test]
+fn growable_array_segment_reuse() {
+    assert_no_leaks(|| {
+        let array = GrowableArray::<usize>::new();
+        let _ = array.get(1 << 20, &epoch::pin());
+        unsafe { array.compact(&epoch::pin()) };
+        reclamation::flush();
+
+        let before = growable_array::debug_allocation_stats();
+        let _ = array.get(1 << 20, &epoch::pin());
+        let after = growable_array::debug_allocation_stats();
+        assert_eq!(after.allocated, before.allocated);
+        assert_eq!(after.reused - before.reused, 5);
+    });
+}
+
+/// The segments that the threads add while the array is truncated are not orphaned.
+#[test]
+fn growable_array_truncate_racing_get() {
+    assert_no_leaks(|| {
+        for _ in 0..20 {
+            let array = GrowableArray::<usize>::new();
+            scope(|s| {
+                for t in 0..4 {
+                    let array = &array;
+                    let _ = s.spawn(move |_| {
+                        for step in 0..100 {
+                            let _ = array.get((step << 20) + (t << 10), &epoch::pin());
+                        }
+                    });
+                }
+                for _ in 0..100 {
+                    array.truncate(1 << 15, &epoch::pin());
+                }
+            })
+            .unwrap();
+        }
+    });
+}
+
+/// `try_get` fails without leaking when a segment can't be allocated, and the array is usable
+/// afterwards.
+#[test]
+fn growable_array_try_get_alloc_failure() {
+    assert_no_leaks(|| {
+        let array = GrowableArray::<usize>::new();
+        let guard = epoch::pin();
+        let _ = array.get(5, &guard);
+
+        let segment = 1024 * std::mem::size_of::<usize>();
+        fail_allocations(segment, || {
+            assert!(array.try_get(6, &guard).is_ok());
+            assert_eq!(array.try_get(1 << 20, &guard).err(), Some(AllocError));
+        });
+        assert!(array.try_get(1 << 20, &guard).is_ok());
+        assert_eq!(
+            array.memory_usage(&guard).segments,
+            array.allocated_segments()
+        );
+    });
+}
+
+/// The deleted nodes are retired to the collector, and freed after the list is dropped.
+#[test]
+fn split_ordered_list_retirement() {
+    // The counter of the list takes the IDs of the threads, which are released to a global pool
+    // when the threads exit. The pool allocates once for the threads, so it is filled beforehand.
+    warm_up(|| {
+        scope(|s| {
+            for _ in 0..4 {
+                let _ = s.spawn(|_| current_thread_id());
+            }
+        })
+        .unwrap();
+    });
+
+    assert_no_leaks(|| {
+        let list = SplitOrderedList::<String>::new();
+        scope(|s| {
+            for t in 0..4 {
+                let list = &list;
+                let _ = s.spawn(move |_| {
+                    for i in 0..1000 {
+                        let key = i * 4 + t;
+                        let guard = epoch::pin();
+                        assert_eq!(list.insert(&key, key.to_string(), &guard), Ok(()));
+                        if i % 2 == 0 {
+                            assert_eq!(list.delete(&key, &guard), Ok(&key.to_string()));
+                        }
+                    }
+                });
+            }
+        })
+        .unwrap();
+    });
+}
+
+/// The evicted entries are freed on eviction, and the rest when the cache is dropped.
+#[test]
+fn clock_cache_eviction() {
+    assert_no_leaks(|| {
+        let cache = ClockCache::new(16);
+        for i in 0..1000 {
+            let _ = cache.insert(i.to_string(), vec![i; 8]);
+            if i % 3 == 0 {
+                let _ = cache.get(&(i / 2).to_string());
+            }
+        }
+        assert_eq!(cache.len(), 16);
+    });
+}
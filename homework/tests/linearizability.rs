@@ -0,0 +1,126 @@
+use cs492_concur_homework::testing::linearizability::{
+    check, NotLinearizable, Operation, Recorder, Specification,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Register that holds a `usize`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Register(usize);
+
+#[derive(Debug)]
+enum RegisterOp {
+    Read,
+    Write(usize),
+}
+
+impl Specification for Register {
+    type Op = RegisterOp;
+    type Ret = Option<usize>;
+
+    fn apply(&mut self, op: &RegisterOp) -> Option<usize> {
+        match op {
+            RegisterOp::Read => Some(self.0),
+            RegisterOp::Write(v) => {
+                self.0 = *v;
+                None
+            }
+        }
+    }
+}
+
+fn op(
+    op: RegisterOp,
+    ret: Option<usize>,
+    invoked: usize,
+    returned: usize,
+) -> Operation<RegisterOp, Option<usize>> {
+    Operation {
+        op,
+        ret,
+        invoked,
+        returned,
+    }
+}
+
+#[test]
+fn sequential() {
+    let history = [
+        op(RegisterOp::Write(1), None, 0, 1),
+        op(RegisterOp::Read, Some(1), 2, 3),
+        op(RegisterOp::Write(2), None, 4, 5),
+        op(RegisterOp::Read, Some(2), 6, 7),
+    ];
+    assert_eq!(check(Register(0), &history), Ok(vec![0, 1, 2, 3]));
+
+    let history = [
+        op(RegisterOp::Write(1), None, 0, 1),
+        op(RegisterOp::Read, Some(0), 2, 3),
+    ];
+    assert_eq!(check(Register(0), &history), Err(NotLinearizable));
+}
+
+#[test]
+fn overlapping() {
+    // The read overlaps with both writes, so it may see either of them.
+    for &v in &[1, 2] {
+        let history = [
+            op(RegisterOp::Write(1), None, 0, 2),
+            op(RegisterOp::Read, Some(v), 1, 5),
+            op(RegisterOp::Write(2), None, 3, 4),
+        ];
+        assert!(check(Register(0), &history).is_ok());
+    }
+
+    // The second read starts after the first one returned 2, so it can't see 1 written before.
+    let history = [
+        op(RegisterOp::Write(1), None, 0, 1),
+        op(RegisterOp::Write(2), None, 2, 7),
+        op(RegisterOp::Read, Some(2), 3, 4),
+        op(RegisterOp::Read, Some(1), 5, 6),
+    ];
+    assert_eq!(check(Register(0), &history), Err(NotLinearizable));
+}
+
+#[test]
+fn atomic_register() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 50;
+    let register = AtomicUsize::new(0);
+    let recorder = Recorder::new();
+
+    let history = crossbeam_utils::thread::scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let register = &register;
+                let recorder = &recorder;
+                s.spawn(move |_| {
+                    (0..STEPS)
+                        .map(|i| {
+                            if i % 2 == 0 {
+                                recorder.record(RegisterOp::Write(t * STEPS + i), |op| match op {
+                                    RegisterOp::Write(v) => {
+                                        register.store(*v, Ordering::SeqCst);
+                                        None
+                                    }
+                                    RegisterOp::Read => unreachable!(),
+                                })
+                            } else {
+                                recorder.record(RegisterOp::Read, |_| {
+                                    Some(register.load(Ordering::SeqCst))
+                                })
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    let order = check(Register(0), &history).unwrap();
+    assert_eq!(order.len(), THREADS * STEPS);
+}
@@ -0,0 +1,67 @@
+use crossbeam_epoch as epoch;
+use cs492_concur_homework::list::List;
+use cs492_concur_homework::NonblockingConcurrentMap;
+
+pub mod map;
+
+#[test]
+pub fn smoke() {
+    let list = List::<usize, usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.harris_insert(37, 37, &guard), Ok(()));
+    assert_eq!(list.harris_michael_insert(42, 42, &guard), Ok(()));
+    assert_eq!(list.harris_insert(37, 0, &guard), Err(0));
+    assert_eq!(list.harris_lookup(&37, &guard), Some(&37));
+    assert_eq!(list.harris_michael_lookup(&42, &guard), Some(&42));
+
+    assert_eq!(list.harris_delete(&37, &guard), Ok(&37));
+    assert_eq!(list.harris_michael_delete(&37, &guard), Err(()));
+    assert_eq!(list.harris_lookup(&37, &guard), None);
+    assert_eq!(list.harris_michael_delete(&42, &guard), Ok(&42));
+    assert_eq!(list.harris_michael_lookup(&42, &guard), None);
+}
+
+#[test]
+fn delete_if() {
+    let list = List::<usize, usize>::new();
+    let guard = epoch::pin();
+    for i in 0..100 {
+        assert_eq!(list.harris_michael_insert(i, i * 10, &guard), Ok(()));
+    }
+
+    assert_eq!(list.delete_if(|k, _| k % 3 == 0, &guard), 34);
+    assert_eq!(list.delete_if(|k, _| k % 3 == 0, &guard), 0);
+    for i in 0..100 {
+        let expected = if i % 3 == 0 { None } else { Some(&(i * 10)) };
+        assert_eq!(list.harris_lookup(&i, &guard), expected);
+    }
+    assert_eq!(list.delete_if(|_, v| *v >= 500, &guard), 33);
+    assert_eq!(list.iter(&guard).count(), 33);
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_concurrent_sequential::<usize, NonblockingConcurrentMap<_, _, List<usize, usize>>>(
+        STEPS,
+    );
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<usize, NonblockingConcurrentMap<_, _, List<usize, usize>>>(
+        THREADS, STEPS,
+    );
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::log_concurrent::<usize, NonblockingConcurrentMap<_, _, List<usize, usize>>>(
+        THREADS, STEPS,
+    );
+}
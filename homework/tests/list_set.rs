@@ -9,6 +9,13 @@ use std::sync::atomic::{
 
 use cs492_concur_homework::OrderedListSet;
 
+pub mod set;
+
+#[test]
+fn conformance() {
+    set::conformance::<OrderedListSet<usize>>();
+}
+
 #[test]
 fn smoke() {
     let set = OrderedListSet::new();
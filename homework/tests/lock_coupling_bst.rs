@@ -0,0 +1,45 @@
+use cs492_concur_homework::{LockCouplingBst, SequentialMap};
+
+pub mod map;
+
+#[test]
+fn lock_coupling_bst_smoke() {
+    let mut bst = map::Sequentialize::<_, _, LockCouplingBst<String, _>>::default();
+    assert!(bst.insert(&String::from("bb"), 37).is_ok());
+    assert!(bst.insert(&String::from("aa"), 42).is_ok());
+    assert!(bst.insert(&String::from("dd"), 11).is_ok());
+    assert!(bst.insert(&String::from("cc"), 29).is_ok());
+    assert!(bst.insert(&String::from("ee"), 73).is_ok());
+    assert!(bst.insert(&String::from("aa"), 0).is_err());
+    assert_eq!(bst.lookup(&String::from("bb")), Some(&37));
+
+    // Deletes a node with two children, which is replaced with its successor.
+    assert_eq!(bst.delete(&String::from("bb")), Ok(37));
+    assert_eq!(bst.delete(&String::from("bb")), Err(()));
+    for (key, value) in &[("aa", 42), ("cc", 29), ("dd", 11), ("ee", 73)] {
+        assert_eq!(bst.lookup(&String::from(*key)), Some(value));
+    }
+    assert_eq!(bst.delete(&String::from("cc")), Ok(29));
+    assert_eq!(bst.delete(&String::from("aa")), Ok(42));
+    assert_eq!(bst.lookup(&String::from("dd")), Some(&11));
+}
+
+#[test]
+fn lock_coupling_bst_stress() {
+    const STEPS: usize = 4096;
+    map::stress_concurrent_sequential::<String, LockCouplingBst<String, usize>>(STEPS);
+}
+
+#[test]
+fn lock_coupling_bst_stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<String, LockCouplingBst<String, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn lock_coupling_bst_log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::log_concurrent::<String, LockCouplingBst<String, usize>>(THREADS, STEPS);
+}
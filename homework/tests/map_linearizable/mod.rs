@@ -0,0 +1,118 @@
+//! Linearizability check of the concurrent maps, separate from `map` since only a few maps use it.
+
+use core::fmt;
+use core::hash::Hash;
+use cs492_concur_homework::testing::linearizability::{self, Recorder, Specification};
+use cs492_concur_homework::{ConcurrentMap, RandGen};
+use std::collections::HashMap;
+
+use rand::prelude::*;
+
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread;
+
+/// The value of a key in the map.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct KeySpec(Option<usize>);
+
+/// An operation on a key, and its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyOp {
+    Lookup,
+    Insert(usize),
+    Delete,
+}
+
+impl Specification for KeySpec {
+    type Op = KeyOp;
+    /// The value looked up or deleted, and whether the insertion or the deletion succeeded.
+    type Ret = (Option<usize>, bool);
+
+    fn apply(&mut self, op: &KeyOp) -> Self::Ret {
+        match *op {
+            KeyOp::Lookup => (self.0, self.0.is_some()),
+            KeyOp::Insert(value) => {
+                if self.0.is_some() {
+                    (None, false)
+                } else {
+                    self.0 = Some(value);
+                    (None, true)
+                }
+            }
+            KeyOp::Delete => {
+                let value = self.0.take();
+                (value, value.is_some())
+            }
+        }
+    }
+}
+
+/// Runs random operations on a few keys concurrently, and checks that the history of each key is
+/// linearizable.
+pub fn linearizable_concurrent<
+    K: fmt::Debug + Clone + Eq + Hash + Send + Sync + RandGen,
+    M: Default + Sync + ConcurrentMap<K, usize>,
+>(
+    threads: usize,
+    steps: usize,
+) {
+    const KEYS: usize = 4;
+    const ROUNDS: usize = 16;
+
+    let mut rng = thread_rng();
+    for _ in 0..ROUNDS {
+        let keys = (0..KEYS).map(|_| K::rand_gen(&mut rng)).collect::<Vec<_>>();
+        let map = M::default();
+        let recorder = Recorder::new();
+
+        let logs = thread::scope(|s| {
+            let handles = (0..threads)
+                .map(|_| {
+                    s.spawn(|_| {
+                        let mut rng = thread_rng();
+                        let mut logs = Vec::new();
+                        for _ in 0..steps {
+                            let key = keys.choose(&mut rng).unwrap().clone();
+                            let op = match rng.gen_range(0, 3) {
+                                0 => KeyOp::Lookup,
+                                1 => KeyOp::Insert(rng.gen()),
+                                _ => KeyOp::Delete,
+                            };
+                            let record = recorder.record(op, |op| match *op {
+                                KeyOp::Lookup => {
+                                    let value = map.lookup(&key, &pin(), |v| v.copied());
+                                    (value, value.is_some())
+                                }
+                                KeyOp::Insert(value) => {
+                                    (None, map.insert(&key, value, &pin()).is_ok())
+                                }
+                                KeyOp::Delete => {
+                                    let value = map.delete(&key, &pin()).ok();
+                                    (value, value.is_some())
+                                }
+                            });
+                            logs.push((key, record));
+                        }
+                        logs
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+
+        // The keys are independent, so their histories are checked separately.
+        let mut per_key_logs = HashMap::<K, Vec<_>>::new();
+        for (key, record) in logs {
+            per_key_logs.entry(key).or_default().push(record);
+        }
+        for (key, history) in &per_key_logs {
+            if let Err(e) = linearizability::check(KeySpec(None), history) {
+                panic!("key {:?}: {}: {:#?}", key, e, history);
+            }
+        }
+    }
+}
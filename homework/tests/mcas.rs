@@ -0,0 +1,199 @@
+use crossbeam_epoch::{self as epoch, Atomic, Owned, Shared};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::mcas::{self, Mcas};
+use rand::seq::index::sample;
+use rand::{thread_rng, Rng};
+
+fn cells(values: &[usize]) -> Vec<Atomic<usize>> {
+    values.iter().map(|&v| Atomic::new(v)).collect()
+}
+
+fn values(cells: &[Atomic<usize>]) -> Vec<usize> {
+    let guard = epoch::pin();
+    cells
+        .iter()
+        .map(|c| unsafe { *mcas::load(c, &guard).deref() })
+        .collect()
+}
+
+fn free(cells: Vec<Atomic<usize>>) {
+    for cell in cells {
+        drop(unsafe { cell.into_owned() });
+    }
+}
+
+#[test]
+fn smoke() {
+    let cells = cells(&[0, 1, 2]);
+    let guard = epoch::pin();
+    let old = cells
+        .iter()
+        .map(|c| mcas::load(c, &guard))
+        .collect::<Vec<_>>();
+
+    // Fails if one of the cells is not the expected value, and changes nothing.
+    let mut op = Mcas::new();
+    let _ = op
+        .add(&cells[0], old[0], Owned::new(10).into_shared(&guard))
+        .add(&cells[1], old[0], Owned::new(11).into_shared(&guard));
+    assert!(!op.execute(&guard));
+    assert_eq!(values(&cells), vec![0, 1, 2]);
+
+    let mut op = Mcas::new();
+    let _ = op
+        .add(&cells[2], old[2], Owned::new(12).into_shared(&guard))
+        .add(&cells[0], old[0], Owned::new(10).into_shared(&guard));
+    assert!(op.execute(&guard));
+    assert_eq!(values(&cells), vec![10, 1, 12]);
+
+    // The empty operation trivially succeeds.
+    assert!(Mcas::<usize>::new().execute(&guard));
+
+    unsafe {
+        guard.defer_destroy(old[0]);
+        guard.defer_destroy(old[2]);
+    }
+    drop(guard);
+    free(cells);
+}
+
+#[test]
+#[should_panic(expected = "a cell is added twice")]
+fn duplicate_cell() {
+    let cell = Atomic::new(0usize);
+    let guard = epoch::pin();
+    let old = mcas::load(&cell, &guard);
+    let mut op = Mcas::new();
+    let _ = op.add(&cell, old, old).add(&cell, old, old);
+    let _ = op.execute(&guard);
+}
+
+#[test]
+fn stress_counters() {
+    const THREADS: usize = 8;
+    const CELLS: usize = 16;
+    const STEPS: usize = 10_000;
+
+    // Each successful operation increments a random subset of the counters. So the sum of the
+    // counters is the sum of the subset sizes of the successful operations.
+    let cells = cells(&[0; CELLS]);
+    let total = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|_| {
+                s.spawn(|_| {
+                    let mut rng = thread_rng();
+                    let mut total = 0;
+                    for _ in 0..STEPS {
+                        let k = rng.gen_range(1, 5);
+                        let guard = epoch::pin();
+                        let mut op = Mcas::new();
+                        let mut olds = Vec::new();
+                        let mut news = Vec::new();
+                        for i in sample(&mut rng, CELLS, k).into_iter() {
+                            let old = mcas::load(&cells[i], &guard);
+                            let new = Owned::new(unsafe { old.deref() } + 1).into_shared(&guard);
+                            let _ = op.add(&cells[i], old, new);
+                            olds.push(old);
+                            news.push(new);
+                        }
+
+                        let garbage = if op.execute(&guard) {
+                            total += k;
+                            olds
+                        } else {
+                            news
+                        };
+                        for value in garbage {
+                            unsafe { guard.defer_destroy(value) };
+                        }
+                    }
+                    total
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum::<usize>()
+    })
+    .unwrap();
+
+    assert_eq!(values(&cells).into_iter().sum::<usize>(), total);
+    free(cells);
+}
+
+/// Reads a consistent snapshot of the cells: an operation that replaces every value with itself
+/// succeeds only if no cell has changed since it was read.
+fn snapshot<'g>(cells: &'g [Atomic<usize>], guard: &'g epoch::Guard) -> Option<Vec<usize>> {
+    let current = cells
+        .iter()
+        .map(|c| mcas::load(c, guard))
+        .collect::<Vec<Shared<'_, usize>>>();
+    let mut op = Mcas::new();
+    for (cell, value) in cells.iter().zip(current.iter()) {
+        let _ = op.add(cell, *value, *value);
+    }
+    if op.execute(guard) {
+        Some(current.iter().map(|v| unsafe { *v.deref() }).collect())
+    } else {
+        None
+    }
+}
+
+#[test]
+fn stress_transfers() {
+    const THREADS: usize = 6;
+    const READERS: usize = 2;
+    const CELLS: usize = 8;
+    const STEPS: usize = 10_000;
+    const BALANCE: usize = 1_000;
+
+    // Transfers between random cells keep the sum, which every consistent snapshot observes.
+    let cells = cells(&[BALANCE; CELLS]);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|_| {
+                let mut rng = thread_rng();
+                for _ in 0..STEPS {
+                    let guard = epoch::pin();
+                    let pair = sample(&mut rng, CELLS, 2).into_vec();
+                    let (from, to) = (&cells[pair[0]], &cells[pair[1]]);
+                    let (old_from, old_to) = (mcas::load(from, &guard), mcas::load(to, &guard));
+                    let (balance_from, balance_to) =
+                        unsafe { (*old_from.deref(), *old_to.deref()) };
+                    let amount = rng.gen_range(0, balance_from + 1);
+                    let new_from = Owned::new(balance_from - amount).into_shared(&guard);
+                    let new_to = Owned::new(balance_to + amount).into_shared(&guard);
+
+                    let mut op = Mcas::new();
+                    let _ = op.add(from, old_from, new_from).add(to, old_to, new_to);
+                    let garbage = if op.execute(&guard) {
+                        [old_from, old_to]
+                    } else {
+                        [new_from, new_to]
+                    };
+                    for value in garbage.iter() {
+                        unsafe { guard.defer_destroy(*value) };
+                    }
+                }
+            });
+        }
+        for _ in 0..READERS {
+            let _ = s.spawn(|_| {
+                let mut consistent = 0;
+                for _ in 0..STEPS {
+                    let guard = epoch::pin();
+                    if let Some(values) = snapshot(&cells, &guard) {
+                        assert_eq!(values.into_iter().sum::<usize>(), BALANCE * CELLS);
+                        consistent += 1;
+                    }
+                }
+                assert!(consistent > 0);
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(values(&cells).into_iter().sum::<usize>(), BALANCE * CELLS);
+    free(cells);
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "metrics")]
+
+use crossbeam_epoch as epoch;
+use cs492_concur_homework::hello_server::ThreadPool;
+use cs492_concur_homework::metrics::{self, MemoryRecorder};
+use cs492_concur_homework::{ExponentialBackoff, GrowableArray, NonblockingMap, SplitOrderedList};
+use lazy_static::lazy_static;
+use std::sync::Once;
+
+lazy_static! {
+    static ref RECORDER: MemoryRecorder = MemoryRecorder::new();
+}
+
+/// Installs `RECORDER`. The tests share it, so they only check the metrics of their own structure.
+fn recorder() -> &'static MemoryRecorder {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| metrics::set_recorder(&*RECORDER).unwrap());
+    &RECORDER
+}
+
+#[test]
+fn set_recorder_once() {
+    let _ = recorder();
+    assert!(metrics::set_recorder(&metrics::NoopRecorder).is_err());
+}
+
+#[test]
+fn backoff() {
+    let recorder = recorder();
+    let total = || {
+        recorder.counter("backoff.spin")
+            + recorder.counter("backoff.yield")
+            + recorder.counter("backoff.park")
+    };
+    let before = total();
+
+    let backoff = ExponentialBackoff::new();
+    for _ in 0..15 {
+        backoff.backoff();
+    }
+
+    assert!(recorder.counter("backoff.park") >= 5);
+    assert!(total() >= before + 15);
+}
+
+#[test]
+fn growable_array() {
+    let recorder = recorder();
+    let before = recorder.counter("growable_array.grow");
+
+    let array = GrowableArray::<usize>::new();
+    let guard = epoch::pin();
+    let _ = array.get(1 << 25, &guard);
+
+    assert!(recorder.counter("growable_array.grow") >= before + 3);
+    assert!(recorder.gauge("growable_array.height").unwrap() >= 3);
+}
+
+#[test]
+fn split_ordered_list() {
+    let recorder = recorder();
+    let before = recorder.counter("split_ordered_list.resize");
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for key in 0..64 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    assert!(recorder.counter("split_ordered_list.resize") >= before + 4);
+    assert!(recorder.gauge("split_ordered_list.buckets").unwrap() >= 32);
+}
+
+#[test]
+fn thread_pool() {
+    let recorder = recorder();
+    let pool = ThreadPool::new(2);
+    for _ in 0..16 {
+        pool.execute(|| {});
+    }
+    pool.join();
+
+    let depth = recorder.histogram("thread_pool.queue_depth").unwrap();
+    assert!(depth.count >= 16);
+    assert!(depth.min >= 1 && depth.max <= 16);
+    assert_eq!(recorder.gauge("thread_pool.jobs"), Some(0));
+}
@@ -0,0 +1,231 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::channel::mpsc::{
+    channel, RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError,
+};
+use cs492_concur_homework::testing::linearizability::{self, Recorder, Specification};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let (tx, rx) = channel(2);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    tx.send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Ok(2));
+
+    drop(tx);
+    assert_eq!(rx.recv(), Err(RecvError));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn many_blocks() {
+    let (tx, rx) = channel(1000);
+    for i in 0..1000 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+    assert_eq!(rx.iter().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+}
+
+#[test]
+fn receiver_dropped() {
+    let (tx, rx) = channel(1);
+    tx.send(1).unwrap();
+    drop(rx);
+    assert_eq!(tx.send(2), Err(SendError(2)));
+    assert_eq!(tx.try_send(3), Err(TrySendError::Disconnected(3)));
+}
+
+#[test]
+fn recv_timeout() {
+    let (tx, rx) = channel(1);
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        tx.send(42).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(42));
+    sender.join().unwrap();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(3)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[test]
+fn send_blocks_when_full() {
+    let (tx, rx) = channel(1);
+    let sent = AtomicBool::new(false);
+    tx.send(0).unwrap();
+
+    scope(|s| {
+        s.spawn(|_| {
+            tx.send(1).unwrap();
+            sent.store(true, Ordering::SeqCst);
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(!sent.load(Ordering::SeqCst));
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(rx.recv(), Ok(1));
+    })
+    .unwrap();
+}
+
+#[test]
+fn blocked_sender_wakes_on_drop() {
+    let (tx, rx) = channel(1);
+    tx.send(0).unwrap();
+    let sender = thread::spawn(move || tx.send(1));
+    thread::sleep(Duration::from_millis(50));
+    drop(rx);
+    assert_eq!(sender.join().unwrap(), Err(SendError(1)));
+}
+
+#[test]
+fn drops_unreceived_messages() {
+    let (tx, rx) = channel(100);
+    let value = Arc::new(());
+    for _ in 0..100 {
+        tx.send(value.clone()).unwrap();
+    }
+    drop(rx.recv().unwrap());
+    drop(tx);
+    drop(rx);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+fn stress() {
+    const SENDERS: usize = 8;
+    const MESSAGES: usize = 10_000;
+
+    let (tx, rx) = channel(16);
+    scope(|s| {
+        for _ in 0..SENDERS {
+            let tx = tx.clone();
+            s.spawn(move |_| {
+                for i in 0..MESSAGES {
+                    tx.send(i).unwrap();
+                }
+            });
+        }
+        drop(tx);
+
+        let mut count = 0;
+        let mut sum = 0;
+        for i in &rx {
+            count += 1;
+            sum += i;
+        }
+        assert_eq!(count, SENDERS * MESSAGES);
+        assert_eq!(sum, SENDERS * MESSAGES * (MESSAGES - 1) / 2);
+    })
+    .unwrap();
+}
+
+/// FIFO queue of messages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+struct QueueSpec(VecDeque<usize>);
+
+#[derive(Debug)]
+enum QueueOp {
+    Send(usize),
+    Recv,
+}
+
+impl Specification for QueueSpec {
+    type Op = QueueOp;
+    type Ret = Option<usize>;
+
+    fn apply(&mut self, op: &QueueOp) -> Option<usize> {
+        match op {
+            QueueOp::Send(value) => {
+                self.0.push_back(*value);
+                None
+            }
+            QueueOp::Recv => self.0.pop_front(),
+        }
+    }
+}
+
+#[test]
+fn linearizable() {
+    const SENDERS: usize = 3;
+    const MESSAGES: usize = 20;
+
+    for _ in 0..16 {
+        // The channel is small so that the senders block and install new blocks often.
+        let (tx, rx) = channel(4);
+        let recorder = Recorder::new();
+        let history = scope(|s| {
+            let handles = (0..SENDERS)
+                .map(|t| {
+                    let tx = tx.clone();
+                    let recorder = &recorder;
+                    s.spawn(move |_| {
+                        (0..MESSAGES)
+                            .map(|i| {
+                                recorder.record(QueueOp::Send(t * MESSAGES + i), |op| match op {
+                                    QueueOp::Send(value) => {
+                                        tx.send(*value).unwrap();
+                                        None
+                                    }
+                                    QueueOp::Recv => unreachable!(),
+                                })
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut history = (0..SENDERS * MESSAGES)
+                .map(|_| recorder.record(QueueOp::Recv, |_| rx.recv().ok()))
+                .collect::<Vec<_>>();
+            for handle in handles {
+                history.extend(handle.join().unwrap());
+            }
+            history
+        })
+        .unwrap();
+
+        assert!(linearizability::check(QueueSpec::default(), &history).is_ok());
+    }
+}
+
+/// The receiver that wakes up a blocked sender after waiting for a message must not deadlock on
+/// the lock it holds while waiting.
+#[test]
+fn recv_with_blocked_sender() {
+    // A deadlock would hang the test, so the threads report to a watchdog.
+    let (done_sender, done_receiver) = std::sync::mpsc::channel();
+    let _ = thread::spawn(move || {
+        let (tx, rx) = channel(1);
+        scope(|s| {
+            let receiver = s.spawn(move |_| (rx.recv(), rx.recv()));
+
+            // The receiver is waiting, and the second message blocks the sender before the
+            // receiver wakes up for the first.
+            thread::sleep(Duration::from_millis(100));
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+            assert_eq!(receiver.join().unwrap(), (Ok(1), Ok(2)));
+        })
+        .unwrap();
+        done_sender.send(()).unwrap();
+    });
+    done_receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("the channel deadlocked");
+}
@@ -0,0 +1,53 @@
+use crossbeam_epoch as epoch;
+use cs492_concur_homework::{NmTree, NonblockingConcurrentMap};
+
+pub mod map;
+
+#[test]
+pub fn smoke() {
+    let tree = NmTree::<usize, usize>::new();
+    let guard = epoch::pin();
+
+    for key in &[37, 42, 11, 73, 29] {
+        assert_eq!(tree.insert(*key, *key, &guard), Ok(()));
+    }
+    assert_eq!(tree.insert(37, 0, &guard), Err(0));
+    assert_eq!(tree.lookup(&37, &guard), Some(&37));
+    assert_eq!(tree.lookup(&38, &guard), None);
+
+    assert_eq!(tree.delete(&37, &guard), Ok(&37));
+    assert_eq!(tree.delete(&37, &guard), Err(()));
+    assert_eq!(tree.lookup(&37, &guard), None);
+    for key in &[42, 11, 73, 29] {
+        assert_eq!(tree.lookup(key, &guard), Some(key));
+    }
+    assert_eq!(tree.delete(&11, &guard), Ok(&11));
+    assert_eq!(tree.insert(37, 37, &guard), Ok(()));
+    assert_eq!(tree.lookup(&37, &guard), Some(&37));
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_concurrent_sequential::<usize, NonblockingConcurrentMap<_, _, NmTree<usize, usize>>>(
+        STEPS,
+    );
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<usize, NonblockingConcurrentMap<_, _, NmTree<usize, usize>>>(
+        THREADS, STEPS,
+    );
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::log_concurrent::<usize, NonblockingConcurrentMap<_, _, NmTree<usize, usize>>>(
+        THREADS, STEPS,
+    );
+}
@@ -0,0 +1,106 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{Latch, Lazy, OnceCell};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const THREADS: usize = 16;
+
+#[test]
+fn smoke() {
+    let cell = OnceCell::new();
+    assert_eq!(cell.get(), None);
+    assert_eq!(cell.set(1), Ok(()));
+    assert_eq!(cell.set(2), Err(2));
+    assert_eq!(cell.get_or_init(|| 3), &1);
+    assert_eq!(cell.into_inner(), Some(1));
+}
+
+#[test]
+fn concurrent_init() {
+    for _ in 0..16 {
+        let cell = OnceCell::new();
+        let start = Latch::new(THREADS);
+        let num_init = AtomicUsize::new(0);
+        scope(|s| {
+            for i in 0..THREADS {
+                let cell = &cell;
+                let start = &start;
+                let num_init = &num_init;
+                s.spawn(move |_| {
+                    start.arrive_and_wait();
+                    let value = cell.get_or_init(|| {
+                        num_init.fetch_add(1, Ordering::Relaxed);
+                        // Make the other threads wait for the initialization.
+                        thread::sleep(Duration::from_millis(10));
+                        i
+                    });
+                    assert_eq!(cell.get(), Some(value));
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(num_init.load(Ordering::Relaxed), 1);
+    }
+}
+
+#[test]
+fn panic_during_init() {
+    let cell = OnceCell::new();
+    let result = catch_unwind(AssertUnwindSafe(|| cell.get_or_init(|| panic!())));
+    assert!(result.is_err());
+    assert_eq!(cell.get(), None);
+    assert_eq!(cell.get_or_init(|| 42), &42);
+}
+
+#[test]
+fn panic_during_concurrent_init() {
+    let cell = OnceCell::new();
+    let start = Latch::new(THREADS);
+    let num_init = AtomicUsize::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                start.arrive_and_wait();
+                // The first initializer panics, and one of the waiters takes over.
+                let result = catch_unwind(AssertUnwindSafe(|| {
+                    *cell.get_or_init(|| {
+                        let n = num_init.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(Duration::from_millis(10));
+                        if n == 0 {
+                            panic!("first initializer fails");
+                        }
+                        n
+                    })
+                }));
+                if let Ok(value) = result {
+                    assert_eq!(value, 1);
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(num_init.load(Ordering::Relaxed), 2);
+    assert_eq!(cell.get(), Some(&1));
+}
+
+// Loom's atomics can't be created in statics.
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn lazy() {
+    static NUM_INIT: AtomicUsize = AtomicUsize::new(0);
+    static LAZY: Lazy<Vec<usize>> = Lazy::new(|| {
+        NUM_INIT.fetch_add(1, Ordering::Relaxed);
+        vec![1, 2, 3]
+    });
+
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| assert_eq!(LAZY.len(), 3));
+        }
+    })
+    .unwrap();
+    assert_eq!(*LAZY, vec![1, 2, 3]);
+    assert_eq!(NUM_INIT.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,68 @@
+use cs492_concur_homework::channel::oneshot::{channel, RecvError, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let (tx, mut rx) = channel();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    tx.send(42).unwrap();
+    assert_eq!(rx.try_recv(), Ok(42));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+}
+
+#[test]
+fn recv_blocks() {
+    let (tx, rx) = channel();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        tx.send(String::from("hello")).unwrap();
+    });
+    assert_eq!(rx.recv().unwrap(), "hello");
+    sender.join().unwrap();
+}
+
+#[test]
+fn sender_dropped() {
+    let (tx, rx) = channel::<i32>();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(tx);
+    });
+    assert_eq!(rx.recv(), Err(RecvError));
+    sender.join().unwrap();
+}
+
+#[test]
+fn receiver_dropped() {
+    let (tx, rx) = channel();
+    assert!(!tx.is_closed());
+    drop(rx);
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(42), Err(42));
+}
+
+#[test]
+fn recv_timeout() {
+    let (tx, mut rx) = channel();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    // The receiver can wait again from another thread after a timeout.
+    let receiver = thread::spawn(move || rx.recv_timeout(Duration::from_secs(3)));
+    thread::sleep(Duration::from_millis(50));
+    tx.send(42).unwrap();
+    assert_eq!(receiver.join().unwrap(), Ok(42));
+}
+
+#[test]
+fn stress() {
+    for i in 0..10_000 {
+        let (tx, rx) = channel();
+        let sender = thread::spawn(move || tx.send(i).unwrap());
+        assert_eq!(rx.recv(), Ok(i));
+        sender.join().unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::Phaser;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const THREADS: usize = 8;
+const PHASES: usize = 256;
+
+#[test]
+fn smoke() {
+    let phaser = Phaser::new(2);
+    assert_eq!(phaser.phase(), 0);
+    assert_eq!(phaser.arrive(), 0);
+    assert_eq!(phaser.arrived_parties(), 1);
+    assert_eq!(
+        phaser.await_advance_timeout(0, Duration::from_millis(10)),
+        None
+    );
+
+    // The last arrival ends the phase.
+    assert_eq!(phaser.arrive(), 0);
+    assert_eq!(phaser.phase(), 1);
+    assert_eq!(phaser.arrived_parties(), 0);
+    assert_eq!(phaser.await_advance(0), 1);
+
+    // A registration in the middle of a phase delays its end.
+    assert_eq!(phaser.arrive(), 1);
+    assert_eq!(phaser.register(), 1);
+    assert_eq!(phaser.arrive(), 1);
+    assert_eq!(phaser.phase(), 1);
+    assert_eq!(phaser.arrive_and_deregister(), 1);
+    assert_eq!(phaser.phase(), 2);
+    assert_eq!(phaser.registered_parties(), 2);
+}
+
+#[test]
+fn deregister_last() {
+    let phaser = Phaser::new(1);
+    assert_eq!(phaser.arrive_and_deregister(), 0);
+    assert_eq!(phaser.phase(), 1);
+    assert_eq!(phaser.registered_parties(), 0);
+
+    // An empty phaser can be reused.
+    assert_eq!(phaser.register(), 1);
+    assert_eq!(phaser.arrive_and_await_advance(), 2);
+}
+
+#[test]
+#[should_panic(expected = "more arrivals than the registered parties")]
+fn arrive_unregistered() {
+    let _ = Phaser::default().arrive();
+}
+
+#[test]
+fn phases() {
+    let phaser = Phaser::new(THREADS);
+    let arrived = AtomicUsize::new(0);
+
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|_| {
+                for phase in 0..PHASES {
+                    let _ = arrived.fetch_add(1, Ordering::Relaxed);
+                    assert_eq!(phaser.arrive_and_await_advance(), phase + 1);
+                    // Every thread has arrived in this phase, and no thread can arrive in the
+                    // phase after the next before this thread.
+                    let n = arrived.load(Ordering::Relaxed);
+                    assert!(n >= (phase + 1) * THREADS && n <= (phase + 2) * THREADS);
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(phaser.phase(), PHASES);
+}
+
+#[test]
+fn dynamic_parties() {
+    // Thread `t` takes part in the first `t + 1` phases only, so the number of parties shrinks
+    // from phase to phase. A phase ends only after all of its parties have done their work.
+    let phaser = Phaser::new(THREADS);
+    let work = (0..THREADS)
+        .map(|_| AtomicUsize::new(0))
+        .collect::<Vec<_>>();
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let (phaser, work) = (&phaser, &work);
+            let _ = s.spawn(move |_| {
+                for (phase, work) in work.iter().enumerate().take(t + 1) {
+                    let _ = work.fetch_add(1, Ordering::Relaxed);
+                    if phase == t {
+                        let _ = phaser.arrive_and_deregister();
+                    } else {
+                        let _ = phaser.arrive_and_await_advance();
+                        assert_eq!(work.load(Ordering::Relaxed), THREADS - phase);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(phaser.phase(), THREADS);
+    assert_eq!(phaser.registered_parties(), 0);
+}
@@ -0,0 +1,100 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{Pool, PoolBox};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let pool = Pool::new(Vec::<usize>::new);
+    let addr = {
+        let mut buf = pool.get();
+        buf.push(1);
+        &*buf as *const _ as usize
+    };
+
+    // The returned object is reused as is.
+    let buf = pool.get();
+    assert_eq!(&*buf as *const _ as usize, addr);
+    assert_eq!(*buf, vec![1]);
+
+    // A new object is created if the pool is empty.
+    let other = pool.get();
+    assert!(other.is_empty());
+
+    let taken = pool.take();
+    pool.put(taken);
+}
+
+#[test]
+fn preallocation() {
+    const CAPACITY: usize = 4;
+
+    let pool = Pool::with_capacity(CAPACITY, || 0);
+    let mut guards = (0..CAPACITY).map(|_| pool.get()).collect::<Vec<_>>();
+    for (i, guard) in guards.iter_mut().enumerate() {
+        **guard = i + 1;
+    }
+    drop(guards);
+
+    let values = (0..CAPACITY).map(|_| *pool.take()).collect::<HashSet<_>>();
+    assert_eq!(values, (1..=CAPACITY).collect());
+}
+
+#[test]
+fn concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 10_000;
+
+    let pool = Arc::new(Pool::new(|| 0usize));
+    scope(|s| {
+        for _ in 0..THREADS {
+            let pool = pool.clone();
+            s.spawn(move |_| {
+                for _ in 0..STEPS {
+                    // Nobody else touches the object while we hold it.
+                    let mut value = pool.clone().get_owned();
+                    *value += 1;
+                    let before = *value;
+                    *value += 1;
+                    assert_eq!(*value, before + 1);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // At most one object per thread was ever created.
+    let mut total = 0;
+    let mut count = 0;
+    while count < THREADS {
+        let value = *pool.take();
+        if value == 0 {
+            break;
+        }
+        total += value;
+        count += 1;
+    }
+    assert_eq!(total, THREADS * STEPS * 2);
+}
+
+#[test]
+fn put_reuses_taken() {
+    let pool = Pool::new(|| 0usize);
+    let mut taken = pool.take();
+    *taken = 1;
+    let addr = &*taken as *const _ as usize;
+    pool.put(taken);
+
+    // The object is pushed as is, and no other object is created.
+    let taken = pool.take();
+    assert_eq!(&*taken as *const _ as usize, addr);
+    assert_eq!(*taken, 1);
+    assert_eq!(pool.created(), 1);
+
+    // The object survives a round trip through a raw pointer.
+    let raw = taken.into_raw();
+    let taken = unsafe { PoolBox::from_raw(raw) };
+    pool.put(taken);
+    assert_eq!(*pool.get(), 1);
+    assert_eq!(pool.created(), 1);
+}
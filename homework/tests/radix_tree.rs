@@ -0,0 +1,130 @@
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::RadixTree;
+use rand::{thread_rng, Rng};
+use std::collections::btree_map::{BTreeMap, Entry};
+
+#[test]
+fn smoke() {
+    let tree = RadixTree::new();
+    let guard = epoch::pin();
+
+    for key in &["/", "/index.html", "/img/a.png", "/img/b.png", "", "/img"] {
+        assert_eq!(tree.insert(key.as_bytes(), key.len(), &guard), Ok(()));
+    }
+    assert_eq!(tree.insert(b"/img", 0, &guard), Err(0));
+    assert_eq!(tree.lookup(b"/img", &guard), Some(&4));
+    assert_eq!(tree.lookup(b"", &guard), Some(&0));
+    assert_eq!(tree.lookup(b"/im", &guard), None);
+    assert_eq!(tree.lookup(b"/img/c.png", &guard), None);
+
+    assert_eq!(tree.delete(b"/img", &guard), Ok(&4));
+    assert_eq!(tree.delete(b"/img", &guard), Err(()));
+    assert_eq!(tree.delete(b"/im", &guard), Err(()));
+    assert_eq!(tree.lookup(b"/img", &guard), None);
+    assert_eq!(tree.lookup(b"/img/a.png", &guard), Some(&10));
+    assert_eq!(tree.insert(b"/img", 1, &guard), Ok(()));
+    assert_eq!(tree.lookup(b"/img", &guard), Some(&1));
+}
+
+#[test]
+fn ordered_iteration() {
+    const KEYS: usize = 2000;
+    let tree = RadixTree::new();
+    let mut reference = BTreeMap::new();
+    let guard = epoch::pin();
+    let mut rng = thread_rng();
+
+    for i in 0..KEYS {
+        let len = rng.gen_range(0, 6);
+        let key = (0..len).map(|_| rng.gen_range(0, 4)).collect::<Vec<u8>>();
+        if rng.gen_range(0, 4) == 0 {
+            assert_eq!(
+                tree.delete(&key, &guard).ok(),
+                reference.remove(&key).as_ref()
+            );
+        } else if let Entry::Vacant(entry) = reference.entry(key) {
+            assert_eq!(tree.insert(entry.key(), i, &guard), Ok(()));
+            let _ = entry.insert(i);
+        }
+    }
+
+    let entries = tree.iter(&guard).map(|(k, v)| (k, *v)).collect::<Vec<_>>();
+    assert_eq!(entries, reference.clone().into_iter().collect::<Vec<_>>());
+
+    for prefix in &[&[][..], &[0], &[1, 2], &[3, 3, 3]] {
+        let entries = tree
+            .scan_prefix(prefix, &guard)
+            .map(|(k, v)| (k, *v))
+            .collect::<Vec<_>>();
+        let expected = reference
+            .iter()
+            .filter(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.clone(), *v))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, expected);
+    }
+}
+
+#[test]
+fn concurrent_insert() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1000;
+    let tree = RadixTree::new();
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let tree = &tree;
+            let _ = s.spawn(move |_| {
+                let guard = epoch::pin();
+                for i in 0..KEYS {
+                    // The threads share the prefixes, so they add children to the same nodes.
+                    let key = format!("/{}/{}", i % 10, i * THREADS + t);
+                    assert_eq!(tree.insert(key.as_bytes(), t, &guard), Ok(()));
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = epoch::pin();
+    let keys = tree.iter(&guard).map(|(k, _)| k).collect::<Vec<_>>();
+    assert_eq!(keys.len(), THREADS * KEYS);
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(
+        tree.scan_prefix(b"/3/", &guard).count(),
+        THREADS * KEYS / 10
+    );
+}
+
+#[test]
+fn concurrent_insert_delete() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 10_000;
+    let tree = RadixTree::new();
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let tree = &tree;
+            let _ = s.spawn(move |_| {
+                let mut rng = thread_rng();
+                for _ in 0..STEPS {
+                    let guard = epoch::pin();
+                    // Each thread owns the keys ending with its ID, and shares the prefixes.
+                    let key = [rng.gen_range(0, 4), rng.gen_range(0, 4), t as u8];
+                    match tree.lookup(&key, &guard) {
+                        Some(v) => {
+                            assert_eq!(*v, t);
+                            assert_eq!(tree.delete(&key, &guard), Ok(&t));
+                        }
+                        None => assert_eq!(tree.insert(&key, t, &guard), Ok(())),
+                    }
+                    for (key, v) in tree.scan_prefix(&key[..1], &guard) {
+                        assert_eq!(key.last().map(|b| *b as usize), Some(*v));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+}
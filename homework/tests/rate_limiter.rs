@@ -0,0 +1,58 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::RateLimiter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let limiter = RateLimiter::new(10, 5);
+    assert_eq!(limiter.available(), 5);
+    assert!(limiter.try_acquire(3));
+    assert!(!limiter.try_acquire(3));
+    assert!(limiter.try_acquire(2));
+    assert!(!limiter.try_acquire(1));
+
+    // More tokens than the burst are never available.
+    assert!(!limiter.try_acquire(6));
+
+    // Refills a token every 100ms.
+    thread::sleep(Duration::from_millis(250));
+    assert!(limiter.try_acquire(2));
+    assert!(!limiter.try_acquire(1));
+
+    // The bucket does not overflow.
+    thread::sleep(Duration::from_millis(1000));
+    assert_eq!(limiter.available(), 5);
+}
+
+#[test]
+#[should_panic(expected = "at most 10^9 per second")]
+fn too_fast() {
+    // A token would be refilled in less than a nanosecond.
+    let _ = RateLimiter::new(2_000_000_000, 1);
+}
+
+#[test]
+fn concurrent() {
+    const THREADS: usize = 8;
+    const BURST: usize = 100;
+
+    // Refills only one token per second, so the threads get about the burst in total.
+    let limiter = RateLimiter::new(1, BURST as u32);
+    let acquired = AtomicUsize::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..BURST {
+                    if limiter.try_acquire(1) {
+                        let _ = acquired.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    let acquired = acquired.load(Ordering::Relaxed);
+    assert!((BURST..=BURST + 1).contains(&acquired), "{}", acquired);
+}
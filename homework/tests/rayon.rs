@@ -0,0 +1,80 @@
+#![cfg(feature = "rayon")]
+
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+use rayon::iter::ParallelIterator;
+
+#[test]
+fn par_iter() {
+    const KEYS: usize = 10_000;
+
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    assert_eq!(list.par_iter(&guard).count(), 0);
+
+    for key in 0..KEYS {
+        assert_eq!(list.insert(&key, key * 2, &guard), Ok(()));
+    }
+    for key in (0..KEYS).step_by(3) {
+        assert_eq!(list.delete(&key, &guard), Ok(&(key * 2)));
+    }
+
+    let mut pairs = list
+        .par_iter(&guard)
+        .map(|(key, value)| (key, *value))
+        .collect::<Vec<_>>();
+    pairs.sort_unstable();
+    let expected = (0..KEYS)
+        .filter(|key| key % 3 != 0)
+        .map(|key| (key, key * 2))
+        .collect::<Vec<_>>();
+    assert_eq!(pairs, expected);
+
+    assert_eq!(
+        list.par_iter(&guard).find_any(|&(key, _)| key == 1),
+        Some((1, &2))
+    );
+}
+
+#[test]
+fn par_iter_concurrent() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    for key in 0..KEYS {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = epoch::pin();
+                // Inserts and deletes keys beyond `KEYS`, which resizes the list.
+                for key in (KEYS + t..KEYS * 4).step_by(THREADS) {
+                    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+                }
+                for key in (KEYS + t..KEYS * 4).step_by(THREADS) {
+                    assert_eq!(list.delete(&key, &guard), Ok(&key));
+                }
+            });
+        }
+
+        // The keys below `KEYS` are always in the list.
+        for _ in 0..16 {
+            let guard = epoch::pin();
+            let count = list
+                .par_iter(&guard)
+                .filter(|&(key, value)| {
+                    assert_eq!(key, *value);
+                    key < KEYS
+                })
+                .count();
+            assert_eq!(count, KEYS);
+        }
+    })
+    .unwrap();
+}
@@ -0,0 +1,134 @@
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{RcuCell, RcuList};
+
+#[test]
+fn smoke() {
+    let cell = RcuCell::new(vec![1, 2, 3]);
+    let guard = pin();
+
+    assert_eq!(cell.read(&guard), &vec![1, 2, 3]);
+    cell.update(|v| {
+        let mut v = v.clone();
+        v.push(4);
+        v
+    });
+    assert_eq!(cell.read(&guard), &vec![1, 2, 3, 4]);
+    cell.store(vec![]);
+    assert!(cell.read(&guard).is_empty());
+}
+
+#[test]
+fn read_during_update() {
+    let cell = RcuCell::new(String::from("old"));
+    let guard = pin();
+
+    // A reference obtained before the update stays valid while the guard is alive.
+    let old = cell.read(&guard);
+    cell.store(String::from("new"));
+    assert_eq!(old, "old");
+    assert_eq!(cell.read(&guard), "new");
+}
+
+#[test]
+fn update_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+
+    let cell = RcuCell::new(0usize);
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..STEPS {
+                    cell.update(|v| v + 1);
+                    let guard = pin();
+                    assert!(*cell.read(&guard) > 0);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(*cell.read(&pin()), THREADS * STEPS);
+}
+
+#[test]
+fn list_smoke() {
+    let list = RcuList::new();
+    let guard = pin();
+    assert!(list.is_empty(&guard));
+
+    for i in 0..4 {
+        list.insert(i);
+    }
+    assert_eq!(
+        list.iter(&guard).copied().collect::<Vec<_>>(),
+        vec![0, 1, 2, 3]
+    );
+
+    assert!(list.remove(|&v| v == 0));
+    assert!(list.remove(|&v| v == 2));
+    assert!(!list.remove(|&v| v == 2));
+    assert!(list.update(|&v| v == 3, |v| v * 10));
+    assert!(!list.update(|&v| v == 3, |v| v * 10));
+    assert_eq!(list.iter(&guard).copied().collect::<Vec<_>>(), vec![1, 30]);
+}
+
+#[test]
+fn list_read_during_update() {
+    let list = RcuList::new();
+    list.insert(String::from("a"));
+    list.insert(String::from("b"));
+    list.insert(String::from("c"));
+    let guard = pin();
+
+    // An iterator on a removed node still reaches the rest of the list.
+    let mut iter = list.iter(&guard);
+    let a = iter.next().unwrap();
+    assert!(list.remove(|v| v == "b"));
+    assert!(list.update(|v| v == "c", |_| String::from("d")));
+    assert_eq!(a, "a");
+    assert_eq!(iter.collect::<Vec<_>>(), vec!["b", "c"]);
+    assert_eq!(list.iter(&guard).collect::<Vec<_>>(), vec!["a", "d"]);
+}
+
+#[test]
+fn list_concurrent() {
+    const WRITERS: usize = 4;
+    const READERS: usize = 4;
+    const STEPS: usize = 1024;
+
+    // Each writer inserts and removes its own values, which readers should see in the insertion
+    // order of each writer.
+    let list = RcuList::new();
+    scope(|s| {
+        for t in 0..WRITERS {
+            let list = &list;
+            s.spawn(move |_| {
+                for i in 0..STEPS {
+                    list.insert((t, i));
+                    if i % 2 == 0 {
+                        assert!(list.remove(|&v| v == (t, i)));
+                    }
+                }
+            });
+        }
+        for _ in 0..READERS {
+            s.spawn(|_| {
+                for _ in 0..STEPS {
+                    let guard = pin();
+                    let mut last = [None; WRITERS];
+                    for &(t, i) in list.iter(&guard) {
+                        assert!(last[t] < Some(i));
+                        last[t] = Some(i);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(list.iter(&guard).count(), WRITERS * STEPS / 2);
+    assert!(list.iter(&guard).all(|&(_, i)| i % 2 == 1));
+}
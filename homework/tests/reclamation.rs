@@ -0,0 +1,90 @@
+use crossbeam_epoch::{self as epoch, Owned};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{reclamation, NonblockingMap, SplitOrderedList};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Counts its drops.
+#[derive(Debug)]
+struct Tracked(Arc<AtomicUsize>);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        let _ = self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn flush() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    {
+        let guard = epoch::pin();
+        for _ in 0..1000 {
+            let garbage = Owned::new(Tracked(drops.clone())).into_shared(&guard);
+            unsafe { guard.defer_destroy(garbage) };
+        }
+    }
+    reclamation::flush();
+    assert_eq!(drops.load(Ordering::Relaxed), 1000);
+}
+
+#[test]
+#[should_panic(expected = "while pinned")]
+fn flush_pinned() {
+    let _guard = epoch::pin();
+    reclamation::flush();
+}
+
+#[test]
+fn split_ordered_list_clear() {
+    const KEYS: usize = 1000;
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let list = SplitOrderedList::new();
+    {
+        let guard = epoch::pin();
+        for key in 0..KEYS {
+            assert!(list.insert(&key, Tracked(drops.clone()), &guard).is_ok());
+        }
+        list.clear(&guard);
+        assert_eq!(list.iter(&guard).count(), 0);
+    }
+
+    reclamation::flush();
+    assert_eq!(drops.load(Ordering::Relaxed), KEYS);
+    assert_eq!(list.sentinel_stats().nodes_freed, 0);
+}
+
+#[test]
+fn split_ordered_list_collect() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 1000;
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let list = SplitOrderedList::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let (list, drops) = (&list, &drops);
+            let _ = s.spawn(move |_| {
+                for i in 0..KEYS {
+                    let key = i * THREADS + t;
+                    let guard = epoch::pin();
+                    assert!(list.insert(&key, Tracked(drops.clone()), &guard).is_ok());
+                    if i % 2 == 0 {
+                        assert!(list.delete(&key, &guard).is_ok());
+                    }
+                }
+                // The exiting thread pushes its garbage to the global queue.
+                list.collect(&epoch::pin());
+            });
+        }
+    })
+    .unwrap();
+
+    list.collect(&epoch::pin());
+    reclamation::flush();
+    assert_eq!(drops.load(Ordering::Relaxed), THREADS * KEYS / 2);
+
+    drop(list);
+    assert_eq!(drops.load(Ordering::Relaxed), THREADS * KEYS);
+}
@@ -0,0 +1,123 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::channel::{broadcast, mpsc, oneshot, Select};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let (tx1, rx1) = mpsc::channel(4);
+    let (tx2, rx2) = mpsc::channel(4);
+
+    let mut sel = Select::new();
+    let i1 = sel.recv(&rx1);
+    let i2 = sel.recv(&rx2);
+    assert_eq!(sel.try_ready(), None);
+    assert_eq!(sel.ready_timeout(Duration::from_millis(10)), None);
+
+    tx2.send(2).unwrap();
+    assert_eq!(sel.ready(), i2);
+    assert_eq!(rx2.try_recv(), Ok(2));
+
+    tx1.send(1).unwrap();
+    assert_eq!(sel.ready(), i1);
+    assert_eq!(rx1.try_recv(), Ok(1));
+
+    // A receiver whose senders are dropped is ready.
+    drop(tx2);
+    assert_eq!(sel.ready(), i2);
+}
+
+#[test]
+fn blocks_until_ready() {
+    let (jobs_tx, jobs) = mpsc::channel::<i32>(4);
+    let (events_tx, mut events) = broadcast::channel(4);
+    let (shutdown_tx, mut shutdown) = oneshot::channel();
+
+    scope(|s| {
+        s.spawn(|_| {
+            thread::sleep(Duration::from_millis(50));
+            events_tx.send("reload").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            shutdown_tx.send(()).unwrap();
+        });
+
+        let mut received = Vec::new();
+        loop {
+            let mut sel = Select::new();
+            let job = sel.recv(&jobs);
+            let event = sel.recv(&events);
+            let stop = sel.recv(&shutdown);
+            let index = sel.ready();
+            if index == job {
+                received.push(jobs.recv().unwrap().to_string());
+            } else if index == event {
+                received.push(events.recv().unwrap().to_string());
+            } else if index == stop {
+                shutdown.try_recv().unwrap();
+                break;
+            }
+        }
+        assert_eq!(received, vec!["reload"]);
+        drop(jobs_tx);
+    })
+    .unwrap();
+}
+
+#[test]
+fn fairness() {
+    let (tx1, rx1) = mpsc::channel(1000);
+    let (tx2, rx2) = mpsc::channel(1000);
+    for i in 0..1000 {
+        tx1.send(i).unwrap();
+        tx2.send(i).unwrap();
+    }
+
+    let mut counts = [0; 2];
+    for _ in 0..1000 {
+        let mut sel = Select::new();
+        let _ = sel.recv(&rx1);
+        let _ = sel.recv(&rx2);
+        let index = sel.ready();
+        counts[index] += 1;
+        let _ = [&rx1, &rx2][index].try_recv().unwrap();
+    }
+
+    // Both receivers are always ready, so each of them should be chosen about half of the time.
+    assert!(counts[0] > 300 && counts[1] > 300, "{:?}", counts);
+}
+
+#[test]
+fn stress() {
+    const SENDERS: usize = 4;
+    const MESSAGES: usize = 10_000;
+
+    let (tx1, rx1) = mpsc::channel(16);
+    let (tx2, rx2) = mpsc::channel(16);
+    scope(|s| {
+        for i in 0..SENDERS {
+            let tx = if i % 2 == 0 { tx1.clone() } else { tx2.clone() };
+            s.spawn(move |_| {
+                for _ in 0..MESSAGES {
+                    tx.send(()).unwrap();
+                }
+            });
+        }
+        drop((tx1, tx2));
+
+        let mut count = 0;
+        let mut sel = Select::new();
+        let _ = sel.recv(&rx1);
+        let _ = sel.recv(&rx2);
+        let mut closed = [false; 2];
+        while closed != [true, true] {
+            let index = sel.ready();
+            match [&rx1, &rx2][index].try_recv() {
+                Ok(()) => count += 1,
+                Err(mpsc::TryRecvError::Empty) => unreachable!(),
+                Err(mpsc::TryRecvError::Disconnected) => closed[index] = true,
+            }
+        }
+        assert_eq!(count, SENDERS * MESSAGES);
+    })
+    .unwrap();
+}
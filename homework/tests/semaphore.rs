@@ -0,0 +1,75 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::Semaphore;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let semaphore = Semaphore::new(2);
+    let p1 = semaphore.try_acquire().unwrap();
+    let p2 = semaphore.acquire();
+    assert_eq!(semaphore.available_permits(), 0);
+    assert!(semaphore.try_acquire().is_none());
+    assert!(semaphore
+        .acquire_timeout(Duration::from_millis(10))
+        .is_none());
+
+    drop(p1);
+    assert_eq!(semaphore.available_permits(), 1);
+    let _p3 = semaphore
+        .acquire_timeout(Duration::from_millis(10))
+        .unwrap();
+    drop(p2);
+    semaphore.add_permits(2);
+    assert_eq!(semaphore.available_permits(), 3);
+}
+
+#[test]
+fn acquire_blocks() {
+    let semaphore = Arc::new(Semaphore::new(1));
+    let permit = semaphore.clone().acquire_owned();
+
+    let acquired = Arc::new(AtomicBool::new(false));
+    let waiter = {
+        let semaphore = semaphore.clone();
+        let acquired = acquired.clone();
+        thread::spawn(move || {
+            let _permit = semaphore.acquire();
+            acquired.store(true, Ordering::SeqCst);
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    assert!(!acquired.load(Ordering::SeqCst));
+
+    drop(permit);
+    waiter.join().unwrap();
+    assert!(acquired.load(Ordering::SeqCst));
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+fn limit_concurrency() {
+    const THREADS: usize = 16;
+    const PERMITS: usize = 3;
+    const STEPS: usize = 256;
+
+    let semaphore = Semaphore::new(PERMITS);
+    let running = AtomicUsize::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..STEPS {
+                    let _permit = semaphore.acquire();
+                    let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    assert!(n <= PERMITS);
+                    thread::yield_now();
+                    running.fetch_sub(1, Ordering::SeqCst);
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(semaphore.available_permits(), PERMITS);
+}
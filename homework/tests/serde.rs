@@ -0,0 +1,49 @@
+#![cfg(feature = "serde")]
+
+use crossbeam_epoch as epoch;
+use cs492_concur_homework::{NonblockingMap, OrderedListSet, SplitOrderedList};
+
+#[test]
+fn ordered_list_set() {
+    let set = OrderedListSet::new();
+    for &key in &[3, 1, 4, 5, 9, 2, 6] {
+        assert_eq!(set.insert(key), Ok(()));
+    }
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(json, "[1,2,3,4,5,6,9]");
+
+    let set: OrderedListSet<usize> = serde_json::from_str("[9,1,5]").unwrap();
+    assert_eq!(set.iter().copied().collect::<Vec<_>>(), [1, 5, 9]);
+    assert_eq!(set.insert(3), Ok(()));
+    assert_eq!(set.remove(&9), Ok(9));
+    assert_eq!(serde_json::to_string(&set).unwrap(), "[1,3,5]");
+
+    assert!(serde_json::from_str::<OrderedListSet<usize>>("[1,2,1]").is_err());
+}
+
+#[test]
+fn split_ordered_list() {
+    let guard = epoch::pin();
+    let list = SplitOrderedList::new();
+    for key in 0..8 {
+        assert_eq!(list.insert(&key, key * 10, &guard), Ok(()));
+    }
+    assert_eq!(list.delete(&3, &guard), Ok(&30));
+
+    // In the split order, i.e. the order of the reversed bits.
+    let json = serde_json::to_string(&list).unwrap();
+    assert_eq!(json, r#"{"0":0,"4":40,"2":20,"6":60,"1":10,"5":50,"7":70}"#);
+
+    let list: SplitOrderedList<usize> = serde_json::from_str(&json).unwrap();
+    for key in 0..8 {
+        let expected = if key == 3 { None } else { Some(key * 10) };
+        assert_eq!(list.lookup(&key, &guard).copied(), expected);
+    }
+
+    assert!(serde_json::from_str::<SplitOrderedList<usize>>(r#"{"1":1,"1":2}"#).is_err());
+
+    // All the `usize` keys are allowed.
+    let list: SplitOrderedList<usize> =
+        serde_json::from_str(r#"{"18446744073709551615":1}"#).unwrap();
+    assert_eq!(list.lookup(&usize::MAX, &guard), Some(&1));
+}
@@ -0,0 +1,152 @@
+//! Conformance tests of `ConcurrentSet`, shared by the tests of the sets.
+
+use cs492_concur_homework::ConcurrentSet;
+use std::collections::HashSet;
+
+use rand::prelude::*;
+
+use crossbeam_utils::thread;
+
+/// Checks the basic operations on a single thread.
+pub fn smoke<S: Default + ConcurrentSet<usize>>() {
+    let set = S::default();
+    assert!(set.is_empty());
+    assert!(!set.contains(&1));
+    assert_eq!(set.remove(&1), Err(()));
+
+    for &key in &[3, 1, 4, 5, 9, 2, 6] {
+        assert_eq!(set.insert(key), Ok(()));
+    }
+    assert_eq!(set.insert(4), Err(4));
+    assert_eq!(set.len(), 7);
+    assert!(set.contains(&9));
+    assert!(!set.contains(&7));
+
+    assert_eq!(set.remove(&4), Ok(4));
+    assert_eq!(set.remove(&4), Err(()));
+    assert!(!set.contains(&4));
+    assert_eq!(set.insert(4), Ok(()));
+    assert_eq!(set.remove(&1), Ok(1));
+
+    let mut snapshot = set.snapshot();
+    snapshot.sort_unstable();
+    assert_eq!(snapshot, [2, 3, 4, 5, 6, 9]);
+    assert_eq!(set.len(), 6);
+}
+
+/// Runs random operations on a single thread, and compares the results with `HashSet`.
+pub fn stress_sequential<S: Default + ConcurrentSet<usize>>(steps: usize, key_range: usize) {
+    let mut rng = thread_rng();
+    let set = S::default();
+    let mut model = HashSet::new();
+
+    for i in 0..steps {
+        let key = rng.gen_range(0, key_range);
+        match rng.gen_range(0, 3) {
+            0 => assert_eq!(
+                set.contains(&key),
+                model.contains(&key),
+                "iteration {}: contains({})",
+                i,
+                key
+            ),
+            1 => assert_eq!(
+                set.insert(key).is_ok(),
+                model.insert(key),
+                "iteration {}: insert({})",
+                i,
+                key
+            ),
+            _ => assert_eq!(
+                set.remove(&key).is_ok(),
+                model.remove(&key),
+                "iteration {}: remove({})",
+                i,
+                key
+            ),
+        }
+    }
+
+    assert_eq!(set.len(), model.len());
+    let mut snapshot = set.snapshot();
+    snapshot.sort_unstable();
+    let mut expected = model.into_iter().collect::<Vec<_>>();
+    expected.sort_unstable();
+    assert_eq!(snapshot, expected);
+}
+
+/// Each thread runs random operations on its own keys, and compares the results with its own
+/// `HashSet`. At the end, the set has the keys of all the `HashSet`s.
+pub fn stress_concurrent<S: Default + Sync + ConcurrentSet<usize>>(
+    threads: usize,
+    steps: usize,
+    key_range: usize,
+) {
+    let set = S::default();
+
+    let models = thread::scope(|s| {
+        let handles = (0..threads)
+            .map(|t| {
+                let set = &set;
+                s.spawn(move |_| {
+                    let mut rng = thread_rng();
+                    let mut model = HashSet::new();
+                    for _ in 0..steps {
+                        // The keys of thread `t` are `t` modulo `threads`.
+                        let key = rng.gen_range(0, key_range) * threads + t;
+                        match rng.gen_range(0, 3) {
+                            0 => assert_eq!(set.contains(&key), model.contains(&key)),
+                            1 => assert_eq!(set.insert(key).is_ok(), model.insert(key)),
+                            _ => assert_eq!(set.remove(&key).is_ok(), model.remove(&key)),
+                        }
+                    }
+                    model
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    let mut snapshot = set.snapshot();
+    snapshot.sort_unstable();
+    let mut expected = models.into_iter().flatten().collect::<Vec<_>>();
+    expected.sort_unstable();
+    assert_eq!(set.len(), expected.len());
+    assert_eq!(snapshot, expected);
+}
+
+/// The threads insert and then remove the same keys. Each key is inserted and removed by exactly
+/// one thread.
+pub fn contended<S: Default + Sync + ConcurrentSet<usize>>(threads: usize, keys: usize) {
+    let set = S::default();
+
+    let count = |f: &(dyn Fn(usize) -> bool + Sync)| {
+        thread::scope(|s| {
+            let handles = (0..threads)
+                .map(|_| s.spawn(move |_| (0..keys).filter(|&key| f(key)).count()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .sum::<usize>()
+        })
+        .unwrap()
+    };
+
+    assert_eq!(count(&|key| set.insert(key).is_ok()), keys);
+    assert_eq!(set.len(), keys);
+    assert_eq!(count(&|key| set.remove(&key).is_ok()), keys);
+    assert!(set.is_empty());
+}
+
+/// Runs all the tests above with moderate sizes.
+pub fn conformance<S: Default + Sync + ConcurrentSet<usize>>() {
+    smoke::<S>();
+    stress_sequential::<S>(10_000, 256);
+    stress_concurrent::<S>(4, 10_000, 256);
+    contended::<S>(4, 1000);
+}
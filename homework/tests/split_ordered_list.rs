@@ -1,7 +1,17 @@
+use core::hash::BuildHasherDefault;
 use crossbeam_epoch as epoch;
-use cs492_concur_homework::{NonblockingConcurrentMap, NonblockingMap, SplitOrderedList};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::debug_dump::{self, Format};
+use cs492_concur_homework::hello_server::ThreadPool;
+use cs492_concur_homework::split_ordered_list::Entry;
+use cs492_concur_homework::{
+    reclamation, MapError, NonblockingConcurrentMap, NonblockingMap, SplitOrderedList,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::ops::{Bound, RangeBounds};
 
 pub mod map;
+pub mod map_linearizable;
 
 #[test]
 pub fn smoke() {
@@ -21,13 +31,976 @@ pub fn smoke() {
     assert_eq!(list.lookup(&42, &guard), Some(&42));
     assert_eq!(list.lookup(&37, &guard), None);
 
-    assert_eq!(list.delete(&37, &guard), Err(()));
+    assert_eq!(list.delete(&37, &guard), Err(MapError::NotFound));
     assert_eq!(list.lookup(&42, &guard), Some(&42));
     assert_eq!(list.lookup(&37, &guard), None);
     // assert_eq!(list.insert(&306244791841062916, 1, &guard), Ok(()));
     // assert_eq!(list.lookup(&306244791841062916, &guard), Some(&1));
 }
 
+#[test]
+fn iter() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).next(), None);
+
+    for key in 0..8 {
+        assert_eq!(list.insert(&key, key * 10, &guard), Ok(()));
+    }
+    assert_eq!(list.delete(&3, &guard), Ok(&30));
+
+    // In the order of the reversed bits.
+    assert_eq!(
+        list.iter(&guard).collect::<Vec<_>>(),
+        [
+            (0, &0),
+            (4, &40),
+            (2, &20),
+            (6, &60),
+            (1, &10),
+            (5, &50),
+            (7, &70)
+        ]
+    );
+}
+
+#[test]
+fn sentinel_arena() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for i in 0..1024 {
+        assert_eq!(list.insert(&i, i, &guard), Ok(()));
+    }
+    for i in 0..1024 {
+        assert_eq!(list.lookup(&i, &guard), Some(&i));
+    }
+
+    // The buckets are initialized lazily, and their sentinel nodes are freed only by shrinking.
+    let stats = list.sentinel_stats();
+    assert!(stats.nodes_allocated > 1);
+    assert_eq!(stats.nodes_freed, 0);
+    assert!(stats.chunks_allocated < stats.nodes_allocated);
+}
+
+#[test]
+fn node_arena() {
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = epoch::pin();
+        for i in 0..KEYS {
+            assert_eq!(list.insert(&i, i, &guard), Ok(()));
+        }
+        // The replaced nodes and the nodes that fail to be inserted are freed, too.
+        for i in 0..KEYS {
+            assert_eq!(list.update(&i, i + 1, &guard), Ok(&i));
+        }
+        assert_eq!(list.insert(&0, 0, &guard), Err(MapError::AlreadyExists(0)));
+        for i in 0..KEYS {
+            assert_eq!(list.delete(&i, &guard), Ok(&(i + 1)));
+        }
+    }
+
+    reclamation::flush();
+    let stats = list.node_stats();
+    assert_eq!(stats.nodes_allocated, 2 * KEYS + 1);
+    assert_eq!(stats.nodes_freed, 2 * KEYS + 1);
+    assert!(stats.chunks_allocated < stats.nodes_allocated);
+}
+
+#[test]
+fn full_key_range() {
+    const KEYS: [usize; 6] = [0, 1, 1 << 63, (1 << 63) + 1, usize::MAX - 1, usize::MAX];
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for (i, &key) in KEYS.iter().enumerate() {
+        assert_eq!(list.insert(&key, i, &guard), Ok(()));
+    }
+    // Enough keys to split the buckets of the large keys.
+    for key in 2..64 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    for (i, &key) in KEYS.iter().enumerate() {
+        assert_eq!(list.lookup(&key, &guard), Some(&i));
+        assert_eq!(
+            list.insert(&key, 0, &guard),
+            Err(MapError::AlreadyExists(0))
+        );
+    }
+
+    // The keys of the same low bits are ordered by the high bits.
+    let keys = list.iter(&guard).map(|(key, _)| key).collect::<Vec<_>>();
+    let position = |key| keys.iter().position(|&k| k == key).unwrap();
+    assert!(position(0) < position(1 << 63));
+    assert!(position(1) < position((1 << 63) + 1));
+    assert!(position(usize::MAX - 1) < position(usize::MAX));
+
+    for (i, &key) in KEYS.iter().enumerate() {
+        assert_eq!(list.delete(&key, &guard), Ok(&i));
+        assert_eq!(list.lookup(&key, &guard), None);
+    }
+}
+
+#[test]
+fn snapshot() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for key in 0..8 {
+        assert_eq!(list.insert(&key, key * 10, &guard), Ok(()));
+    }
+    assert_eq!(list.delete(&3, &guard), Ok(&30));
+
+    let snapshot = list.snapshot(&guard);
+    assert_eq!(
+        snapshot,
+        [(0, 0), (4, 40), (2, 20), (6, 60), (1, 10), (5, 50), (7, 70)]
+    );
+
+    let restored = snapshot.into_iter().collect::<SplitOrderedList<_>>();
+    assert_eq!(restored.snapshot(&guard), list.snapshot(&guard));
+
+    // The last value of a key is kept.
+    let list = vec![(1, 10), (2, 20), (1, 11)]
+        .into_iter()
+        .collect::<SplitOrderedList<_>>();
+    assert_eq!(list.snapshot(&guard), [(2, 20), (1, 11)]);
+}
+
+#[test]
+fn shrink() {
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = epoch::pin();
+        for i in 0..KEYS {
+            assert_eq!(list.insert(&i, i, &guard), Ok(()));
+        }
+        for i in 0..KEYS {
+            assert_eq!(list.lookup(&i, &guard), Some(&i));
+        }
+        for i in 0..KEYS {
+            assert_eq!(list.delete(&i, &guard), Ok(&i));
+        }
+        for i in 0..KEYS {
+            assert_eq!(list.lookup(&i, &guard), None);
+        }
+        let dump = debug_dump::split_ordered_list(&list, Format::Text, &guard);
+        assert!(
+            dump.starts_with("SplitOrderedList: 2 buckets, 0 items\n"),
+            "{}",
+            dump
+        );
+    }
+
+    // The sentinels of the removed buckets are freed once they are unlinked.
+    list.collect(&epoch::pin());
+    reclamation::flush();
+    let stats = list.sentinel_stats();
+    assert!(stats.nodes_freed > 0);
+    assert!(
+        stats.nodes_allocated - stats.nodes_freed <= 2,
+        "{:?}",
+        stats
+    );
+
+    // The list grows back.
+    let guard = epoch::pin();
+    for i in 0..KEYS {
+        assert_eq!(list.insert(&i, i, &guard), Ok(()));
+    }
+    for i in 0..KEYS {
+        assert_eq!(list.lookup(&i, &guard), Some(&i));
+    }
+}
+
+#[test]
+fn shrink_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 512;
+    const ROUNDS: usize = 16;
+
+    let list = SplitOrderedList::<usize>::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            let _ = s.spawn(move |_| {
+                // Each round grows the list and shrinks it back.
+                for round in 0..ROUNDS {
+                    let guard = epoch::pin();
+                    for i in 0..KEYS {
+                        let key = i * THREADS + t;
+                        assert_eq!(list.insert(&key, round, &guard), Ok(()));
+                    }
+                    for i in 0..KEYS {
+                        let key = i * THREADS + t;
+                        assert_eq!(list.lookup(&key, &guard), Some(&round));
+                        assert_eq!(list.delete(&key, &guard), Ok(&round));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).count(), 0);
+    for key in 0..KEYS * THREADS {
+        assert_eq!(list.lookup(&key, &guard), None);
+    }
+}
+
+#[test]
+fn clear() {
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for i in 0..KEYS {
+        assert_eq!(list.insert(&i, i, &guard), Ok(()));
+    }
+    list.clear(&guard);
+    for i in 0..KEYS {
+        assert_eq!(list.lookup(&i, &guard), None);
+    }
+    let dump = debug_dump::split_ordered_list(&list, Format::Text, &guard);
+    assert!(dump.contains(" buckets, 0 items\n"), "{}", dump);
+
+    for i in 0..KEYS {
+        assert_eq!(list.insert(&i, i + 1, &guard), Ok(()));
+    }
+    for i in 0..KEYS {
+        assert_eq!(list.lookup(&i, &guard), Some(&(i + 1)));
+    }
+}
+
+#[test]
+fn clear_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            let _ = s.spawn(move |_| {
+                let guard = epoch::pin();
+                for i in 0..KEYS {
+                    let key = i * THREADS + t;
+                    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+                    if i % 256 == 0 {
+                        list.clear(&guard);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // The count matches the pairs that survived the clears.
+    let guard = epoch::pin();
+    let count = list.iter(&guard).count();
+    let dump = debug_dump::split_ordered_list(&list, Format::Text, &guard);
+    assert!(
+        dump.contains(&format!(" buckets, {} items\n", count)),
+        "{}",
+        dump
+    );
+    list.clear(&guard);
+    assert_eq!(list.iter(&guard).count(), 0);
+}
+
+#[test]
+fn get_or_insert_with() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.get_or_insert_with(&1, || 10, &guard), &10);
+    assert_eq!(list.get_or_insert_with(&1, || panic!(), &guard), &10);
+    assert_eq!(list.lookup(&1, &guard), Some(&10));
+    assert_eq!(list.delete(&1, &guard), Ok(&10));
+    assert_eq!(list.get_or_insert_with(&1, || 11, &guard), &11);
+}
+
+#[test]
+fn get_or_insert_with_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let values = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let list = &list;
+                s.spawn(move |_| {
+                    let guard = epoch::pin();
+                    (0..KEYS)
+                        .map(|key| *list.get_or_insert_with(&key, || t, &guard))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    // All the threads get the value of the thread that inserted the key.
+    let guard = epoch::pin();
+    for key in 0..KEYS {
+        let value = list.lookup(&key, &guard).unwrap();
+        assert!(values.iter().all(|values| values[key] == *value));
+    }
+    assert_eq!(list.iter(&guard).count(), KEYS);
+}
+
+#[test]
+fn try_insert_with() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.try_insert_with(&1, || 10, &guard), Ok(&10));
+    assert_eq!(list.try_insert_with(&1, || panic!(), &guard), Err(&10));
+    assert_eq!(list.lookup(&1, &guard), Some(&10));
+    assert_eq!(list.delete(&1, &guard), Ok(&10));
+    assert_eq!(list.try_insert_with(&1, || 11, &guard), Ok(&11));
+}
+
+#[test]
+fn try_insert_with_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let inserted = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let list = &list;
+                s.spawn(move |_| {
+                    let guard = epoch::pin();
+                    (0..KEYS)
+                        .filter(|key| list.try_insert_with(key, || t, &guard).is_ok())
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum::<usize>()
+    })
+    .unwrap();
+
+    // Each key is inserted by exactly one thread.
+    assert_eq!(inserted, KEYS);
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).count(), KEYS);
+}
+
+#[test]
+fn remove_if() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.remove_if(&1, |_| true, &guard), Err(()));
+    assert_eq!(list.insert(&1, 10, &guard), Ok(()));
+    assert_eq!(list.remove_if(&1, |v| *v == 11, &guard), Err(()));
+    assert_eq!(list.lookup(&1, &guard), Some(&10));
+    assert_eq!(list.remove_if(&1, |v| *v == 10, &guard), Ok(&10));
+    assert_eq!(list.lookup(&1, &guard), None);
+}
+
+#[test]
+fn delete_if_eq() {
+    let list = SplitOrderedList::<String>::new();
+    let guard = epoch::pin();
+
+    let foo = "foo".to_string();
+    assert_eq!(list.delete_if_eq(&1, &foo, &guard), Err(()));
+    assert_eq!(list.insert(&1, foo.clone(), &guard), Ok(()));
+    let observed = list.lookup(&1, &guard).unwrap().clone();
+    assert_eq!(list.update(&1, "bar".to_string(), &guard), Ok(&foo));
+    // The value is changed after it is observed.
+    assert_eq!(list.delete_if_eq(&1, &observed, &guard), Err(()));
+    assert_eq!(list.lookup(&1, &guard), Some(&"bar".to_string()));
+    assert_eq!(
+        list.delete_if_eq(&1, &"bar".to_string(), &guard),
+        Ok(&"bar".to_string())
+    );
+    assert_eq!(list.lookup(&1, &guard), None);
+}
+
+#[test]
+fn remove_if_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = epoch::pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, 0, &guard), Ok(()));
+        }
+    }
+
+    // The updaters change the values from 0 to 1, and the removers remove the values of 0. So
+    // either of them succeeds for each key.
+    let succeeded = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let list = &list;
+                s.spawn(move |_| {
+                    let guard = epoch::pin();
+                    (0..KEYS)
+                        .filter(|key| {
+                            if t % 2 == 0 {
+                                list.compare_and_update(key, &0, 1, &guard).is_ok()
+                            } else {
+                                list.remove_if(key, |v| *v == 0, &guard).is_ok()
+                            }
+                        })
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    let updated = succeeded.iter().step_by(2).sum::<usize>();
+    let removed = succeeded.iter().skip(1).step_by(2).sum::<usize>();
+    assert_eq!(updated + removed, KEYS);
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).count(), updated);
+    assert!(list.iter(&guard).all(|(_, v)| *v == 1));
+}
+
+#[test]
+fn entry() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.entry(&1, &guard).or_insert(10), &10);
+    assert_eq!(list.entry(&1, &guard).or_insert(11), &10);
+    assert_eq!(list.entry(&1, &guard).key(), 1);
+    let entry = list.entry(&1, &guard).and_modify(|v| v + 1);
+    match entry {
+        Entry::Occupied(entry) => {
+            assert_eq!(entry.get(), &11);
+            assert_eq!(entry.remove(), Ok(&11));
+        }
+        Entry::Vacant(_) => panic!("1 should be occupied"),
+    }
+    assert_eq!(list.lookup(&1, &guard), None);
+
+    match list.entry(&2, &guard).and_modify(|v| v + 1) {
+        Entry::Occupied(_) => panic!("2 should be vacant"),
+        Entry::Vacant(entry) => assert_eq!(entry.insert(20), Ok(&20)),
+    }
+    assert_eq!(list.lookup(&2, &guard), Some(&20));
+
+    // A stale entry fails.
+    let mut entry = match list.entry(&2, &guard) {
+        Entry::Occupied(entry) => entry,
+        Entry::Vacant(_) => panic!("2 should be occupied"),
+    };
+    assert_eq!(list.update(&2, 21, &guard), Ok(&20));
+    assert_eq!(entry.replace(22), Err(22));
+    let vacant = list.entry(&3, &guard);
+    assert_eq!(list.insert(&3, 30, &guard), Ok(()));
+    match vacant {
+        Entry::Occupied(_) => panic!("3 should be vacant"),
+        Entry::Vacant(entry) => assert_eq!(entry.insert(31), Err(31)),
+    }
+}
+
+#[test]
+fn entry_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 64;
+    const STEPS: usize = 1024;
+
+    // Counts with the read-modify-write of the entries.
+    let list = SplitOrderedList::<usize>::new();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let list = &list;
+            let _ = s.spawn(move |_| {
+                for step in 0..STEPS {
+                    let guard = epoch::pin();
+                    let key = step % KEYS;
+                    loop {
+                        let done = match list.entry(&key, &guard) {
+                            Entry::Occupied(mut entry) => {
+                                let value = *entry.get();
+                                entry.replace(value + 1).is_ok()
+                            }
+                            Entry::Vacant(entry) => entry.insert(1).is_ok(),
+                        };
+                        if done {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).count(), KEYS);
+    assert!(list.iter(&guard).all(|(_, v)| *v == THREADS * STEPS / KEYS));
+}
+
+#[test]
+fn stats() {
+    const ITEMS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    assert_eq!(list.stats(&guard).items, 0);
+
+    for key in 0..ITEMS {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    for key in 0..ITEMS {
+        assert_eq!(list.lookup(&key, &guard), Some(&key));
+    }
+
+    let stats = list.stats(&guard);
+    assert_eq!(stats.items, ITEMS);
+    assert!(stats.sentinels <= stats.buckets);
+    assert!(ITEMS <= stats.buckets * 2);
+    assert_eq!(stats.chain_lengths.iter().sum::<usize>(), stats.sentinels);
+    assert_eq!(
+        stats
+            .chain_lengths
+            .iter()
+            .enumerate()
+            .map(|(length, count)| length * count)
+            .sum::<usize>(),
+        stats.items
+    );
+    // The consecutive keys are spread evenly over the buckets.
+    assert!(
+        stats.max_chain_length() <= 2,
+        "{}",
+        debug_dump::split_ordered_list(&list, Format::Text, &guard)
+    );
+}
+
+#[test]
+fn with_capacity() {
+    const ITEMS: usize = 1000;
+
+    let list = SplitOrderedList::<usize>::with_capacity(ITEMS);
+    let guard = epoch::pin();
+    let stats = list.stats(&guard);
+    assert_eq!(stats.items, 0);
+    assert_eq!(stats.buckets, (ITEMS / 2).next_power_of_two());
+    assert_eq!(stats.sentinels, stats.buckets);
+    assert_eq!(list.validate(&guard), Ok(()));
+
+    // The list neither grows nor initializes a bucket until it has `ITEMS` items.
+    for key in 0..ITEMS {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    let after = list.stats(&guard);
+    assert_eq!(after.items, ITEMS);
+    assert_eq!(after.buckets, stats.buckets);
+    assert_eq!(after.sentinels, stats.sentinels);
+    assert_eq!(list.validate(&guard), Ok(()));
+    // The buckets fit in a leaf.
+    assert_eq!(list.bucket_memory_usage(&guard).segments, 1);
+
+    let empty = SplitOrderedList::<usize>::with_capacity(0);
+    assert_eq!(empty.stats(&guard).sentinels, 2);
+    assert_eq!(empty.insert(&1, 1, &guard), Ok(()));
+    assert_eq!(empty.lookup(&1, &guard), Some(&1));
+}
+
+#[test]
+fn lookup_miss() {
+    const ITEMS: usize = 64;
+
+    // The keys fall in bucket 0, so the other buckets are not initialized.
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for i in 0..ITEMS {
+        assert_eq!(list.insert(&(i << 20), i, &guard), Ok(()));
+    }
+    let stats = list.stats(&guard);
+    assert!(stats.buckets > 2);
+    assert_eq!(stats.sentinels, 1);
+
+    for key in 1..ITEMS {
+        assert_eq!(list.lookup(&key, &guard), None);
+    }
+    for i in 0..ITEMS {
+        assert_eq!(list.lookup(&(i << 20), &guard), Some(&i));
+    }
+    assert_eq!(list.stats(&guard).sentinels, 1);
+}
+
+#[test]
+fn hasher() {
+    const ITEMS: usize = 1024;
+
+    // The keys share the low bits, so they fall in the same bucket unless they are hashed.
+    let keys = (0..ITEMS).map(|i| i << 20).collect::<Vec<_>>();
+    let identity = SplitOrderedList::<usize>::new();
+    let hashed = SplitOrderedList::<usize, BuildHasherDefault<DefaultHasher>>::default();
+    let guard = epoch::pin();
+    for &key in &keys {
+        assert_eq!(identity.insert(&key, key, &guard), Ok(()));
+        assert_eq!(hashed.insert(&key, key, &guard), Ok(()));
+    }
+    for &key in &keys {
+        assert_eq!(identity.lookup(&key, &guard), Some(&key));
+        assert_eq!(hashed.lookup(&key, &guard), Some(&key));
+    }
+
+    assert_eq!(identity.stats(&guard).max_chain_length(), ITEMS);
+    let stats = hashed.stats(&guard);
+    assert_eq!(stats.items, ITEMS);
+    assert!(
+        stats.max_chain_length() <= 16,
+        "{}",
+        debug_dump::split_ordered_list(&hashed, Format::Text, &guard)
+    );
+
+    let mut iterated = hashed.iter(&guard).map(|(key, _)| key).collect::<Vec<_>>();
+    iterated.sort_unstable();
+    assert_eq!(iterated, keys);
+    for &key in &keys {
+        assert_eq!(hashed.delete(&key, &guard), Ok(&key));
+    }
+    assert_eq!(hashed.iter(&guard).count(), 0);
+}
+
+#[test]
+fn range() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    let range = |list: &SplitOrderedList<usize>, range: (Bound<usize>, Bound<usize>)| {
+        let mut pairs = list
+            .range(range, &guard)
+            .map(|(key, value)| {
+                assert_eq!(key, *value);
+                key
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_unstable();
+        pairs
+    };
+
+    let keys = [0, 1, 2, 3, 5, 8, 13, usize::MAX - 1, usize::MAX];
+    for &key in keys.iter() {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    // Both the narrow ranges that are looked up and the wide ranges that are scanned.
+    for &(start, end) in [
+        (0, 0),
+        (0, 1),
+        (1, 5),
+        (3, 9),
+        (6, 7),
+        (2, 13),
+        (0, 100),
+        (5, usize::MAX),
+        (usize::MAX - 1, usize::MAX),
+        (usize::MAX, usize::MAX),
+        (0, usize::MAX),
+    ]
+    .iter()
+    {
+        for &start in [
+            Bound::Included(start),
+            Bound::Excluded(start),
+            Bound::Unbounded,
+        ]
+        .iter()
+        {
+            for &end in [Bound::Included(end), Bound::Excluded(end), Bound::Unbounded].iter() {
+                let expected = keys
+                    .iter()
+                    .copied()
+                    .filter(|key| (start, end).contains(key))
+                    .collect::<Vec<_>>();
+                assert_eq!(range(&list, (start, end)), expected, "{:?}", (start, end));
+            }
+        }
+    }
+
+    // The empty ranges.
+    assert_eq!(list.range(5..5, &guard).count(), 0);
+    assert_eq!(
+        list.range((Bound::Included(6), Bound::Included(5)), &guard)
+            .count(),
+        0
+    );
+    assert_eq!(list.range(..0, &guard).count(), 0);
+    assert_eq!(
+        list.range((Bound::Excluded(usize::MAX), Bound::Unbounded), &guard)
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn par_from_iter() {
+    const ITEMS: usize = 1 << 14;
+
+    let pool = ThreadPool::new(4);
+    // The second value of each even key is kept.
+    let pairs = (0..ITEMS)
+        .map(|key| (key, key))
+        .chain((0..ITEMS).step_by(2).map(|key| (key, key + 1)));
+    let list = SplitOrderedList::<usize>::par_from_iter(pairs, &pool);
+
+    let guard = epoch::pin();
+    for key in 0..ITEMS {
+        let expected = if key % 2 == 0 { key + 1 } else { key };
+        assert_eq!(list.lookup(&key, &guard), Some(&expected));
+    }
+    let stats = list.stats(&guard);
+    assert_eq!(stats.items, ITEMS);
+    // The buckets are grown for all the pairs, including the duplicates.
+    assert_eq!(stats.buckets, ((ITEMS + ITEMS / 2) / 2).next_power_of_two());
+
+    let empty = SplitOrderedList::<usize>::par_from_iter(Vec::new(), &pool);
+    assert_eq!(empty.iter(&guard).count(), 0);
+}
+
+#[test]
+fn validate() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1 << 12;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    assert_eq!(list.validate(&guard), Ok(()));
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = epoch::pin();
+                for key in (t..KEYS).step_by(THREADS) {
+                    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+                }
+                // Shrinks the buckets.
+                for key in (t..KEYS).step_by(THREADS).filter(|key| key % 16 != 0) {
+                    assert_eq!(list.delete(&key, &guard), Ok(&key));
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(
+        list.validate(&guard),
+        Ok(()),
+        "{}",
+        debug_dump::split_ordered_list(&list, Format::Text, &guard)
+    );
+    assert_eq!(list.stats(&guard).items, KEYS / 16);
+
+    list.clear(&guard);
+    assert_eq!(list.validate(&guard), Ok(()));
+    list.collect(&guard);
+    assert_eq!(list.validate(&guard), Ok(()));
+}
+
+#[test]
+fn map_error() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.delete(&1, &guard), Err(MapError::NotFound));
+    assert_eq!(list.insert(&1, 10, &guard), Ok(()));
+    assert_eq!(
+        list.insert(&1, 11, &guard),
+        Err(MapError::AlreadyExists(11))
+    );
+    assert_eq!(list.delete(&1, &guard), Ok(&10));
+    assert_eq!(MapError::<()>::NotFound.to_string(), "the key is not found");
+}
+
+#[test]
+fn clone() {
+    const ITEMS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+    for key in 0..ITEMS {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    let cloned = list.clone();
+    assert_eq!(cloned.validate(&guard), Ok(()));
+    assert_eq!(cloned.stats(&guard).buckets, list.stats(&guard).buckets);
+    assert_eq!(cloned.snapshot(&guard), list.snapshot(&guard));
+
+    // The lists are independent.
+    assert_eq!(cloned.update(&0, 1, &guard), Ok(&0));
+    assert_eq!(cloned.delete(&1, &guard), Ok(&1));
+    assert_eq!(list.lookup(&0, &guard), Some(&0));
+    assert_eq!(list.lookup(&1, &guard), Some(&1));
+    drop(list);
+    assert_eq!(cloned.lookup(&2, &guard), Some(&2));
+}
+
+#[test]
+fn owned() {
+    let list = SplitOrderedList::<String>::new();
+
+    assert_eq!(list.insert_owned(&1, "foo".to_string()), Ok(()));
+    assert_eq!(
+        list.insert_owned(&1, "bar".to_string()),
+        Err(MapError::AlreadyExists("bar".to_string()))
+    );
+    assert_eq!(list.lookup_cloned(&1), Some("foo".to_string()));
+    assert_eq!(list.lookup_cloned(&2), None);
+    assert_eq!(list.delete_owned(&1), Some("foo".to_string()));
+    assert_eq!(list.delete_owned(&1), None);
+    assert_eq!(list.lookup_cloned(&1), None);
+}
+
+#[test]
+fn insert_or_replace() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.insert_or_replace(&1, 10, &guard), None);
+    assert_eq!(list.insert_or_replace(&1, 11, &guard), Some(&10));
+    assert_eq!(list.lookup(&1, &guard), Some(&11));
+    assert_eq!(list.delete(&1, &guard), Ok(&11));
+    assert_eq!(list.insert_or_replace(&1, 12, &guard), None);
+    assert_eq!(list.snapshot(&guard), [(1, 12)]);
+}
+
+#[test]
+fn insert_or_replace_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 64;
+    const STEPS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let inserted = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let list = &list;
+                s.spawn(move |_| {
+                    let guard = epoch::pin();
+                    (0..STEPS)
+                        .filter(|step| {
+                            let key = step % KEYS;
+                            let replaced = list.insert_or_replace(&key, t, &guard);
+                            // The key is never missing once inserted.
+                            assert!(list.lookup(&key, &guard).is_some());
+                            replaced.is_none()
+                        })
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum::<usize>()
+    })
+    .unwrap();
+
+    // Each key is inserted once, and replaced afterwards.
+    assert_eq!(inserted, KEYS);
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).count(), KEYS);
+}
+
+#[test]
+fn update() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.update(&1, 10, &guard), Err(10));
+    assert_eq!(list.insert(&1, 10, &guard), Ok(()));
+    assert_eq!(list.update(&1, 11, &guard), Ok(&10));
+    assert_eq!(list.lookup(&1, &guard), Some(&11));
+
+    assert_eq!(list.compare_and_update(&1, &10, 12, &guard), Err(12));
+    assert_eq!(list.compare_and_update(&1, &11, 12, &guard), Ok(&11));
+    assert_eq!(list.compare_and_update(&2, &11, 12, &guard), Err(12));
+    assert_eq!(list.lookup(&1, &guard), Some(&12));
+
+    assert_eq!(list.delete(&1, &guard), Ok(&12));
+    assert_eq!(list.update(&1, 13, &guard), Err(13));
+    assert_eq!(list.iter(&guard).count(), 0);
+}
+
+#[test]
+fn update_concurrent() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 64;
+    const STEPS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = epoch::pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, 0, &guard), Ok(()));
+        }
+    }
+    scope(|s| {
+        // Each writer increments the values with `compare_and_update`.
+        for _ in 0..THREADS {
+            let list = &list;
+            let _ = s.spawn(move |_| {
+                for step in 0..STEPS {
+                    let guard = epoch::pin();
+                    let key = step % KEYS;
+                    loop {
+                        let value = *list.lookup(&key, &guard).unwrap();
+                        if list
+                            .compare_and_update(&key, &value, value + 1, &guard)
+                            .is_ok()
+                        {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+        // The readers never miss a key, and see its value increase.
+        for _ in 0..THREADS {
+            let list = &list;
+            let _ = s.spawn(move |_| {
+                let mut last = [0; KEYS];
+                for step in 0..STEPS {
+                    let guard = epoch::pin();
+                    let key = step % KEYS;
+                    let value = *list.lookup(&key, &guard).unwrap();
+                    assert!(value >= last[key]);
+                    last[key] = value;
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = epoch::pin();
+    let sum = list.iter(&guard).map(|(_, value)| value).sum::<usize>();
+    assert_eq!(sum, THREADS * STEPS);
+    assert_eq!(list.iter(&guard).count(), KEYS);
+}
+
 #[test]
 fn stress_sequential() {
     const STEPS: usize = 4096;
@@ -54,3 +1027,13 @@ fn log_concurrent() {
         THREADS, STEPS,
     );
 }
+
+#[test]
+fn linearizable_concurrent() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 64;
+    map_linearizable::linearizable_concurrent::<
+        usize,
+        NonblockingConcurrentMap<_, _, SplitOrderedList<usize>>,
+    >(THREADS, STEPS);
+}
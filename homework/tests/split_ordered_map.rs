@@ -0,0 +1,102 @@
+use core::hash::{BuildHasherDefault, Hasher};
+use crossbeam_epoch as epoch;
+use cs492_concur_homework::{NonblockingConcurrentMap, SplitOrderedMap};
+
+pub mod map;
+pub mod map_linearizable;
+
+/// Hashes every key into one of 4 values, so that most keys collide.
+#[derive(Debug, Default)]
+struct CollidingHasher(u64);
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        self.0 % 4
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(b as u64);
+        }
+    }
+}
+
+type CollidingMap<V> = SplitOrderedMap<String, V, BuildHasherDefault<CollidingHasher>>;
+
+#[test]
+fn smoke() {
+    let map = SplitOrderedMap::new();
+    let guard = epoch::pin();
+
+    assert_eq!(map.insert("foo".to_string(), 1, &guard), Ok(()));
+    assert_eq!(map.insert("bar".to_string(), 2, &guard), Ok(()));
+    assert_eq!(
+        map.insert("foo".to_string(), 3, &guard),
+        Err(("foo".to_string(), 3))
+    );
+    assert_eq!(map.lookup(&"foo".to_string(), &guard), Some(&1));
+    assert_eq!(map.lookup(&"baz".to_string(), &guard), None);
+
+    assert_eq!(map.delete(&"foo".to_string(), &guard), Ok(&1));
+    assert_eq!(map.delete(&"foo".to_string(), &guard), Err(()));
+    assert_eq!(map.lookup(&"foo".to_string(), &guard), None);
+    assert_eq!(map.lookup(&"bar".to_string(), &guard), Some(&2));
+}
+
+#[test]
+fn collision() {
+    let map = CollidingMap::default();
+    let guard = epoch::pin();
+
+    for i in 0..100 {
+        assert_eq!(map.insert(i.to_string(), i, &guard), Ok(()));
+    }
+    for i in (0..100).step_by(3) {
+        assert_eq!(map.delete(&i.to_string(), &guard), Ok(&i));
+    }
+    for i in 0..100 {
+        let expected = if i % 3 == 0 { None } else { Some(&i) };
+        assert_eq!(map.lookup(&i.to_string(), &guard), expected);
+    }
+    for i in (0..100).step_by(3) {
+        assert_eq!(map.insert(i.to_string(), i * 2, &guard), Ok(()));
+        assert_eq!(map.lookup(&i.to_string(), &guard), Some(&(i * 2)));
+    }
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_concurrent_sequential::<
+        String,
+        NonblockingConcurrentMap<_, _, SplitOrderedMap<String, usize>>,
+    >(STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<String, NonblockingConcurrentMap<_, _, SplitOrderedMap<String, usize>>>(
+        THREADS, STEPS,
+    );
+}
+
+#[test]
+fn stress_concurrent_collision() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<String, NonblockingConcurrentMap<_, _, CollidingMap<usize>>>(
+        THREADS, STEPS,
+    );
+}
+
+#[test]
+fn linearizable_concurrent_collision() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 16;
+    map_linearizable::linearizable_concurrent::<
+        String,
+        NonblockingConcurrentMap<_, _, CollidingMap<usize>>,
+    >(THREADS, STEPS);
+}
@@ -0,0 +1,76 @@
+use crossbeam_epoch as epoch;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::SplitOrderedMultimap;
+
+#[test]
+fn smoke() {
+    let map = SplitOrderedMultimap::new();
+    let guard = epoch::pin();
+
+    map.insert(&1, 10, &guard);
+    map.insert(&1, 11, &guard);
+    map.insert(&2, 20, &guard);
+    map.insert(&1, 10, &guard);
+    assert_eq!(
+        map.get_all(&1, &guard).copied().collect::<Vec<_>>(),
+        [10, 11, 10]
+    );
+    assert_eq!(map.get_all(&2, &guard).copied().collect::<Vec<_>>(), [20]);
+    assert_eq!(map.get_all(&3, &guard).count(), 0);
+
+    assert_eq!(map.remove_one(&2, &guard), Ok(&20));
+    assert_eq!(map.remove_one(&2, &guard), Err(()));
+    assert_eq!(map.remove_all(&1, &guard), 3);
+    assert_eq!(map.get_all(&1, &guard).count(), 0);
+    assert_eq!(map.remove_all(&1, &guard), 0);
+}
+
+#[test]
+fn full_key_range() {
+    let map = SplitOrderedMultimap::new();
+    let guard = epoch::pin();
+
+    for &key in [0, 1, usize::MAX, usize::MAX - 1].iter() {
+        map.insert(&key, key, &guard);
+        map.insert(&key, key, &guard);
+    }
+    for &key in [0, 1, usize::MAX, usize::MAX - 1].iter() {
+        assert_eq!(
+            map.get_all(&key, &guard).copied().collect::<Vec<_>>(),
+            [key, key]
+        );
+    }
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 16;
+    const STEPS: usize = 1024;
+
+    let map = SplitOrderedMultimap::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let map = &map;
+            s.spawn(move |_| {
+                let guard = epoch::pin();
+                for step in 0..STEPS {
+                    let key = step % KEYS;
+                    map.insert(&key, t, &guard);
+                    // Each removal follows an insertion of the same key by the same thread, so the
+                    // key has a value to remove.
+                    if step % 2 == 1 {
+                        assert!(map.remove_one(&key, &guard).is_ok());
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = epoch::pin();
+    let remaining = (0..KEYS)
+        .map(|key| map.remove_all(&key, &guard))
+        .sum::<usize>();
+    assert_eq!(remaining, THREADS * STEPS / 2);
+}
@@ -0,0 +1,84 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::StampedAtomic;
+use std::sync::atomic::Ordering;
+
+#[test]
+fn smoke() {
+    let mut a = 1;
+    let mut b = 2;
+    let (a, b) = (&mut a as *mut i32, &mut b as *mut i32);
+
+    let atomic = StampedAtomic::new(a);
+    let first = atomic.load(Ordering::Relaxed);
+    assert_eq!((first.ptr(), first.stamp()), (a, 0));
+
+    // A -> B -> A changes the stamp, so the stale CAS fails.
+    assert!(atomic
+        .compare_exchange(first, b, Ordering::AcqRel, Ordering::Relaxed)
+        .is_ok());
+    atomic.store(a, Ordering::Release);
+    let current = atomic.load(Ordering::Relaxed);
+    assert_eq!((current.ptr(), current.stamp()), (a, 2));
+    assert_eq!(
+        atomic.compare_exchange(first, b, Ordering::AcqRel, Ordering::Relaxed),
+        Err(current)
+    );
+
+    assert_eq!(
+        atomic.compare_exchange(current, b, Ordering::AcqRel, Ordering::Relaxed),
+        Ok(current)
+    );
+    assert_eq!(atomic.load(Ordering::Relaxed).ptr(), b);
+}
+
+#[test]
+fn stamp_wraps_around() {
+    let mut a = 1;
+    let a = &mut a as *mut i32;
+    let atomic = StampedAtomic::new(a);
+    for _ in 0..(1 << 16) {
+        atomic.store(a, Ordering::Relaxed);
+    }
+    let current = atomic.load(Ordering::Relaxed);
+    assert_eq!(current.ptr(), a);
+    assert!(current.stamp() < 1 << 16);
+}
+
+#[test]
+fn concurrent_counter() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 10_000;
+
+    // Every successful CAS bumps the stamp exactly once.
+    let mut value = 0;
+    let addr = &mut value as *mut i32 as usize;
+    let atomic = StampedAtomic::new(addr as *mut i32);
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..STEPS {
+                    let ptr = addr as *mut i32;
+                    let mut current = atomic.load(Ordering::Relaxed);
+                    while let Err(c) =
+                        atomic.compare_exchange(current, ptr, Ordering::AcqRel, Ordering::Relaxed)
+                    {
+                        current = c;
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(
+        atomic.load(Ordering::Relaxed).stamp(),
+        THREADS * STEPS % (1 << 16)
+    );
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+#[should_panic(expected = "does not fit in 48 bits")]
+fn wide_pointer() {
+    // The stamp would overwrite the upper bits of the address.
+    let _ = StampedAtomic::new((1usize << 60) as *mut u64);
+}
@@ -0,0 +1,114 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{atomically, Abort, TVar};
+use rand::{thread_rng, Rng};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let var = TVar::new(1);
+    assert_eq!(var.load(), 1);
+    var.store(2);
+    assert_eq!(var.load(), 2);
+
+    // A transaction reads its own writes.
+    let (before, after) = atomically(|tx| {
+        let before = tx.read(&var)?;
+        tx.write(&var, before + 1)?;
+        Ok((before, tx.read(&var)?))
+    });
+    assert_eq!((before, after), (2, 3));
+    assert_eq!(var.load(), 3);
+}
+
+#[test]
+fn counter() {
+    const THREADS: usize = 8;
+    const INCREMENTS: usize = 10_000;
+
+    let counter = TVar::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for _ in 0..INCREMENTS {
+                    atomically(|tx| tx.modify(&counter, |v| v + 1));
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(counter.load(), THREADS * INCREMENTS);
+}
+
+#[test]
+fn bank_transfer() {
+    const ACCOUNTS: usize = 8;
+    const THREADS: usize = 4;
+    const TRANSFERS: usize = 10_000;
+    const INITIAL: i64 = 1000;
+
+    let accounts = (0..ACCOUNTS)
+        .map(|_| TVar::new(INITIAL))
+        .collect::<Vec<_>>();
+    let total = || {
+        atomically(|tx| {
+            let mut sum = 0;
+            for account in &accounts {
+                sum += tx.read(account)?;
+            }
+            Ok(sum)
+        })
+    };
+
+    scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                let mut rng = thread_rng();
+                for _ in 0..TRANSFERS {
+                    let from = &accounts[rng.gen_range(0, ACCOUNTS)];
+                    let to = &accounts[rng.gen_range(0, ACCOUNTS)];
+                    let amount = rng.gen_range(0, 100);
+                    atomically(|tx| {
+                        tx.modify(from, |v| v - amount)?;
+                        tx.modify(to, |v| v + amount)
+                    });
+                }
+            });
+        }
+
+        // Every snapshot sees the same total.
+        s.spawn(|_| {
+            for _ in 0..1000 {
+                assert_eq!(total(), INITIAL * ACCOUNTS as i64);
+            }
+        });
+    })
+    .unwrap();
+    assert_eq!(total(), INITIAL * ACCOUNTS as i64);
+}
+
+#[test]
+fn abort_retries() {
+    let ready = TVar::new(false);
+    let value = TVar::new(0);
+
+    scope(|s| {
+        s.spawn(|_| {
+            thread::sleep(Duration::from_millis(50));
+            atomically(|tx| {
+                tx.write(&value, 42)?;
+                tx.write(&ready, true)
+            });
+        });
+
+        // Aborts until the flag is set, and then sees the value written together with it.
+        let v = atomically(|tx| {
+            if !tx.read(&ready)? {
+                return Err(Abort);
+            }
+            tx.read(&value)
+        });
+        assert_eq!(v, 42);
+    })
+    .unwrap();
+}
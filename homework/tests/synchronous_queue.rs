@@ -0,0 +1,147 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::channel::SynchronousQueue;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Spins until `n` threads are waiting on `queue`.
+fn wait_for<T>(queue: &SynchronousQueue<T>, n: usize) {
+    while queue.waiting() != n {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn smoke() {
+    let queue = SynchronousQueue::new();
+    assert_eq!(queue.offer(1), Err(1));
+    assert_eq!(queue.poll(), None);
+
+    scope(|s| {
+        let _ = s.spawn(|_| queue.put(42));
+        assert_eq!(queue.take(), 42);
+
+        let taker = s.spawn(|_| queue.take());
+        wait_for(&queue, 1);
+        assert_eq!(queue.offer(7), Ok(()));
+        assert_eq!(taker.join().unwrap(), 7);
+    })
+    .unwrap();
+    assert_eq!(queue.waiting(), 0);
+}
+
+#[test]
+fn timeout() {
+    let queue = SynchronousQueue::new();
+    assert_eq!(queue.put_timeout(1, Duration::from_millis(50)), Err(1));
+    assert_eq!(queue.take_timeout(Duration::from_millis(50)), None);
+    assert_eq!(queue.waiting(), 0);
+
+    scope(|s| {
+        let _ = s.spawn(|_| {
+            thread::sleep(Duration::from_millis(50));
+            queue.put(2);
+        });
+        assert_eq!(queue.take_timeout(Duration::from_secs(10)), Some(2));
+    })
+    .unwrap();
+}
+
+fn take_order(queue: SynchronousQueue<usize>) -> Vec<usize> {
+    const PUTTERS: usize = 4;
+
+    scope(|s| {
+        for i in 0..PUTTERS {
+            let queue = &queue;
+            let _ = s.spawn(move |_| queue.put(i));
+            wait_for(queue, i + 1);
+        }
+        (0..PUTTERS).map(|_| queue.take()).collect()
+    })
+    .unwrap()
+}
+
+#[test]
+fn fairness() {
+    assert_eq!(take_order(SynchronousQueue::fair()), vec![0, 1, 2, 3]);
+    assert_eq!(take_order(SynchronousQueue::new()), vec![3, 2, 1, 0]);
+}
+
+#[test]
+fn stress() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 1_000;
+
+    // Every value put is taken exactly once, with or without timeouts.
+    for queue in [SynchronousQueue::new(), SynchronousQueue::fair()].iter() {
+        let taken = (0..THREADS * STEPS)
+            .map(|_| AtomicUsize::new(0))
+            .collect::<Vec<_>>();
+        scope(|s| {
+            for t in 0..THREADS {
+                let taken = &taken;
+                let _ = s.spawn(move |_| {
+                    for i in 0..STEPS {
+                        let mut value = t * STEPS + i;
+                        while let Err(v) = queue.put_timeout(value, Duration::from_micros(100)) {
+                            value = v;
+                        }
+                    }
+                });
+                let _ = s.spawn(move |_| {
+                    for _ in 0..STEPS {
+                        let value = loop {
+                            if let Some(v) = queue.take_timeout(Duration::from_micros(100)) {
+                                break v;
+                            }
+                        };
+                        let _ = taken[value].fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert!(taken.iter().all(|t| t.load(Ordering::Relaxed) == 1));
+        assert_eq!(queue.waiting(), 0);
+    }
+}
+
+#[test]
+fn handoff_pool() {
+    const JOBS: usize = 100;
+    const KEEP_ALIVE: Duration = Duration::from_millis(20);
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    // A thread pool without a job queue: a job is handed over to an idle worker, or a new worker
+    // is spawned for it. A worker exits when idle for `KEEP_ALIVE`.
+    let queue = Arc::new(SynchronousQueue::<Job>::new());
+    let mut workers = Vec::new();
+    let done = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..JOBS {
+        let done = done.clone();
+        let job: Job = Box::new(move || {
+            thread::sleep(Duration::from_millis(1));
+            let _ = done.fetch_add(1, Ordering::Relaxed);
+        });
+        if let Err(job) = queue.offer(job) {
+            let queue = queue.clone();
+            workers.push(thread::spawn(move || {
+                job();
+                while let Some(job) = queue.take_timeout(KEEP_ALIVE) {
+                    job();
+                }
+            }));
+        }
+    }
+
+    let num_workers = workers.len();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    assert_eq!(done.load(Ordering::Relaxed), JOBS);
+    assert!(num_workers <= JOBS);
+    assert_eq!(queue.waiting(), 0);
+}
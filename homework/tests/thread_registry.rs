@@ -0,0 +1,104 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::{current_thread_id, ThreadRegistry};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+
+#[test]
+fn smoke() {
+    let registry = ThreadRegistry::new();
+    assert_eq!(registry.get(), None);
+    assert_eq!(*registry.get_or(|| 1), 1);
+    assert_eq!(*registry.get_or(|| 2), 1);
+    assert_eq!(registry.get(), Some(&1));
+    assert_eq!(registry.iter().collect::<Vec<_>>(), [&1]);
+
+    let mut registry = registry;
+    registry.clear();
+    assert_eq!(registry.get(), None);
+    assert_eq!(registry.iter().count(), 0);
+}
+
+#[test]
+fn distinct_ids() {
+    const THREADS: usize = 16;
+    let ids = Mutex::new(HashSet::new());
+    let barrier = Barrier::new(THREADS);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|_| {
+                assert!(ids.lock().unwrap().insert(current_thread_id()));
+                // Keeps the threads alive so that no ID is reused.
+                let _ = barrier.wait();
+                assert_eq!(current_thread_id(), current_thread_id());
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(ids.into_inner().unwrap().len(), THREADS);
+}
+
+#[test]
+fn sharded_counter() {
+    const THREADS: usize = 8;
+    const COUNT: usize = 10_000;
+    let counter = ThreadRegistry::<AtomicUsize>::new();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|_| {
+                for _ in 0..COUNT {
+                    let _ = counter.get_or_default().fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    })
+    .unwrap();
+    // The shards of the exited threads are kept.
+    let total: usize = counter.iter().map(|c| c.load(Ordering::Relaxed)).sum();
+    assert_eq!(total, THREADS * COUNT);
+    assert!(counter.iter().count() <= THREADS);
+}
+
+#[test]
+fn reuse_after_exit() {
+    let registry = Arc::new(ThreadRegistry::<AtomicUsize>::new());
+    for _ in 0..10 {
+        let registry = registry.clone();
+        thread::spawn(move || {
+            let _ = registry.get_or_default().fetch_add(1, Ordering::Relaxed);
+        })
+        .join()
+        .unwrap();
+    }
+    // The threads ran one at a time, while the other tests may run their threads.
+    let total: usize = registry.iter().map(|c| c.load(Ordering::Relaxed)).sum();
+    assert_eq!(total, 10);
+    assert!(registry.iter().count() <= 10);
+}
+
+#[test]
+fn drop_values() {
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            let _ = self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    const THREADS: usize = 8;
+    let drops = Arc::new(AtomicUsize::new(0));
+    let registry = ThreadRegistry::new();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|_| {
+                let _ = registry.get_or(|| Counted(drops.clone()));
+            });
+        }
+    })
+    .unwrap();
+    let values = registry.iter().count();
+    drop(registry);
+    assert_eq!(drops.load(Ordering::Relaxed), values);
+}
@@ -0,0 +1,70 @@
+use cs492_concur_homework::channel::oneshot;
+use cs492_concur_homework::WaitGroup;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const THREADS: usize = 16;
+
+#[test]
+fn wait_all() {
+    let wg = WaitGroup::new();
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..THREADS {
+        let wg = wg.clone();
+        let counter = counter.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            counter.fetch_add(1, Ordering::Relaxed);
+            drop(wg);
+        });
+    }
+
+    wg.wait();
+    assert_eq!(counter.load(Ordering::Relaxed), THREADS);
+}
+
+#[test]
+fn wait_blocks() {
+    let wg = WaitGroup::new();
+    let (sender, mut receiver) = oneshot::channel();
+
+    let participant = wg.clone();
+    let waiter = thread::spawn(move || {
+        wg.wait();
+        sender.send(()).unwrap();
+    });
+
+    // The waiter must be blocked while a participant is alive.
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    drop(participant);
+    receiver.recv_timeout(Duration::from_secs(3)).unwrap();
+    waiter.join().unwrap();
+}
+
+#[test]
+fn nested_participants() {
+    let wg = WaitGroup::new();
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..THREADS {
+        let wg = wg.clone();
+        let counter = counter.clone();
+        thread::spawn(move || {
+            // A participant may add more participants before it finishes.
+            for _ in 0..4 {
+                let wg = wg.clone();
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    drop(wg);
+                });
+            }
+        });
+    }
+
+    wg.wait();
+    assert_eq!(counter.load(Ordering::Relaxed), THREADS * 4);
+}
@@ -0,0 +1,45 @@
+use cs492_concur_homework::testing::workload::{KeyGenerator, Target, Workload};
+use cs492_concur_homework::{
+    AtomicHamt, LockCouplingBst, NmTree, OrderedListSet, RadixTree, SplitOrderedList,
+};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+#[test]
+fn key_distribution() {
+    const SAMPLES: usize = 100_000;
+    // The number of samples of the 10 most popular keys.
+    let top_hits = |workload: &Workload| {
+        let mut keys = KeyGenerator::new(workload, 0);
+        let mut hits = vec![0; workload.key_range];
+        for _ in 0..SAMPLES {
+            hits[keys.next_key()] += 1;
+        }
+        hits.sort_unstable();
+        hits.iter().rev().take(10).sum::<usize>()
+    };
+    assert!(top_hits(&Workload::READ_HEAVY) < SAMPLES / 50);
+    assert!(top_hits(&Workload::ZIPFIAN) > SAMPLES / 4);
+}
+
+fn run<T: Target>(target: T) {
+    for workload in &[
+        Workload::READ_HEAVY,
+        Workload::WRITE_HEAVY,
+        Workload::ZIPFIAN,
+    ] {
+        workload.prefill(&target);
+        let _ = workload.run(&target, 4, 1000);
+    }
+}
+
+#[test]
+fn targets() {
+    run(Mutex::new(BTreeSet::new()));
+    run(OrderedListSet::new());
+    run(SplitOrderedList::<()>::new());
+    run(RadixTree::<()>::new());
+    run(AtomicHamt::<usize, ()>::new());
+    run(NmTree::<usize, ()>::new());
+    run(LockCouplingBst::<usize, ()>::new());
+}
@@ -0,0 +1,93 @@
+//! Compares the cohort lock with the flat locks.
+//!
+//! For each lock, reports the throughput and the number of times the lock moves to another node
+//! per 1000 acquisitions, which is the cross-node traffic on the lock and the data it protects.
+//! The nodes are the NUMA nodes of the machine, and 2 virtual nodes to which the threads are
+//! assigned alternately, which shows the effect of cohorting on a single-node machine.
+//!
+//! Run with `cargo bench --bench cohort`.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_utils::thread::scope;
+use lock::{CohortLock, Lock, McsLock, RawLock, SysfsTopology, TicketLock, Topology};
+use std::time::{Duration, Instant};
+
+const THREADS: [usize; 3] = [4, 8, 16];
+const DURATION: Duration = Duration::from_millis(500);
+
+/// Assigns the threads to 2 virtual nodes alternately.
+struct VirtualTopology;
+
+impl Topology for VirtualTopology {
+    fn num_nodes() -> usize {
+        2
+    }
+
+    fn current_node() -> usize {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static NODE: usize = NEXT.fetch_add(1, Ordering::Relaxed) % 2;
+        }
+        NODE.with(|node| *node)
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    last_node: usize,
+    handoffs: usize,
+    acquisitions: usize,
+}
+
+/// Runs `threads` threads incrementing a shared counter under `L` for `DURATION`. Returns the
+/// acquisitions per microsecond and the handoffs between nodes per 1000 acquisitions.
+fn run<L: RawLock, T: Topology>(threads: usize) -> (f64, f64) {
+    let lock = Lock::<L, Shared>::new(Shared::default());
+    let start = Instant::now();
+    scope(|s| {
+        for _ in 0..threads {
+            let _ = s.spawn(|_| {
+                let node = T::current_node();
+                while start.elapsed() < DURATION {
+                    let mut shared = lock.lock();
+                    if shared.last_node != node {
+                        shared.last_node = node;
+                        shared.handoffs += 1;
+                    }
+                    shared.acquisitions += 1;
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let shared = lock.into_inner();
+    let throughput = shared.acquisitions as f64 / start.elapsed().as_micros() as f64;
+    let handoffs = shared.handoffs as f64 * 1000.0 / shared.acquisitions as f64;
+    (throughput, handoffs)
+}
+
+fn report<T: Topology>(name: &str) {
+    println!("{} ({} nodes)", name, T::num_nodes());
+    println!(
+        "{:>8} {:>24} {:>24} {:>24}",
+        "threads", "TicketLock", "McsLock", "CohortLock"
+    );
+    for &threads in THREADS.iter() {
+        let results = [
+            run::<TicketLock, T>(threads),
+            run::<McsLock, T>(threads),
+            run::<CohortLock<T>, T>(threads),
+        ];
+        print!("{:>8}", threads);
+        for (throughput, handoffs) in results.iter() {
+            print!(" {:>10.2} op/us {:>6.1} h/k", throughput, handoffs);
+        }
+        println!();
+    }
+}
+
+fn main() {
+    report::<SysfsTopology>("NUMA nodes");
+    report::<VirtualTopology>("virtual nodes");
+}
@@ -1,3 +1,4 @@
+use alloc::boxed::Box;
 use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
 
 use crossbeam_utils::{Backoff, CachePadded};
@@ -0,0 +1,234 @@
+//! Cohort lock.
+//!
+//! - From Dice, Marathe, Shavit. Lock Cohorting: A General Technique for Designing NUMA Locks.
+//!   PPoPP 2012
+//!
+//! Threads on the same NUMA node form a cohort that shares a local lock, and the owner of a local
+//! lock competes for the global lock. When releasing, the owner passes the global lock to the
+//! next thread of its cohort if there is one, so that the lock and the data it protects stay on
+//! the same node for a while. The number of consecutive passes is bounded to avoid starving the
+//! other nodes.
+
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::fs;
+
+use crossbeam_utils::CachePadded;
+
+use crate::lock::*;
+use crate::ticketlock::TicketLock;
+
+/// The maximum number of consecutive passes of the global lock within a cohort.
+const MAX_PASSES: usize = 64;
+
+/// Source of the NUMA topology.
+pub trait Topology: Send + Sync + 'static {
+    /// Returns the number of NUMA nodes.
+    fn num_nodes() -> usize;
+
+    /// Returns the NUMA node of the current thread, which is less than `num_nodes()`.
+    fn current_node() -> usize;
+}
+
+/// Topology read from the Linux sysfs.
+///
+/// The node of a thread is the node of the CPU it is running on when it first asks, since a thread
+/// rarely migrates between nodes. Without sysfs, there is a single node.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SysfsTopology;
+
+const NODE_DIR: &str = "/sys/devices/system/node";
+
+/// Returns the IDs of the NUMA nodes.
+fn node_ids() -> Vec<usize> {
+    let entries = match fs::read_dir(NODE_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_prefix("node")?.parse().ok()
+        })
+        .collect()
+}
+
+/// Returns `true` if the CPU list such as `0-3,8-11` contains `cpu`.
+fn cpulist_contains(list: &str, cpu: usize) -> bool {
+    list.trim().split(',').any(|range| {
+        let mut bounds = range.splitn(2, '-').map(|b| b.parse::<usize>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(lo)), None) => lo == cpu,
+            (Some(Ok(lo)), Some(Ok(hi))) => lo <= cpu && cpu <= hi,
+            _ => false,
+        }
+    })
+}
+
+/// Returns the CPU the current thread is running on.
+fn current_cpu() -> Option<usize> {
+    let stat = fs::read_to_string("/proc/thread-self/stat").ok()?;
+    // The fields after the command name, which may contain spaces, start from the 3rd field. The
+    // CPU is the 39th field.
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(39 - 3)?.parse().ok()
+}
+
+/// Returns the index of the current thread's node among `node_ids()`.
+fn current_node_index() -> usize {
+    let cpu = match current_cpu() {
+        Some(cpu) => cpu,
+        None => return 0,
+    };
+    let mut ids = node_ids();
+    ids.sort_unstable();
+    ids.iter()
+        .position(|id| {
+            fs::read_to_string(format!("{}/node{}/cpulist", NODE_DIR, id))
+                .map(|list| cpulist_contains(&list, cpu))
+                .unwrap_or(false)
+        })
+        .unwrap_or(0)
+}
+
+impl Topology for SysfsTopology {
+    fn num_nodes() -> usize {
+        // 0 means unknown.
+        static NUM_NODES: AtomicUsize = AtomicUsize::new(0);
+        let n = NUM_NODES.load(Ordering::Relaxed);
+        if n != 0 {
+            return n;
+        }
+        let n = node_ids().len().max(1);
+        NUM_NODES.store(n, Ordering::Relaxed);
+        n
+    }
+
+    fn current_node() -> usize {
+        thread_local! {
+            static NODE: usize = current_node_index();
+        }
+        NODE.with(|node| *node).min(Self::num_nodes() - 1)
+    }
+}
+
+struct Cohort {
+    lock: TicketLock,
+    /// Whether the global lock is passed to the next owner of the local lock.
+    global_passed: AtomicBool,
+    /// The ticket of the global lock held by the cohort.
+    global_ticket: AtomicUsize,
+    /// The number of consecutive passes of the global lock.
+    passes: AtomicUsize,
+}
+
+/// Cohort lock with ticket locks as both the global and the local locks.
+pub struct CohortLock<T: Topology = SysfsTopology> {
+    global: TicketLock,
+    cohorts: Box<[CachePadded<Cohort>]>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Topology> Default for CohortLock<T> {
+    fn default() -> Self {
+        Self {
+            global: TicketLock::default(),
+            cohorts: (0..T::num_nodes())
+                .map(|_| {
+                    CachePadded::new(Cohort {
+                        lock: TicketLock::default(),
+                        global_passed: AtomicBool::new(false),
+                        global_ticket: AtomicUsize::new(0),
+                        passes: AtomicUsize::new(0),
+                    })
+                })
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Token {
+    node: usize,
+    local_ticket: usize,
+}
+
+impl<T: Topology> RawLock for CohortLock<T> {
+    type Token = Token;
+
+    fn lock(&self) -> Token {
+        let node = T::current_node();
+        let cohort = &self.cohorts[node];
+        let local_ticket = cohort.lock.lock();
+
+        // The fields of the cohort are accessed only by the owner of the local lock.
+        if !cohort.global_passed.load(Ordering::Relaxed) {
+            let ticket = self.global.lock();
+            cohort.global_ticket.store(ticket, Ordering::Relaxed);
+        }
+        Token { node, local_ticket }
+    }
+
+    unsafe fn unlock(&self, token: Token) {
+        let cohort = &self.cohorts[token.node];
+        let passes = cohort.passes.load(Ordering::Relaxed);
+
+        if cohort.lock.has_waiters(token.local_ticket) && passes < MAX_PASSES {
+            cohort.global_passed.store(true, Ordering::Relaxed);
+            cohort.passes.store(passes + 1, Ordering::Relaxed);
+        } else {
+            cohort.global_passed.store(false, Ordering::Relaxed);
+            cohort.passes.store(0, Ordering::Relaxed);
+            self.global
+                .unlock(cohort.global_ticket.load(Ordering::Relaxed));
+        }
+        cohort.lock.unlock(token.local_ticket);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::AtomicUsize;
+
+    /// Puts the threads on 4 virtual nodes in a round-robin fashion.
+    struct RoundRobinTopology;
+
+    impl Topology for RoundRobinTopology {
+        fn num_nodes() -> usize {
+            4
+        }
+
+        fn current_node() -> usize {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            thread_local! {
+                static NODE: usize = NEXT.fetch_add(1, Ordering::Relaxed) % 4;
+            }
+            NODE.with(|node| *node)
+        }
+    }
+
+    #[test]
+    fn smoke() {
+        crate::lock::tests::smoke::<CohortLock>();
+    }
+
+    #[test]
+    fn smoke_virtual_nodes() {
+        crate::lock::tests::smoke::<CohortLock<RoundRobinTopology>>();
+    }
+
+    #[test]
+    fn cpulist() {
+        assert!(cpulist_contains("0-3,8-11\n", 9));
+        assert!(cpulist_contains("5", 5));
+        assert!(!cpulist_contains("0-3,8-11", 4));
+        assert!(!cpulist_contains("", 0));
+    }
+
+    #[test]
+    fn sysfs_topology() {
+        assert!(SysfsTopology::current_node() < SysfsTopology::num_nodes());
+    }
+}
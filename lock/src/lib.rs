@@ -1,16 +1,25 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 extern crate crossbeam_utils;
 
 mod clhlock;
+#[cfg(feature = "std")]
+mod cohortlock;
 mod lock;
 mod mcslock;
+#[cfg(feature = "std")]
 mod mcsparkinglock;
 pub mod seqlock;
 mod spinlock;
 mod ticketlock;
 
 pub use crate::clhlock::ClhLock;
+#[cfg(feature = "std")]
+pub use crate::cohortlock::{CohortLock, SysfsTopology, Topology};
 pub use crate::lock::{Lock, LockGuard, RawLock, RawTryLock};
 pub use crate::mcslock::McsLock;
+#[cfg(feature = "std")]
 pub use crate::mcsparkinglock::McsParkingLock;
 pub use crate::spinlock::SpinLock;
 pub use crate::ticketlock::TicketLock;
@@ -1,3 +1,4 @@
+use alloc::boxed::Box;
 use core::ptr;
 use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
 
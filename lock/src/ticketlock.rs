@@ -18,6 +18,14 @@ impl Default for TicketLock {
     }
 }
 
+impl TicketLock {
+    /// Returns `true` if another thread is waiting for the lock held with `ticket`.
+    #[cfg(feature = "std")]
+    pub(crate) fn has_waiters(&self, ticket: usize) -> bool {
+        self.next.load(Ordering::Relaxed) != ticket.wrapping_add(1)
+    }
+}
+
 impl RawLock for TicketLock {
     type Token = usize;
 
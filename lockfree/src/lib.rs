@@ -2,7 +2,9 @@
 
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
 extern crate crossbeam_epoch;
 extern crate crossbeam_utils;
 
@@ -11,7 +13,9 @@ mod utils;
 pub mod list;
 mod queue;
 mod stack;
+mod stamped_atomic;
 
 pub use list::List;
 pub use queue::Queue;
 pub use stack::Stack;
+pub use stamped_atomic::{Stamped, StampedAtomic};
@@ -2,8 +2,8 @@
 
 use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Pointer, Shared};
 
-use std::cmp::Ordering::{Equal, Greater, Less};
-use std::sync::atomic::Ordering;
+use core::cmp::Ordering::{Equal, Greater, Less};
+use core::sync::atomic::Ordering;
 
 /// Linked list node.
 #[derive(Debug)]
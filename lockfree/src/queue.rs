@@ -12,14 +12,19 @@ use core::sync::atomic::Ordering;
 use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
 use crossbeam_utils::CachePadded;
 
+use crate::stamped_atomic::StampedAtomic;
+
 /// Michael-Scott queue.
+///
+/// The head and the tail are stamped, so that a CAS on them fails if they have been moved away and
+/// back in the meantime, even if the nodes are reused without waiting for the epoch to advance.
 // The representation here is a singly-linked list, with a sentinel node at the front. In general
 // the `tail` pointer may lag behind the actual tail. Non-sentinel nodes are either all `Data` or
 // all `Blocked` (requests for data from blocked threads).
 #[derive(Debug)]
 pub struct Queue<T> {
-    head: CachePadded<Atomic<Node<T>>>,
-    tail: CachePadded<Atomic<Node<T>>>,
+    head: CachePadded<StampedAtomic<Node<T>>>,
+    tail: CachePadded<StampedAtomic<Node<T>>>,
 }
 
 #[derive(Debug)]
@@ -41,23 +46,17 @@ unsafe impl<T: Send> Send for Queue<T> {}
 
 impl<T> Default for Queue<T> {
     fn default() -> Self {
-        let q = Self {
-            head: CachePadded::new(Atomic::null()),
-            tail: CachePadded::new(Atomic::null()),
-        };
         // TODO(taiki-e): when the minimum supported Rust version is bumped to 1.36+,
         // replace this with `mem::MaybeUninit`.
         #[allow(deprecated)]
-        let sentinel = Owned::new(Node {
+        let sentinel = Owned::<Node<T>>::new(Node {
             data: MaybeUninit::uninit(),
             next: Atomic::null(),
         });
-        unsafe {
-            let guard = &unprotected();
-            let sentinel = sentinel.into_shared(guard);
-            q.head.store(sentinel, Ordering::Relaxed);
-            q.tail.store(sentinel, Ordering::Relaxed);
-            q
+        let sentinel = unsafe { sentinel.into_shared(unprotected()) }.as_raw() as *mut _;
+        Self {
+            head: CachePadded::new(StampedAtomic::new(sentinel)),
+            tail: CachePadded::new(StampedAtomic::new(sentinel)),
         }
     }
 }
@@ -78,17 +77,20 @@ impl<T> Queue<T> {
 
         loop {
             // We push onto the tail, so we'll start optimistically by looking there first.
-            let tail = self.tail.load(Ordering::Acquire, guard);
+            let tail = self.tail.load(Ordering::Acquire);
 
             // Attempt to push onto the `tail` snapshot; fails if `tail.next` has changed.
-            let tail_ref = unsafe { tail.deref() };
+            let tail_ref = unsafe { &*tail.ptr() };
             let next = tail_ref.next.load(Ordering::Acquire, guard);
 
             // If `tail` is not the actual tail, try to "help" by moving the tail pointer forward.
             if !next.is_null() {
-                let _ = self
-                    .tail
-                    .compare_and_set(tail, next, Ordering::Release, guard);
+                let _ = self.tail.compare_exchange(
+                    tail,
+                    next.as_raw() as *mut _,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
                 continue;
             }
 
@@ -99,9 +101,12 @@ impl<T> Queue<T> {
                 .is_ok()
             {
                 // try to move the tail pointer forward.
-                let _ = self
-                    .tail
-                    .compare_and_set(tail, new, Ordering::Release, guard);
+                let _ = self.tail.compare_exchange(
+                    tail,
+                    new.as_raw() as *mut _,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
                 break;
             }
         }
@@ -112,27 +117,35 @@ impl<T> Queue<T> {
     /// Returns `None` if the queue is observed to be empty.
     pub fn try_pop(&self, guard: &Guard) -> Option<T> {
         loop {
-            let head = self.head.load(Ordering::Acquire, guard);
-            let h = unsafe { head.deref() };
+            let head = self.head.load(Ordering::Acquire);
+            let h = unsafe { &*head.ptr() };
             let next = h.next.load(Ordering::Acquire, guard);
             let next_ref = some_or!(unsafe { next.as_ref() }, return None);
 
             // Moves `tail` if it's stale. Relaxed load is enough because if tail == head, then the
             // messages for that node are already acquired.
-            let tail = self.tail.load(Ordering::Relaxed, guard);
-            if tail == head {
-                let _ = self
-                    .tail
-                    .compare_and_set(tail, next, Ordering::Release, guard);
+            let tail = self.tail.load(Ordering::Relaxed);
+            if tail.ptr() == head.ptr() {
+                let _ = self.tail.compare_exchange(
+                    tail,
+                    next.as_raw() as *mut _,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
             }
 
             if self
                 .head
-                .compare_and_set(head, next, Ordering::Release, guard)
+                .compare_exchange(
+                    head,
+                    next.as_raw() as *mut _,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                )
                 .is_ok()
             {
                 unsafe {
-                    guard.defer_destroy(head);
+                    guard.defer_destroy(Shared::from(head.ptr() as *const Node<T>));
                     return Some(ptr::read(&next_ref.data).assume_init());
                 }
             }
@@ -148,8 +161,8 @@ impl<T> Drop for Queue<T> {
             while self.try_pop(guard).is_some() {}
 
             // Destroy the remaining sentinel node.
-            let sentinel = self.head.load(Ordering::Relaxed, guard);
-            drop(sentinel.into_owned());
+            let sentinel = self.head.load(Ordering::Relaxed).ptr();
+            drop(Owned::from_raw(sentinel));
         }
     }
 }
@@ -178,8 +191,8 @@ mod test {
 
         pub fn is_empty(&self) -> bool {
             let guard = &pin();
-            let head = self.queue.head.load(Ordering::Acquire, guard);
-            let h = unsafe { head.deref() };
+            let head = self.queue.head.load(Ordering::Acquire);
+            let h = unsafe { &*head.ptr() };
             h.next.load(Ordering::Acquire, guard).is_null()
         }
 
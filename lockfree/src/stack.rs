@@ -2,7 +2,7 @@ use core::mem::ManuallyDrop;
 use core::ptr;
 use core::sync::atomic::Ordering;
 
-use crossbeam_epoch::{Atomic, Owned};
+use crossbeam_epoch::{unprotected, Atomic, Guard, Owned};
 
 /// Treiber's lock-free stack.
 ///
@@ -33,21 +33,25 @@ impl<T> Stack<T> {
     }
 
     /// Pushes a value on top of the stack.
+    #[cfg(feature = "std")]
     pub fn push(&self, t: T) {
+        self.push_with(t, &crossbeam_epoch::pin());
+    }
+
+    /// Pushes a value on top of the stack, protected by `guard`.
+    pub fn push_with(&self, t: T, guard: &Guard) {
         let mut n = Owned::new(Node {
             data: ManuallyDrop::new(t),
             next: Atomic::null(),
         });
 
-        let guard = crossbeam_epoch::pin();
-
         loop {
-            let head = self.head.load(Ordering::Relaxed, &guard);
+            let head = self.head.load(Ordering::Relaxed, guard);
             n.next.store(head, Ordering::Relaxed);
 
             match self
                 .head
-                .compare_and_set(head, n, Ordering::Release, &guard)
+                .compare_and_set(head, n, Ordering::Release, guard)
             {
                 Ok(_) => break,
                 Err(e) => n = e.new,
@@ -58,18 +62,25 @@ impl<T> Stack<T> {
     /// Attempts to pop the top element from the stack.
     ///
     /// Returns `None` if the stack is empty.
+    #[cfg(feature = "std")]
     pub fn pop(&self) -> Option<T> {
-        let guard = crossbeam_epoch::pin();
+        self.pop_with(&crossbeam_epoch::pin())
+    }
+
+    /// Attempts to pop the top element from the stack, protected by `guard`.
+    ///
+    /// Returns `None` if the stack is empty.
+    pub fn pop_with(&self, guard: &Guard) -> Option<T> {
         loop {
-            let head = self.head.load(Ordering::Acquire, &guard);
+            let head = self.head.load(Ordering::Acquire, guard);
 
             match unsafe { head.as_ref() } {
                 Some(h) => {
-                    let next = h.next.load(Ordering::Relaxed, &guard);
+                    let next = h.next.load(Ordering::Relaxed, guard);
 
                     if self
                         .head
-                        .compare_and_set(head, next, Ordering::Relaxed, &guard)
+                        .compare_and_set(head, next, Ordering::Relaxed, guard)
                         .is_ok()
                     {
                         unsafe {
@@ -84,15 +95,22 @@ impl<T> Stack<T> {
     }
 
     /// Returns `true` if the stack is empty.
+    #[cfg(feature = "std")]
     pub fn is_empty(&self) -> bool {
-        let guard = crossbeam_epoch::pin();
-        self.head.load(Ordering::Acquire, &guard).is_null()
+        self.is_empty_with(&crossbeam_epoch::pin())
+    }
+
+    /// Returns `true` if the stack is empty, protected by `guard`.
+    pub fn is_empty_with(&self, guard: &Guard) -> bool {
+        self.head.load(Ordering::Acquire, guard).is_null()
     }
 }
 
 impl<T> Drop for Stack<T> {
     fn drop(&mut self) {
-        while self.pop().is_some() {}
+        // No other thread can access the stack.
+        let guard = unsafe { unprotected() };
+        while self.pop_with(guard).is_some() {}
     }
 }
 
@@ -0,0 +1,170 @@
+//! Atomic pointer with a version stamp.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_pointer_width = "64")] {
+        // The user-space addresses fit in the lower 48 bits, so the stamp is in the upper 16 bits.
+        const STAMP_SHIFT: u32 = 48;
+        const PTR_MASK: usize = (1 << STAMP_SHIFT) - 1;
+
+        fn stamp_bits(_align: usize) -> u32 {
+            64 - STAMP_SHIFT
+        }
+
+        fn pack<T>(ptr: *mut T, stamp: usize) -> usize {
+            // Otherwise, the stamp would corrupt the pointer.
+            assert_eq!(ptr as usize & !PTR_MASK, 0, "pointer does not fit in 48 bits");
+            ptr as usize | (stamp << STAMP_SHIFT)
+        }
+
+        fn unpack<T>(data: usize) -> (*mut T, usize) {
+            ((data & PTR_MASK) as *mut T, data >> STAMP_SHIFT)
+        }
+    } else {
+        // Otherwise, the stamp is in the lower bits that are zero because of the alignment.
+        fn stamp_bits(align: usize) -> u32 {
+            align.trailing_zeros()
+        }
+
+        fn pack<T>(ptr: *mut T, stamp: usize) -> usize {
+            ptr as usize | stamp
+        }
+
+        fn unpack<T>(data: usize) -> (*mut T, usize) {
+            let mask = (1 << stamp_bits(mem::align_of::<T>())) - 1;
+            ((data & !mask) as *mut T, data & mask)
+        }
+    }
+}
+
+/// A pointer together with the stamp it had when it was loaded.
+pub struct Stamped<T> {
+    ptr: *mut T,
+    stamp: usize,
+}
+
+impl<T> Clone for Stamped<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Stamped<T> {}
+
+impl<T> PartialEq for Stamped<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr && self.stamp == other.stamp
+    }
+}
+
+impl<T> Eq for Stamped<T> {}
+
+impl<T> fmt::Debug for Stamped<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stamped")
+            .field("ptr", &self.ptr)
+            .field("stamp", &self.stamp)
+            .finish()
+    }
+}
+
+impl<T> Stamped<T> {
+    /// Returns the pointer.
+    pub fn ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Returns the stamp.
+    pub fn stamp(&self) -> usize {
+        self.stamp
+    }
+}
+
+/// Atomic pointer whose every update increments a version stamp.
+///
+/// `compare_exchange` compares the stamp as well as the pointer. So it fails if the pointer has
+/// been changed and then changed back in the meantime (the ABA problem), which a plain pointer
+/// comparison can't detect. The stamp is packed with the pointer in a single word, in the unused
+/// upper bits of the address on 64-bit targets and in the alignment bits otherwise, so it wraps
+/// around after `2^16` updates on 64-bit targets. There, storing a pointer that doesn't fit in
+/// the lower 48 bits panics.
+///
+/// NOTE: A double-word CAS would give a full-width stamp, but it is not available on stable Rust.
+pub struct StampedAtomic<T> {
+    data: AtomicUsize,
+    _marker: PhantomData<*mut T>,
+}
+
+unsafe impl<T: Send + Sync> Send for StampedAtomic<T> {}
+unsafe impl<T: Send + Sync> Sync for StampedAtomic<T> {}
+
+impl<T> fmt::Debug for StampedAtomic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StampedAtomic")
+            .field(&self.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<T> Default for StampedAtomic<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> StampedAtomic<T> {
+    /// Creates a new atomic pointer with the stamp 0.
+    pub fn new(ptr: *mut T) -> Self {
+        Self {
+            data: AtomicUsize::new(pack(ptr, 0)),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new null pointer with the stamp 0.
+    pub fn null() -> Self {
+        Self::new(core::ptr::null_mut())
+    }
+
+    fn next_stamp(stamp: usize) -> usize {
+        stamp.wrapping_add(1) & ((1 << stamp_bits(mem::align_of::<T>())) - 1)
+    }
+
+    /// Loads the pointer and its stamp.
+    pub fn load(&self, order: Ordering) -> Stamped<T> {
+        let (ptr, stamp) = unpack(self.data.load(order));
+        Stamped { ptr, stamp }
+    }
+
+    /// Stores `ptr`, incrementing the stamp.
+    pub fn store(&self, ptr: *mut T, order: Ordering) {
+        let _ = self.data.fetch_update(order, Ordering::Relaxed, |data| {
+            let (_, stamp) = unpack::<T>(data);
+            Some(pack(ptr, Self::next_stamp(stamp)))
+        });
+    }
+
+    /// Stores `new` if the pointer and the stamp are the same as `current`, incrementing the
+    /// stamp. Returns the previous value on success, and the current value on failure.
+    pub fn compare_exchange(
+        &self,
+        current: Stamped<T>,
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Stamped<T>, Stamped<T>> {
+        let expected = pack(current.ptr, current.stamp);
+        let new = pack(new, Self::next_stamp(current.stamp));
+        self.data
+            .compare_exchange(expected, new, success, failure)
+            .map(|_| current)
+            .map_err(|data| {
+                let (ptr, stamp) = unpack(data);
+                Stamped { ptr, stamp }
+            })
+    }
+}